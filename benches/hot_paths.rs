@@ -0,0 +1,137 @@
+//! Benchmarks of the paths every refresh and frame goes through, on large synthetic input
+// criterion_group! declares an undocumented public function
+#![allow(missing_docs)]
+
+use std::{
+    fmt::Write,
+    hint::black_box,
+    path::PathBuf,
+    process::Command,
+};
+
+use criterion::{
+    Criterion,
+    criterion_group,
+    criterion_main,
+};
+use jjkk::bench::{
+    App,
+    Args,
+    ChangeType,
+    FileStatus,
+    highlight,
+    parse_status,
+    render_ui,
+};
+use ratatui::{
+    Terminal,
+    backend::TestBackend,
+};
+use syntect::{
+    highlighting::ThemeSet,
+    parsing::SyntaxSet,
+};
+
+const STATUS_FILES: usize = 10_000;
+const DIFF_LINES: usize = 50_000;
+
+/// `jj status` of a working copy with `STATUS_FILES` changed files
+fn status_output() -> String {
+    let mut output = String::from("Working copy changes:\n");
+    for index in 0..STATUS_FILES {
+        let status = ['M', 'A', 'D'][index % 3];
+        let _ = writeln!(
+            output,
+            "{status} src/module_{}/file_{index}.rs",
+            index / 100
+        );
+    }
+    output.push_str("Working copy  (@) : kxqpwmlu 1a2b3c4d Change everything\n");
+    output.push_str("Parent commit (@-): zzzzzzzz 00000000 main | Initial commit\n");
+    output
+}
+
+/// Git diff of a rust file with `DIFF_LINES` lines, in hunks of context, removals and additions
+fn diff_output() -> String {
+    let mut diff = String::from("diff --git a/src/big.rs b/src/big.rs\n");
+    diff.push_str("--- a/src/big.rs\n+++ b/src/big.rs\n");
+    let mut line = 0;
+    while line < DIFF_LINES {
+        let _ = writeln!(diff, "@@ -{line},8 +{line},8 @@ impl Big {{");
+        for offset in 0..8 {
+            let marker = match offset {
+                3 | 4 => '-',
+                5 | 6 => '+',
+                _ => ' ',
+            };
+            let _ = writeln!(
+                diff,
+                "{marker}    fn item_{line}_{offset}(&self) -> Option<usize> {{ self.items.get({offset}).copied() }}"
+            );
+        }
+        line += 9;
+    }
+    diff
+}
+
+fn bench_status(c: &mut Criterion) {
+    let output = status_output();
+    c.bench_function("parse status of 10k files", |b| {
+        b.iter(|| parse_status(black_box(&output)));
+    });
+}
+
+fn bench_highlight(c: &mut Criterion) {
+    let diff = diff_output();
+    let syntax_set = SyntaxSet::load_defaults_newlines();
+    let themes = ThemeSet::load_defaults();
+    let theme = &themes.themes["base16-ocean.dark"];
+    c.bench_function("highlight 50k line diff", |b| {
+        b.iter(|| highlight(black_box(&diff), "src/big.rs", &syntax_set, theme));
+    });
+}
+
+/// A fresh jj repo to open the app in, `None` without a jj executable
+fn bench_repo() -> Option<PathBuf> {
+    let root = std::env::temp_dir().join(format!("jjkk-bench-{}", std::process::id()));
+    std::fs::create_dir_all(&root).ok()?;
+    let status = Command::new("jj")
+        .args(["git", "init"])
+        .current_dir(&root)
+        .output()
+        .ok()?
+        .status;
+    status.success().then_some(root)
+}
+
+fn bench_render(c: &mut Criterion) {
+    let Some(root) = bench_repo() else {
+        eprintln!("jj not found, skipping the render benchmark");
+        return;
+    };
+    std::env::set_current_dir(&root).expect("bench repo is a directory");
+
+    let mut app = App::new(&Args::default()).expect("app opens the bench repo");
+    app.files = (0..STATUS_FILES)
+        .map(|index| FileStatus {
+            path:     format!("src/module_{}/file_{index}.rs", index / 100),
+            status:   ChangeType::Modified,
+            old_path: None,
+        })
+        .collect();
+    app.current_diff = Some(diff_output());
+
+    let mut terminal = Terminal::new(TestBackend::new(200, 60)).expect("test backend");
+    c.bench_function("render frame with 10k files and 50k line diff", |b| {
+        b.iter(|| {
+            terminal
+                .draw(|f| render_ui(f, &mut app))
+                .expect("drawing to the test backend");
+        });
+    });
+
+    let _ = std::fs::remove_dir_all(root);
+}
+
+criterion_group!(benches, bench_status, bench_highlight, bench_render);
+criterion_main!(benches);
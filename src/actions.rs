@@ -0,0 +1,97 @@
+//! Operations the TUI and `jjkk exec` both run, so the settings apply to them the same way
+
+use anyhow::Result;
+
+use crate::{
+    config::Settings,
+    jj::{
+        diff::{
+            self,
+            HunkPick,
+        },
+        native_operations::Native,
+        operations as jj_ops,
+        results::PushResult,
+    },
+};
+
+/// Commit the working copy, or only the hunks in `pick`. With `advance_bookmarks_on_commit`
+/// the bookmarks of the parent move to the new commit
+pub fn commit(
+    native: &mut Native,
+    settings: &Settings,
+    message: &str,
+    author: Option<&str>,
+    pick: Option<&HunkPick>,
+) -> Result<String> {
+    // The parent is what a git branch would point at, jj leaves its bookmarks behind
+    let advancing = if settings.advance_bookmarks_on_commit {
+        jj_ops::get_bookmarks_at("@-").unwrap_or_default()
+    } else {
+        Vec::new()
+    };
+    let committed = match pick {
+        Some(pick) => diff::commit_hunks(pick, message, author)?,
+        None => native.commit(message, author)?,
+    };
+    Ok(advance_bookmarks(settings, committed, &advancing))
+}
+
+/// Move `names` to the commit just made, protected bookmarks stay where they are
+fn advance_bookmarks(settings: &Settings, mut message: String, names: &[String]) -> String {
+    let (protected, movable): (Vec<&String>, Vec<&String>) = names
+        .iter()
+        .partition(|name| settings.is_protected_bookmark(name));
+
+    let mut moved = Vec::new();
+    let mut failed = Vec::new();
+    for name in movable {
+        match jj_ops::move_bookmark(name, "@-") {
+            Ok(_) => moved.push(name.as_str()),
+            Err(_) => failed.push(name.as_str()),
+        }
+    }
+
+    if !moved.is_empty() {
+        message = format!("{message}, moved {} to it", moved.join(", "));
+    }
+    if !failed.is_empty() {
+        message = format!("{message}, failed to move {}", failed.join(", "));
+    }
+    if !protected.is_empty() {
+        let protected: Vec<&str> = protected.iter().map(|name| name.as_str()).collect();
+        message = format!("{message}, left protected {} behind", protected.join(", "));
+    }
+    message
+}
+
+/// A push previewed with `--dry-run`, waiting to be confirmed
+#[derive(Debug, Clone)]
+pub struct PushPlan {
+    /// `None` pushes the current change under a new bookmark
+    pub bookmark: Option<String>,
+    pub preview:  PushResult,
+}
+
+impl PushPlan {
+    /// Preview pushing `bookmark`, or the current bookmark when `None`
+    pub fn new(bookmark: Option<String>, settings: &Settings) -> Result<Self> {
+        // The current bookmark carries a `*` suffix when it diverged from the remote
+        let bookmark = match bookmark {
+            Some(name) => Some(name),
+            None => {
+                jj_ops::get_current_bookmark()?.map(|name| name.trim_end_matches('*').to_string())
+            }
+        };
+        let preview =
+            jj_ops::git_push_dry_run(bookmark.as_deref(), settings.default_remote.as_deref())?;
+        Ok(Self { bookmark, preview })
+    }
+
+    /// The bookmark to type to confirm the push, when it is protected
+    pub fn protected_bookmark(&self, settings: &Settings) -> Option<&str> {
+        self.bookmark
+            .as_deref()
+            .filter(|name| settings.is_protected_bookmark(name))
+    }
+}
@@ -1,41 +1,214 @@
-use std::time::Instant;
+use std::{
+    cell::{
+        Cell,
+        OnceCell,
+    },
+    collections::HashMap,
+    ffi::OsStr,
+    iter,
+    num::NonZeroU32,
+    ops::Range,
+    path::{
+        Path,
+        PathBuf,
+    },
+    sync::Arc,
+    time::{
+        Duration,
+        Instant,
+        SystemTime,
+    },
+};
 
 use anyhow::Result;
+use chrono::Local;
 use crossterm::event::{
     KeyCode,
     KeyEvent,
     KeyModifiers,
+    MouseButton,
+    MouseEvent,
+    MouseEventKind,
 };
 use ratatui::widgets::ListState;
 use syntect::{
     highlighting::ThemeSet,
     parsing::SyntaxSet,
 };
-use tui_textarea::TextArea;
+use tui_textarea::{
+    CursorMove,
+    TextArea,
+};
 
 use crate::{
+    actions::{
+        self,
+        PushPlan,
+    },
+    cli::Args,
+    clipboard,
     config::{
         Settings,
         Theme,
+        syntax,
     },
+    forge,
     jj::{
+        askpass::{
+            self,
+            AuthMethod,
+            Credentials,
+        },
+        background::BackgroundTask,
+        command::{
+            self,
+            fileset_or_glob,
+            revset_symbol,
+        },
+        diff::{
+            self,
+            DiffAnchors,
+            FilePatch,
+            HunkLocation,
+            HunkPick,
+        },
+        interop::{
+            self,
+            GitInteropStatus,
+        },
+        journal::{
+            self,
+            JournalEntry,
+        },
         log::{
             self,
             CommitInfo,
+            LogFilter,
         },
         native_operations::Native,
+        notes::{
+            Note,
+            Notes,
+        },
+        op_diff::{
+            self,
+            OperationDiff,
+        },
         operations::{
             self as jj_ops,
             BookmarkInfo,
+            BookmarkTarget,
+            ConflictSide,
+            RemoteInfo,
+        },
+        passthrough,
+        recent::{
+            RecentContext,
+            RecentContexts,
         },
         repo::{
+            ChangeType,
             FileStatus,
             JjRepo,
         },
-        status,
+        results::{
+            PushAction,
+            PushResult,
+        },
+        runner,
+        session::{
+            self,
+            Session,
+        },
+        status::{
+            self,
+            StatusLoader,
+            StatusUpdate,
+        },
+        version::{
+            self,
+            MINIMUM_SUPPORTED,
+        },
+    },
+    launcher,
+    notifications::{
+        NotificationLevel,
+        NotificationLog,
+        format_duration,
+    },
+    ui::{
+        text::{
+            single_line,
+            wrapped_rows,
+        },
+        widgets::{
+            diff_view::{
+                self,
+                DiffHighlighter,
+                WordDiffLine,
+            },
+            form::{
+                self,
+                Form,
+                FormEvent,
+                FormField,
+            },
+            fuzzy_finder::{
+                FinderEvent,
+                FuzzyFinder,
+            },
+            revision_picker::{
+                CandidateKind,
+                PickerEvent,
+                RevisionCandidate,
+                RevisionPicker,
+            },
+            status_bar::clock_minute,
+        },
     },
 };
 
+/// Conflicted commits an operation added that are listed to jump to
+const MAX_LISTED_CONFLICTS: usize = 100;
+
+/// Largest paste inserted into a popup, anything longer is refused
+const MAX_PASTE_BYTES: usize = 64 * 1024;
+
+/// Commits in the mini log when it is toggled on without a configured size
+const DEFAULT_MINI_LOG_LINES: usize = 5;
+
+/// With more changed files than this, loading the status computes no diff until a file is
+/// selected
+const MAX_INITIAL_DIFF_FILES: usize = 1000;
+
+/// Most commits loaded into the log while looking for the revision to go to
+const MAX_GOTO_LOG_COMMITS: usize = 10_000;
+
+/// How much of the ui is shown around the current pane
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayoutMode {
+    Normal,
+    /// Only the current pane, without tab bar and status bar
+    Zen,
+}
+
+/// Things that need the terminal given back to the shell for a while
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TerminalAction {
+    /// Stop like a shell job (Ctrl+Z), until resumed with `fg`
+    Suspend,
+    /// Run a shell command and show its output
+    Shell { command: String },
+    /// Resolve the conflicts of `path` in a merge tool, jj's `ui.merge-editor` without `tool`
+    Resolve { path: String, tool: Option<String> },
+    /// Edit `path` at `line`, in the configured `editor` or `$EDITOR`
+    Edit {
+        path:   PathBuf,
+        line:   usize,
+        editor: Option<String>,
+    },
+}
+
 /// Each tab of the ui that can be selected
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Tab {
@@ -45,6 +218,8 @@ pub enum Tab {
     Bookmarks,
     /// Log tab
     Log,
+    /// Git remotes tab
+    Remotes,
 }
 
 impl Tab {
@@ -52,32 +227,63 @@ impl Tab {
         match self {
             Self::WorkingCopy => Self::Bookmarks,
             Self::Bookmarks => Self::Log,
-            Self::Log => Self::WorkingCopy,
+            Self::Log => Self::Remotes,
+            Self::Remotes => Self::WorkingCopy,
         }
     }
 
     pub const fn prev(self) -> Self {
         match self {
-            Self::WorkingCopy => Self::Log,
+            Self::WorkingCopy => Self::Remotes,
             Self::Bookmarks => Self::WorkingCopy,
             Self::Log => Self::Bookmarks,
+            Self::Remotes => Self::Log,
         }
     }
 }
 
+/// What Enter does in the list of locations in the change
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HunkAction {
+    /// Open the editor at the location, the list stays open to go on with the next one. The
+    /// list shows every changed hunk
+    Edit,
+    /// Close the list and scroll the diff to the location. The list shows the leftover markers
+    ShowInDiff,
+}
+
 #[derive(Debug, Clone)]
 pub enum PopupState {
     None,
     Input {
-        title:    String,
-        textarea: Box<TextArea<'static>>,
-        callback: PopupCallback,
+        title:         String,
+        textarea:      Box<TextArea<'static>>,
+        callback:      PopupCallback,
+        /// Entry of the input history currently shown, `None` while editing a new input
+        history_index: Option<usize>,
+        /// What was typed before browsing the history
+        draft:         String,
+        /// Extra information shown below the input, the first line is a heading
+        details:       Vec<String>,
     },
     BookmarkSelect {
-        content: String,
-        cursor_position: usize,
-        available_bookmarks: Vec<BookmarkInfo>,
-        selected_index: usize,
+        finder:  FuzzyFinder<BookmarkInfo>,
+        purpose: BookmarkPurpose,
+    },
+    /// Pick the file or directory the log is limited to
+    PathSelect {
+        finder: FuzzyFinder<String>,
+    },
+    /// Several inputs at once, the values are passed to `action` once all are valid
+    Form {
+        title:  String,
+        form:   Form,
+        action: FormAction,
+    },
+    /// Pick a revision, the validated revision is passed to the callback
+    RevisionPicker {
+        picker:   RevisionPicker,
+        callback: PopupCallback,
     },
     Error {
         message: String,
@@ -86,13 +292,242 @@ pub enum PopupState {
         message: String,
     },
     Help,
+    Notifications,
+    /// Commits an operation left conflicted, Enter jumps to the first conflict
+    Conflicts {
+        commits: Vec<CommitInfo>,
+    },
+    /// Actions that apply to the selected item, picking one presses its key
+    ContextMenu {
+        title:    String,
+        items:    Vec<MenuItem>,
+        selected: usize,
+        target:   MenuTarget,
+    },
+    /// Pick which target of a conflicted bookmark wins
+    BookmarkConflict {
+        name:     String,
+        targets:  Vec<BookmarkTarget>,
+        selected: usize,
+    },
+    /// Locations in the change as a quickfix list, like every changed hunk or the leftover
+    /// markers. Enter does `action` with the selected one
+    Hunks {
+        /// What is listed, after the number of locations
+        title:    String,
+        hunks:    Vec<HunkLocation>,
+        selected: usize,
+        action:   HunkAction,
+    },
+    /// Files and hunks of the change to commit some of them, the rest stays in the working copy.
+    /// `picked` has an entry per hunk of each file, or one for files only committed as a whole
+    CommitHunks {
+        files:    Vec<FilePatch>,
+        picked:   Vec<Vec<bool>>,
+        /// Row of `diff::pick_rows`
+        selected: usize,
+    },
+    /// Review notes on the working copy change, Enter shows the file of the selected one
+    Notes {
+        selected: usize,
+    },
+    /// Recently checked out bookmarks and edited changes, Enter switches back to one
+    Recent {
+        selected: usize,
+    },
+    /// Built-in themes, the selected one is applied right away and saved on Enter
+    ThemePicker {
+        selected: usize,
+        /// Theme before the picker opened, restored on Esc
+        original: String,
+    },
+    /// Changes jjkk made to the repo, newest first, Enter restores the repo to before one
+    Journal {
+        entries:  Vec<JournalEntry>,
+        selected: usize,
+    },
+    /// What the operation `steps` operations before the current one changed
+    OperationDiff {
+        steps: usize,
+        diff:  OperationDiff,
+    },
+    /// Output of a command typed at the `:` prompt
+    CommandOutput {
+        title:   String,
+        output:  String,
+        success: bool,
+    },
+    /// Ask before running an action, showing what it is going to do
+    Confirm {
+        title:          String,
+        lines:          Vec<String>,
+        action:         ConfirmAction,
+        /// Text that has to be typed before confirming, used for protected bookmarks
+        required_input: Option<String>,
+        typed:          String,
+    },
 }
 
+/// Entry of the context menu, `key` is the binding it runs
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MenuItem {
+    pub key:   KeyCode,
+    pub label: &'static str,
+}
+
+/// What picking an entry of a menu runs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MenuTarget {
+    /// The key of the entry, as if it was pressed on the current tab
+    Tab,
+    /// Follow-up of pushing `bookmark`, `web_url` is the page of the remote repository
+    PushedBookmark {
+        bookmark: String,
+        web_url:  Option<String>,
+    },
+}
+
+impl MenuItem {
+    const fn new(key: char, label: &'static str) -> Self {
+        Self {
+            key: KeyCode::Char(key),
+            label,
+        }
+    }
+}
+
+/// Actions that are only run after the user confirmed their preview
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfirmAction {
+    Rebase {
+        destination: String,
+    },
+    Push {
+        bookmark: Option<String>,
+    },
+    MoveBookmark {
+        name:     String,
+        revision: String,
+    },
+    CheckoutBookmark {
+        name: String,
+    },
+    DeleteBookmarks {
+        names: Vec<String>,
+    },
+    PushDeleted,
+    RemoveRemote {
+        name: String,
+    },
+    AbandonChanges {
+        change_ids: Vec<String>,
+    },
+    RebaseChanges {
+        change_ids:  Vec<String>,
+        destination: String,
+    },
+    PushBookmarks {
+        names: Vec<String>,
+    },
+    ResetAuthor {
+        change_id: String,
+    },
+    TakeConflictSide {
+        path: String,
+        side: ConflictSide,
+    },
+    RestoreOperation {
+        operation: String,
+    },
+    /// Quit while a push is still running
+    Quit,
+}
+
+/// What the bookmark picked in the bookmark popup is used for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BookmarkPurpose {
+    /// Create the bookmark at or move it to this revision
+    Set { revision: String },
+    /// Fetch only this bookmark from the remote
+    Fetch,
+}
+
+/// Operations talking to a remote, retried once credentials were entered
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RemoteOperation {
+    Fetch {
+        bookmark: Option<String>,
+        depth:    Option<NonZeroU32>,
+    },
+    Push(PushTarget),
+}
+
+/// What a push sends to the remote
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PushTarget {
+    /// A bookmark, or the current change when `None`
+    Bookmark(Option<String>),
+    Bookmarks(Vec<String>),
+    /// The deletion of locally deleted bookmarks
+    Deleted,
+}
+
+impl PushTarget {
+    const fn loading_message(&self) -> &'static str {
+        match self {
+            Self::Bookmark(_) | Self::Bookmarks(_) => "Pushing to remote",
+            Self::Deleted => "Pushing deleted bookmarks",
+        }
+    }
+
+    fn push(&self, remote: Option<&str>) -> Result<PushResult> {
+        match self {
+            Self::Bookmark(bookmark) => jj_ops::git_push(bookmark.as_deref(), remote),
+            Self::Bookmarks(names) => jj_ops::git_push_bookmarks(names, remote),
+            Self::Deleted => jj_ops::git_push_deleted(remote, false),
+        }
+    }
+}
+
+/// What a submitted form does with its values
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FormAction {
+    AddRemote,
+    /// Rename and change the url of the remote that had this name and url
+    EditRemote {
+        name: String,
+        url:  String,
+    },
+    /// Note on `path` of the working copy change, the line is the first field
+    AddNote {
+        path: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum PopupCallback {
     Describe,
     Commit,
     Rebase,
+    FetchDepth,
+    Username,
+    /// Password or passphrase, masked and never kept in the input history
+    Secret,
+    WritePatch,
+    ApplyPatch,
+    ShellCommand,
+    JjCommand,
+    Goto,
+    /// Destination for rebasing the commits selected in the log
+    RebaseSelected,
+    /// `Name <email>` to describe and commit as
+    Author,
+    /// `since..until` range the log is limited to
+    LogDates,
+    /// Fileset or glob the Working Copy file list is limited to
+    StatusFilter,
+    /// Revision the bookmark popup below creates or moves the bookmark to
+    BookmarkRevision,
 }
 
 pub struct App {
@@ -101,27 +536,94 @@ pub struct App {
     pub settings: Settings,
     pub theme: Theme,
     pub should_quit: bool,
+    /// The popup on top, the one that gets the keys
     pub popup_state: PopupState,
+    /// Popups below `popup_state` with their scroll offset, closing it returns to the last one
+    popup_stack: Vec<(PopupState, usize)>,
     pub status_message: Option<String>,
     pub status_message_timestamp: Option<Instant>,
     pub loading_message: Option<String>,
     pub loading_start: Option<Instant>,
+    /// Start of the jj operation currently running, used to report its duration
+    pub operation_start: Option<Instant>,
+    pub notifications: NotificationLog,
+    /// Submitted inputs of the text popups this session, oldest first
+    pub input_history: HashMap<PopupCallback, Vec<String>>,
+    /// Scroll offset of the text in read-only popups (help, errors, ...)
+    pub popup_scroll: usize,
+    /// Largest useful `popup_scroll`, written while rendering the popup
+    pub popup_scroll_max: Cell<usize>,
+    /// Largest useful `diff_scroll_offset`, written while rendering the diff
+    pub diff_scroll_max: Cell<usize>,
+    /// Columns long diff lines wrap at, written while rendering the diff
+    pub diff_wrap_width: Cell<usize>,
+    /// Minute of the day the status bar clock shows, written while rendering it
+    pub clock_minute: Cell<Option<u32>>,
     pub selected_file_index: usize,
     pub selected_bookmark_index: usize,
     pub selected_log_index: usize,
+    /// Commits loaded into the log, grows beyond `ui.log_commits_count` when going to older ones
+    log_limit: usize,
+    pub selected_remote_index: usize,
     pub diff_scroll_offset: usize,
+    /// Diff scroll offsets of files left while reviewing, by path
+    diff_scroll_offsets: HashMap<String, usize>,
+    /// Operation the remembered diff scroll offsets belong to
+    diff_scroll_operation: Option<String>,
+    /// File sections of the whole-change diff, `None` when showing the diff of the selected file
+    pub diff_anchors: Option<DiffAnchors>,
+    /// Whether log entries show a second line with more of the description
+    pub show_log_preview: bool,
+    pub layout_mode: LayoutMode,
+    /// Picked up by the main loop, which owns the terminal
+    terminal_action: Option<TerminalAction>,
+    /// Commits in the mini log of the Working Copy tab, 0 when it is hidden
+    pub mini_log_lines: usize,
+    /// Change id of the log entry marked as the other end of a commit range
+    pub marked_log_change: Option<String>,
+    /// Change ids picked with Space in the log for a batch action, in the order they were picked
+    pub selected_changes: Vec<String>,
+    /// Path and date range the log is limited to
+    pub log_filter: LogFilter,
+    /// Fileset the Working Copy files, their diff and restoring are limited to
+    pub status_filter: Option<String>,
+    /// `Name <email>` used instead of the jj user when describing and committing this session
+    pub commit_author: Option<String>,
+    /// Hunks the open commit popup commits, the whole change when `None`
+    commit_pick: Option<HunkPick>,
     /// Marked with underscore to indicate it's currently unused
     _scroll_offset: usize,
-    /// Marked with underscore to indicate it's currently unused
-    _repo: JjRepo,
+    repo: JjRepo,
     pub files: Vec<FileStatus>,
     pub current_diff: Option<String>,
+    /// Review notes on the changes of this workspace, shown in their diffs
+    pub notes: Notes,
+    /// Bookmarks and changes worked on before, to switch back to
+    pub recent: RecentContexts,
+    /// The working copy commit and its (first) parent, shown above the file list
+    pub working_copy: Option<CommitInfo>,
+    pub working_copy_parent: Option<CommitInfo>,
+    /// Local bookmark jj's `trunk()` resolves to, the default base for rebases and comparisons
+    pub trunk: Option<String>,
+    /// Commits the current stack is ahead of trunk, and trunk got ahead of the stack's base
+    pub trunk_distance: Option<(usize, usize)>,
 
-    pub native_ops: Native,
+    pub native_ops:    Native,
+    /// Streams the status of huge working copies into `files` while jj is still listing them
+    pub status_loader: StatusLoader,
+    /// The running push, what it pushed and how it went
+    push_task:         BackgroundTask<(PushTarget, Result<PushResult>)>,
+    /// Compares the refs of jj and git for the Remotes tab, that takes a few processes
+    interop_task:      BackgroundTask<Option<GitInteropStatus>>,
 
     // Performance optimization: cache syntax highlighting resources
-    pub syntax_set: SyntaxSet,
-    pub theme_set:  ThemeSet,
+    pub syntax_set:   Arc<SyntaxSet>,
+    pub theme_set:    ThemeSet,
+    /// Name of the theme in `theme_set` used to highlight diffs
+    pub syntax_theme: String,
+    pub highlighter:  DiffHighlighter,
+    /// How each line of the current diff is shown, empty when no prose file changed
+    pub word_diff:    Vec<WordDiffLine>,
 
     // Redraw optimization: only redraw when needed
     pub needs_redraw: bool,
@@ -130,93 +632,567 @@ pub struct App {
     pub file_list_state:     ListState,
     pub bookmark_list_state: ListState,
     pub log_list_state:      ListState,
+    pub remote_list_state:   ListState,
 
     // Performance optimization: cache external command results
-    pub bookmarks:   Vec<BookmarkInfo>,
+    pub bookmarks: Vec<BookmarkInfo>,
     pub log_commits: Vec<CommitInfo>,
+    pub remotes: Vec<RemoteInfo>,
+    pub interop: Option<GitInteropStatus>,
+    /// Remote bookmarks no local bookmark tracks, shown on the Bookmarks tab title
+    pub untracked_remote_bookmarks: usize,
+    /// Ancestors of @ that are on no remote yet, shown on the Log tab title
+    pub unpushed_commits: usize,
+
+    /// File selected while keys were still coming in, its diff is loaded once they stop
+    pending_diff: Option<usize>,
 
-    // Key event debouncing for smooth scrolling
-    pub last_key_event: Option<(KeyCode, Instant)>,
+    // Detect jj commands run outside of jjkk
+    /// Operation the cached data was loaded at
+    known_operation: Option<String>,
+    last_operation_check: Instant,
+    /// Operation created outside of jjkk since the data was loaded
+    pub external_operation: Option<String>,
+    /// Commit git's HEAD was moved to outside of jj, in colocated repos
+    pub git_head_drift: Option<String>,
+    /// Checked once, `None` when the repo is not colocated
+    colocated_git_dir: OnceCell<Option<PathBuf>>,
+    /// When git's HEAD last moved and the operation jj was at, as of the last check of
+    /// `git_head_drift`
+    git_head_checked: Option<(SystemTime, Option<String>)>,
+    /// When the terminal lost focus, `None` while it has focus. Background checks and the
+    /// spinner pause meanwhile
+    unfocused_since: Option<Instant>,
+    /// Lock on the workspace, `None` when the lock file can't be written
+    session: Option<Session>,
+
+    // Credential prompts for remotes that need authentication
+    /// Remote operation to retry once the credentials are entered
+    auth_retry:    Option<RemoteOperation>,
+    auth_username: Option<String>,
 }
 
 impl App {
-    pub fn new() -> Result<Self> {
+    pub fn new(args: &Args) -> Result<Self> {
         let settings = Settings::load()?;
-        let theme = Theme::catppuccin_mocha();
+        if let Some(jj_executable) = &settings.jj_executable {
+            command::set_executable(jj_executable.clone());
+        }
+        if let Some(seconds) = settings.command_timeout_secs {
+            runner::set_timeout(seconds);
+        }
+        if let Some(path) = &settings.command_log {
+            runner::set_log_file(path.clone());
+        }
         let repo = JjRepo::open(None)?;
+        journal::open(repo.workspace_root());
+        session::coordinate_transactions(repo.workspace_root());
+        let session = session::lock_session(repo.workspace_root()).ok();
+        Self::with_repo(args, settings, repo, Native::new(), session)
+    }
+
+    /// App on `repo`, leaving out the process wide setup `new` does
+    pub fn with_repo(
+        args: &Args,
+        settings: Settings,
+        repo: JjRepo,
+        mut native_ops: Native,
+        session: Option<Session>,
+    ) -> Result<Self> {
+        let theme = if args.no_color {
+            Theme::no_color()
+        } else {
+            Theme::from_name(&settings.theme.name).adapt_to(settings.theme.color_support)
+        };
+        // An unreadable file only costs its notes or recent list, kept in memory instead so
+        // the file is left as it is
+        let mut load_warnings = Vec::new();
+        let notes = Notes::load(repo.workspace_root()).unwrap_or_else(|e| {
+            load_warnings.push(format!("{e:#}\n\nNotes are only kept until jjkk quits"));
+            Notes::default()
+        });
+        let recent = RecentContexts::load(repo.workspace_root()).unwrap_or_else(|e| {
+            load_warnings.push(format!(
+                "{e:#}\n\nRecent bookmarks and changes are only kept until jjkk quits"
+            ));
+            RecentContexts::default()
+        });
+        let show_log_preview = settings.ui.log_description_preview;
+        let mini_log_lines = settings.ui.mini_log.unwrap_or(0);
+        let log_limit = settings.ui.log_commits_count;
+        let diff_anchors = settings.ui.whole_change_diff.then(DiffAnchors::default);
+        if let Some(remote) = &settings.default_remote {
+            native_ops.default_remote.clone_from(remote);
+        }
 
-        Ok(Self {
+        let mut app = Self {
             current_tab: Tab::WorkingCopy,
             previous_tab: Tab::WorkingCopy,
             settings,
             theme,
             should_quit: false,
             popup_state: PopupState::None,
+            popup_stack: Vec::new(),
             status_message: None,
             status_message_timestamp: None,
             loading_message: None,
             loading_start: None,
+            operation_start: None,
+            notifications: NotificationLog::default(),
+            input_history: HashMap::new(),
+            popup_scroll: 0,
+            popup_scroll_max: Cell::new(0),
+            clock_minute: Cell::new(None),
+            diff_scroll_max: Cell::new(0),
+            diff_wrap_width: Cell::new(0),
             selected_file_index: 0,
             selected_bookmark_index: 0,
             selected_log_index: 0,
+            log_limit,
+            selected_remote_index: 0,
             diff_scroll_offset: 0,
+            diff_scroll_offsets: HashMap::new(),
+            diff_scroll_operation: None,
+            diff_anchors,
+            marked_log_change: None,
+            selected_changes: Vec::new(),
+            log_filter: LogFilter::default(),
+            status_filter: None,
+            commit_author: None,
+            commit_pick: None,
+            mini_log_lines,
+            layout_mode: LayoutMode::Normal,
+            terminal_action: None,
+            show_log_preview,
             _scroll_offset: 0,
-            _repo: repo,
+            repo,
             files: Vec::new(),
             current_diff: None,
-            native_ops: Native::new(),
-            syntax_set: SyntaxSet::load_defaults_newlines(),
+            notes,
+            recent,
+            working_copy: None,
+            working_copy_parent: None,
+            trunk: None,
+            trunk_distance: None,
+            native_ops,
+            syntax_set: Arc::new(SyntaxSet::load_defaults_newlines()),
             theme_set: ThemeSet::load_defaults(),
+            syntax_theme: syntax::FALLBACK_SYNTAX_THEME.to_string(),
+            highlighter: DiffHighlighter::default(),
+            word_diff: Vec::new(),
+            status_loader: StatusLoader::default(),
+            push_task: BackgroundTask::default(),
+            interop_task: BackgroundTask::default(),
             needs_redraw: true,
             file_list_state: ListState::default(),
             bookmark_list_state: ListState::default(),
             log_list_state: ListState::default(),
+            remote_list_state: ListState::default(),
             bookmarks: Vec::new(),
             log_commits: Vec::new(),
-            last_key_event: None,
-        })
+            remotes: Vec::new(),
+            interop: None,
+            untracked_remote_bookmarks: 0,
+            unpushed_commits: 0,
+            pending_diff: None,
+            known_operation: None,
+            last_operation_check: Instant::now(),
+            git_head_drift: None,
+            colocated_git_dir: OnceCell::new(),
+            git_head_checked: None,
+            unfocused_since: None,
+            session,
+            external_operation: None,
+            auth_retry: None,
+            auth_username: None,
+        };
+        for warning in load_warnings {
+            app.show_warning(warning);
+        }
+        Ok(app)
+    }
+
+    /// Load the user's syntaxes and pick the syntax theme, warning about broken config
+    pub fn load_highlighting(&mut self) {
+        match syntax::load_syntax_set() {
+            Ok(Some(syntax_set)) => self.syntax_set = Arc::new(syntax_set),
+            Ok(None) => {}
+            Err(e) => self.show_warning(e.to_string()),
+        }
+        match syntax::resolve_syntax_theme(
+            self.settings.theme.syntax_theme.as_deref(),
+            &self.settings.theme.name,
+            &self.theme_set,
+        ) {
+            Ok(name) => self.syntax_theme = name,
+            Err(message) => self.show_warning(message),
+        }
+        self.start_highlighting();
+    }
+
+    /// Pick a theme, each one is shown on the whole ui while it is selected
+    fn show_theme_picker(&mut self) {
+        let selected = Theme::NAMES
+            .iter()
+            .position(|name| *name == self.theme.name)
+            .unwrap_or(0);
+        self.open_popup(PopupState::ThemePicker {
+            selected,
+            original: self.theme.name.clone(),
+        });
+    }
+
+    /// Switch to the theme called `name` for this session, with the syntax theme matching it
+    fn apply_theme(&mut self, name: &str) {
+        self.theme = Theme::from_name(name).adapt_to(self.settings.theme.color_support);
+        // A broken configured syntax theme was already reported on start
+        if let Ok(syntax_theme) = syntax::resolve_syntax_theme(
+            self.settings.theme.syntax_theme.as_deref(),
+            name,
+            &self.theme_set,
+        ) && syntax_theme != self.syntax_theme
+        {
+            self.syntax_theme = syntax_theme;
+            self.start_highlighting();
+        }
+    }
+
+    /// Highlight the current diff in the background, it shows plain until that is done
+    fn start_highlighting(&mut self) {
+        let path = self
+            .files
+            .get(self.selected_file_index)
+            .map(|file| file.path.clone());
+        match (&self.current_diff, path) {
+            (Some(diff), Some(path)) if self.should_highlight(diff) => self.highlighter.start(
+                diff.clone(),
+                path,
+                Arc::clone(&self.syntax_set),
+                self.theme_set.themes[&self.syntax_theme].clone(),
+            ),
+            _ => self.highlighter.clear(),
+        }
+    }
+
+    /// The whole-change diff mixes files, it is only colored by line type like huge diffs
+    fn should_highlight(&self, diff: &str) -> bool {
+        self.settings.ui.syntax_highlighting
+            && self.theme.uses_color()
+            && self.diff_anchors.is_none()
+            && diff.lines().count() <= self.settings.ui.highlight_max_lines
+    }
+
+    /// Redraw once the background highlighting is done
+    pub fn poll_highlighting(&mut self) {
+        if self.highlighter.poll() {
+            self.needs_redraw = true;
+        }
+    }
+
+    /// Detect the installed jj version and warn when it is too old or missing,
+    /// instead of failing later with confusing parse errors
+    pub fn check_jj_version(&mut self) {
+        match version::detect() {
+            Ok(version) if !version.is_supported() => {
+                self.show_warning(format!(
+                    "jj {version} is older than the minimum supported version \
+                     {MINIMUM_SUPPORTED}. Some features may not work, please upgrade jj."
+                ));
+            }
+            Ok(_) => {}
+            Err(e) => {
+                self.show_warning(format!("Could not detect the jj version: {e}"));
+            }
+        }
+    }
+
+    /// Warn when another jjkk already runs on this workspace
+    pub fn check_other_instance(&mut self) {
+        if let Some(Session::Shared { pid }) = &self.session {
+            let pid = pid.map(|pid| format!(" (pid {pid})")).unwrap_or_default();
+            self.show_warning(format!(
+                "Another jjkk is already running on this workspace{pid}. \
+                 Its changes are picked up here automatically, changes made at the same \
+                 time wait for each other."
+            ));
+        }
     }
 
     pub fn refresh_status(&mut self) -> Result<()> {
-        self.files = status::get_working_copy_status()?;
-        self.selected_file_index = self
-            .selected_file_index
-            .min(self.files.len().saturating_sub(1));
+        // Loaded right here, whatever the background load still finds is outdated
+        if self.status_loader.is_loading() {
+            self.status_loader.cancel();
+            self.clear_loading();
+        }
+        let selected = self
+            .files
+            .get(self.selected_file_index)
+            .map(|file| file.path.clone());
+        self.remember_diff_scroll();
+        // Diffs of another operation have changed, the offsets don't fit them anymore
+        let operation = jj_ops::get_operation_id().ok();
+        if operation != self.diff_scroll_operation {
+            self.diff_scroll_offsets.clear();
+            self.diff_scroll_operation = operation;
+        }
+
+        self.files = status::get_working_copy_status(self.status_filter.as_deref())?;
+        self.selected_file_index = relocate(
+            &self.files,
+            selected.as_deref(),
+            self.selected_file_index,
+            |file| &file.path,
+        );
         self.file_list_state.select(Some(self.selected_file_index));
-        self.diff_scroll_offset = 0;
+        // The whole-change diff stays where it is
+        if self.diff_anchors.is_none() {
+            self.restore_diff_scroll();
+        }
         self.update_diff()?;
+        self.refresh_working_copy_header();
         self.needs_redraw = true;
         Ok(())
     }
 
     pub fn refresh_bookmarks(&mut self) {
         if let Ok(bookmarks) = jj_ops::get_bookmarks() {
+            let selected = self
+                .bookmarks
+                .get(self.selected_bookmark_index)
+                .map(|bookmark| bookmark.name.clone());
             self.bookmarks = bookmarks;
-            self.selected_bookmark_index = self
-                .selected_bookmark_index
-                .min(self.bookmarks.len().saturating_sub(1));
+            self.selected_bookmark_index = relocate(
+                &self.bookmarks,
+                selected.as_deref(),
+                self.selected_bookmark_index,
+                |bookmark| &bookmark.name,
+            );
             self.bookmark_list_state
                 .select(Some(self.selected_bookmark_index));
             self.needs_redraw = true;
         }
+        // Follows `revset-aliases."trunk()"` and the default branch of the remote
+        self.trunk = jj_ops::get_trunk_bookmark().ok().flatten();
+        // A fetch moves trunk, so this is refreshed along with the bookmarks
+        self.trunk_distance = jj_ops::count_commits(jj_ops::AHEAD_OF_TRUNK_REVSET)
+            .and_then(|ahead| Ok((ahead, jj_ops::count_commits(jj_ops::BEHIND_TRUNK_REVSET)?)))
+            .ok();
+        if let Ok(untracked) = jj_ops::get_untracked_remote_bookmarks() {
+            self.untracked_remote_bookmarks = untracked.len();
+        }
+    }
+
+    /// Revset of `filter` within the commits the log shows unfiltered
+    fn filter_revset(filter: &LogFilter) -> Result<Option<String>> {
+        if filter.is_empty() {
+            return Ok(None);
+        }
+        Ok(filter.revset(&jj_ops::get_log_revset()?))
     }
 
     pub fn refresh_log(&mut self) {
-        let limit = self.settings.ui.log_commits_count;
-        if let Ok(commits) = log::get_log(limit) {
+        let commits = Self::filter_revset(&self.log_filter)
+            .and_then(|revset| log::get_log_for_revset(revset.as_deref(), self.log_limit));
+        if let Ok(commits) = commits {
+            let selected = self
+                .log_commits
+                .get(self.selected_log_index)
+                .map(|commit| commit.change_id.clone());
             self.log_commits = commits;
-            self.selected_log_index = self
-                .selected_log_index
-                .min(self.log_commits.len().saturating_sub(1));
+            // Abandoned or hidden commits can't be part of a batch action anymore
+            let log_commits = &self.log_commits;
+            self.selected_changes
+                .retain(|id| log_commits.iter().any(|commit| commit.change_id == *id));
+            self.selected_log_index = relocate(
+                &self.log_commits,
+                selected.as_deref(),
+                self.selected_log_index,
+                |commit| &commit.change_id,
+            );
             self.log_list_state.select(Some(self.selected_log_index));
             self.needs_redraw = true;
         }
+        // Counted apart from the log, which may be filtered or not reach that far back
+        if let Ok(count) = jj_ops::count_commits(jj_ops::UNPUSHED_REVSET) {
+            self.unpushed_commits = count;
+        }
+    }
+
+    pub fn refresh_remotes(&mut self) {
+        match jj_ops::get_remotes() {
+            Ok(remotes) => {
+                let selected = self
+                    .remotes
+                    .get(self.selected_remote_index)
+                    .map(|remote| remote.name.clone());
+                self.remotes = remotes;
+                self.selected_remote_index = relocate(
+                    &self.remotes,
+                    selected.as_deref(),
+                    self.selected_remote_index,
+                    |remote| &remote.name,
+                );
+                self.remote_list_state
+                    .select(Some(self.selected_remote_index));
+                self.needs_redraw = true;
+            }
+            Err(e) => {
+                self.show_error(format!("Failed to list remotes: {e}"));
+                return;
+            }
+        }
+        // Repos without a git backend have no interop status to show
+        self.interop_task
+            .start(|| interop::get_interop_status().ok());
+    }
+
+    /// Show the interop status once it was compared in the background
+    pub fn poll_interop(&mut self) {
+        if let Some((interop, _)) = self.interop_task.poll() {
+            self.interop = interop;
+            self.needs_redraw = true;
+        }
+    }
+
+    /// Run `jj git import` or `jj git export` to reconcile jj with the git refs
+    fn sync_git_refs(&mut self, import: bool) {
+        let (result, done) = if import {
+            (interop::git_import(), "Imported git refs")
+        } else {
+            (interop::git_export(), "Exported bookmarks to git")
+        };
+        match result {
+            Ok(_) => {
+                self.set_status_message(done.to_string());
+                self.refresh_remotes();
+                // Commits and bookmarks made with git show up everywhere else too, an export
+                // is an operation of its own
+                if import {
+                    self.git_head_drift = None;
+                }
+                if let Err(e) = self.refresh_all() {
+                    self.show_error(format!("Failed to refresh: {e}"));
+                }
+            }
+            Err(e) => self.show_error(e.to_string()),
+        }
+    }
+
+    /// Like `refresh_all`, but the status is loaded in the background and the list fills up
+    /// as jj lists the files. Bookmarks and the log follow once it is done
+    pub fn start_refresh_all(&mut self) {
+        self.status_loader.start(self.status_filter.clone());
+        self.show_loading("Loading the working copy".to_string());
+    }
+
+    /// Take in the files loaded in the background so far
+    pub fn poll_status(&mut self) {
+        match self.status_loader.poll() {
+            None => {}
+            Some(StatusUpdate::Partial(files)) => {
+                self.loading_message = Some(format!(
+                    "Loading the working copy, {} files so far",
+                    files.len()
+                ));
+                self.files = files;
+                self.selected_file_index = self
+                    .selected_file_index
+                    .min(self.files.len().saturating_sub(1));
+                self.file_list_state.select(Some(self.selected_file_index));
+                self.needs_redraw = true;
+            }
+            Some(StatusUpdate::Done(result)) => {
+                self.clear_loading();
+                match result {
+                    Ok(files) => self.finish_status_load(files),
+                    Err(e) => self.show_error(format!("Failed to load the working copy: {e}")),
+                }
+                self.refresh_bookmarks();
+                self.refresh_log();
+                self.known_operation = jj_ops::get_operation_id().ok();
+                self.external_operation = None;
+            }
+        }
+    }
+
+    /// The header is informational, failing to load it never fails a refresh
+    fn refresh_working_copy_header(&mut self) {
+        let previous = self.working_copy.take();
+        self.working_copy = log::get_log_for_revset(Some("@"), 1)
+            .ok()
+            .and_then(|commits| commits.into_iter().next());
+        // Remember the change that was left. Those without a description are mostly scratch
+        // changes jj abandons once they are left empty
+        if let (Some(previous), Some(current)) = (previous, &self.working_copy)
+            && previous.change_id != current.change_id
+            && !previous.description.is_empty()
+        {
+            self.remember(RecentContext::Change {
+                change_id:   previous.change_id,
+                description: previous.description,
+            });
+        }
+        self.working_copy_parent = log::get_log_for_revset(Some("@-"), 1)
+            .ok()
+            .and_then(|commits| commits.into_iter().next());
+    }
+
+    fn finish_status_load(&mut self, files: Vec<FileStatus>) {
+        let selected = self
+            .files
+            .get(self.selected_file_index)
+            .map(|file| file.path.clone());
+        self.files = files;
+        self.selected_file_index = relocate(
+            &self.files,
+            selected.as_deref(),
+            self.selected_file_index,
+            |file| &file.path,
+        );
+        self.file_list_state.select(Some(self.selected_file_index));
+
+        // Diffing thousands of files up front stalls the start, wait for a file to be picked
+        if self.files.len() > MAX_INITIAL_DIFF_FILES {
+            let whole_change = self.diff_anchors.take().is_some();
+            self.set_status_message(format!(
+                "{} changed files, {}select a file to show its diff",
+                self.files.len(),
+                if whole_change {
+                    "too many for the whole-change diff, "
+                } else {
+                    ""
+                }
+            ));
+        } else if let Err(e) = self.update_diff() {
+            self.show_error(format!("Failed to load the diff: {e}"));
+        }
+        self.refresh_working_copy_header();
+        self.needs_redraw = true;
     }
 
     pub fn refresh_all(&mut self) -> Result<()> {
         self.refresh_status()?;
         self.refresh_bookmarks();
         self.refresh_log();
+        // Everything is up to date with the latest operation again
+        self.known_operation = jj_ops::get_operation_id().ok();
+        self.external_operation = None;
+        Ok(())
+    }
+
+    /// Reload only the data shown on the current tab
+    pub fn refresh_current_tab(&mut self) -> Result<()> {
+        match self.current_tab {
+            Tab::WorkingCopy => {
+                self.refresh_status()?;
+                if self.mini_log_lines > 0 {
+                    self.refresh_log();
+                }
+            }
+            Tab::Bookmarks => self.refresh_bookmarks(),
+            Tab::Log => self.refresh_log(),
+            Tab::Remotes => self.refresh_remotes(),
+        }
         Ok(())
     }
 
@@ -229,6 +1205,7 @@ impl App {
             match new_tab {
                 Tab::Bookmarks => self.refresh_bookmarks(),
                 Tab::Log => self.refresh_log(),
+                Tab::Remotes => self.refresh_remotes(),
                 Tab::WorkingCopy => {
                     // Working copy is already refreshed via refresh_status
                 }
@@ -236,196 +1213,1740 @@ impl App {
         }
     }
 
-    /// Check if we should process a navigation key event (for debouncing)
-    /// Returns true if enough time has passed since the last similar key event
-    fn should_process_navigation_key(&mut self, key_code: KeyCode) -> bool {
-        const DEBOUNCE_MS: u128 = 50; // 50ms debounce threshold
+    /// Select the file at `index`, remembering where the diff of the previous file was scrolled to.
+    /// Its diff is loaded by `update_pending_diff`, so holding j/k doesn't run jj for every file
+    fn select_file(&mut self, index: usize) {
+        self.remember_diff_scroll();
+        self.selected_file_index = index;
+        self.file_list_state.select(Some(index));
+        if self.diff_anchors.is_some() {
+            // The whole-change diff is already loaded, jump to the file's section
+            self.scroll_to_selected_file();
+            return;
+        }
+        self.restore_diff_scroll();
+        self.pending_diff = Some(index);
+    }
 
-        let now = Instant::now();
+    /// Load the diff of the file selected last, called once no more keys are waiting
+    pub fn update_pending_diff(&mut self) -> Result<()> {
+        if self.pending_diff.is_some() {
+            self.update_diff()?;
+            self.needs_redraw = true;
+        }
+        Ok(())
+    }
 
-        if let Some((last_key, last_time)) = self.last_key_event {
-            // If it's the same key and not enough time has passed, skip it
-            if last_key == key_code && last_time.elapsed().as_millis() < DEBOUNCE_MS {
-                return false;
-            }
+    /// Switch between the diff of the selected file and the diff of the whole change
+    fn toggle_whole_change_diff(&mut self) -> Result<()> {
+        if self.diff_anchors.take().is_some() {
+            self.restore_diff_scroll();
+            return self.update_diff();
         }
+        self.remember_diff_scroll();
+        self.diff_anchors = Some(DiffAnchors::default());
+        self.update_diff()?;
+        self.scroll_to_selected_file();
+        Ok(())
+    }
 
-        // Update last key event
-        self.last_key_event = Some((key_code, now));
-        true
+    fn scroll_to_selected_file(&mut self) {
+        self.diff_scroll_offset = self
+            .files
+            .get(self.selected_file_index)
+            .zip(self.diff_anchors.as_ref())
+            .and_then(|(file, anchors)| anchors.line_of(&file.path))
+            .map_or(0, |line| self.diff_row_of_line(line));
     }
 
-    pub fn update_diff(&mut self) -> Result<()> {
-        if let Some(file) = self.files.get(self.selected_file_index) {
-            self.current_diff = Some(jj_ops::get_file_diff(&file.path)?);
+    /// Rows the lines of the current diff take up once wrapped, the scroll offset counts rows
+    fn diff_line_rows(&self) -> impl Iterator<Item = usize> {
+        let columns = self.diff_wrap_width.get();
+        // Every note below a line takes a row of its own
+        let mut note_rows: HashMap<usize, usize> = HashMap::new();
+        for (index, _) in self.diff_notes() {
+            *note_rows.entry(index).or_default() += 1;
+        }
+        let word_diff = &self.word_diff;
+        self.current_diff
+            .as_deref()
+            .unwrap_or_default()
+            .lines()
+            .enumerate()
+            .map(move |(i, line)| {
+                let rows = match word_diff.get(i) {
+                    Some(WordDiffLine::Folded) => 0,
+                    // Nothing was drawn yet to know the width
+                    _ if columns == 0 => 1,
+                    // Drawn after the `~` marking it
+                    Some(WordDiffLine::Words(words)) => wrapped_rows(
+                        iter::once("~").chain(words.iter().map(|(_, text)| text.as_str())),
+                        columns,
+                    ),
+                    _ => wrapped_rows([line], columns),
+                };
+                rows + note_rows.get(&i).copied().unwrap_or(0)
+            })
+    }
+
+    /// First row of `line` in the wrapped diff
+    fn diff_row_of_line(&self, line: usize) -> usize {
+        self.diff_line_rows().take(line).sum()
+    }
+
+    /// Line of the diff shown at `row` once wrapped
+    fn diff_line_at_row(&self, row: usize) -> usize {
+        let mut start = 0;
+        self.diff_line_rows()
+            .take_while(|rows| {
+                start += rows;
+                start <= row
+            })
+            .count()
+    }
+
+    /// Mark the selected log entry as one end of a commit range, or clear the mark
+    fn toggle_log_mark(&mut self) {
+        let Some(change_id) = self
+            .log_commits
+            .get(self.selected_log_index)
+            .map(|commit| commit.change_id.clone())
+        else {
+            return;
+        };
+        if self.marked_log_change.as_ref() == Some(&change_id) {
+            self.marked_log_change = None;
+            self.set_status_message("Cleared the mark".to_string());
         } else {
-            self.current_diff = None;
+            self.set_status_message(format!("Marked {change_id}, patches span up to it"));
+            self.marked_log_change = Some(change_id);
         }
-        Ok(())
     }
 
-    pub fn handle_key_event(&mut self, key: KeyEvent) -> Result<()> {
-        // Handle popup input first with tui-textarea
-        if let PopupState::Input {
-            ref mut textarea,
-            callback,
-            ..
-        } = self.popup_state
-        {
-            match key.code {
-                KeyCode::Esc => {
-                    self.popup_state = PopupState::None;
+    /// Open the menu of actions for the item selected on the current tab
+    pub fn show_context_menu(&mut self) {
+        let (title, items) = match self.current_tab {
+            Tab::WorkingCopy => {
+                let Some(file) = self.files.get(self.selected_file_index) else {
+                    return;
+                };
+                let mut items = Vec::new();
+                if file.status == ChangeType::Conflicted {
+                    items.extend([
+                        MenuItem::new('M', "Resolve in the merge tool"),
+                        MenuItem::new('<', "Take ours for the whole file"),
+                        MenuItem::new('>', "Take theirs for the whole file"),
+                    ]);
                 }
-                KeyCode::Enter if !key.modifiers.contains(KeyModifiers::ALT) => {
-                    // Regular Enter (no modifiers) submits the form
-                    let text = textarea.lines().join("\n");
-                    let cb = callback;
-                    self.popup_state = PopupState::None;
-                    self.execute_popup_callback(cb, &text)?;
+                if matches!(file.status, ChangeType::TooLarge { .. }) {
+                    items.push(MenuItem::new('T', "Track this file anyway"));
+                    items.push(MenuItem::new('G', "Add this file to .gitignore"));
                 }
-                _ => {
-                    // Convert KeyEvent to tui_textarea::Input
-                    // tui-textarea expects crossterm::event::Event, not just KeyEvent
-                    use crossterm::event::Event;
-                    textarea.input(Event::Key(key));
+                items.extend([
+                    MenuItem::new('v', "Toggle the whole-change diff"),
+                    MenuItem::new('d', "Describe the change"),
+                    MenuItem::new('c', "Commit"),
+                    MenuItem::new('C', "Commit only some files and hunks"),
+                    MenuItem::new('n', "New change"),
+                    MenuItem::new('b', "Set a bookmark here"),
+                    MenuItem::new('r', "Rebase"),
+                    MenuItem::new('A', "Apply a patch"),
+                    MenuItem::new('U', "Reset the author to you"),
+                    MenuItem::new('/', "Filter the files"),
+                    MenuItem::new('X', "Restore the working copy"),
+                    MenuItem::new('o', "Open the folder in the file manager"),
+                    MenuItem::new('i', "Review notes"),
+                    MenuItem::new('S', "Find TODO/FIXME/XXX in the added lines"),
+                ]);
+                (file.path.clone(), items)
+            }
+            Tab::Bookmarks => {
+                let Some(bookmark) = self.bookmarks.get(self.selected_bookmark_index) else {
+                    return;
+                };
+                let enter = if bookmark.conflicted {
+                    "Resolve the conflict"
+                } else {
+                    "Check out"
+                };
+                let items = vec![
+                    MenuItem {
+                        key:   KeyCode::Enter,
+                        label: enter,
+                    },
+                    MenuItem::new('D', "Delete"),
+                    MenuItem::new('C', "Clean up merged bookmarks"),
+                ];
+                (bookmark.name.clone(), items)
+            }
+            Tab::Log => {
+                let Some(commit) = self.log_commits.get(self.selected_log_index) else {
+                    return;
+                };
+                let selected = self.selected_changes.contains(&commit.change_id);
+                let mut items = vec![
+                    MenuItem::new(
+                        ' ',
+                        if selected {
+                            "Unselect"
+                        } else {
+                            "Select for a batch action"
+                        },
+                    ),
+                    MenuItem::new('M', "Mark as the other end of a range"),
+                    MenuItem::new('y', "Copy the patch"),
+                    MenuItem::new('w', "Write the patch to a file"),
+                    MenuItem::new('U', "Reset the author to you"),
+                    MenuItem::new('P', "Limit the log to a path"),
+                    MenuItem::new('W', "Limit the log to a date range"),
+                ];
+                if !self.selected_changes.is_empty() {
+                    items.extend([
+                        MenuItem::new('x', "Abandon the selected commits"),
+                        MenuItem::new('r', "Rebase the selected commits"),
+                        MenuItem::new('p', "Push bookmarks of the selected commits"),
+                    ]);
+                }
+                (commit.change_id.clone(), items)
+            }
+            Tab::Remotes => {
+                let Some(remote) = self.remotes.get(self.selected_remote_index) else {
+                    return;
+                };
+                let items = vec![
+                    MenuItem {
+                        key:   KeyCode::Enter,
+                        label: "Make the default remote",
+                    },
+                    MenuItem::new('e', "Edit the name and url"),
+                    MenuItem::new('D', "Remove"),
+                    MenuItem::new('a', "Add a remote"),
+                ];
+                (remote.name.clone(), items)
+            }
+        };
+
+        self.open_popup(PopupState::ContextMenu {
+            title,
+            items,
+            selected: 0,
+            target: MenuTarget::Tab,
+        });
+    }
+
+    /// Insert pasted text into the input of the popup at once, typing it key by key redraws
+    /// the popup for every character. Pastes over `MAX_PASTE_BYTES` are refused
+    pub fn handle_paste(&mut self, text: &str) {
+        let accepts_text = matches!(
+            self.popup_state,
+            PopupState::Input { .. }
+                | PopupState::Form { .. }
+                | PopupState::RevisionPicker { .. }
+                | PopupState::BookmarkSelect { .. }
+                | PopupState::PathSelect { .. }
+        );
+        if !accepts_text {
+            return;
+        }
+        if text.len() > MAX_PASTE_BYTES {
+            // A file pasted by accident, not a commit message
+            self.set_status_message(format!(
+                "Not pasted: {} KiB is over the {} KiB limit",
+                text.len() / 1024,
+                MAX_PASTE_BYTES / 1024
+            ));
+            return;
+        }
+        match &mut self.popup_state {
+            // Only descriptions have more than one line
+            PopupState::Input {
+                textarea,
+                callback: PopupCallback::Describe | PopupCallback::Commit,
+                ..
+            } => {
+                textarea.insert_str(text);
+            }
+            PopupState::Input { textarea, .. } => {
+                textarea.insert_str(single_line(text));
+            }
+            PopupState::Form { form, .. } => form.insert_str(text),
+            PopupState::RevisionPicker { picker, .. } => picker.insert_str(text),
+            PopupState::BookmarkSelect { finder, .. } => finder.insert_str(text),
+            PopupState::PathSelect { finder } => finder.insert_str(text),
+            _ => {}
+        }
+    }
+
+    /// Right click opens the context menu, like `m` it waits for a running push
+    pub fn handle_mouse_event(&mut self, mouse: MouseEvent) {
+        if mouse.kind != MouseEventKind::Down(MouseButton::Right)
+            || !matches!(self.popup_state, PopupState::None)
+        {
+            return;
+        }
+        if self.push_task.is_running() {
+            self.set_status_message("Wait for the push to finish first".to_string());
+            return;
+        }
+        self.show_context_menu();
+    }
+
+    fn toggle_selected_change(&mut self) {
+        let Some(change_id) = self
+            .log_commits
+            .get(self.selected_log_index)
+            .map(|commit| commit.change_id.clone())
+        else {
+            return;
+        };
+        if let Some(index) = self.selected_changes.iter().position(|id| *id == change_id) {
+            self.selected_changes.remove(index);
+        } else {
+            self.selected_changes.push(change_id);
+        }
+        // Selecting usually goes down the log
+        if self.selected_log_index + 1 < self.log_commits.len() {
+            self.selected_log_index += 1;
+            self.log_list_state.select(Some(self.selected_log_index));
+        }
+    }
+
+    /// "change id description" of each commit selected for a batch action
+    fn selected_change_lines(&self) -> Vec<String> {
+        self.selected_changes
+            .iter()
+            .map(|change_id| {
+                self.log_commits
+                    .iter()
+                    .find(|commit| commit.change_id == *change_id)
+                    .map_or_else(
+                        || change_id.clone(),
+                        |commit| format!("{change_id} {}", commit.description),
+                    )
+            })
+            .collect()
+    }
+
+    fn confirm_abandon_selected(&mut self) {
+        if self.selected_changes.is_empty() {
+            self.show_warning("Select commits with Space first".to_string());
+            return;
+        }
+        let mut lines = vec![format!(
+            "{} commit(s) will be abandoned:",
+            self.selected_changes.len()
+        )];
+        lines.extend(self.selected_change_lines());
+        self.show_confirm(
+            "Confirm abandon",
+            lines,
+            ConfirmAction::AbandonChanges {
+                change_ids: self.selected_changes.clone(),
+            },
+        );
+    }
+
+    /// Ask before making the current user the author of the change on the current tab
+    fn confirm_reset_author(&mut self) {
+        let commit = match self.current_tab {
+            Tab::WorkingCopy => self.working_copy.as_ref(),
+            Tab::Log => self.log_commits.get(self.selected_log_index),
+            _ => None,
+        };
+        let Some(commit) = commit else {
+            return;
+        };
+        let change_id = commit.change_id.clone();
+        let lines = vec![
+            format!("{change_id} {}", commit.description),
+            format!("Author: {}", commit.author),
+            String::new(),
+            "The author becomes you, with the current time as author date".to_string(),
+        ];
+
+        match jj_ops::is_immutable(&change_id) {
+            Ok(false) => {}
+            Ok(true) => {
+                self.show_warning(format!(
+                    "{change_id} is immutable, its author can't be changed"
+                ));
+                return;
+            }
+            Err(e) => {
+                self.show_error(format!("Failed to check {change_id}: {e}"));
+                return;
+            }
+        }
+
+        self.show_confirm(
+            "Reset author",
+            lines,
+            ConfirmAction::ResetAuthor { change_id },
+        );
+    }
+
+    fn confirm_push_selected(&mut self) {
+        let names: Vec<String> = self
+            .log_commits
+            .iter()
+            .filter(|commit| self.selected_changes.contains(&commit.change_id))
+            .flat_map(|commit| commit.bookmarks.iter().cloned())
+            .collect();
+        if names.is_empty() {
+            self.show_warning("None of the selected commits has a bookmark to push".to_string());
+            return;
+        }
+
+        let mut lines = vec![format!("{} bookmark(s) will be pushed:", names.len())];
+        lines.extend(names.iter().map(|name| format!("  {name}")));
+        let protected: Vec<&String> = names
+            .iter()
+            .filter(|name| self.settings.is_protected_bookmark(name))
+            .collect();
+        match protected.as_slice() {
+            [] => self.show_confirm(
+                "Confirm push",
+                lines,
+                ConfirmAction::PushBookmarks { names },
+            ),
+            [name] => {
+                let name = (*name).clone();
+                self.show_protected_confirm(&name, lines, ConfirmAction::PushBookmarks { names });
+            }
+            // Typing one name must not confirm the push of another protected bookmark
+            _ => {
+                let protected: Vec<&str> = protected.iter().map(|name| name.as_str()).collect();
+                self.show_warning(format!(
+                    "Push protected bookmarks one at a time: {}",
+                    protected.join(", ")
+                ));
+            }
+        }
+    }
+
+    /// The selected commit, or the range between it and the marked one
+    fn patch_revset(&self) -> Option<String> {
+        let selected = &self.log_commits.get(self.selected_log_index)?.change_id;
+        Some(match &self.marked_log_change {
+            // Only one of the two directions is a range, the other one is empty
+            Some(marked) if marked != selected => {
+                format!("{marked}::{selected} | {selected}::{marked}")
+            }
+            _ => selected.clone(),
+        })
+    }
+
+    /// Apply the patch in `file`, or on the clipboard when empty
+    fn apply_patch(&mut self, file: &str) -> Result<()> {
+        let patch = if file.is_empty() {
+            clipboard::paste()
+        } else {
+            std::fs::read_to_string(file).map_err(Into::into)
+        };
+        let result = match patch.and_then(|patch| diff::apply_patch(&patch)) {
+            Ok(result) => result,
+            Err(e) => {
+                self.show_error(format!("Failed to apply patch: {e}"));
+                return Ok(());
+            }
+        };
+        self.refresh_status()?;
+        self.set_status_message(result.summary());
+        Ok(())
+    }
+
+    /// Show what the operation `steps` operations before the current one changed,
+    /// staying on the shown operation when there is none that old
+    fn show_operation_diff(&mut self, steps: usize) {
+        match op_diff::get_operation_diff(steps) {
+            // Stepping through operations replaces the diff instead of stacking them
+            Ok(Some(diff)) if matches!(self.popup_state, PopupState::OperationDiff { .. }) => {
+                self.popup_scroll = 0;
+                self.popup_state = PopupState::OperationDiff { steps, diff };
+            }
+            Ok(Some(diff)) => self.open_popup(PopupState::OperationDiff { steps, diff }),
+            Ok(None) => self.set_status_message("No older operation".to_string()),
+            Err(e) => self.show_error(format!("Failed to load operation diff: {e}")),
+        }
+    }
+
+    /// List the TODO, FIXME and XXX markers in the lines the change adds, to catch leftovers
+    /// before committing
+    fn show_leftover_markers(&mut self) {
+        let markers = match diff::get_change_diff(self.status_filter.as_deref()) {
+            Ok(change_diff) => diff::leftover_markers(&change_diff),
+            Err(e) => {
+                self.show_error(format!("Failed to load the diff: {e}"));
+                return;
+            }
+        };
+        if markers.is_empty() {
+            self.set_status_message("No TODO, FIXME or XXX in the added lines".to_string());
+            return;
+        }
+        self.open_popup(PopupState::Hunks {
+            title:    "leftover marker(s) in the added lines".to_string(),
+            hunks:    markers,
+            selected: 0,
+            action:   HunkAction::ShowInDiff,
+        });
+    }
+
+    /// Select the file at `path` and scroll its diff to `line`, when the diff shows it
+    fn jump_to_diff_line(&mut self, path: &str, line: usize) -> Result<()> {
+        let Some(index) = self.files.iter().position(|file| file.path == path) else {
+            self.show_warning(format!("{path} is no longer changed"));
+            return Ok(());
+        };
+        self.select_file(index);
+        // The line can only be found once the diff of the file is loaded
+        self.update_pending_diff()?;
+        let numbers = diff::new_line_numbers(self.current_diff.as_deref().unwrap_or_default());
+        if let Some(line) = self.diff_index_of(&numbers, path, Some(line)) {
+            self.diff_scroll_offset = self.diff_row_of_line(line);
+        }
+        Ok(())
+    }
+
+    /// Ask for a note on `line` of `path`, or on the whole file when the line is left empty
+    fn show_note_form(&mut self, path: String, line: Option<usize>) {
+        let line = line.map(|line| line.to_string()).unwrap_or_default();
+        self.open_popup(PopupState::Form {
+            title:  format!("Note on {path}"),
+            form:   Form::new(vec![
+                FormField::new("Line (empty for the whole file)", form::line_number)
+                    .with_value(&line),
+                FormField::new("Note", form::required),
+            ]),
+            action: FormAction::AddNote { path },
+        });
+        // Most notes are on the line already filled in
+        if let PopupState::Form { form, .. } = &mut self.popup_state {
+            form.focused = 1;
+        }
+    }
+
+    /// Notes on the working copy change, by file and line
+    pub fn working_copy_notes(&self) -> Vec<&Note> {
+        self.working_copy
+            .as_ref()
+            .map(|commit| self.notes.of_change(&commit.change_id))
+            .unwrap_or_default()
+    }
+
+    /// Notes on the working copy shown in the current diff, with the line of the diff each
+    /// one goes below. Notes on lines no longer in the diff are only listed in the notes popup
+    pub fn diff_notes(&self) -> Vec<(usize, &Note)> {
+        let Some(diff) = self.current_diff.as_deref() else {
+            return Vec::new();
+        };
+        let notes = self.working_copy_notes();
+        if notes.is_empty() {
+            return Vec::new();
+        }
+        let numbers = diff::new_line_numbers(diff);
+        let mut placed: Vec<(usize, &Note)> = notes
+            .into_iter()
+            .filter_map(|note| Some((self.diff_index_of(&numbers, &note.path, note.line)?, note)))
+            .collect();
+        placed.sort_by_key(|(index, _)| *index);
+        placed
+    }
+
+    /// Line of the current diff showing `line` of `path`, the first line of the file's section
+    /// for `None`. `numbers` are the `diff::new_line_numbers` of the current diff
+    fn diff_index_of(
+        &self,
+        numbers: &[Option<usize>],
+        path: &str,
+        line: Option<usize>,
+    ) -> Option<usize> {
+        let mut lines = self.diff_lines_of(path, numbers.len())?;
+        match line {
+            None => Some(lines.start),
+            Some(line) => lines.find(|&i| numbers[i] == Some(line)),
+        }
+    }
+
+    /// Lines of the current diff, `line_count` lines long, that show `path`
+    fn diff_lines_of(&self, path: &str, line_count: usize) -> Option<Range<usize>> {
+        self.diff_anchors.as_ref().map_or_else(
+            || {
+                self.files
+                    .get(self.selected_file_index)
+                    .is_some_and(|file| file.path == path)
+                    .then_some(0..line_count)
+            },
+            |anchors| anchors.lines_of(path, line_count),
+        )
+    }
+
+    /// Line of `path` at the top of the diff view, where a new note on the file likely goes
+    fn line_in_view(&self, path: &str) -> Option<usize> {
+        let numbers = diff::new_line_numbers(self.current_diff.as_deref()?);
+        let lines = self.diff_lines_of(path, numbers.len())?;
+        let top = self
+            .diff_line_at_row(self.diff_scroll_offset)
+            .max(lines.start);
+        numbers.get(top..lines.end)?.iter().find_map(|line| *line)
+    }
+
+    /// Recent bookmarks and changes to switch back to, without the change being worked on
+    pub fn recent_contexts(&self) -> Vec<&RecentContext> {
+        let current = self.working_copy.as_ref().map(|commit| &commit.change_id);
+        self.recent
+            .contexts()
+            .iter()
+            .filter(|context| {
+                !matches!(context, RecentContext::Change { change_id, .. } if Some(change_id) == current)
+            })
+            .collect()
+    }
+
+    fn show_recent_contexts(&mut self) {
+        if self.recent_contexts().is_empty() {
+            self.set_status_message(
+                "Nothing to switch back to yet, check out a bookmark or move on from a change"
+                    .to_string(),
+            );
+            return;
+        }
+        self.open_popup(PopupState::Recent { selected: 0 });
+    }
+
+    /// Keep `context` at the top of the recent contexts
+    fn remember(&mut self, context: RecentContext) {
+        // Only a convenience, a failed write must not get in the way of the switch itself
+        let _ = self.recent.visit(context);
+    }
+
+    /// Pick up the work on `context` again: a new change on the bookmark, or the change itself
+    fn switch_to_context(&mut self, context: &RecentContext) -> Result<()> {
+        self.begin_operation();
+        let result = match context {
+            RecentContext::Bookmark(name) => {
+                jj_ops::new_on_bookmark(name).map(|_| format!("Started a new change on {name}"))
+            }
+            RecentContext::Change {
+                change_id,
+                description,
+            } => {
+                jj_ops::edit_change(change_id).map(|_| format!("Editing {change_id} {description}"))
+            }
+        };
+        match result {
+            Ok(message) => {
+                if let RecentContext::Bookmark(_) = context {
+                    self.remember(context.clone());
+                }
+                self.set_status_message(message);
+                self.refresh_all()?;
+            }
+            Err(e) => self.show_error(format!("Failed to switch: {e}")),
+        }
+        Ok(())
+    }
+
+    /// Everything jjkk changed in this repo, also after a crash, to restore to any point
+    pub fn show_journal(&mut self) {
+        match journal::load() {
+            Ok(entries) if entries.is_empty() => {
+                self.set_status_message("jjkk has not changed this repo yet".to_string());
+            }
+            Ok(entries) => self.open_popup(PopupState::Journal {
+                entries,
+                selected: 0,
+            }),
+            Err(e) => self.show_error(format!("Failed to load the journal: {e}")),
+        }
+    }
+
+    fn confirm_restore(&mut self, entry: &JournalEntry) {
+        self.show_confirm(
+            "Restore operation",
+            vec![
+                format!("Restore the repo to operation {},", entry.short_before()),
+                format!(
+                    "right before {} {}",
+                    entry.action,
+                    entry.arguments.lines().next().unwrap_or_default()
+                ),
+                String::new(),
+                "This undoes every later change too, the operations stay in jj op log".to_string(),
+            ],
+            ConfirmAction::RestoreOperation {
+                operation: entry.before.clone(),
+            },
+        );
+    }
+
+    /// Show the folder of the selected file in the file manager, the workspace root on
+    /// the other tabs
+    fn open_file_manager(&mut self) {
+        let root = self.repo.workspace_root();
+        let mut dir = match self.files.get(self.selected_file_index) {
+            Some(file) if self.current_tab == Tab::WorkingCopy => root.join(&file.path),
+            _ => root.to_path_buf(),
+        };
+        // Deleted files and folders are gone, show what is left of the path
+        while !dir.is_dir() && dir.pop() {}
+        match launcher::open_file_manager(self.settings.file_manager.as_deref(), &dir) {
+            Ok(()) => self.set_status_message(format!("Opened {}", dir.display())),
+            Err(e) => self.show_error(format!("Failed to open the file manager: {e}")),
+        }
+    }
+
+    /// List every changed hunk of the change, starting at the first one of the selected file
+    fn show_hunks(&mut self) {
+        let hunks = match diff::get_change_diff(self.status_filter.as_deref()) {
+            Ok(change_diff) => diff::hunk_locations(&change_diff),
+            Err(e) => {
+                self.show_error(format!("Failed to load the diff: {e}"));
+                return;
+            }
+        };
+        if hunks.is_empty() {
+            self.set_status_message("No changed lines to edit".to_string());
+            return;
+        }
+        let selected = self
+            .files
+            .get(self.selected_file_index)
+            .and_then(|file| hunks.iter().position(|hunk| hunk.path == file.path))
+            .unwrap_or(0);
+        self.open_popup(PopupState::Hunks {
+            title: "changed hunk(s)".to_string(),
+            hunks,
+            selected,
+            action: HunkAction::Edit,
+        });
+    }
+
+    /// Load the hunks of the open hunk list again, keeping the selection where it was
+    fn reload_hunk_list(&mut self) {
+        let PopupState::Hunks {
+            hunks,
+            selected,
+            action,
+            ..
+        } = &mut self.popup_state
+        else {
+            return;
+        };
+        match diff::get_change_diff(self.status_filter.as_deref()) {
+            Ok(change_diff) => {
+                *hunks = match action {
+                    HunkAction::Edit => diff::hunk_locations(&change_diff),
+                    HunkAction::ShowInDiff => diff::leftover_markers(&change_diff),
+                };
+            }
+            Err(e) => {
+                self.show_error(format!("Failed to load the diff: {e}"));
+                return;
+            }
+        }
+        if hunks.is_empty() {
+            self.close_popup();
+            self.set_status_message("No changed lines left".to_string());
+            return;
+        }
+        *selected = (*selected).min(hunks.len() - 1);
+    }
+
+    /// Pick the files and hunks of the change to commit, like `jj commit -i`
+    fn show_commit_hunks(&mut self) {
+        let files = match diff::get_change_diff(self.status_filter.as_deref()) {
+            Ok(change_diff) => diff::split_patch(&change_diff),
+            Err(e) => {
+                self.show_error(format!("Failed to load the diff: {e}"));
+                return;
+            }
+        };
+        if files.is_empty() {
+            self.set_status_message("Nothing to commit".to_string());
+            return;
+        }
+        let picked = files
+            .iter()
+            .map(|file| vec![false; file.hunks.len().max(1)])
+            .collect();
+        self.open_popup(PopupState::CommitHunks {
+            files,
+            picked,
+            selected: 0,
+        });
+    }
+
+    /// Start a new terminal window in the workspace root
+    fn open_terminal(&mut self) {
+        let root = self.repo.workspace_root().to_path_buf();
+        match launcher::open_terminal(self.settings.terminal.as_deref(), &root) {
+            Ok(()) => self.set_status_message(format!("Opened a terminal in {}", root.display())),
+            Err(e) => self.show_error(format!("Failed to open a terminal: {e}")),
+        }
+    }
+
+    /// Path and size of the selected file, when jj refused to snapshot it for its size
+    fn selected_large_file(&mut self) -> Option<(String, u64)> {
+        let file = self.files.get(self.selected_file_index)?;
+        if let ChangeType::TooLarge { size } = file.status {
+            Some((file.path.clone(), size))
+        } else {
+            self.show_warning("The selected file is not over the snapshot size limit".to_string());
+            None
+        }
+    }
+
+    /// Path of the selected file when it is conflicted, warns otherwise
+    fn selected_conflicted_file(&mut self) -> Option<String> {
+        let file = self.files.get(self.selected_file_index)?;
+        if file.status == ChangeType::Conflicted {
+            Some(file.path.clone())
+        } else {
+            self.show_warning("The selected file has no conflicts".to_string());
+            None
+        }
+    }
+
+    /// Hand the terminal to the merge tool for the selected conflicted file
+    fn resolve_selected_file(&mut self) {
+        let Some(path) = self.selected_conflicted_file() else {
+            return;
+        };
+        self.terminal_action = Some(TerminalAction::Resolve {
+            path,
+            tool: self.settings.merge_tool.clone(),
+        });
+    }
+
+    /// Resolve the whole selected file with one side, after asking
+    fn confirm_take_conflict_side(&mut self, side: ConflictSide) {
+        let Some(path) = self.selected_conflicted_file() else {
+            return;
+        };
+        let lines = vec![
+            format!(
+                "Resolve every conflict in {path} by taking {}?",
+                side.label()
+            ),
+            "The other side's changes to this file are dropped (jj undo brings them back)"
+                .to_string(),
+        ];
+        self.show_confirm(
+            &format!("Take {}", side.label()),
+            lines,
+            ConfirmAction::TakeConflictSide { path, side },
+        );
+    }
+
+    /// Snapshot the selected large file anyway
+    fn track_large_file(&mut self) -> Result<()> {
+        let Some((path, size)) = self.selected_large_file() else {
+            return Ok(());
+        };
+        self.begin_operation();
+        match jj_ops::track_large_file(&path, size) {
+            Ok(_) => {
+                self.refresh_status()?;
+                self.set_status_message(format!("Tracked {path}"));
+            }
+            Err(e) => self.show_error(format!("Failed to track {path}: {e}")),
+        }
+        Ok(())
+    }
+
+    /// Add the selected large file to `.gitignore`, so jj stops warning about it
+    fn ignore_large_file(&mut self) -> Result<()> {
+        let Some((path, _)) = self.selected_large_file() else {
+            return Ok(());
+        };
+        match jj_ops::ignore_file(&path) {
+            Ok(()) => {
+                self.refresh_status()?;
+                self.set_status_message(format!("Added {path} to .gitignore"));
+            }
+            Err(e) => self.show_error(format!("Failed to ignore {path}: {e}")),
+        }
+        Ok(())
+    }
+
+    /// Change and commit ids of all the commits with unresolved conflicts, to tell which
+    /// ones an operation added
+    fn conflicted_commits() -> Vec<(String, String)> {
+        jj_ops::get_ids("conflicts()").unwrap_or_default()
+    }
+
+    /// Point out the commits that are conflicted now but were not in `before`
+    fn warn_about_new_conflicts(&mut self, before: &[(String, String)]) {
+        // Rewritten commits keep their change id, only changes conflicted for the first time
+        // are new
+        let added: Vec<String> = Self::conflicted_commits()
+            .into_iter()
+            .filter(|(change_id, _)| !before.iter().any(|(known, _)| known == change_id))
+            .map(|(_, commit_id)| commit_id)
+            .collect();
+        if added.is_empty() {
+            return;
+        }
+        let commits = log::get_log_for_revset(Some(&added.join(" | ")), MAX_LISTED_CONFLICTS)
+            .unwrap_or_default();
+        if !commits.is_empty() {
+            self.open_popup(PopupState::Conflicts { commits });
+        }
+    }
+
+    /// Select the first conflicted file when the working copy is conflicted,
+    /// otherwise the first conflicted commit in the log
+    fn jump_to_conflict(&mut self, commits: &[CommitInfo]) {
+        let working_copy_conflicted = self.working_copy.as_ref().is_some_and(|working_copy| {
+            commits
+                .iter()
+                .any(|commit| commit.change_id == working_copy.change_id)
+        });
+        let first_file = self
+            .files
+            .iter()
+            .position(|file| file.status == ChangeType::Conflicted);
+
+        if working_copy_conflicted && let Some(index) = first_file {
+            self.switch_to_tab(Tab::WorkingCopy);
+            self.select_file(index);
+        } else if let Some(commit) = commits.first() {
+            self.goto_revision(&commit.change_id.clone());
+        }
+    }
+
+    /// Select `revision` in the log, loading older commits until it shows up
+    fn goto_revision(&mut self, revision: &str) {
+        if revision.is_empty() {
+            return;
+        }
+        let commits = match log::get_log_for_revset(Some(revision), 1) {
+            Ok(commits) => commits,
+            Err(e) => {
+                self.show_error(format!("Failed to resolve {revision}: {e}"));
+                return;
+            }
+        };
+        let Some(change_id) = commits.into_iter().next().map(|commit| commit.change_id) else {
+            self.show_warning(format!("{revision} doesn't match any commit"));
+            return;
+        };
+
+        self.switch_to_tab(Tab::Log);
+        loop {
+            if let Some(index) = self
+                .log_commits
+                .iter()
+                .position(|commit| commit.change_id == change_id)
+            {
+                self.selected_log_index = index;
+                self.log_list_state.select(Some(index));
+                return;
+            }
+            // The whole log is loaded when it is shorter than the limit
+            if self.log_commits.len() < self.log_limit || self.log_limit >= MAX_GOTO_LOG_COMMITS {
+                break;
+            }
+            self.log_limit = (self.log_limit * 2).clamp(100, MAX_GOTO_LOG_COMMITS);
+            self.refresh_log();
+        }
+        let reason = if self.log_filter.is_empty() {
+            "it is outside of the log revset"
+        } else {
+            "it is filtered out or outside of the log revset"
+        };
+        self.show_warning(format!(
+            "{revision} ({change_id}) is not in the log, {reason}"
+        ));
+    }
+
+    /// Run a jj command typed at the `:` prompt and show its output
+    fn run_jj_command(&mut self, command_line: &str) -> Result<()> {
+        if command_line.is_empty() {
+            return Ok(());
+        }
+        let result = match passthrough::run(command_line) {
+            Ok(result) => result,
+            Err(e) => {
+                self.show_error(format!("Failed to run command: {e}"));
+                return Ok(());
+            }
+        };
+        // The command may have changed anything in the repo
+        self.refresh_all()?;
+
+        self.open_popup(PopupState::CommandOutput {
+            title:   command_line
+                .strip_prefix("jj ")
+                .unwrap_or(command_line)
+                .to_string(),
+            output:  result.output,
+            success: result.success,
+        });
+        Ok(())
+    }
+
+    fn copy_patch(&mut self) {
+        let Some(revset) = self.patch_revset() else {
+            return;
+        };
+        match diff::get_patch(&revset).and_then(|patch| clipboard::copy(&patch)) {
+            Ok(()) => self.set_status_message("Copied patch to the clipboard".to_string()),
+            Err(e) => self.show_error(format!("Failed to copy patch: {e}")),
+        }
+    }
+
+    /// Select the file whose section of the whole-change diff is scrolled into view
+    fn follow_diff_scroll(&mut self) {
+        let Some(path) = self
+            .diff_anchors
+            .as_ref()
+            .and_then(|anchors| anchors.file_at(self.diff_line_at_row(self.diff_scroll_offset)))
+        else {
+            return;
+        };
+        if let Some(index) = self.files.iter().position(|file| file.path == path) {
+            self.selected_file_index = index;
+            self.file_list_state.select(Some(index));
+        }
+    }
+
+    /// Offsets in the whole-change diff don't belong to a single file
+    fn remember_diff_scroll(&mut self) {
+        if self.diff_anchors.is_some() {
+            return;
+        }
+        if let Some(file) = self.files.get(self.selected_file_index) {
+            self.diff_scroll_offsets
+                .insert(file.path.clone(), self.diff_scroll_offset);
+        }
+    }
+
+    fn restore_diff_scroll(&mut self) {
+        self.diff_scroll_offset = self
+            .files
+            .get(self.selected_file_index)
+            .and_then(|file| self.diff_scroll_offsets.get(&file.path))
+            .copied()
+            .unwrap_or(0);
+    }
+
+    pub fn update_diff(&mut self) -> Result<()> {
+        self.pending_diff = None;
+        let diff = if self.diff_anchors.is_some() {
+            let change_diff = diff::get_change_diff(self.status_filter.as_deref())?;
+            self.diff_anchors = Some(DiffAnchors::parse(&change_diff));
+            (!change_diff.is_empty()).then_some(change_diff)
+        } else if let Some(file) = self.files.get(self.selected_file_index) {
+            // Not snapshotted, jj has no diff for it
+            if matches!(file.status, ChangeType::TooLarge { .. }) {
+                None
+            } else {
+                Some(jj_ops::get_file_diff(&file.path)?)
+            }
+        } else {
+            None
+        };
+        // An unchanged diff keeps its highlighting instead of flashing plain on refresh
+        if diff != self.current_diff {
+            self.current_diff = diff;
+            self.start_highlighting();
+            self.update_word_diff();
+        }
+        Ok(())
+    }
+
+    /// Show the changes to prose files in the current diff as changed words
+    fn update_word_diff(&mut self) {
+        let extensions = &self.settings.ui.word_diff_extensions;
+        let is_prose = |path: &str| {
+            Path::new(path)
+                .extension()
+                .and_then(OsStr::to_str)
+                .is_some_and(|ext| extensions.iter().any(|e| e.eq_ignore_ascii_case(ext)))
+        };
+        self.word_diff = match (&self.current_diff, &self.diff_anchors) {
+            (Some(diff), Some(anchors)) => {
+                diff_view::word_diff(diff, |line| anchors.file_at(line).is_some_and(is_prose))
+            }
+            (Some(diff), None)
+                if self
+                    .files
+                    .get(self.selected_file_index)
+                    .is_some_and(|file| is_prose(&file.path)) =>
+            {
+                diff_view::word_diff(diff, |_| true)
+            }
+            _ => Vec::new(),
+        };
+    }
+
+    pub fn handle_key_event(&mut self, key: KeyEvent) -> Result<()> {
+        // Handle popup input first with tui-textarea
+        if let PopupState::Input {
+            ref mut textarea,
+            callback,
+            ref mut history_index,
+            ref mut draft,
+            ..
+        } = self.popup_state
+        {
+            let history = self
+                .input_history
+                .get(&callback)
+                .map_or(&[][..], Vec::as_slice);
+            let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+
+            match key.code {
+                KeyCode::Esc => {
+                    self.close_popup();
+                }
+                KeyCode::Enter if !key.modifiers.contains(KeyModifiers::ALT) => {
+                    // Regular Enter (no modifiers) submits the form
+                    let text = textarea.lines().join("\n");
+                    let cb = callback;
+                    self.close_popup();
+                    self.remember_input(cb, &text);
+                    self.execute_popup_callback(cb, &text)?;
+                }
+                // Recall older inputs when the cursor is on the first line
+                KeyCode::Up if textarea.cursor().0 == 0 && !history.is_empty() => {
+                    if history_index.is_none() {
+                        *draft = textarea.lines().join("\n");
+                    }
+                    let index =
+                        history_index.map_or(history.len() - 1, |index| index.saturating_sub(1));
+                    *history_index = Some(index);
+                    set_textarea_text(textarea, &history[index]);
+                }
+                // Go back towards the newest input, and finally the draft
+                KeyCode::Down
+                    if history_index.is_some()
+                        && textarea.cursor().0 + 1 >= textarea.lines().len() =>
+                {
+                    let next = history_index.map_or(history.len(), |index| index + 1);
+                    if next < history.len() {
+                        *history_index = Some(next);
+                        set_textarea_text(textarea, &history[next]);
+                    } else {
+                        *history_index = None;
+                        set_textarea_text(textarea, draft);
+                    }
+                }
+                // Trailers for commit messages
+                KeyCode::Char('s')
+                    if key.modifiers.contains(KeyModifiers::ALT)
+                        && matches!(callback, PopupCallback::Describe | PopupCallback::Commit) =>
+                {
+                    match jj_ops::get_user_signature() {
+                        Ok(signature) => insert_trailer(textarea, "Signed-off-by", &signature),
+                        Err(e) => self.set_status_message(format!("No jj user configured: {e}")),
+                    }
+                }
+                KeyCode::Char('c')
+                    if key.modifiers.contains(KeyModifiers::ALT)
+                        && matches!(callback, PopupCallback::Describe | PopupCallback::Commit) =>
+                {
+                    // Each press adds the next teammate that isn't a co-author yet
+                    let message = textarea.lines().join("\n");
+                    let teammate = self
+                        .settings
+                        .teammates
+                        .iter()
+                        .find(|teammate| !message.contains(teammate.as_str()));
+                    match teammate {
+                        Some(teammate) => insert_trailer(textarea, "Co-authored-by", teammate),
+                        None if self.settings.teammates.is_empty() => self.set_status_message(
+                            "Add teammates to the config to insert co-authors".to_string(),
+                        ),
+                        None => self
+                            .set_status_message("All teammates are co-authors already".to_string()),
+                    }
+                }
+                KeyCode::Char('a')
+                    if key.modifiers.contains(KeyModifiers::ALT)
+                        && matches!(callback, PopupCallback::Describe | PopupCallback::Commit) =>
+                {
+                    // Stacked on the message popup, which comes back with its text once done
+                    self.show_author_popup();
+                }
+                // Readline style editing, Ctrl+Y pastes what these removed
+                KeyCode::Char('a') if ctrl => textarea.move_cursor(CursorMove::Head),
+                KeyCode::Char('e') if ctrl => textarea.move_cursor(CursorMove::End),
+                KeyCode::Char('w') if ctrl => {
+                    textarea.delete_word();
+                }
+                KeyCode::Char('u') if ctrl => {
+                    textarea.delete_line_by_head();
+                }
+                _ => {
+                    // Convert KeyEvent to tui_textarea::Input
+                    // tui-textarea expects crossterm::event::Event, not just KeyEvent
+                    use crossterm::event::Event;
+                    textarea.input(Event::Key(key));
+                }
+            }
+            return Ok(());
+        }
+
+        // Handle bookmark selection popup
+        if let PopupState::BookmarkSelect {
+            ref mut finder,
+            ref purpose,
+        } = self.popup_state
+        {
+            let purpose = purpose.clone();
+            if key.code == KeyCode::Char('r')
+                && key.modifiers.contains(KeyModifiers::ALT)
+                && matches!(purpose, BookmarkPurpose::Set { .. })
+            {
+                // Stacked on the bookmark popup, which keeps what was typed
+                self.show_bookmark_revision_picker();
+                return Ok(());
+            }
+            let (name, exists) = match finder.handle_key(key) {
+                FinderEvent::None | FinderEvent::Changed => return Ok(()),
+                FinderEvent::Cancel => {
+                    self.close_popup();
+                    return Ok(());
+                }
+                FinderEvent::Pick(index) => (finder.items[index].name.clone(), true),
+                FinderEvent::Query(name) => {
+                    let exists = finder.items.iter().any(|b| b.name == name);
+                    (name, exists)
+                }
+            };
+            self.close_popup();
+
+            match purpose {
+                BookmarkPurpose::Set { revision } if exists => {
+                    self.confirm_move_bookmark(&name, revision);
+                }
+                // A name that is not in the list creates a new bookmark
+                BookmarkPurpose::Set { revision } => {
+                    self.create_bookmark(&name, &revision)?;
+                }
+                // Names not in the list may still exist on the remote
+                BookmarkPurpose::Fetch => {
+                    self.fetch(Some(&name), self.settings.fetch_depth)?;
                 }
             }
             return Ok(());
         }
 
-        // Handle bookmark selection popup
-        if let PopupState::BookmarkSelect {
-            ref mut content,
-            ref mut cursor_position,
-            ref available_bookmarks,
-            ref mut selected_index,
+        // Handle path selection popup
+        if let PopupState::PathSelect { ref mut finder } = self.popup_state {
+            let path = match finder.handle_key(key) {
+                FinderEvent::None | FinderEvent::Changed => return Ok(()),
+                FinderEvent::Cancel => {
+                    self.close_popup();
+                    return Ok(());
+                }
+                FinderEvent::Pick(index) => finder.items[index].clone(),
+                FinderEvent::Query(path) => path,
+            };
+            self.close_popup();
+            self.apply_log_filter(LogFilter {
+                path: Some(path),
+                ..self.log_filter.clone()
+            });
+            return Ok(());
+        }
+
+        // Handle form popup
+        if let PopupState::Form {
+            ref mut form,
+            ref action,
+            ..
         } = self.popup_state
         {
-            // Helper to get byte position from character position
-            let char_to_byte = |s: &str, char_pos: usize| -> usize {
-                s.char_indices()
-                    .nth(char_pos)
-                    .map_or(s.len(), |(byte_pos, _)| byte_pos)
-            };
+            match form.handle_key(key) {
+                FormEvent::None => {}
+                FormEvent::Cancel => self.close_popup(),
+                FormEvent::Submit(values) => {
+                    let action = action.clone();
+                    self.close_popup();
+                    self.execute_form_action(action, &values);
+                }
+            }
+            return Ok(());
+        }
 
-            // Filter bookmarks based on current content
-            let filtered: Vec<&BookmarkInfo> = if content.is_empty() {
-                available_bookmarks.iter().collect()
-            } else {
-                available_bookmarks
+        // Handle revision picker popup
+        if let PopupState::RevisionPicker {
+            ref mut picker,
+            callback,
+        } = self.popup_state
+        {
+            match picker.handle_key(key) {
+                PickerEvent::None => {}
+                PickerEvent::Cancel => {
+                    self.close_popup();
+                }
+                // Keep the picker open until the revision resolves
+                PickerEvent::Submit(revision) => match jj_ops::resolve_revision(&revision) {
+                    Ok(_) => {
+                        self.close_popup();
+                        self.remember_input(callback, &revision);
+                        self.execute_popup_callback(callback, &revision)?;
+                    }
+                    Err(e) => {
+                        picker.error = Some(e.to_string());
+                    }
+                },
+            }
+            return Ok(());
+        }
+
+        // Handle new conflicts popup
+        if let PopupState::Conflicts { ref commits } = self.popup_state {
+            match key.code {
+                KeyCode::Enter => {
+                    let commits = commits.clone();
+                    self.close_popup();
+                    self.jump_to_conflict(&commits);
+                }
+                KeyCode::Esc | KeyCode::Char('q') => self.close_popup(),
+                _ => self.scroll_popup(key.code),
+            }
+            return Ok(());
+        }
+
+        // Handle error and warning popups
+        if let PopupState::Error { .. } | PopupState::Warning { .. } = self.popup_state {
+            match key.code {
+                KeyCode::Enter | KeyCode::Esc => {
+                    self.close_popup();
+                }
+                _ => self.scroll_popup(key.code),
+            }
+            return Ok(());
+        }
+
+        // Handle confirmation popup
+        if let PopupState::Confirm {
+            ref action,
+            ref required_input,
+            ref mut typed,
+            ..
+        } = self.popup_state
+        {
+            if let Some(required) = required_input {
+                match key.code {
+                    KeyCode::Enter if typed == required => {
+                        let action = action.clone();
+                        self.close_popup();
+                        self.execute_confirmed_action(action)?;
+                    }
+                    KeyCode::Esc => {
+                        self.close_popup();
+                        self.set_status_message("Cancelled".to_string());
+                    }
+                    KeyCode::Char(c) => typed.push(c),
+                    KeyCode::Backspace => {
+                        typed.pop();
+                    }
+                    _ => {}
+                }
+                return Ok(());
+            }
+
+            match key.code {
+                KeyCode::Enter | KeyCode::Char('y') => {
+                    let action = action.clone();
+                    self.close_popup();
+                    self.execute_confirmed_action(action)?;
+                }
+                KeyCode::Esc | KeyCode::Char('n' | 'q') => {
+                    self.close_popup();
+                    self.set_status_message("Cancelled".to_string());
+                }
+                _ => self.scroll_popup(key.code),
+            }
+            return Ok(());
+        }
+
+        // Handle notification log popup
+        if matches!(self.popup_state, PopupState::Notifications) {
+            match key.code {
+                KeyCode::Char('N' | 'q') | KeyCode::Esc | KeyCode::Enter => {
+                    self.close_popup();
+                }
+                _ => self.scroll_popup(key.code),
+            }
+            return Ok(());
+        }
+
+        // Handle context menu
+        if let PopupState::ContextMenu {
+            ref items,
+            ref mut selected,
+            ref target,
+            ..
+        } = self.popup_state
+        {
+            let chosen = match key.code {
+                KeyCode::Char('j') | KeyCode::Down => {
+                    *selected = (*selected + 1).min(items.len().saturating_sub(1));
+                    None
+                }
+                KeyCode::Char('k') | KeyCode::Up => {
+                    *selected = selected.saturating_sub(1);
+                    None
+                }
+                KeyCode::Enter => items.get(*selected).map(|item| item.key),
+                KeyCode::Esc | KeyCode::Char('q' | 'm') => {
+                    self.close_popup();
+                    return Ok(());
+                }
+                // The key of an entry runs it right away
+                code => items
                     .iter()
-                    .filter(|b| b.name.to_lowercase().contains(&content.to_lowercase()))
-                    .collect()
+                    .find(|item| item.key == code)
+                    .map(|item| item.key),
             };
+            if let Some(code) = chosen {
+                let target = target.clone();
+                self.close_popup();
+                match target {
+                    MenuTarget::Tab => self.handle_key_event(KeyEvent::from(code))?,
+                    MenuTarget::PushedBookmark { bookmark, web_url } => {
+                        self.run_push_follow_up(code, &bookmark, web_url.as_deref())?;
+                    }
+                }
+            }
+            return Ok(());
+        }
 
+        // Handle bookmark conflict popup
+        if let PopupState::BookmarkConflict {
+            ref name,
+            ref targets,
+            ref mut selected,
+        } = self.popup_state
+        {
             match key.code {
-                KeyCode::Esc => {
-                    self.popup_state = PopupState::None;
+                KeyCode::Char('j') | KeyCode::Down => {
+                    *selected = (*selected + 1).min(targets.len().saturating_sub(1));
                 }
+                KeyCode::Char('k') | KeyCode::Up => *selected = selected.saturating_sub(1),
                 KeyCode::Enter => {
-                    // If there's filtered content and user selected from list, use that
-                    let bookmark_name = if !filtered.is_empty() && *selected_index < filtered.len()
-                    {
-                        filtered[*selected_index].name.clone()
-                    } else if !content.is_empty() {
-                        // Otherwise use the typed content as new bookmark name
-                        content.clone()
-                    } else {
-                        // Empty input, do nothing
-                        self.popup_state = PopupState::None;
+                    if let Some(target) = targets.get(*selected) {
+                        let name = name.clone();
+                        let commit_id = target.commit_id.clone();
+                        self.close_popup();
+                        self.resolve_bookmark(&name, &commit_id);
+                    }
+                }
+                KeyCode::Esc | KeyCode::Char('q') => self.close_popup(),
+                _ => {}
+            }
+            return Ok(());
+        }
+
+        // Handle the popup picking the hunks to commit
+        if let PopupState::CommitHunks {
+            ref files,
+            ref mut picked,
+            ref mut selected,
+        } = self.popup_state
+        {
+            let rows = diff::pick_rows(files);
+            if move_selection(selected, rows.len(), key.code) {
+                return Ok(());
+            }
+            match key.code {
+                KeyCode::Char(' ') => match rows.get(*selected) {
+                    // A file goes in or out with all of its hunks
+                    Some(&(file, None)) => {
+                        let pick = !picked[file].iter().all(|&picked| picked);
+                        picked[file].fill(pick);
+                    }
+                    Some(&(file, Some(hunk))) => picked[file][hunk] = !picked[file][hunk],
+                    None => {}
+                },
+                KeyCode::Char('a') => {
+                    let pick = !picked.iter().flatten().all(|&picked| picked);
+                    for hunks in picked.iter_mut() {
+                        hunks.fill(pick);
+                    }
+                }
+                KeyCode::Enter => {
+                    let pick = diff::pick_hunks(files, picked);
+                    if pick.paths.is_empty() {
+                        self.set_status_message(
+                            "Pick the files or hunks to commit with Space first".to_string(),
+                        );
                         return Ok(());
-                    };
+                    }
+                    // Stacked on the picker, Esc goes back to it with the picks kept
+                    self.commit_pick = Some(pick);
+                    self.show_message_popup(PopupCallback::Commit);
+                }
+                KeyCode::Esc | KeyCode::Char('q') => self.close_popup(),
+                _ => {}
+            }
+            return Ok(());
+        }
 
-                    self.popup_state = PopupState::None;
-                    match jj_ops::set_bookmark(&bookmark_name) {
-                        Ok(_) => {
-                            self.set_status_message(format!("Set bookmark: {bookmark_name}"));
-                            self.refresh_all()?;
-                        }
-                        Err(e) => {
-                            self.show_error(format!("Failed to set bookmark: {e}"));
-                        }
+        // Handle hunk list popup
+        if let PopupState::Hunks {
+            ref hunks,
+            ref mut selected,
+            action,
+            ..
+        } = self.popup_state
+        {
+            if move_selection(selected, hunks.len(), key.code) {
+                return Ok(());
+            }
+            let Some(hunk) = hunks.get(*selected) else {
+                if matches!(key.code, KeyCode::Esc | KeyCode::Char('q')) {
+                    self.close_popup();
+                }
+                return Ok(());
+            };
+            let (path, line) = (hunk.path.clone(), hunk.line);
+            match key.code {
+                KeyCode::Enter if action == HunkAction::ShowInDiff => {
+                    self.close_popup();
+                    self.jump_to_diff_line(&path, line)?;
+                }
+                // Still open after editing, to go on with the next one
+                KeyCode::Enter | KeyCode::Char('e') => {
+                    self.terminal_action = Some(TerminalAction::Edit {
+                        path: self.repo.workspace_root().join(&path),
+                        line,
+                        editor: self.settings.editor.clone(),
+                    });
+                }
+                KeyCode::Char('y') => {
+                    let list: Vec<String> = hunks.iter().map(HunkLocation::quickfix_line).collect();
+                    match clipboard::copy(&list.join("\n")) {
+                        Ok(()) => self.set_status_message(format!(
+                            "Copied {} location(s) in quickfix format",
+                            list.len()
+                        )),
+                        Err(e) => self.show_error(format!("Failed to copy: {e}")),
                     }
                 }
-                KeyCode::Up | KeyCode::Char('k') => {
-                    if !filtered.is_empty() {
-                        *selected_index = selected_index.saturating_sub(1);
+                KeyCode::Char('i') => self.show_note_form(path, Some(line)),
+                KeyCode::Esc | KeyCode::Char('q') => self.close_popup(),
+                _ => {}
+            }
+            return Ok(());
+        }
+
+        // Handle notes popup
+        if let PopupState::Notes { mut selected } = self.popup_state {
+            let notes: Vec<Note> = self.working_copy_notes().into_iter().cloned().collect();
+            let selected = match key.code {
+                _ if move_selection(&mut selected, notes.len(), key.code) => selected,
+                KeyCode::Enter => {
+                    if let Some(note) = notes.get(selected) {
+                        if let Some(index) = self.files.iter().position(|f| f.path == note.path) {
+                            self.close_popup();
+                            self.select_file(index);
+                        } else {
+                            self.show_warning(format!("{} is no longer changed", note.path));
+                        }
                     }
+                    selected
                 }
-                KeyCode::Down | KeyCode::Char('j') => {
-                    if !filtered.is_empty() {
-                        *selected_index = (*selected_index + 1).min(filtered.len() - 1);
+                KeyCode::Char('a') => {
+                    if let Some(file) = self.files.get(self.selected_file_index) {
+                        let path = file.path.clone();
+                        let line = self.line_in_view(&path);
+                        self.show_note_form(path, line);
                     }
+                    selected
                 }
-                KeyCode::Tab => {
-                    // Autocomplete with selected bookmark
-                    if !filtered.is_empty() && *selected_index < filtered.len() {
-                        *content = filtered[*selected_index].name.clone();
-                        *cursor_position = content.chars().count();
+                KeyCode::Char('d') => {
+                    if let Some(note) = notes.get(selected)
+                        && let Err(e) = self.notes.remove(note)
+                    {
+                        self.show_error(format!("Failed to delete the note: {e}"));
                     }
+                    selected.min(notes.len().saturating_sub(2))
                 }
-                KeyCode::Char(c) => {
-                    let byte_pos = char_to_byte(content, *cursor_position);
-                    content.insert(byte_pos, c);
-                    *cursor_position += 1;
-                    *selected_index = 0; // Reset selection when typing
+                KeyCode::Esc | KeyCode::Char('q') => {
+                    self.close_popup();
+                    return Ok(());
+                }
+                _ => selected,
+            };
+            // Other keys opened a popup on top, or closed this one
+            if let PopupState::Notes { selected: current } = &mut self.popup_state {
+                *current = selected;
+            }
+            return Ok(());
+        }
+
+        // Handle recent contexts popup
+        if let PopupState::Recent { mut selected } = self.popup_state {
+            let contexts: Vec<RecentContext> =
+                self.recent_contexts().into_iter().cloned().collect();
+            match key.code {
+                _ if move_selection(&mut selected, contexts.len(), key.code) => {}
+                KeyCode::Enter => {
+                    self.close_popup();
+                    if let Some(context) = contexts.get(selected) {
+                        self.switch_to_context(context)?;
+                    }
+                    return Ok(());
                 }
-                KeyCode::Backspace => {
-                    if *cursor_position > 0 {
-                        *cursor_position -= 1;
-                        let byte_pos = char_to_byte(content, *cursor_position);
-                        content.remove(byte_pos);
-                        *selected_index = 0; // Reset selection when deleting
+                KeyCode::Char('d') => {
+                    // Bookmarks that were deleted and changes that were abandoned
+                    if let Some(context) = contexts.get(selected)
+                        && let Err(e) = self.recent.remove(context)
+                    {
+                        self.show_error(format!("Failed to forget it: {e}"));
+                        return Ok(());
+                    }
+                    if contexts.len() <= 1 {
+                        self.close_popup();
+                        return Ok(());
                     }
+                    selected = selected.min(contexts.len() - 2);
                 }
-                KeyCode::Left => {
-                    *cursor_position = cursor_position.saturating_sub(1);
+                KeyCode::Esc | KeyCode::Char('q') => {
+                    self.close_popup();
+                    return Ok(());
                 }
-                KeyCode::Right => {
-                    let char_len = content.chars().count();
-                    *cursor_position = (*cursor_position + 1).min(char_len);
+                _ => {}
+            }
+            if let PopupState::Recent { selected: current } = &mut self.popup_state {
+                *current = selected;
+            }
+            return Ok(());
+        }
+
+        // Handle theme picker popup
+        if let PopupState::ThemePicker {
+            ref mut selected,
+            ref original,
+        } = self.popup_state
+        {
+            let previous = *selected;
+            match key.code {
+                KeyCode::Char('j') | KeyCode::Down => {
+                    *selected = (*selected + 1).min(Theme::NAMES.len() - 1);
                 }
-                KeyCode::Home => {
-                    *cursor_position = 0;
+                KeyCode::Char('k') | KeyCode::Up => *selected = selected.saturating_sub(1),
+                KeyCode::Enter => {
+                    let name = Theme::NAMES[*selected];
+                    self.close_popup();
+                    match self.settings.save_theme_name(name) {
+                        Ok(()) => self.set_status_message(format!("Switched to the {name} theme")),
+                        Err(e) => self.show_error(format!("Failed to save the theme: {e}")),
+                    }
+                    return Ok(());
                 }
-                KeyCode::End => {
-                    *cursor_position = content.chars().count();
+                KeyCode::Esc | KeyCode::Char('q') => {
+                    let original = original.clone();
+                    self.close_popup();
+                    self.apply_theme(&original);
+                    return Ok(());
                 }
                 _ => {}
             }
+            if *selected != previous {
+                let name = Theme::NAMES[*selected];
+                self.apply_theme(name);
+            }
             return Ok(());
         }
 
-        // Handle error popup
-        if let PopupState::Error { .. } = self.popup_state {
+        // Handle journal popup
+        if let PopupState::Journal {
+            ref entries,
+            ref mut selected,
+        } = self.popup_state
+        {
+            if move_selection(selected, entries.len(), key.code) {
+                return Ok(());
+            }
             match key.code {
-                KeyCode::Enter | KeyCode::Esc => {
-                    self.popup_state = PopupState::None;
+                KeyCode::Enter => {
+                    if let Some(entry) = entries.get(*selected) {
+                        let entry = entry.clone();
+                        self.close_popup();
+                        self.confirm_restore(&entry);
+                    }
                 }
+                KeyCode::Esc | KeyCode::Char('q') => self.close_popup(),
                 _ => {}
             }
             return Ok(());
         }
 
+        // Handle operation diff popup
+        if let PopupState::OperationDiff { steps, .. } = self.popup_state {
+            match key.code {
+                KeyCode::Char('O' | 'q') | KeyCode::Esc => {
+                    self.close_popup();
+                }
+                KeyCode::Char('[' | 'h') | KeyCode::Left => self.show_operation_diff(steps + 1),
+                KeyCode::Char(']' | 'l') | KeyCode::Right if steps > 0 => {
+                    self.show_operation_diff(steps - 1);
+                }
+                _ => self.scroll_popup(key.code),
+            }
+            return Ok(());
+        }
+
+        // Handle command output popup
+        if matches!(self.popup_state, PopupState::CommandOutput { .. }) {
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc | KeyCode::Enter => {
+                    self.close_popup();
+                }
+                _ => self.scroll_popup(key.code),
+            }
+            return Ok(());
+        }
+
         // Handle help popup
         if matches!(self.popup_state, PopupState::Help) {
             match key.code {
                 KeyCode::Char('?' | 'q') | KeyCode::Esc => {
-                    self.popup_state = PopupState::None;
+                    self.close_popup();
                 }
-                _ => {}
+                _ => self.scroll_popup(key.code),
             }
             return Ok(());
         }
 
+        // Only looking around while a push runs, jj changing the repo at the same time would
+        // mix up the journal and what each operation did
+        if self.push_task.is_running() && !is_view_key(key) {
+            self.set_status_message("Wait for the push to finish first".to_string());
+            return Ok(());
+        }
+
         // Handle normal key events
         match key.code {
             KeyCode::Char('?') => {
-                self.popup_state = PopupState::Help;
+                self.open_popup(PopupState::Help);
+            }
+            KeyCode::Char('q') if self.push_task.is_running() => {
+                self.show_confirm(
+                    "Push still running",
+                    vec![
+                        "Quit anyway? jj keeps pushing, but whether the push went through is \
+                         not reported"
+                            .to_string(),
+                    ],
+                    ConfirmAction::Quit,
+                );
             }
             KeyCode::Char('q') => {
                 self.should_quit = true;
             }
+            KeyCode::Char('N') => {
+                self.open_popup(PopupState::Notifications);
+            }
             KeyCode::Char('1') => {
                 self.switch_to_tab(Tab::WorkingCopy);
             }
@@ -435,125 +2956,338 @@ impl App {
             KeyCode::Char('3') => {
                 self.switch_to_tab(Tab::Log);
             }
+            KeyCode::Char('4') => {
+                self.switch_to_tab(Tab::Remotes);
+            }
             KeyCode::Tab => {
                 self.switch_to_tab(self.current_tab.next());
             }
             KeyCode::BackTab => {
                 self.switch_to_tab(self.current_tab.prev());
             }
-            KeyCode::Char('j') | KeyCode::Down => {
-                // Apply debouncing to navigation keys
-                if !self.should_process_navigation_key(key.code) {
-                    return Ok(());
-                }
-
-                match self.current_tab {
-                    Tab::WorkingCopy => {
-                        if !self.files.is_empty() {
-                            self.selected_file_index =
-                                (self.selected_file_index + 1).min(self.files.len() - 1);
-                            self.file_list_state.select(Some(self.selected_file_index));
-                            self.update_diff()?;
-                            self.diff_scroll_offset = 0; // Reset scroll when changing files
-                        }
-                    }
-                    Tab::Bookmarks => {
-                        if !self.bookmarks.is_empty() {
-                            self.selected_bookmark_index =
-                                (self.selected_bookmark_index + 1).min(self.bookmarks.len() - 1);
-                            self.bookmark_list_state
-                                .select(Some(self.selected_bookmark_index));
-                        }
-                    }
-                    Tab::Log => {
-                        if !self.log_commits.is_empty() {
-                            self.selected_log_index =
-                                (self.selected_log_index + 1).min(self.log_commits.len() - 1);
-                            self.log_list_state.select(Some(self.selected_log_index));
-                        }
+            KeyCode::Char('j') | KeyCode::Down => match self.current_tab {
+                Tab::WorkingCopy => {
+                    if !self.files.is_empty() {
+                        self.select_file((self.selected_file_index + 1).min(self.files.len() - 1));
                     }
                 }
-            }
-            KeyCode::Char('k') | KeyCode::Up => {
-                // Apply debouncing to navigation keys
-                if !self.should_process_navigation_key(key.code) {
-                    return Ok(());
-                }
-
-                match self.current_tab {
-                    Tab::WorkingCopy => {
-                        self.selected_file_index = self.selected_file_index.saturating_sub(1);
-                        self.file_list_state.select(Some(self.selected_file_index));
-                        self.update_diff()?;
-                        self.diff_scroll_offset = 0; // Reset scroll when changing files
-                    }
-                    Tab::Bookmarks => {
+                Tab::Bookmarks => {
+                    if !self.bookmarks.is_empty() {
                         self.selected_bookmark_index =
-                            self.selected_bookmark_index.saturating_sub(1);
+                            (self.selected_bookmark_index + 1).min(self.bookmarks.len() - 1);
                         self.bookmark_list_state
                             .select(Some(self.selected_bookmark_index));
                     }
-                    Tab::Log => {
-                        self.selected_log_index = self.selected_log_index.saturating_sub(1);
+                }
+                Tab::Log => {
+                    if !self.log_commits.is_empty() {
+                        self.selected_log_index =
+                            (self.selected_log_index + 1).min(self.log_commits.len() - 1);
                         self.log_list_state.select(Some(self.selected_log_index));
                     }
                 }
-            }
+                Tab::Remotes => {
+                    if !self.remotes.is_empty() {
+                        self.selected_remote_index =
+                            (self.selected_remote_index + 1).min(self.remotes.len() - 1);
+                        self.remote_list_state
+                            .select(Some(self.selected_remote_index));
+                    }
+                }
+            },
+            KeyCode::Char('k') | KeyCode::Up => match self.current_tab {
+                Tab::WorkingCopy => {
+                    self.select_file(self.selected_file_index.saturating_sub(1));
+                }
+                Tab::Bookmarks => {
+                    self.selected_bookmark_index = self.selected_bookmark_index.saturating_sub(1);
+                    self.bookmark_list_state
+                        .select(Some(self.selected_bookmark_index));
+                }
+                Tab::Log => {
+                    self.selected_log_index = self.selected_log_index.saturating_sub(1);
+                    self.log_list_state.select(Some(self.selected_log_index));
+                }
+                Tab::Remotes => {
+                    self.selected_remote_index = self.selected_remote_index.saturating_sub(1);
+                    self.remote_list_state
+                        .select(Some(self.selected_remote_index));
+                }
+            },
             KeyCode::Char('J') => {
                 // Shift+J for scrolling diff down
                 if self.current_tab == Tab::WorkingCopy && self.current_diff.is_some() {
                     self.diff_scroll_offset += 1;
+                    self.follow_diff_scroll();
                 }
             }
             KeyCode::Char('K') => {
                 // Shift+K for scrolling diff up
                 if self.current_tab == Tab::WorkingCopy {
                     self.diff_scroll_offset = self.diff_scroll_offset.saturating_sub(1);
+                    self.follow_diff_scroll();
                 }
             }
+            KeyCode::Enter
+                if self.current_tab == Tab::Bookmarks
+                    && key.modifiers.contains(KeyModifiers::ALT) =>
+            {
+                self.checkout_bookmark_in_git();
+            }
             KeyCode::Enter => {
                 match self.current_tab {
                     Tab::Bookmarks => {
                         self.handle_bookmark_checkout()?;
                     }
+                    Tab::Remotes => {
+                        self.set_default_remote();
+                    }
                     Tab::Log | Tab::WorkingCopy => {
                         // TODO: Show commit details
                     }
                 }
             }
+            KeyCode::Char('D') if self.current_tab == Tab::Bookmarks => {
+                self.handle_bookmark_delete();
+            }
+            KeyCode::Char('C') if self.current_tab == Tab::Bookmarks => {
+                self.handle_bookmark_cleanup();
+            }
+            KeyCode::Char('a') if self.current_tab == Tab::Remotes => {
+                self.show_add_remote_form();
+            }
+            KeyCode::Char('e') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.show_recent_contexts();
+            }
+            KeyCode::Char('e') if self.current_tab == Tab::Remotes => {
+                self.show_edit_remote_form();
+            }
+            KeyCode::Char('D') if self.current_tab == Tab::Remotes => {
+                self.confirm_remove_remote();
+            }
+            KeyCode::Char('I')
+                if self.current_tab == Tab::Remotes || self.git_head_drift.is_some() =>
+            {
+                self.sync_git_refs(true);
+            }
+            KeyCode::Char('E') if self.current_tab == Tab::Remotes => {
+                self.sync_git_refs(false);
+            }
+            KeyCode::Char('v') if self.current_tab == Tab::Log => {
+                self.show_log_preview = !self.show_log_preview;
+            }
+            KeyCode::Char(' ') if self.current_tab == Tab::Log => {
+                self.toggle_selected_change();
+            }
+            KeyCode::Esc if self.current_tab == Tab::Log && !self.selected_changes.is_empty() => {
+                self.selected_changes.clear();
+                self.set_status_message("Cleared the selection".to_string());
+            }
+            KeyCode::Esc
+                if self.current_tab == Tab::WorkingCopy && self.status_filter.is_some() =>
+            {
+                self.apply_status_filter(None)?;
+            }
+            KeyCode::Char('/') if self.current_tab == Tab::WorkingCopy => {
+                let filter = self.status_filter.clone().unwrap_or_default();
+                self.show_prefilled_input_popup(
+                    "Filter files (fileset or glob), empty for all",
+                    PopupCallback::StatusFilter,
+                    &filter,
+                    vec![
+                        "Examples".to_string(),
+                        "src/**/*.rs".to_string(),
+                        "src ~ glob:\"**/*_test.rs\"".to_string(),
+                    ],
+                );
+            }
+            KeyCode::Esc if self.current_tab == Tab::Log && !self.log_filter.is_empty() => {
+                self.apply_log_filter(LogFilter::default());
+            }
+            KeyCode::Char('P') if self.current_tab == Tab::Log => {
+                self.show_path_filter_popup();
+            }
+            KeyCode::Char('W') if self.current_tab == Tab::Log => {
+                let range = match (&self.log_filter.since, &self.log_filter.until) {
+                    (None, None) => String::new(),
+                    (since, until) => format!(
+                        "{}..{}",
+                        since.as_deref().unwrap_or_default(),
+                        until.as_deref().unwrap_or_default()
+                    ),
+                };
+                self.show_prefilled_input_popup(
+                    "Authored between (since..until), empty for any time",
+                    PopupCallback::LogDates,
+                    &range,
+                    vec![
+                        "Examples".to_string(),
+                        "2024-01-01..2024-02-01".to_string(),
+                        "2 weeks ago..".to_string(),
+                        "..yesterday".to_string(),
+                    ],
+                );
+            }
+            KeyCode::Char('x') if self.current_tab == Tab::Log => {
+                self.confirm_abandon_selected();
+            }
+            KeyCode::Char('r')
+                if self.current_tab == Tab::Log && !self.selected_changes.is_empty() =>
+            {
+                self.show_revision_picker(
+                    "Rebase selected commits onto",
+                    PopupCallback::RebaseSelected,
+                    None,
+                );
+            }
+            KeyCode::Char('p')
+                if self.current_tab == Tab::Log && !self.selected_changes.is_empty() =>
+            {
+                self.confirm_push_selected();
+            }
+            KeyCode::Char('m') => self.show_context_menu(),
+            KeyCode::Char('M') if self.current_tab == Tab::WorkingCopy => {
+                self.resolve_selected_file();
+            }
+            KeyCode::Char('<') if self.current_tab == Tab::WorkingCopy => {
+                self.confirm_take_conflict_side(ConflictSide::Ours);
+            }
+            KeyCode::Char('>') if self.current_tab == Tab::WorkingCopy => {
+                self.confirm_take_conflict_side(ConflictSide::Theirs);
+            }
+            KeyCode::Char('M') if self.current_tab == Tab::Log => {
+                self.toggle_log_mark();
+            }
+            KeyCode::Char('y') if self.current_tab == Tab::Log => {
+                self.copy_patch();
+            }
+            KeyCode::Char('w') if self.current_tab == Tab::Log => {
+                if self.patch_revset().is_some() {
+                    self.show_input_popup("Write patch to file", PopupCallback::WritePatch);
+                }
+            }
+            KeyCode::Char('U') if matches!(self.current_tab, Tab::WorkingCopy | Tab::Log) => {
+                self.confirm_reset_author();
+            }
+            KeyCode::Char('A') if self.current_tab == Tab::WorkingCopy => {
+                self.show_input_popup(
+                    "Apply patch from file (empty for the clipboard)",
+                    PopupCallback::ApplyPatch,
+                );
+            }
+            KeyCode::Char('H') if self.current_tab == Tab::WorkingCopy => {
+                // Only for this session, the config stays untouched
+                let highlighting = !self.settings.ui.syntax_highlighting;
+                self.settings.ui.syntax_highlighting = highlighting;
+                self.start_highlighting();
+                self.set_status_message(format!(
+                    "Syntax highlighting {}",
+                    if highlighting { "on" } else { "off" }
+                ));
+            }
+            KeyCode::Char('L') if self.current_tab == Tab::WorkingCopy => {
+                self.mini_log_lines = if self.mini_log_lines > 0 {
+                    0
+                } else {
+                    self.settings.ui.mini_log.unwrap_or(DEFAULT_MINI_LOG_LINES)
+                };
+            }
+            KeyCode::Char('T') if self.current_tab == Tab::WorkingCopy => {
+                self.track_large_file()?;
+            }
+            KeyCode::Char('G') if self.current_tab == Tab::WorkingCopy => {
+                self.ignore_large_file()?;
+            }
+            KeyCode::Char('v') if self.current_tab == Tab::WorkingCopy => {
+                self.toggle_whole_change_diff()?;
+            }
+            KeyCode::Char('e') if self.current_tab == Tab::WorkingCopy => {
+                self.show_hunks();
+            }
+            KeyCode::Char('i') if self.current_tab == Tab::WorkingCopy => {
+                self.open_popup(PopupState::Notes { selected: 0 });
+            }
+            KeyCode::Char('S') if self.current_tab == Tab::WorkingCopy => {
+                self.show_leftover_markers();
+            }
             KeyCode::Char('d') if self.current_tab == Tab::WorkingCopy => {
                 self.show_describe_popup();
             }
             KeyCode::Char('c') if self.current_tab == Tab::WorkingCopy => {
                 self.show_commit_popup();
             }
+            KeyCode::Char('C') if self.current_tab == Tab::WorkingCopy => {
+                self.show_commit_hunks();
+            }
             KeyCode::Char('n') if self.current_tab == Tab::WorkingCopy => {
                 self.handle_new_commit()?;
             }
+            KeyCode::Char('f') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.show_fetch_bookmark_popup();
+            }
             KeyCode::Char('f') => {
-                self.handle_fetch()?;
+                self.fetch(None, self.settings.fetch_depth)?;
+            }
+            KeyCode::Char('F') => {
+                self.show_fetch_popup();
             }
             KeyCode::Char('p') => {
                 self.handle_push()?;
             }
+            KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.begin_operation();
+                self.refresh_all()?;
+                self.set_status_message("Refreshed everything".to_string());
+            }
             KeyCode::Char('r') => {
                 self.show_rebase_popup();
             }
             KeyCode::Char('b') => {
                 self.show_bookmark_popup();
             }
+            KeyCode::Char('t') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.open_terminal();
+            }
+            KeyCode::Char('t') if key.modifiers.contains(KeyModifiers::ALT) => {
+                self.show_theme_picker();
+            }
             KeyCode::Char('t') => {
                 self.track_current_bookmark();
             }
+            KeyCode::Char('o') if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.open_file_manager();
+            }
             KeyCode::Char('R') => {
-                // Capital R to refresh status
-                self.refresh_all()?;
+                // Capital R to refresh the current tab, Ctrl+R for everything
+                self.begin_operation();
+                self.refresh_current_tab()?;
                 self.set_status_message("Refreshed".to_string());
             }
+            KeyCode::Char('z') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.terminal_action = Some(TerminalAction::Suspend);
+            }
+            KeyCode::Char('!') => {
+                self.show_input_popup("Shell command", PopupCallback::ShellCommand);
+            }
+            KeyCode::Char('O') => self.show_operation_diff(0),
+            KeyCode::Char('o') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.show_journal();
+            }
+            KeyCode::Char('g') => {
+                self.show_input_popup("Go to change, commit or bookmark", PopupCallback::Goto);
+            }
+            KeyCode::Char(':') => {
+                self.show_input_popup("jj command", PopupCallback::JjCommand);
+            }
+            KeyCode::Char('z') => {
+                self.layout_mode = match self.layout_mode {
+                    LayoutMode::Normal => LayoutMode::Zen,
+                    LayoutMode::Zen => LayoutMode::Normal,
+                };
+            }
             KeyCode::Char('X') => {
                 // Capital X to restore the working copy (aka discard changes)
                 self.restore_working_copy()?;
-                self.set_status_message("Restored working copy".to_owned());
             }
             _ => {}
         }
@@ -561,6 +3295,23 @@ impl App {
         Ok(())
     }
 
+    /// Scroll read-only popups with j/k, arrows and page up/down
+    fn scroll_popup(&mut self, key_code: KeyCode) {
+        const PAGE: usize = 10;
+
+        let max = self.popup_scroll_max.get();
+        self.popup_scroll = match key_code {
+            KeyCode::Char('j') | KeyCode::Down => self.popup_scroll + 1,
+            KeyCode::Char('k') | KeyCode::Up => self.popup_scroll.saturating_sub(1),
+            KeyCode::PageDown => self.popup_scroll + PAGE,
+            KeyCode::PageUp => self.popup_scroll.saturating_sub(PAGE),
+            KeyCode::Home => 0,
+            KeyCode::End => max,
+            _ => return,
+        }
+        .min(max);
+    }
+
     fn track_current_bookmark(&mut self) {
         let bookmark = jj_ops::get_current_bookmark().ok().flatten();
         let Some(bookmark) = bookmark else {
@@ -568,19 +3319,32 @@ impl App {
             return;
         };
 
+        let conflicted = Self::conflicted_commits();
+        self.begin_operation();
         match self.native_ops.track(&bookmark, None) {
             Ok(_) => {
                 self.set_status_message(format!("Tracking bookmark: {bookmark}"));
+                self.warn_about_new_conflicts(&conflicted);
             }
             Err(e) => {
                 self.show_error(format!("Failed to track bookmark: {e}"));
             }
         }
+        if let Err(e) = self.refresh_all() {
+            self.show_error(format!("Failed to refresh: {e}"));
+        }
     }
 
     fn restore_working_copy(&mut self) -> Result<()> {
-        match jj_ops::restore_working_copy() {
+        self.begin_operation();
+        match jj_ops::restore_working_copy(self.status_filter.as_deref()) {
             Ok(_) => {
+                match &self.status_filter {
+                    Some(fileset) => {
+                        self.set_status_message(format!("Restored the files matching {fileset}"));
+                    }
+                    None => self.set_status_message("Restored working copy".to_owned()),
+                }
                 self.refresh_all()?;
             }
             Err(e) => {
@@ -590,62 +3354,352 @@ impl App {
         Ok(())
     }
 
+    /// Show `popup` on top of the current one, which comes back when it closes
+    fn open_popup(&mut self, popup: PopupState) {
+        let below = std::mem::replace(&mut self.popup_state, popup);
+        if !matches!(below, PopupState::None) {
+            self.popup_stack.push((below, self.popup_scroll));
+        }
+        self.popup_scroll = 0;
+    }
+
+    /// Close the popup on top, going back to the one below it if any
+    fn close_popup(&mut self) {
+        (self.popup_state, self.popup_scroll) =
+            self.popup_stack.pop().unwrap_or((PopupState::None, 0));
+    }
+
+    fn show_input_popup(&mut self, title: &str, callback: PopupCallback) {
+        self.show_prefilled_input_popup(title, callback, "", Vec::new());
+    }
+
+    fn show_prefilled_input_popup(
+        &mut self,
+        title: &str,
+        callback: PopupCallback,
+        initial: &str,
+        details: Vec<String>,
+    ) {
+        let mut textarea = Box::new(TextArea::default());
+        set_textarea_text(&mut textarea, initial);
+
+        self.open_popup(PopupState::Input {
+            title: title.to_string(),
+            textarea,
+            callback,
+            history_index: None,
+            draft: String::new(),
+            details,
+        });
+    }
+
     fn show_describe_popup(&mut self) {
-        self.popup_state = PopupState::Input {
-            title:    "Describe".to_string(),
-            textarea: Box::new(TextArea::default()),
-            callback: PopupCallback::Describe,
-        };
+        self.show_message_popup(PopupCallback::Describe);
     }
 
     fn show_commit_popup(&mut self) {
-        self.popup_state = PopupState::Input {
-            title:    "Commit".to_string(),
-            textarea: Box::new(TextArea::default()),
-            callback: PopupCallback::Commit,
+        self.commit_pick = None;
+        self.show_message_popup(PopupCallback::Commit);
+    }
+
+    fn show_message_popup(&mut self, callback: PopupCallback) {
+        let title = self.message_popup_title(callback);
+        self.show_prefilled_input_popup(&title, callback, "", trailer_details());
+    }
+
+    /// Title of the describe or commit popup, naming the author override if any
+    fn message_popup_title(&self, callback: PopupCallback) -> String {
+        let action = if callback == PopupCallback::Commit {
+            "Commit"
+        } else {
+            "Describe"
+        };
+        self.commit_author.as_ref().map_or_else(
+            || action.to_string(),
+            |author| format!("{action} as {author}"),
+        )
+    }
+
+    fn show_author_popup(&mut self) {
+        // Up recalls the recent authors, newest last like the rest of the history
+        let recent = self.settings.recent_authors.iter().rev().cloned().collect();
+        self.input_history.insert(PopupCallback::Author, recent);
+
+        let author = self.commit_author.clone().unwrap_or_default();
+        self.show_prefilled_input_popup(
+            "Author (Name <email>), empty for yourself",
+            PopupCallback::Author,
+            &author,
+            Vec::new(),
+        );
+    }
+
+    /// Describe and commit as `author` from now on, or as the jj user again when empty
+    fn set_commit_author(&mut self, author: &str) {
+        if author.is_empty() {
+            self.commit_author = None;
+            self.set_status_message("Describing and committing as yourself".to_string());
+            return;
+        }
+        if let Err(e) = jj_ops::parse_author(author) {
+            self.set_status_message(format!("Author not changed: {e}"));
+            return;
+        }
+
+        self.commit_author = Some(author.to_string());
+        match self.settings.save_recent_author(author) {
+            Ok(()) => self.set_status_message(format!("Describing and committing as {author}")),
+            Err(e) => self.set_status_message(format!(
+                "Describing and committing as {author}, but failed to remember them: {e}"
+            )),
+        }
+    }
+
+    /// Name the new author in the title of the message popup the author popup was stacked on
+    fn retitle_message_popup(&mut self) {
+        let PopupState::Input { callback, .. } = self.popup_state else {
+            return;
         };
+        if matches!(callback, PopupCallback::Describe | PopupCallback::Commit) {
+            let new_title = self.message_popup_title(callback);
+            if let PopupState::Input { title, .. } = &mut self.popup_state {
+                *title = new_title;
+            }
+        }
     }
 
     fn show_rebase_popup(&mut self) {
-        self.popup_state = PopupState::Input {
-            title:    "Rebase destination".to_string(),
-            textarea: Box::new(TextArea::default()),
-            callback: PopupCallback::Rebase,
+        // Rebasing the current stack onto trunk is by far the most common case
+        let trunk = self.trunk.clone().map_or_else(
+            || RevisionCandidate {
+                revision:    "trunk()".to_string(),
+                label:       "trunk()".to_string(),
+                description: "Head of the default remote branch".to_string(),
+                kind:        CandidateKind::Revset,
+            },
+            |name| RevisionCandidate {
+                revision:    name.clone(),
+                label:       name,
+                description: "trunk".to_string(),
+                kind:        CandidateKind::Bookmark,
+            },
+        );
+
+        self.show_revision_picker("Rebase destination", PopupCallback::Rebase, Some(trunk));
+    }
+
+    /// Let the user pick a revision out of the cached bookmarks and log,
+    /// `first` is preselected
+    fn show_revision_picker(
+        &mut self,
+        title: &str,
+        callback: PopupCallback,
+        first: Option<RevisionCandidate>,
+    ) {
+        let candidates = RevisionPicker::candidates(first, &self.bookmarks, &self.log_commits);
+        self.open_popup(PopupState::RevisionPicker {
+            picker: RevisionPicker::new(title, candidates),
+            callback,
+        });
+    }
+
+    /// Describe the commits that move when rebasing the current stack onto `destination`
+    fn rebase_preview(destination: &str) -> Vec<String> {
+        const PREVIEW_LIMIT: usize = 20;
+
+        let revset = jj_ops::rebase_revset(destination);
+        let Ok(commits) = log::get_log_for_revset(Some(&revset), PREVIEW_LIMIT) else {
+            return Vec::new();
         };
+
+        let mut details = vec![match commits.len() {
+            0 => format!("Nothing to move, @ is already on top of {destination}"),
+            PREVIEW_LIMIT.. => {
+                format!("{PREVIEW_LIMIT}+ commits will be rewritten onto {destination}:")
+            }
+            count => format!("{count} commit(s) will be rewritten onto {destination}:"),
+        }];
+        details.extend(
+            commits
+                .iter()
+                .map(|commit| format!("{} {}", commit.change_id, commit.description)),
+        );
+        details
+    }
+
+    /// Add a submitted input to the history of its popup
+    fn remember_input(&mut self, callback: PopupCallback, text: &str) {
+        if callback == PopupCallback::Secret || text.trim().is_empty() {
+            return;
+        }
+        let history = self.input_history.entry(callback).or_default();
+        if history.last().is_none_or(|last| last != text) {
+            history.push(text.to_string());
+        }
     }
 
     fn show_bookmark_popup(&mut self) {
         // Fetch available bookmarks
         let bookmarks = jj_ops::get_bookmarks().unwrap_or_else(|_| Vec::new());
 
-        self.popup_state = PopupState::BookmarkSelect {
-            content: String::new(),
-            cursor_position: 0,
-            available_bookmarks: bookmarks,
-            selected_index: 0,
+        // Target the selected commit when coming from the log
+        let revision = match self.current_tab {
+            Tab::Log => self
+                .log_commits
+                .get(self.selected_log_index)
+                .map_or_else(|| "@".to_string(), |commit| commit.change_id.clone()),
+            Tab::WorkingCopy | Tab::Bookmarks | Tab::Remotes => "@".to_string(),
+        };
+
+        self.open_popup(PopupState::BookmarkSelect {
+            finder:  FuzzyFinder::new(bookmarks, true),
+            purpose: BookmarkPurpose::Set { revision },
+        });
+    }
+
+    fn show_bookmark_revision_picker(&mut self) {
+        let working_copy = RevisionCandidate {
+            revision:    "@".to_string(),
+            label:       "@".to_string(),
+            description: "Working copy".to_string(),
+            kind:        CandidateKind::Revset,
+        };
+        self.show_revision_picker(
+            "Bookmark revision",
+            PopupCallback::BookmarkRevision,
+            Some(working_copy),
+        );
+    }
+
+    /// Pick a file or directory to limit the log to, typed paths don't have to exist anymore
+    fn show_path_filter_popup(&mut self) {
+        let paths = match jj_ops::get_tracked_paths() {
+            Ok(paths) => paths,
+            Err(e) => {
+                self.show_error(format!("Failed to list files: {e}"));
+                return;
+            }
+        };
+        let mut finder = FuzzyFinder::new(paths, true);
+        if let Some(path) = &self.log_filter.path {
+            finder.query.clone_from(path);
+            finder.cursor_position = path.len();
+        }
+        self.open_popup(PopupState::PathSelect { finder });
+    }
+
+    /// Limit the Working Copy files to `fileset`, an invalid fileset keeps the current filter
+    fn apply_status_filter(&mut self, fileset: Option<String>) -> Result<()> {
+        self.begin_operation();
+        if let Err(e) = status::get_working_copy_status(fileset.as_deref()) {
+            self.show_error(format!("Failed to filter the files: {e}"));
+            return Ok(());
+        }
+
+        self.status_filter = fileset;
+        self.selected_file_index = 0;
+        self.refresh_status()?;
+        match &self.status_filter {
+            Some(fileset) => self.set_status_message(format!(
+                "Showing the {} file(s) matching {fileset}, Esc to clear",
+                self.files.len()
+            )),
+            None => self.set_status_message("Showing all files".to_string()),
+        }
+        Ok(())
+    }
+
+    /// Limit the log to `filter`, starting at the newest matching commit. An invalid path
+    /// or date keeps the current filter
+    fn apply_log_filter(&mut self, filter: LogFilter) {
+        self.begin_operation();
+        if let Err(e) = Self::filter_revset(&filter)
+            .and_then(|revset| log::get_log_for_revset(revset.as_deref(), 1))
+        {
+            self.show_error(format!("Failed to filter the log: {e}"));
+            return;
+        }
+
+        self.log_filter = filter;
+        self.selected_log_index = 0;
+        self.log_list_state.select(Some(0));
+        self.refresh_log();
+        if self.log_filter.is_empty() {
+            self.set_status_message("Showing the whole log".to_string());
+        } else {
+            self.set_status_message(format!(
+                "Log limited to {}, Esc to clear",
+                self.log_filter.label()
+            ));
+        }
+    }
+
+    /// Pick a single bookmark to fetch, typed names don't have to exist locally
+    fn show_fetch_bookmark_popup(&mut self) {
+        let bookmarks = jj_ops::get_bookmarks().unwrap_or_else(|_| Vec::new());
+        self.open_popup(PopupState::BookmarkSelect {
+            finder:  FuzzyFinder::new(bookmarks, true),
+            purpose: BookmarkPurpose::Fetch,
+        });
+    }
+
+    /// Ask before moving an existing bookmark, showing where it points now
+    fn confirm_move_bookmark(&mut self, name: &str, revision: String) {
+        let lines = vec![
+            format!("Move bookmark {name} here?"),
+            String::new(),
+            format!("from: {}", describe_revision(&revset_symbol(name))),
+            format!("to:   {}", describe_revision(&revision)),
+        ];
+        let action = ConfirmAction::MoveBookmark {
+            name: name.to_string(),
+            revision,
         };
+
+        if self.settings.is_protected_bookmark(name) {
+            self.show_protected_confirm(name, lines, action);
+        } else {
+            self.show_confirm("Move bookmark", lines, action);
+        }
     }
 
     fn execute_popup_callback(&mut self, callback: PopupCallback, text: &str) -> Result<()> {
+        self.begin_operation();
         match callback {
-            PopupCallback::Describe => match self.native_ops.describe(text) {
-                Ok(_) => {
-                    self.set_status_message("Description updated".to_string());
+            PopupCallback::Describe => match self
+                .native_ops
+                .describe(text, self.commit_author.as_deref())
+            {
+                Ok(message) => {
+                    self.set_status_message(message);
                     self.refresh_all()?;
                 }
                 Err(e) => {
                     self.show_error(format!("Failed to describe: {e}"));
                 }
             },
-            PopupCallback::Commit => match self.native_ops.commit(text) {
-                Ok(_) => {
-                    self.set_status_message("Committed successfully".to_string());
-                    self.refresh_all()?;
-                }
-                Err(e) => {
-                    self.show_error(format!("Failed to commit: {e}"));
+            PopupCallback::Commit => {
+                let pick = self.commit_pick.take();
+                match actions::commit(
+                    &mut self.native_ops,
+                    &self.settings,
+                    text,
+                    self.commit_author.as_deref(),
+                    pick.as_ref(),
+                ) {
+                    Ok(message) => {
+                        self.set_status_message(message);
+                        // The picked hunks are committed, the picker below is out of date
+                        if matches!(self.popup_state, PopupState::CommitHunks { .. }) {
+                            self.close_popup();
+                        }
+                        self.refresh_all()?;
+                    }
+                    Err(e) => {
+                        self.show_error(format!("Failed to commit: {e}"));
+                    }
                 }
-            },
+            }
             PopupCallback::Rebase => {
                 let text = if text.trim().is_empty() {
                     "@"
@@ -653,32 +3707,332 @@ impl App {
                     text.trim()
                 };
 
-                match jj_ops::rebase(text) {
-                    Ok(_) => {
-                        self.set_status_message(format!("Rebased to {text}"));
+                // Show what is going to be rewritten before touching anything
+                self.show_confirm(
+                    "Confirm rebase",
+                    Self::rebase_preview(text),
+                    ConfirmAction::Rebase {
+                        destination: text.to_string(),
+                    },
+                );
+            }
+            PopupCallback::FetchDepth => match text.trim() {
+                "" => self.fetch(None, None)?,
+                depth => match depth.parse::<NonZeroU32>() {
+                    Ok(depth) => self.fetch(None, Some(depth))?,
+                    Err(_) => {
+                        self.show_error(format!("Invalid fetch depth: {depth}"));
+                    }
+                },
+            },
+            PopupCallback::WritePatch => {
+                let path = text.trim();
+                if path.is_empty() {
+                    return Ok(());
+                }
+                let Some(revset) = self.patch_revset() else {
+                    return Ok(());
+                };
+                match diff::write_patch(&revset, path) {
+                    Ok(()) => self.set_status_message(format!("Wrote patch to {path}")),
+                    Err(e) => self.show_error(format!("Failed to write patch: {e}")),
+                }
+            }
+            PopupCallback::ApplyPatch => self.apply_patch(text.trim())?,
+            PopupCallback::ShellCommand => {
+                let command = text.trim();
+                if !command.is_empty() {
+                    self.terminal_action = Some(TerminalAction::Shell {
+                        command: command.to_string(),
+                    });
+                }
+            }
+            PopupCallback::JjCommand => self.run_jj_command(text.trim())?,
+            PopupCallback::Goto => self.goto_revision(text.trim()),
+            PopupCallback::StatusFilter => {
+                let filter = text.trim();
+                self.apply_status_filter((!filter.is_empty()).then(|| fileset_or_glob(filter)))?;
+            }
+            PopupCallback::LogDates => {
+                let mut filter = self.log_filter.clone();
+                filter.set_date_range(text);
+                self.apply_log_filter(filter);
+            }
+            PopupCallback::BookmarkRevision => {
+                if let PopupState::BookmarkSelect {
+                    purpose: BookmarkPurpose::Set { revision },
+                    ..
+                } = &mut self.popup_state
+                {
+                    *revision = text.trim().to_string();
+                }
+            }
+            PopupCallback::Author => {
+                self.set_commit_author(text.trim());
+                self.retitle_message_popup();
+            }
+            PopupCallback::RebaseSelected => {
+                let destination = text.trim().to_string();
+                let mut lines = vec![format!(
+                    "{} commit(s) will be rebased onto {destination}, their descendants stay:",
+                    self.selected_changes.len()
+                )];
+                lines.extend(self.selected_change_lines());
+                self.show_confirm(
+                    "Confirm rebase of selected commits",
+                    lines,
+                    ConfirmAction::RebaseChanges {
+                        change_ids: self.selected_changes.clone(),
+                        destination,
+                    },
+                );
+            }
+            PopupCallback::Username => {
+                self.auth_username = Some(text.trim().to_string());
+                self.show_secret_popup("Password or token");
+            }
+            PopupCallback::Secret => {
+                askpass::set_credentials(Credentials {
+                    username: self.auth_username.take(),
+                    secret:   text.to_string(),
+                });
+                if let Some(operation) = self.auth_retry.take() {
+                    self.run_remote_operation(operation)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn show_secret_popup(&mut self, title: &str) {
+        self.show_input_popup(title, PopupCallback::Secret);
+        if let PopupState::Input { textarea, .. } = &mut self.popup_state {
+            textarea.set_mask_char('•');
+        }
+    }
+
+    /// Prompt for credentials when `error` is an authentication failure and retry
+    /// `operation` once they are entered. Returns false for any other error
+    fn prompt_for_credentials(
+        &mut self,
+        error: &anyhow::Error,
+        operation: RemoteOperation,
+    ) -> bool {
+        let Some(method) = askpass::auth_method(error) else {
+            return false;
+        };
+
+        // Whatever was entered before didn't work
+        askpass::clear_credentials();
+        self.clear_loading();
+        self.take_operation_duration();
+        self.auth_retry = Some(operation);
+        match method {
+            AuthMethod::Ssh => {
+                self.show_secret_popup("SSH key passphrase (or load the key with ssh-add)");
+            }
+            AuthMethod::Https => {
+                self.show_input_popup("Username for the remote", PopupCallback::Username);
+            }
+        }
+        true
+    }
+
+    fn run_remote_operation(&mut self, operation: RemoteOperation) -> Result<()> {
+        match operation {
+            RemoteOperation::Fetch { bookmark, depth } => self.fetch(bookmark.as_deref(), depth),
+            RemoteOperation::Push(target) => {
+                self.start_push(target);
+                Ok(())
+            }
+        }
+    }
+
+    fn show_confirm(&mut self, title: &str, mut lines: Vec<String>, action: ConfirmAction) {
+        lines.push(String::new());
+        lines.push("Enter/y to confirm, Esc/n to cancel".to_string());
+        self.open_popup(PopupState::Confirm {
+            title: title.to_string(),
+            lines,
+            action,
+            required_input: None,
+            typed: String::new(),
+        });
+    }
+
+    /// Ask for the bookmark name to be typed out before touching a protected bookmark
+    fn show_protected_confirm(
+        &mut self,
+        bookmark: &str,
+        mut lines: Vec<String>,
+        action: ConfirmAction,
+    ) {
+        let bookmark = bookmark.trim_end_matches('*').to_string();
+        lines.push(String::new());
+        lines.push(format!(
+            "{bookmark} is protected, type its name and press Enter to continue (Esc to cancel)"
+        ));
+        self.open_popup(PopupState::Confirm {
+            title: "Protected bookmark".to_string(),
+            lines,
+            action,
+            required_input: Some(bookmark),
+            typed: String::new(),
+        });
+    }
+
+    fn execute_confirmed_action(&mut self, action: ConfirmAction) -> Result<()> {
+        match action {
+            ConfirmAction::Rebase { destination } => {
+                let conflicted = Self::conflicted_commits();
+                self.begin_operation();
+                match jj_ops::rebase(&destination) {
+                    Ok(result) => {
+                        self.set_status_message(result.summary(&destination));
                         self.refresh_all()?;
+                        self.warn_about_new_conflicts(&conflicted);
                     }
                     Err(e) => {
                         self.show_error(format!("Failed to rebase: {e}"));
                     }
                 }
             }
+            ConfirmAction::Push { bookmark } => {
+                self.start_push(PushTarget::Bookmark(bookmark));
+            }
+            ConfirmAction::MoveBookmark { name, revision } => {
+                self.begin_operation();
+                match jj_ops::move_bookmark(&name, &revision) {
+                    Ok(_) => {
+                        self.set_status_message(format!("Moved bookmark {name} to {revision}"));
+                        self.refresh_all()?;
+                    }
+                    Err(e) => {
+                        self.show_error(format!("Failed to move bookmark: {e}"));
+                    }
+                }
+            }
+            ConfirmAction::CheckoutBookmark { name } => {
+                self.checkout_bookmark(&name)?;
+            }
+            ConfirmAction::DeleteBookmarks { names } => {
+                self.delete_bookmarks(&names)?;
+            }
+            ConfirmAction::RemoveRemote { name } => {
+                self.begin_operation();
+                match jj_ops::remove_remote(&name) {
+                    Ok(_) => {
+                        self.set_status_message(format!("Removed remote {name}"));
+                        self.refresh_remotes();
+                        if self.native_ops.default_remote == name {
+                            self.forget_default_remote();
+                        }
+                        self.refresh_all()?;
+                    }
+                    Err(e) => {
+                        self.show_error(format!("Failed to remove remote: {e}"));
+                    }
+                }
+            }
+            ConfirmAction::AbandonChanges { change_ids } => {
+                self.begin_operation();
+                match jj_ops::abandon(&change_ids) {
+                    Ok(_) => {
+                        self.selected_changes.clear();
+                        self.set_status_message(format!(
+                            "Abandoned {} commit(s)",
+                            change_ids.len()
+                        ));
+                        self.refresh_all()?;
+                    }
+                    Err(e) => self.show_error(format!("Failed to abandon: {e}")),
+                }
+            }
+            ConfirmAction::RebaseChanges {
+                change_ids,
+                destination,
+            } => {
+                let conflicted = Self::conflicted_commits();
+                self.begin_operation();
+                match jj_ops::rebase_revisions(&change_ids, &destination) {
+                    Ok(result) => {
+                        self.selected_changes.clear();
+                        self.set_status_message(result.summary(&destination));
+                        self.refresh_all()?;
+                        self.warn_about_new_conflicts(&conflicted);
+                    }
+                    Err(e) => self.show_error(format!("Failed to rebase: {e}")),
+                }
+            }
+            ConfirmAction::PushBookmarks { names } => {
+                self.start_push(PushTarget::Bookmarks(names));
+            }
+            ConfirmAction::TakeConflictSide { path, side } => {
+                self.begin_operation();
+                match jj_ops::take_conflict_side(&path, side) {
+                    Ok(_) => {
+                        self.set_status_message(format!("Resolved {path} with {}", side.label()));
+                        self.refresh_all()?;
+                    }
+                    Err(e) => self.show_error(format!("Failed to resolve {path}: {e}")),
+                }
+            }
+            ConfirmAction::ResetAuthor { change_id } => {
+                self.begin_operation();
+                match self.native_ops.reset_author(&change_id) {
+                    Ok(message) => {
+                        self.set_status_message(message);
+                        self.refresh_all()?;
+                    }
+                    Err(e) => self.show_error(format!("Failed to reset the author: {e}")),
+                }
+            }
+            ConfirmAction::PushDeleted => self.start_push(PushTarget::Deleted),
+            ConfirmAction::RestoreOperation { operation } => {
+                self.begin_operation();
+                match jj_ops::restore_operation(&operation) {
+                    Ok(_) => {
+                        self.set_status_message(format!(
+                            "Restored operation {}",
+                            &operation[..operation.len().min(12)]
+                        ));
+                        self.refresh_all()?;
+                    }
+                    Err(e) => self.show_error(format!("Failed to restore the operation: {e}")),
+                }
+            }
+            ConfirmAction::Quit => self.should_quit = true,
+        }
+        Ok(())
+    }
+
+    fn create_bookmark(&mut self, name: &str, revision: &str) -> Result<()> {
+        self.begin_operation();
+        match jj_ops::create_bookmark(name, revision) {
+            Ok(_) => {
+                self.set_status_message(format!("Created bookmark {name} at {revision}"));
+                self.refresh_all()?;
+            }
+            Err(e) => {
+                self.show_error(format!("Failed to create bookmark: {e}"));
+            }
         }
         Ok(())
     }
 
     fn handle_new_commit(&mut self) -> Result<()> {
         // Check if working copy is already empty
-        match jj_ops::is_working_copy_empty() {
+        match self.native_ops.is_working_copy_empty() {
             Ok(true) => {
                 self.show_warning("Already on an empty commit. Add changes first.".to_string());
                 return Ok(());
             }
             Ok(false) => {
                 // Working copy has changes, proceed with new commit
+                self.begin_operation();
                 match jj_ops::new_commit() {
-                    Ok(_) => {
-                        self.set_status_message("Created new commit".to_string());
+                    Ok(change) => {
+                        self.set_status_message(change.summary());
                         self.refresh_all()?;
                     }
                     Err(e) => {
@@ -687,52 +4041,236 @@ impl App {
                 }
             }
             Err(e) => {
-                self.show_error(format!("Failed to check working copy: {e}"));
+                self.show_error(format!("Failed to check working copy: {e}"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Ask for the fetch depth, prefilled with the configured one
+    fn show_fetch_popup(&mut self) {
+        let depth = self
+            .settings
+            .fetch_depth
+            .map(|depth| depth.to_string())
+            .unwrap_or_default();
+        self.show_prefilled_input_popup(
+            "Fetch depth (empty for the full history)",
+            PopupCallback::FetchDepth,
+            &depth,
+            Vec::new(),
+        );
+    }
+
+    /// Fetch `bookmark` or all bookmarks when `None`, with at most `depth` commits
+    /// of history, everything when `None`
+    fn fetch(&mut self, bookmark: Option<&str>, depth: Option<NonZeroU32>) -> Result<()> {
+        self.loading_start = Some(Instant::now());
+        self.begin_operation();
+        // For now pick the default remote from the native_operations crate
+        // Should create a proper selection at some point, or a config option
+        // to set a preferred remote - for now default is just fine as most will use 'origin'
+        match self.native_ops.git_fetch(None, bookmark, depth) {
+            Ok(stats) => {
+                self.clear_loading();
+                self.set_status_message(stats.summary());
+                self.refresh_all()?;
+            }
+            Err(e) => {
+                let operation = RemoteOperation::Fetch {
+                    bookmark: bookmark.map(str::to_string),
+                    depth,
+                };
+                if !self.prompt_for_credentials(&e, operation) {
+                    self.show_error(format!("Failed to fetch: {e}"));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Preview the push with `--dry-run` and ask for confirmation
+    fn handle_push(&mut self) -> Result<()> {
+        match PushPlan::new(None, &self.settings) {
+            Ok(plan) if plan.preview.is_empty() => {
+                self.set_status_message(plan.preview.summary());
+            }
+            Ok(plan) => {
+                let mut lines = vec![plan.bookmark.as_ref().map_or_else(
+                    || "Pushing the current change creates a new bookmark:".to_string(),
+                    |b| format!("Pushing bookmark {b}:"),
+                )];
+                lines.extend(
+                    plan.preview
+                        .bookmarks
+                        .iter()
+                        .map(|b| format!("  {}", b.detail)),
+                );
+
+                let action = ConfirmAction::Push {
+                    bookmark: plan.bookmark.clone(),
+                };
+                match plan.protected_bookmark(&self.settings) {
+                    Some(name) => self.show_protected_confirm(name, lines, action),
+                    None => self.show_confirm("Confirm push", lines, action),
+                }
+            }
+            Err(e) => {
+                self.show_error(format!("Failed to push: {e}"));
             }
         }
         Ok(())
     }
 
-    fn handle_fetch(&mut self) -> Result<()> {
-        self.loading_start = Some(Instant::now());
-        // For now pick the default remote from the native_operations crate
-        // Should create a proper selection at some point, or a config option
-        // to set a preferred remote - for now default is just fine as most will use 'origin'
-        match self.native_ops.git_fetch(None) {
-            Ok(_) => {
-                self.clear_loading();
-                self.set_status_message("Fetched from remote".to_string());
+    /// Push in the background, the ui keeps drawing the spinner until `poll_push` picks
+    /// up the result
+    fn start_push(&mut self, target: PushTarget) {
+        if self.push_task.is_running() {
+            self.show_warning("Wait for the running push to finish first".to_string());
+            return;
+        }
+        self.show_loading(target.loading_message().to_string());
+        let remote = self.settings.default_remote.clone();
+        self.push_task.start(move || {
+            let result = target.push(remote.as_deref());
+            (target, result)
+        });
+    }
+
+    /// Report the push once it is done
+    pub fn poll_push(&mut self) -> Result<()> {
+        let Some(((target, result), duration)) = self.push_task.poll() else {
+            return Ok(());
+        };
+        self.clear_loading();
+        // Other operations may have run meanwhile, the message reports the push
+        self.operation_start = Instant::now().checked_sub(duration);
+        match result {
+            Ok(result) => {
+                if matches!(target, PushTarget::Bookmarks(_)) {
+                    self.selected_changes.clear();
+                }
+                self.set_status_message(result.summary());
                 self.refresh_all()?;
+                if matches!(target, PushTarget::Bookmark(_)) {
+                    self.show_push_follow_ups(&result);
+                }
             }
             Err(e) => {
-                self.show_error(format!("Failed to fetch: {e}"));
+                let message = format!("Failed to push: {e}");
+                if !self.prompt_for_credentials(&e, RemoteOperation::Push(target)) {
+                    self.show_error(message);
+                }
             }
         }
         Ok(())
     }
 
-    fn handle_push(&mut self) -> Result<()> {
-        self.show_loading("Pushing to remote".to_string());
-        let bookmark = jj_ops::get_current_bookmark().ok().flatten();
-        match jj_ops::git_push(bookmark.as_deref()) {
-            Ok(_) => {
-                self.clear_loading();
-                let msg = bookmark.map_or_else(
-                    || "Pushed current change (created temporary bookmark)".to_string(),
-                    |b| format!("Pushed bookmark: {b}"),
-                );
-                self.set_status_message(msg);
-                self.refresh_all()?;
+    /// Offer what usually comes after pushing a single bookmark, like opening a pull request
+    fn show_push_follow_ups(&mut self, result: &PushResult) {
+        let [pushed] = result.bookmarks.as_slice() else {
+            return;
+        };
+        if pushed.action == PushAction::Delete {
+            return;
+        }
+
+        let remote = result
+            .remote
+            .as_deref()
+            .or(self.settings.default_remote.as_deref());
+        // The Remotes tab may not have been loaded yet
+        let remotes = jj_ops::get_remotes().unwrap_or_default();
+        let web_url = remote
+            .and_then(|name| remotes.iter().find(|remote| remote.name == name))
+            .and_then(|remote| forge::web_url(&remote.url));
+
+        let mut items = Vec::new();
+        if web_url.is_some() {
+            items.push(MenuItem::new('o', "Open or create a pull request"));
+            // Comparing needs a branch name on the remote
+            if self.trunk.is_some() {
+                items.push(MenuItem::new('u', "Copy the compare url"));
             }
-            Err(e) => {
-                self.clear_loading();
-                self.show_error(format!("Failed to push: {e}"));
+        }
+        items.extend([
+            MenuItem::new('y', "Copy the bookmark name"),
+            MenuItem::new('n', "Start a new change on trunk"),
+        ]);
+
+        self.open_popup(PopupState::ContextMenu {
+            title: format!("Pushed {}", pushed.name),
+            items,
+            selected: 0,
+            target: MenuTarget::PushedBookmark {
+                bookmark: pushed.name.clone(),
+                web_url,
+            },
+        });
+    }
+
+    fn run_push_follow_up(
+        &mut self,
+        code: KeyCode,
+        bookmark: &str,
+        web_url: Option<&str>,
+    ) -> Result<()> {
+        match (code, web_url) {
+            (KeyCode::Char('o'), Some(web_url)) => {
+                let url = forge::pull_request_url(web_url, bookmark);
+                match forge::open(&url) {
+                    Ok(()) => self.set_status_message(format!("Opened {url}")),
+                    Err(e) => self.show_error(format!("Failed to open {url}: {e}")),
+                }
+            }
+            (KeyCode::Char('u'), Some(web_url)) => {
+                let Some(base) = self.trunk.clone() else {
+                    return Ok(());
+                };
+                let url = forge::compare_url(web_url, &base, bookmark);
+                match clipboard::copy(&url) {
+                    Ok(()) => self.set_status_message(format!("Copied {url}")),
+                    Err(e) => self.show_error(format!("Failed to copy the url: {e}")),
+                }
+            }
+            (KeyCode::Char('y'), _) => match clipboard::copy(bookmark) {
+                Ok(()) => self.set_status_message(format!("Copied {bookmark}")),
+                Err(e) => self.show_error(format!("Failed to copy the bookmark name: {e}")),
+            },
+            (KeyCode::Char('n'), _) => {
+                self.begin_operation();
+                let trunk = self.trunk.clone().unwrap_or_else(|| "trunk()".to_string());
+                match jj_ops::new_on_bookmark(&trunk) {
+                    Ok(change) => {
+                        self.set_status_message(format!("{} on {trunk}", change.summary()));
+                        self.refresh_all()?;
+                    }
+                    Err(e) => self.show_error(format!("Failed to create a new change: {e}")),
+                }
             }
+            _ => {}
         }
         Ok(())
     }
 
+    /// Mark the start of a jj operation, the next status message or error
+    /// reports how long it took
+    fn begin_operation(&mut self) {
+        self.operation_start = Some(Instant::now());
+    }
+
+    fn take_operation_duration(&mut self) -> Option<Duration> {
+        self.operation_start.take().map(|start| start.elapsed())
+    }
+
     pub fn set_status_message(&mut self, message: String) {
+        let duration = self.take_operation_duration();
+        self.notifications
+            .push(NotificationLevel::Info, message.clone(), duration);
+        let message = match duration {
+            Some(duration) => format!("{message} ({})", format_duration(duration)),
+            None => message,
+        };
         self.status_message = Some(message);
         self.status_message_timestamp = Some(Instant::now());
         self.needs_redraw = true;
@@ -744,6 +4282,160 @@ impl App {
         self.needs_redraw = true;
     }
 
+    /// Check every few seconds whether another process changed the repo,
+    /// so the user knows the shown data is stale
+    pub fn check_external_changes(&mut self) {
+        const CHECK_INTERVAL: Duration = Duration::from_secs(2);
+
+        if self.external_operation.is_some() || self.last_operation_check.elapsed() < CHECK_INTERVAL
+        {
+            return;
+        }
+        self.last_operation_check = Instant::now();
+        self.check_git_head();
+
+        let Some(known) = &self.known_operation else {
+            return;
+        };
+        if let Ok(current) = jj_ops::get_operation_id()
+            && &current != known
+        {
+            // Another jjkk on this workspace changes it all the time, follow along unless
+            // that would pull the data from under a popup or a running operation. Both of
+            // them journal their changes, whichever started first
+            if journal::recorded(&current)
+                && matches!(self.popup_state, PopupState::None)
+                && self.loading_message.is_none()
+            {
+                if let Err(e) = self.refresh_all() {
+                    self.show_error(format!("Failed to refresh: {e}"));
+                }
+                return;
+            }
+            self.external_operation = Some(current);
+            self.needs_redraw = true;
+        }
+    }
+
+    /// Notice `git checkout` or `git commit` run in a colocated repo, jj only sees them once
+    /// it imports the git refs
+    fn check_git_head(&mut self) {
+        let git_dir = self
+            .colocated_git_dir
+            .get_or_init(|| interop::colocated_git_dir().ok().flatten());
+        let Some(git_dir) = git_dir else {
+            return;
+        };
+        // Asking jj and git takes two processes, only worth it once git's HEAD moved or jj
+        // ran an operation, like an import
+        let checked = interop::git_head_modified(git_dir)
+            .map(|modified| (modified, self.known_operation.clone()));
+        if checked.is_some() && checked == self.git_head_checked {
+            return;
+        }
+        self.git_head_checked = checked;
+        let drift = interop::git_head_drift(git_dir).ok().flatten();
+        if drift != self.git_head_drift {
+            self.git_head_drift = drift;
+            self.needs_redraw = true;
+        }
+    }
+
+    pub const fn has_terminal_action(&self) -> bool {
+        self.terminal_action.is_some()
+    }
+
+    pub const fn take_terminal_action(&mut self) -> Option<TerminalAction> {
+        self.terminal_action.take()
+    }
+
+    /// Back from the shell, where anything could have happened to the repo
+    pub fn finish_terminal_action(
+        &mut self,
+        action: &TerminalAction,
+        result: Result<String>,
+    ) -> Result<()> {
+        self.refresh_all()?;
+        match result {
+            Ok(message) => self.set_status_message(message),
+            Err(e) => self.show_error(e.to_string()),
+        }
+
+        // Editing moves the lines below, the hunk list still open has to follow
+        if matches!(action, TerminalAction::Edit { .. }) {
+            self.reload_hunk_list();
+        }
+
+        // Merge tools can exit without resolving everything
+        if let TerminalAction::Resolve { path, .. } = action {
+            let conflicted: Vec<&str> = self
+                .files
+                .iter()
+                .filter(|file| file.status == ChangeType::Conflicted)
+                .map(|file| file.path.as_str())
+                .collect();
+            if conflicted.contains(&path.as_str()) {
+                self.show_warning(format!("{path} still has conflicts"));
+            } else if let Some(next) = conflicted.first() {
+                let message = format!(
+                    "Resolved {path}, {} conflicted file(s) left",
+                    conflicted.len()
+                );
+                if let Some(index) = self.files.iter().position(|file| file.path == *next) {
+                    self.select_file(index);
+                }
+                self.set_status_message(message);
+            }
+        }
+        Ok(())
+    }
+
+    /// The terminal was resized, everything has to be laid out again
+    pub const fn handle_resize(&mut self) {
+        self.needs_redraw = true;
+    }
+
+    pub const fn is_focused(&self) -> bool {
+        self.unfocused_since.is_none()
+    }
+
+    pub fn handle_focus_lost(&mut self) {
+        self.unfocused_since = Some(Instant::now());
+    }
+
+    /// Back in the terminal, files may have been edited or jj run elsewhere in the meantime
+    pub fn handle_focus_gained(&mut self) {
+        if self.unfocused_since.take().is_none() {
+            return;
+        }
+        // Reload in the background, a failure shows up as an error popup once it is done. With a
+        // popup open or an operation running, only point out changes made elsewhere
+        if matches!(self.popup_state, PopupState::None) && self.loading_message.is_none() {
+            self.start_refresh_all();
+        } else {
+            self.check_external_changes();
+        }
+        self.needs_redraw = true;
+    }
+
+    /// Keep scroll offsets within what the last draw could show, a smaller
+    /// terminal or pane would otherwise leave them pointing past the content
+    pub fn clamp_scroll_offsets(&mut self) {
+        self.diff_scroll_offset = self.diff_scroll_offset.min(self.diff_scroll_max.get());
+        self.popup_scroll = self.popup_scroll.min(self.popup_scroll_max.get());
+    }
+
+    /// Redraw when the minute the status bar clock shows is over
+    pub fn check_clock(&mut self) {
+        if self
+            .clock_minute
+            .get()
+            .is_some_and(|minute| minute != clock_minute(&Local::now()))
+        {
+            self.needs_redraw = true;
+        }
+    }
+
     pub fn update_status_message_timeout(&mut self) {
         if let Some(timestamp) = self.status_message_timestamp
             && timestamp.elapsed().as_secs() >= 2
@@ -753,11 +4445,17 @@ impl App {
     }
 
     pub fn show_error(&mut self, message: String) {
-        self.popup_state = PopupState::Error { message };
+        let duration = self.take_operation_duration();
+        self.notifications
+            .push(NotificationLevel::Error, message.clone(), duration);
+        self.open_popup(PopupState::Error { message });
     }
 
     pub fn show_warning(&mut self, message: String) {
-        self.popup_state = PopupState::Warning { message };
+        let duration = self.take_operation_duration();
+        self.notifications
+            .push(NotificationLevel::Warning, message.clone(), duration);
+        self.open_popup(PopupState::Warning { message });
     }
 
     pub fn show_loading(&mut self, message: String) {
@@ -774,7 +4472,11 @@ impl App {
 
     pub fn get_spinner_char(&self) -> char {
         self.loading_start.map_or(' ', |start| {
-            let frames = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+            let frames: &[char] = if self.theme.ascii_markers() {
+                &['|', '/', '-', '\\']
+            } else {
+                &['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏']
+            };
             let elapsed = start.elapsed().as_millis();
             let frame_index = (elapsed / 80) as usize % frames.len();
             frames[frame_index]
@@ -785,18 +4487,484 @@ impl App {
         // Use cached bookmarks instead of fetching again
         if let Some(bookmark) = self.bookmarks.get(self.selected_bookmark_index) {
             let bookmark_name = bookmark.name.clone();
-            match jj_ops::checkout_bookmark(&bookmark_name) {
+            // A conflicted bookmark can't be checked out, it has to be resolved first
+            if bookmark.conflicted {
+                self.show_bookmark_conflict(bookmark_name);
+                return Ok(());
+            }
+            // Checking out moves the bookmark to @
+            if self.settings.is_protected_bookmark(&bookmark_name) {
+                self.show_protected_confirm(
+                    &bookmark_name,
+                    vec![format!(
+                        "Checking out moves protected bookmark {bookmark_name} to @"
+                    )],
+                    ConfirmAction::CheckoutBookmark {
+                        name: bookmark_name.clone(),
+                    },
+                );
+            } else {
+                self.checkout_bookmark(&bookmark_name)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Start a new change on the selected bookmark and put git on its branch too, so IDEs and
+    /// build scripts reading git see the same branch
+    fn checkout_bookmark_in_git(&mut self) {
+        let Some(bookmark) = self.bookmarks.get(self.selected_bookmark_index) else {
+            return;
+        };
+        let name = bookmark.name.clone();
+        if bookmark.conflicted {
+            self.show_bookmark_conflict(name);
+            return;
+        }
+
+        self.begin_operation();
+        match jj_ops::new_on_bookmark(&name) {
+            Ok(_) => {
+                self.remember(RecentContext::Bookmark(name.clone()));
+                match interop::attach_git_head(&name) {
+                    Ok(message) => {
+                        self.set_status_message(format!(
+                            "Started a new change on {name}, {message}"
+                        ));
+                    }
+                    Err(e) => self.show_warning(format!(
+                        "Started a new change on {name}, but git stays detached: {e}"
+                    )),
+                }
+            }
+            Err(e) => self.show_error(format!("Failed to start a new change on {name}: {e}")),
+        }
+        if let Err(e) = self.refresh_all() {
+            self.show_error(format!("Failed to refresh: {e}"));
+        }
+    }
+
+    fn show_bookmark_conflict(&mut self, name: String) {
+        match jj_ops::get_bookmark_targets(&name) {
+            Ok(targets) if targets.len() > 1 => {
+                self.open_popup(PopupState::BookmarkConflict {
+                    name,
+                    targets,
+                    selected: 0,
+                });
+            }
+            Ok(_) => {
+                self.show_warning(format!("Bookmark {name} is no longer conflicted"));
+                self.refresh_bookmarks();
+            }
+            Err(e) => self.show_error(format!("Failed to load targets of {name}: {e}")),
+        }
+    }
+
+    /// Point the conflicted bookmark `name` at `commit_id`, one of its targets
+    fn resolve_bookmark(&mut self, name: &str, commit_id: &str) {
+        self.begin_operation();
+        match self.native_ops.resolve_bookmark(name, commit_id) {
+            Ok(message) => {
+                self.set_status_message(message);
+                if let Err(e) = self.refresh_all() {
+                    self.show_error(format!("Failed to refresh: {e}"));
+                }
+            }
+            Err(e) => self.show_error(format!("Failed to resolve bookmark {name}: {e}")),
+        }
+    }
+
+    fn handle_bookmark_delete(&mut self) {
+        let Some(bookmark) = self.bookmarks.get(self.selected_bookmark_index) else {
+            return;
+        };
+        let name = bookmark.name.clone();
+        let lines = vec![format!("Delete bookmark {name}?")];
+        let action = ConfirmAction::DeleteBookmarks {
+            names: vec![name.clone()],
+        };
+
+        if self.settings.is_protected_bookmark(&name) {
+            self.show_protected_confirm(&name, lines, action);
+        } else {
+            self.show_confirm("Delete bookmark", lines, action);
+        }
+    }
+
+    /// Offer to delete local bookmarks that are already merged into trunk
+    fn handle_bookmark_cleanup(&mut self) {
+        let merged = match jj_ops::get_merged_bookmarks() {
+            Ok(merged) => merged,
+            Err(e) => {
+                self.show_error(format!("Failed to find merged bookmarks: {e}"));
+                return;
+            }
+        };
+        // Protected bookmarks are never cleaned up in bulk
+        let names: Vec<String> = merged
+            .into_iter()
+            .filter(|name| !self.settings.is_protected_bookmark(name))
+            .collect();
+
+        if names.is_empty() {
+            self.set_status_message("No merged bookmarks to clean up".to_string());
+            return;
+        }
+
+        let mut lines = vec![format!(
+            "Delete {} bookmark(s) already merged into trunk:",
+            names.len()
+        )];
+        lines.extend(names.iter().map(|name| format!("  {name}")));
+        self.show_confirm(
+            "Clean up merged bookmarks",
+            lines,
+            ConfirmAction::DeleteBookmarks { names },
+        );
+    }
+
+    fn delete_bookmarks(&mut self, names: &[String]) -> Result<()> {
+        self.begin_operation();
+        match jj_ops::delete_bookmarks(names) {
+            Ok(_) => {
+                self.set_status_message(format!("Deleted bookmark(s): {}", names.join(", ")));
+                self.refresh_all()?;
+                self.offer_push_deleted();
+            }
+            Err(e) => {
+                self.show_error(format!("Failed to delete bookmark(s): {e}"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Ask whether deleted bookmarks should also be deleted on the remote,
+    /// only when the dry-run shows there is something to push
+    fn offer_push_deleted(&mut self) {
+        match jj_ops::git_push_deleted(self.settings.default_remote.as_deref(), true) {
+            Ok(preview) if preview.is_empty() => {}
+            Ok(preview) => {
+                let mut lines = vec!["Delete the bookmark(s) on the remote as well?".to_string()];
+                lines.extend(preview.bookmarks.iter().map(|b| format!("  {}", b.detail)));
+                self.show_confirm("Push deleted bookmarks", lines, ConfirmAction::PushDeleted);
+            }
+            Err(e) => {
+                self.show_error(format!("Failed to preview pushing deleted bookmarks: {e}"));
+            }
+        }
+    }
+
+    fn show_add_remote_form(&mut self) {
+        self.open_popup(PopupState::Form {
+            title:  "Add remote".to_string(),
+            form:   Form::new(vec![
+                FormField::new("Name", form::name),
+                FormField::new("URL", form::required),
+            ]),
+            action: FormAction::AddRemote,
+        });
+    }
+
+    /// Change the name and url of the selected remote in one form
+    fn show_edit_remote_form(&mut self) {
+        let Some(remote) = self.remotes.get(self.selected_remote_index) else {
+            return;
+        };
+        self.open_popup(PopupState::Form {
+            title:  format!("Edit remote {}", remote.name),
+            form:   Form::new(vec![
+                FormField::new("Name", form::name).with_value(&remote.name),
+                FormField::new("URL", form::required).with_value(&remote.url),
+            ]),
+            action: FormAction::EditRemote {
+                name: remote.name.clone(),
+                url:  remote.url.clone(),
+            },
+        });
+    }
+
+    /// Run `action` with the values of a submitted form, in the order of its fields
+    fn execute_form_action(&mut self, action: FormAction, values: &[String]) {
+        match (action, values) {
+            (FormAction::AddRemote, [name, url]) => match jj_ops::add_remote(name, url) {
                 Ok(_) => {
-                    self.set_status_message(format!("Checked out bookmark: {bookmark_name}"));
-                    // auto track the bookmark
-                    jj_ops::auto_track_bookmark(&bookmark_name).ok();
-                    self.refresh_all()?;
+                    self.set_status_message(format!("Added remote {name}"));
+                    self.refresh_remotes();
                 }
                 Err(e) => {
-                    self.show_error(format!("Failed to checkout bookmark: {e}"));
+                    self.show_error(format!("Failed to add remote: {e}"));
+                }
+            },
+            (FormAction::EditRemote { name, url }, [new_name, new_url]) => {
+                self.edit_remote(&name, &url, new_name, new_url);
+            }
+            (FormAction::AddNote { path }, [line, text]) => {
+                let Some(working_copy) = &self.working_copy else {
+                    return;
+                };
+                let note = Note {
+                    change_id: working_copy.change_id.clone(),
+                    path,
+                    line: line.parse().ok(),
+                    text: text.clone(),
+                };
+                match self.notes.add(note) {
+                    Ok(()) => self.set_status_message("Added the note".to_string()),
+                    Err(e) => self.show_error(format!("Failed to save the note: {e}")),
                 }
             }
+            (
+                FormAction::AddRemote | FormAction::EditRemote { .. } | FormAction::AddNote { .. },
+                _,
+            ) => {}
+        }
+    }
+
+    /// Rename the remote and point it at `new_url`, whichever of them changed
+    fn edit_remote(&mut self, name: &str, url: &str, new_name: &str, new_url: &str) {
+        if new_name != name {
+            if let Err(e) = jj_ops::rename_remote(name, new_name) {
+                self.show_error(format!("Failed to rename remote: {e}"));
+                return;
+            }
+            if self.native_ops.default_remote == name {
+                self.save_default_remote(new_name);
+            }
+            // Follow the remote to its new name
+            if let Some(remote) = self.remotes.get_mut(self.selected_remote_index) {
+                remote.name = new_name.to_string();
+            }
+            // Renaming moves the remote bookmarks in a jj operation of its own
+            if let Err(e) = self.refresh_all() {
+                self.show_error(format!("Failed to refresh: {e}"));
+            }
+        }
+        if new_url != url
+            && let Err(e) = jj_ops::set_remote_url(new_name, new_url)
+        {
+            self.show_error(format!("Failed to change the url of {new_name}: {e}"));
+            self.refresh_remotes();
+            return;
+        }
+        self.set_status_message(format!("Updated remote {new_name}"));
+        self.refresh_remotes();
+    }
+
+    fn confirm_remove_remote(&mut self) {
+        let Some(remote) = self.remotes.get(self.selected_remote_index) else {
+            return;
+        };
+        let lines = vec![
+            format!("Remove remote {} ({})?", remote.name, remote.url),
+            "Its remote bookmarks are forgotten, local bookmarks are kept".to_string(),
+        ];
+        let action = ConfirmAction::RemoveRemote {
+            name: remote.name.clone(),
+        };
+        self.show_confirm("Remove remote", lines, action);
+    }
+
+    /// Fetch from and push to the selected remote from now on
+    fn set_default_remote(&mut self) {
+        let Some(name) = self
+            .remotes
+            .get(self.selected_remote_index)
+            .map(|remote| remote.name.clone())
+        else {
+            return;
+        };
+        self.save_default_remote(&name);
+        self.set_status_message(format!("Default remote is now {name}"));
+    }
+
+    /// Use `name` as default remote and remember it in the config file
+    fn save_default_remote(&mut self, name: &str) {
+        self.native_ops.default_remote = name.to_string();
+        if let Err(e) = self.settings.save_default_remote(name) {
+            self.show_error(format!("Failed to save the default remote: {e}"));
+        }
+    }
+
+    /// Fall back to the first remote left, like on startup, once the default one is removed
+    fn forget_default_remote(&mut self) {
+        self.native_ops.default_remote = self
+            .remotes
+            .first()
+            .map_or_else(|| "origin".to_string(), |remote| remote.name.clone());
+        if let Err(e) = self.settings.clear_default_remote() {
+            self.show_error(format!("Failed to forget the default remote: {e}"));
+        }
+    }
+
+    fn checkout_bookmark(&mut self, bookmark_name: &str) -> Result<()> {
+        self.begin_operation();
+        match jj_ops::checkout_bookmark(bookmark_name) {
+            Ok(_) => {
+                self.set_status_message(format!("Checked out bookmark: {bookmark_name}"));
+                self.remember(RecentContext::Bookmark(bookmark_name.to_string()));
+                // auto track the bookmark
+                jj_ops::auto_track_bookmark(bookmark_name).ok();
+                self.refresh_all()?;
+            }
+            Err(e) => {
+                self.show_error(format!("Failed to checkout bookmark: {e}"));
+            }
         }
         Ok(())
     }
 }
+
+/// Move `selected` through a list of `len` rows with j/k or the arrows, false for other keys
+fn move_selection(selected: &mut usize, len: usize, key: KeyCode) -> bool {
+    match key {
+        KeyCode::Char('j') | KeyCode::Down => {
+            *selected = (*selected + 1).min(len.saturating_sub(1));
+        }
+        KeyCode::Char('k') | KeyCode::Up => *selected = selected.saturating_sub(1),
+        _ => return false,
+    }
+    true
+}
+
+/// Keys that only move around or change what is shown, they never make jj change the repo
+fn is_view_key(key: KeyEvent) -> bool {
+    match key.code {
+        KeyCode::Up
+        | KeyCode::Down
+        | KeyCode::PageUp
+        | KeyCode::PageDown
+        | KeyCode::Tab
+        | KeyCode::BackTab
+        | KeyCode::Esc => true,
+        KeyCode::Char(c) => {
+            !key.modifiers
+                .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+                && "jkJK1234?qNzvHL".contains(c)
+        }
+        _ => false,
+    }
+}
+
+/// One line summary of the commit `revision` points at
+fn describe_revision(revision: &str) -> String {
+    log::get_log_for_revset(Some(revision), 1)
+        .ok()
+        .and_then(|commits| commits.into_iter().next())
+        .map_or_else(
+            || format!("{revision} (not found)"),
+            |commit| format!("{} {}", commit.change_id, commit.description),
+        )
+}
+
+/// Index of the item identified by `selected` in the reloaded `items`,
+/// `index` clamped to the list when that item is gone
+fn relocate<T>(
+    items: &[T],
+    selected: Option<&str>,
+    index: usize,
+    id: impl Fn(&T) -> &str,
+) -> usize {
+    selected
+        .and_then(|selected| items.iter().position(|item| id(item) == selected))
+        .unwrap_or_else(|| index.min(items.len().saturating_sub(1)))
+}
+
+/// Replace the content of a textarea, leaving the cursor at the end
+fn set_textarea_text(textarea: &mut TextArea<'static>, text: &str) {
+    *textarea = TextArea::new(text.lines().map(str::to_string).collect());
+    textarea.move_cursor(CursorMove::Bottom);
+    textarea.move_cursor(CursorMove::End);
+}
+
+/// Shortcuts of the describe and commit popups
+fn trailer_details() -> Vec<String> {
+    vec![
+        "Trailers".to_string(),
+        "Alt+S: Signed-off-by you".to_string(),
+        "Alt+C: Co-authored-by the next teammate".to_string(),
+        "Alt+A: Author override".to_string(),
+    ]
+}
+
+/// Insert a `key: value` trailer at the cursor, on a line of its own. The first trailer
+/// starts a block of its own, separated from the message by a blank line
+fn insert_trailer(textarea: &mut TextArea<'static>, key: &str, value: &str) {
+    if textarea.cursor().1 > 0 {
+        textarea.insert_newline();
+    }
+    let row = textarea.cursor().0;
+    let previous = row
+        .checked_sub(1)
+        .map(|row| (row, textarea.lines()[row].as_str()));
+    // The subject is never a trailer, even when it reads like `fix: the parser`
+    if previous
+        .is_some_and(|(row, line)| !line.trim().is_empty() && (row == 0 || !is_trailer(line)))
+    {
+        textarea.insert_newline();
+    }
+    textarea.insert_str(format!("{key}: {value}"));
+}
+
+/// Whether `line` is a `Key: value` trailer like `Signed-off-by: Me <me@example.com>`
+fn is_trailer(line: &str) -> bool {
+    line.split_once(": ").is_some_and(|(key, _)| {
+        !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_trailer() {
+        let mut textarea = TextArea::default();
+        set_textarea_text(&mut textarea, "Fix the parser");
+        insert_trailer(&mut textarea, "Signed-off-by", "Me <me@example.com>");
+        assert_eq!(
+            textarea.lines(),
+            ["Fix the parser", "", "Signed-off-by: Me <me@example.com>"]
+        );
+
+        // Further trailers join the block
+        insert_trailer(&mut textarea, "Co-authored-by", "You <you@example.com>");
+        assert_eq!(
+            textarea.lines(),
+            [
+                "Fix the parser",
+                "",
+                "Signed-off-by: Me <me@example.com>",
+                "Co-authored-by: You <you@example.com>"
+            ]
+        );
+
+        set_textarea_text(&mut textarea, "fix: the parser");
+        insert_trailer(&mut textarea, "Signed-off-by", "Me <me@example.com>");
+        assert_eq!(
+            textarea.lines(),
+            ["fix: the parser", "", "Signed-off-by: Me <me@example.com>"]
+        );
+    }
+
+    #[test]
+    fn test_move_selection() {
+        let mut selected = 0;
+        assert!(move_selection(&mut selected, 2, KeyCode::Char('j')));
+        assert!(move_selection(&mut selected, 2, KeyCode::Down));
+        assert_eq!(selected, 1);
+        assert!(move_selection(&mut selected, 2, KeyCode::Up));
+        assert_eq!(selected, 0);
+        assert!(!move_selection(&mut selected, 2, KeyCode::Enter));
+    }
+
+    #[test]
+    fn test_relocate() {
+        let items = ["a", "b", "c"];
+        assert_eq!(relocate(&items, Some("c"), 0, |item| item), 2);
+        // Gone, keep the position
+        assert_eq!(relocate(&items, Some("x"), 1, |item| item), 1);
+        assert_eq!(relocate(&items, Some("x"), 5, |item| item), 2);
+        assert_eq!(relocate(&[] as &[&str], Some("a"), 3, |item| item), 0);
+    }
+}
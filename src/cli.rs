@@ -0,0 +1,39 @@
+/// Command line options for jjkk
+#[derive(Debug, Default)]
+pub struct Args {
+    /// Disable all colors, also enabled through the `NO_COLOR` environment variable
+    pub no_color: bool,
+    /// Arguments of `jjkk exec`, running a single action without the TUI
+    pub exec:     Option<Vec<String>>,
+    /// `jjkk recover`, start on the journal of everything jjkk changed in the repo
+    pub recover:  bool,
+}
+
+impl Args {
+    /// Parse the arguments the process was started with
+    pub fn parse() -> Self {
+        let mut args = Self::default();
+
+        let mut process_args = std::env::args().skip(1).peekable();
+        if process_args.peek().is_some_and(|arg| arg == "exec") {
+            args.exec = Some(process_args.skip(1).collect());
+            return args;
+        }
+        if process_args.next_if(|arg| arg == "recover").is_some() {
+            args.recover = true;
+        }
+
+        for arg in process_args {
+            if arg.as_str() == "--no-color" {
+                args.no_color = true;
+            }
+        }
+
+        // See https://no-color.org - any non-empty value disables colors
+        if std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty()) {
+            args.no_color = true;
+        }
+
+        args
+    }
+}
@@ -0,0 +1,67 @@
+use std::{
+    io::Write,
+    process::Command,
+};
+
+use anyhow::Result;
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Copy `text` with the OSC 52 escape sequence, which also works over ssh
+/// and inside tmux with `set-clipboard on`
+pub fn copy(text: &str) -> Result<()> {
+    let mut stdout = std::io::stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", base64(text.as_bytes()))?;
+    stdout.flush()?;
+    Ok(())
+}
+
+/// Read the clipboard with the first clipboard tool of the platform that is installed
+pub fn paste() -> Result<String> {
+    const TOOLS: [(&str, &[&str]); 4] = [
+        ("pbpaste", &[]),
+        ("wl-paste", &["--no-newline"]),
+        ("xclip", &["-selection", "clipboard", "-o"]),
+        ("xsel", &["--clipboard", "--output"]),
+    ];
+
+    for (tool, args) in TOOLS {
+        if let Ok(output) = Command::new(tool).args(args).output()
+            && output.status.success()
+        {
+            return Ok(String::from_utf8_lossy(&output.stdout).to_string());
+        }
+    }
+    anyhow::bail!("No clipboard tool found, install wl-clipboard, xclip or xsel")
+}
+
+fn base64(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (i, byte)| {
+            group | u32::from(*byte) << (16 - 8 * i)
+        });
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(char::from(BASE64[(group >> (18 - 6 * i)) as usize & 63]));
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base64() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"diff --git"), "ZGlmZiAtLWdpdA==");
+    }
+}
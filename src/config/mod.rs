@@ -1,4 +1,5 @@
 pub mod settings;
+pub mod syntax;
 pub mod theme;
 
 pub use settings::Settings;
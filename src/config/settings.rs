@@ -1,10 +1,15 @@
-use std::path::PathBuf;
+use std::{
+    num::NonZeroU32,
+    path::PathBuf,
+};
 
 use serde::{
     Deserialize,
     Serialize,
 };
 
+use super::theme::ColorSupport;
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct Settings {
     #[serde(default)]
@@ -13,12 +18,62 @@ pub struct Settings {
     pub ui: UiSettings,
     #[serde(default)]
     pub auto_track_local: bool,
+    /// Path to the jj executable, looked up on `PATH` when not set
+    #[serde(default)]
+    pub jj_executable: Option<PathBuf>,
+    /// Bookmarks that need a typed confirmation before pushing or moving them,
+    /// `*` matches any number of characters (e.g. `release/*`)
+    #[serde(default)]
+    pub protected_bookmarks: Vec<String>,
+    /// Number of commits to fetch from the remote, the full history when not set
+    #[serde(default)]
+    pub fetch_depth: Option<NonZeroU32>,
+    /// Remote to fetch from and push to, set from the Remotes tab
+    #[serde(default)]
+    pub default_remote: Option<String>,
+    /// Co-authors offered in the describe and commit popups, as `Name <email>`
+    #[serde(default)]
+    pub teammates: Vec<String>,
+    /// Authors last used to describe or commit as someone else, newest first
+    #[serde(default)]
+    pub recent_authors: Vec<String>,
+    /// Move the bookmarks on the parent of the working copy to each new commit,
+    /// like a git branch following HEAD
+    #[serde(default)]
+    pub advance_bookmarks_on_commit: bool,
+    /// Merge tool passed to `jj resolve --tool`, jj's `ui.merge-editor` when not set
+    #[serde(default)]
+    pub merge_tool: Option<String>,
+    /// Seconds a jj command may run before it is stopped, 0 never stops it, 120 when not set
+    #[serde(default)]
+    pub command_timeout_secs: Option<u64>,
+    /// File every jj command jjkk runs is appended to, with its duration and exit code
+    #[serde(default)]
+    pub command_log: Option<PathBuf>,
+    /// Command showing a directory, `{path}` is replaced by it or it is appended.
+    /// The platform's file manager when not set
+    #[serde(default)]
+    pub file_manager: Option<String>,
+    /// Command starting a terminal in the workspace root, `{path}` is replaced by it.
+    /// `$TERMINAL` or the platform's terminal when not set
+    #[serde(default)]
+    pub terminal: Option<String>,
+    /// Command editing a file at a line, `{path}` and `{line}` are replaced by them.
+    /// `$VISUAL` or `$EDITOR` with `+{line} {path}` when not set
+    #[serde(default)]
+    pub editor: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ThemeSettings {
     #[serde(default)]
-    pub name: String,
+    pub name:          String,
+    /// Overrides the detected color support of the terminal
+    #[serde(default)]
+    pub color_support: ColorSupport,
+    /// Theme of the diff syntax highlighting, matched to `name` when not set
+    #[serde(default)]
+    pub syntax_theme:  Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -28,13 +83,91 @@ pub struct UiSettings {
     #[serde(default)]
     pub visible_diff_lines: usize,
     #[serde(default)]
-    pub log_commits_count:  usize,
+    pub log_commits_count: usize,
+    /// Show a dimmed second line under each log entry with more of the description
+    #[serde(default = "default_true")]
+    pub log_description_preview: bool,
+    /// Start the Working Copy tab with the diff of the whole change instead of the selected file
+    #[serde(default)]
+    pub whole_change_diff: bool,
+    /// Highlight the syntax of diffs, otherwise only added and removed lines are colored
+    #[serde(default = "default_true")]
+    pub syntax_highlighting: bool,
+    /// Diffs with more lines are never syntax highlighted
+    #[serde(default = "default_highlight_max_lines")]
+    pub highlight_max_lines: usize,
+    /// Number of commits in the mini log below the Working Copy tab, hidden when not set
+    #[serde(default)]
+    pub mini_log: Option<usize>,
+    /// Segments of the status bar from left to right, hidden when left out
+    #[serde(default = "default_status_bar")]
+    pub status_bar: Vec<StatusSegment>,
+    /// What the change id and author of log entries are colored by
+    #[serde(default)]
+    pub log_colors: LogColors,
+    /// Extensions of prose files, their diffs show the changed words instead of changed lines
+    #[serde(default = "default_word_diff_extensions")]
+    pub word_diff_extensions: Vec<String>,
+}
+
+/// Coloring of the log entries
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LogColors {
+    /// The same colors for every entry
+    #[default]
+    Default,
+    /// A color per author, to tell teammates' commits apart in shared histories
+    Author,
+}
+
+/// A piece of the status bar
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum StatusSegment {
+    /// Change id and bookmarks of the working copy
+    Repo,
+    /// Spinner of the running operation, or a warning about stale data
+    Operation,
+    /// The last status message
+    Message,
+    /// Common keybindings, only while nothing else needs to be told
+    Keyhints,
+    /// Local time
+    Clock,
+}
+
+/// How many authors `recent_authors` remembers
+const MAX_RECENT_AUTHORS: usize = 10;
+
+const fn default_true() -> bool {
+    true
+}
+
+const fn default_highlight_max_lines() -> usize {
+    5000
+}
+
+fn default_word_diff_extensions() -> Vec<String> {
+    ["md", "markdown", "txt", "rst", "adoc"]
+        .map(str::to_string)
+        .to_vec()
+}
+
+fn default_status_bar() -> Vec<StatusSegment> {
+    vec![
+        StatusSegment::Operation,
+        StatusSegment::Message,
+        StatusSegment::Keyhints,
+    ]
 }
 
 impl Default for ThemeSettings {
     fn default() -> Self {
         Self {
-            name: "catppuccin-mocha".to_owned(),
+            name:          "catppuccin-mocha".to_owned(),
+            color_support: ColorSupport::default(),
+            syntax_theme:  None,
         }
     }
 }
@@ -44,7 +177,15 @@ impl Default for UiSettings {
         Self {
             diff_context_lines: 3,
             visible_diff_lines: 30,
-            log_commits_count:  100,
+            log_commits_count: 100,
+            log_description_preview: true,
+            whole_change_diff: false,
+            syntax_highlighting: true,
+            highlight_max_lines: default_highlight_max_lines(),
+            mini_log: None,
+            status_bar: default_status_bar(),
+            log_colors: LogColors::default(),
+            word_diff_extensions: default_word_diff_extensions(),
         }
     }
 }
@@ -62,9 +203,193 @@ impl Settings {
         Ok(settings)
     }
 
+    /// Remember `remote` as default remote, only that key of the config file is rewritten
+    pub fn save_default_remote(&mut self, remote: &str) -> anyhow::Result<()> {
+        Self::save_key(&["default_remote"], toml_edit::value(remote))?;
+        self.default_remote = Some(remote.to_string());
+        Ok(())
+    }
+
+    /// Forget the default remote, removing it from the config file
+    pub fn clear_default_remote(&mut self) -> anyhow::Result<()> {
+        Self::save_key(&["default_remote"], toml_edit::Item::None)?;
+        self.default_remote = None;
+        Ok(())
+    }
+
+    /// Move `author` to the front of the recent authors and remember them in the config file
+    pub fn save_recent_author(&mut self, author: &str) -> anyhow::Result<()> {
+        let mut authors = self.recent_authors.clone();
+        authors.retain(|recent| recent != author);
+        authors.insert(0, author.to_string());
+        authors.truncate(MAX_RECENT_AUTHORS);
+
+        Self::save_key(
+            &["recent_authors"],
+            toml_edit::value(
+                authors
+                    .iter()
+                    .map(String::as_str)
+                    .collect::<toml_edit::Array>(),
+            ),
+        )?;
+        self.recent_authors = authors;
+        Ok(())
+    }
+
+    /// Remember `name` as the theme in the config file
+    pub fn save_theme_name(&mut self, name: &str) -> anyhow::Result<()> {
+        Self::save_key(&["theme", "name"], toml_edit::value(name))?;
+        self.theme.name = name.to_string();
+        Ok(())
+    }
+
+    /// Write `item` to the key at `path` of the config file, like `["theme", "name"]` for
+    /// `name` in the `[theme]` table, leaving the rest of it untouched
+    fn save_key(path: &[&str], item: toml_edit::Item) -> anyhow::Result<()> {
+        let config_path = Self::config_path()?;
+        let config = if config_path.exists() {
+            std::fs::read_to_string(&config_path)?
+        } else {
+            String::new()
+        };
+        let config = with_key(&config, path, item)?;
+
+        if let Some(dir) = config_path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&config_path, config)?;
+        Ok(())
+    }
+
+    /// Whether `bookmark` matches one of the protected bookmark patterns
+    pub fn is_protected_bookmark(&self, bookmark: &str) -> bool {
+        // The current bookmark carries a `*` suffix when it diverged from the remote
+        let bookmark = bookmark.trim_end_matches('*');
+        self.protected_bookmarks
+            .iter()
+            .any(|pattern| glob_match(pattern, bookmark))
+    }
+
     pub fn config_path() -> anyhow::Result<PathBuf> {
         let config_dir = dirs::config_dir()
             .ok_or_else(|| anyhow::anyhow!("Could not determine config directory"))?;
         Ok(config_dir.join("jjkk").join("config.toml"))
     }
 }
+
+/// `config` with `item` at the key at `path`, `Item::None` removes the key. Comments and
+/// the layout of the rest of the file are kept
+fn with_key(config: &str, path: &[&str], mut item: toml_edit::Item) -> anyhow::Result<String> {
+    let mut config = config.parse::<toml_edit::DocumentMut>()?;
+    let Some((key, tables)) = path.split_last() else {
+        return Ok(config.to_string());
+    };
+    let mut table: &mut dyn toml_edit::TableLike = config.as_table_mut();
+    for name in tables {
+        table = table
+            .entry(name)
+            .or_insert_with(toml_edit::table)
+            .as_table_like_mut()
+            .ok_or_else(|| anyhow::anyhow!("{name} in the config file is not a table"))?;
+    }
+    // Replaced in place, inserting would drop the comments above the key
+    match table.get_mut(key) {
+        _ if item.is_none() => {
+            table.remove(key);
+        }
+        Some(old) => {
+            // Keep a comment behind the old value
+            if let (Some(value), Some(old)) = (item.as_value_mut(), old.as_value()) {
+                *value.decor_mut() = old.decor().clone();
+            }
+            *old = item;
+        }
+        None => {
+            table.insert(key, item);
+        }
+    }
+    Ok(config.to_string())
+}
+
+/// Match `text` against a pattern where `*` stands for any number of characters
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let Some((prefix, rest)) = pattern.split_once('*') else {
+        return pattern == text;
+    };
+    let Some(mut remaining) = text.strip_prefix(prefix) else {
+        return false;
+    };
+
+    let mut parts: Vec<&str> = rest.split('*').collect();
+    let suffix = parts.pop().unwrap_or_default();
+    for part in parts {
+        match remaining.find(part) {
+            Some(pos) => remaining = &remaining[pos + part.len()..],
+            None => return false,
+        }
+    }
+    remaining.ends_with(suffix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("main", "main"));
+        assert!(!glob_match("main", "main2"));
+        assert!(glob_match("release/*", "release/1.0"));
+        assert!(!glob_match("release/*", "feature/release"));
+        assert!(glob_match("*-stable", "1.0-stable"));
+        assert!(glob_match("a*b*c", "aXXbYYc"));
+        assert!(!glob_match("a*b*c", "aXXcYYb"));
+        assert!(glob_match("*", "anything"));
+    }
+
+    #[test]
+    fn test_protected_bookmark_ignores_divergence_marker() {
+        let settings = Settings {
+            protected_bookmarks: vec!["main".to_string()],
+            ..Settings::default()
+        };
+        assert!(settings.is_protected_bookmark("main*"));
+        assert!(!settings.is_protected_bookmark("feature"));
+    }
+
+    #[test]
+    fn test_status_bar_segments() {
+        let settings: Settings =
+            toml::from_str("[ui]\nstatus_bar = [\"repo\", \"clock\"]").unwrap();
+        assert_eq!(
+            settings.ui.status_bar,
+            vec![StatusSegment::Repo, StatusSegment::Clock]
+        );
+        // Left out entirely, the status bar looks like it always did
+        let settings: Settings = toml::from_str("[ui]").unwrap();
+        assert_eq!(settings.ui.status_bar, default_status_bar());
+    }
+
+    #[test]
+    fn test_with_key_keeps_comments() {
+        let config = "# Picked by hand\ndefault_remote = \"origin\" # the fork\n";
+        assert_eq!(
+            with_key(config, &["default_remote"], toml_edit::value("upstream")).unwrap(),
+            "# Picked by hand\ndefault_remote = \"upstream\" # the fork\n"
+        );
+        assert_eq!(
+            with_key("", &["theme", "name"], toml_edit::value("light")).unwrap(),
+            "[theme]\nname = \"light\"\n"
+        );
+        assert_eq!(
+            with_key(
+                "default_remote = \"upstream\"\n# Kept\nfetch_depth = 1\n",
+                &["default_remote"],
+                toml_edit::Item::None
+            )
+            .unwrap(),
+            "# Kept\nfetch_depth = 1\n"
+        );
+    }
+}
@@ -0,0 +1,85 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use syntect::{
+    highlighting::ThemeSet,
+    parsing::SyntaxSet,
+};
+
+/// Syntax theme used when the configured one doesn't exist
+pub const FALLBACK_SYNTAX_THEME: &str = "base16-ocean.dark";
+
+/// Directory with additional `.sublime-syntax` definitions
+pub fn syntaxes_dir() -> Result<PathBuf> {
+    let config_dir = dirs::config_dir()
+        .ok_or_else(|| anyhow::anyhow!("Could not determine config directory"))?;
+    Ok(config_dir.join("jjkk").join("syntaxes"))
+}
+
+/// The bundled syntaxes plus the ones in the syntaxes directory,
+/// `None` when there is no syntaxes directory
+pub fn load_syntax_set() -> Result<Option<SyntaxSet>> {
+    let dir = syntaxes_dir()?;
+    if !dir.is_dir() {
+        return Ok(None);
+    }
+
+    let mut builder = SyntaxSet::load_defaults_newlines().into_builder();
+    builder
+        .add_from_folder(&dir, true)
+        .map_err(|e| anyhow::anyhow!("Failed to load syntaxes from {}: {e}", dir.display()))?;
+    Ok(Some(builder.build()))
+}
+
+/// The syntax theme closest to the app theme `theme_name`
+pub fn matching_syntax_theme(theme_name: &str) -> &'static str {
+    match theme_name {
+        "catppuccin-mocha" => "base16-mocha.dark",
+        "high-contrast" => "base16-eighties.dark",
+        _ => FALLBACK_SYNTAX_THEME,
+    }
+}
+
+/// Name of the syntax theme to use, `configured` when it exists in `themes`
+pub fn resolve_syntax_theme(
+    configured: Option<&str>,
+    theme_name: &str,
+    themes: &ThemeSet,
+) -> Result<String, String> {
+    let Some(name) = configured else {
+        return Ok(matching_syntax_theme(theme_name).to_string());
+    };
+    if themes.themes.contains_key(name) {
+        return Ok(name.to_string());
+    }
+
+    let mut available: Vec<&str> = themes.themes.keys().map(String::as_str).collect();
+    available.sort_unstable();
+    Err(format!(
+        "Unknown syntax theme {name}, using {FALLBACK_SYNTAX_THEME}. Available themes: {}",
+        available.join(", ")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_syntax_theme() {
+        let themes = ThemeSet::load_defaults();
+        assert_eq!(
+            resolve_syntax_theme(None, "catppuccin-mocha", &themes),
+            Ok("base16-mocha.dark".to_string())
+        );
+        assert_eq!(
+            resolve_syntax_theme(Some("InspiredGitHub"), "catppuccin-mocha", &themes),
+            Ok("InspiredGitHub".to_string())
+        );
+        assert!(resolve_syntax_theme(Some("nope"), "catppuccin-mocha", &themes).is_err());
+        // Every automatic pick is one of the bundled themes
+        for name in ["catppuccin-mocha", "high-contrast", "no-color"] {
+            assert!(themes.themes.contains_key(matching_syntax_theme(name)));
+        }
+    }
+}
@@ -1,9 +1,166 @@
-use ratatui::style::Color;
+use ratatui::style::{
+    Color,
+    Modifier,
+    Style,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+/// Number of colors the terminal can display
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ColorSupport {
+    /// Detect from the `COLORTERM` and `TERM` environment variables
+    #[default]
+    #[serde(rename = "auto")]
+    Auto,
+    /// 24-bit RGB colors
+    #[serde(rename = "truecolor")]
+    TrueColor,
+    /// The xterm 256 color palette
+    #[serde(rename = "256")]
+    Ansi256,
+    /// The basic 16 ANSI colors
+    #[serde(rename = "16")]
+    Ansi16,
+}
+
+impl ColorSupport {
+    /// Resolve `Auto` to the color support of the current terminal
+    pub fn resolve(self) -> Self {
+        match self {
+            Self::Auto => Self::detect(),
+            other => other,
+        }
+    }
+
+    fn detect() -> Self {
+        let colorterm = std::env::var("COLORTERM").unwrap_or_default();
+        if colorterm == "truecolor" || colorterm == "24bit" {
+            return Self::TrueColor;
+        }
+
+        let term = std::env::var("TERM").unwrap_or_default();
+        if term.contains("256color") {
+            Self::Ansi256
+        } else if term.is_empty() || term == "dumb" || term.starts_with("xterm") {
+            // plain xterm and friends are 16 color terminals unless told otherwise
+            Self::Ansi16
+        } else {
+            // Unknown terminals, most modern ones handle at least 256 colors
+            Self::Ansi256
+        }
+    }
+
+    /// Map a color to the closest one this terminal can display
+    pub fn adapt(self, color: Color) -> Color {
+        let Color::Rgb(r, g, b) = color else {
+            return color;
+        };
+
+        match self.resolve() {
+            Self::TrueColor | Self::Auto => color,
+            Self::Ansi256 => Color::Indexed(nearest_ansi256(r, g, b)),
+            Self::Ansi16 => nearest_ansi16(r, g, b),
+        }
+    }
+}
+
+/// Levels of each channel in the 6x6x6 color cube of the 256 color palette
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// The 16 ANSI colors with their default xterm RGB values
+const ANSI16: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::Red, (205, 0, 0)),
+    (Color::Green, (0, 205, 0)),
+    (Color::Yellow, (205, 205, 0)),
+    (Color::Blue, (0, 0, 238)),
+    (Color::Magenta, (205, 0, 205)),
+    (Color::Cyan, (0, 205, 205)),
+    (Color::Gray, (229, 229, 229)),
+    (Color::DarkGray, (127, 127, 127)),
+    (Color::LightRed, (255, 0, 0)),
+    (Color::LightGreen, (0, 255, 0)),
+    (Color::LightYellow, (255, 255, 0)),
+    (Color::LightBlue, (92, 92, 255)),
+    (Color::LightMagenta, (255, 0, 255)),
+    (Color::LightCyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let dr = u32::from(a.0.abs_diff(b.0));
+    let dg = u32::from(a.1.abs_diff(b.1));
+    let db = u32::from(a.2.abs_diff(b.2));
+    dr * dr + dg * dg + db * db
+}
+
+fn nearest_cube_index(value: u8) -> u8 {
+    let mut best = 0;
+    for (index, level) in CUBE_LEVELS.iter().enumerate() {
+        if value.abs_diff(*level) < value.abs_diff(CUBE_LEVELS[best]) {
+            best = index;
+        }
+    }
+    // there are only six levels, so this always fits
+    u8::try_from(best).unwrap_or(0)
+}
+
+/// Closest entry of the xterm 256 color palette, either from the color cube
+/// or the grayscale ramp
+fn nearest_ansi256(r: u8, g: u8, b: u8) -> u8 {
+    let (ri, gi, bi) = (
+        nearest_cube_index(r),
+        nearest_cube_index(g),
+        nearest_cube_index(b),
+    );
+    let cube_color = (
+        CUBE_LEVELS[usize::from(ri)],
+        CUBE_LEVELS[usize::from(gi)],
+        CUBE_LEVELS[usize::from(bi)],
+    );
+    let cube_index = 16 + 36 * ri + 6 * gi + bi;
+
+    // grayscale ramp goes from 8 to 238 in steps of 10
+    let average = (u16::from(r) + u16::from(g) + u16::from(b)) / 3;
+    let gray_step = u8::try_from(average.saturating_sub(3) / 10)
+        .unwrap_or(23)
+        .min(23);
+    let gray_value = 8 + gray_step * 10;
+    let gray_index = 232 + gray_step;
+
+    if distance((r, g, b), (gray_value, gray_value, gray_value)) < distance((r, g, b), cube_color) {
+        gray_index
+    } else {
+        cube_index
+    }
+}
+
+fn nearest_ansi16(r: u8, g: u8, b: u8) -> Color {
+    ANSI16
+        .iter()
+        .min_by_key(|(_, rgb)| distance((r, g, b), *rgb))
+        .map_or(Color::Reset, |(color, _)| *color)
+}
+
+/// How the theme conveys information to the user
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorMode {
+    /// Full RGB palette
+    Color,
+    /// Basic ANSI colors with strong contrast, selection is shown reversed
+    HighContrast,
+    /// No colors at all, only bold/underline/reverse and ASCII markers
+    NoColor,
+}
 
 #[derive(Debug, Clone)]
 #[allow(dead_code)] // only allow this for now as i am too lazy to implement the theme correctly
 pub struct Theme {
     pub name:      String,
+    pub mode:      ColorMode,
     pub base:      Color,
     pub surface0:  Color,
     pub surface1:  Color,
@@ -34,6 +191,7 @@ impl Theme {
     pub fn catppuccin_mocha() -> Self {
         Self {
             name:      "catppuccin-mocha".to_string(),
+            mode:      ColorMode::Color,
             base:      Color::Rgb(30, 30, 46),
             surface0:  Color::Rgb(49, 50, 68),
             surface1:  Color::Rgb(69, 71, 90),
@@ -62,8 +220,216 @@ impl Theme {
     }
 }
 
+impl Theme {
+    /// Basic ANSI colors only, for colorblind users and low quality displays
+    pub fn high_contrast() -> Self {
+        Self {
+            name:      "high-contrast".to_string(),
+            mode:      ColorMode::HighContrast,
+            base:      Color::Black,
+            surface0:  Color::Black,
+            surface1:  Color::DarkGray,
+            surface2:  Color::DarkGray,
+            text:      Color::White,
+            subtext0:  Color::Gray,
+            subtext1:  Color::Gray,
+            overlay0:  Color::Gray,
+            overlay1:  Color::Gray,
+            overlay2:  Color::White,
+            blue:      Color::LightBlue,
+            lavender:  Color::LightCyan,
+            sapphire:  Color::LightBlue,
+            sky:       Color::LightCyan,
+            teal:      Color::LightCyan,
+            green:     Color::LightGreen,
+            yellow:    Color::LightYellow,
+            peach:     Color::LightYellow,
+            maroon:    Color::LightRed,
+            red:       Color::LightRed,
+            mauve:     Color::LightMagenta,
+            pink:      Color::LightMagenta,
+            flamingo:  Color::LightRed,
+            rosewater: Color::White,
+        }
+    }
+
+    /// Terminal default colors everywhere, see <https://no-color.org>
+    pub fn no_color() -> Self {
+        Self {
+            name:      "no-color".to_string(),
+            mode:      ColorMode::NoColor,
+            base:      Color::Reset,
+            surface0:  Color::Reset,
+            surface1:  Color::Reset,
+            surface2:  Color::Reset,
+            text:      Color::Reset,
+            subtext0:  Color::Reset,
+            subtext1:  Color::Reset,
+            overlay0:  Color::Reset,
+            overlay1:  Color::Reset,
+            overlay2:  Color::Reset,
+            blue:      Color::Reset,
+            lavender:  Color::Reset,
+            sapphire:  Color::Reset,
+            sky:       Color::Reset,
+            teal:      Color::Reset,
+            green:     Color::Reset,
+            yellow:    Color::Reset,
+            peach:     Color::Reset,
+            maroon:    Color::Reset,
+            red:       Color::Reset,
+            mauve:     Color::Reset,
+            pink:      Color::Reset,
+            flamingo:  Color::Reset,
+            rosewater: Color::Reset,
+        }
+    }
+
+    /// Names of the built-in themes, as used in the config file
+    pub const NAMES: [&str; 3] = ["catppuccin-mocha", "high-contrast", "no-color"];
+
+    /// Look up a theme by the name used in the config file,
+    /// falls back to the default theme for unknown names
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "high-contrast" => Self::high_contrast(),
+            "no-color" => Self::no_color(),
+            _ => Self::catppuccin_mocha(),
+        }
+    }
+
+    /// Map every color of the theme to what the terminal can display
+    pub fn adapt_to(self, support: ColorSupport) -> Self {
+        // only look at the environment once
+        let support = support.resolve();
+        let adapt = |color| support.adapt(color);
+        Self {
+            base: adapt(self.base),
+            surface0: adapt(self.surface0),
+            surface1: adapt(self.surface1),
+            surface2: adapt(self.surface2),
+            text: adapt(self.text),
+            subtext0: adapt(self.subtext0),
+            subtext1: adapt(self.subtext1),
+            overlay0: adapt(self.overlay0),
+            overlay1: adapt(self.overlay1),
+            overlay2: adapt(self.overlay2),
+            blue: adapt(self.blue),
+            lavender: adapt(self.lavender),
+            sapphire: adapt(self.sapphire),
+            sky: adapt(self.sky),
+            teal: adapt(self.teal),
+            green: adapt(self.green),
+            yellow: adapt(self.yellow),
+            peach: adapt(self.peach),
+            maroon: adapt(self.maroon),
+            red: adapt(self.red),
+            mauve: adapt(self.mauve),
+            pink: adapt(self.pink),
+            flamingo: adapt(self.flamingo),
+            rosewater: adapt(self.rosewater),
+            ..self
+        }
+    }
+
+    /// Whether the theme should be decorated with colors at all,
+    /// syntax highlighting is skipped when this is false
+    pub fn uses_color(&self) -> bool {
+        self.mode != ColorMode::NoColor
+    }
+
+    /// Whether only plain ASCII should be used for markers like the spinner
+    pub fn ascii_markers(&self) -> bool {
+        self.mode == ColorMode::NoColor
+    }
+
+    /// Color of `author`, the same on every run so people are recognized by it. Text and
+    /// surface colors are left out, they would not stand out
+    pub fn author_color(&self, author: &str) -> Color {
+        let palette = [
+            self.blue,
+            self.green,
+            self.yellow,
+            self.peach,
+            self.mauve,
+            self.teal,
+            self.pink,
+            self.sky,
+            self.maroon,
+            self.lavender,
+        ];
+        // FNV-1a, std's hashers may change between Rust versions
+        let hash = author
+            .bytes()
+            .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+                (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+            });
+        let index = usize::try_from(hash % palette.len() as u64).unwrap_or_default();
+        palette[index]
+    }
+
+    /// Style applied on top of the selected row of a list
+    pub fn highlight_style(&self) -> Style {
+        match self.mode {
+            ColorMode::Color => Style::default()
+                .bg(self.surface1)
+                .add_modifier(Modifier::BOLD),
+            ColorMode::HighContrast | ColorMode::NoColor => {
+                Style::default().add_modifier(Modifier::BOLD | Modifier::REVERSED)
+            }
+        }
+    }
+}
+
 impl Default for Theme {
     fn default() -> Self {
         Self::catppuccin_mocha()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ansi256_maps_pure_colors_to_cube() {
+        assert_eq!(nearest_ansi256(0, 0, 0), 16);
+        assert_eq!(nearest_ansi256(255, 0, 0), 196);
+        assert_eq!(nearest_ansi256(255, 255, 255), 231);
+    }
+
+    #[test]
+    fn test_ansi256_prefers_grayscale_ramp_for_grays() {
+        assert_eq!(nearest_ansi256(30, 30, 30), 234);
+    }
+
+    #[test]
+    fn test_ansi16_nearest() {
+        assert_eq!(nearest_ansi16(250, 10, 10), Color::LightRed);
+        assert_eq!(nearest_ansi16(30, 30, 46), Color::Black);
+    }
+
+    #[test]
+    fn test_author_color_is_stable() {
+        let theme = Theme::catppuccin_mocha();
+        assert_eq!(
+            theme.author_color("<ada@example.com>"),
+            theme.author_color("<ada@example.com>")
+        );
+        // Not every pair differs, but a handful of authors should not share one color
+        let colors: std::collections::HashSet<String> = ["<a@x>", "<b@x>", "<c@x>", "<d@x>"]
+            .iter()
+            .map(|author| format!("{:?}", theme.author_color(author)))
+            .collect();
+        assert!(colors.len() > 1);
+    }
+
+    #[test]
+    fn test_adapt_keeps_named_colors() {
+        assert_eq!(ColorSupport::Ansi16.adapt(Color::Reset), Color::Reset);
+        assert_eq!(
+            ColorSupport::TrueColor.adapt(Color::Rgb(1, 2, 3)),
+            Color::Rgb(1, 2, 3)
+        );
+    }
+}
@@ -0,0 +1,350 @@
+use std::{
+    num::NonZeroU32,
+    sync::Arc,
+};
+
+use anyhow::{
+    Context,
+    Result,
+};
+
+use crate::{
+    actions::{
+        self,
+        PushPlan,
+    },
+    config::Settings,
+    jj::{
+        command,
+        journal,
+        native_operations::Native,
+        operations as jj_ops,
+        repo::JjRepo,
+        runner::{
+            self,
+            DryRunRunner,
+            SkippedCommand,
+        },
+        session,
+    },
+};
+
+const USAGE: &str = "\
+Usage: jjkk exec [--dry-run] <action> [args]
+
+Actions:
+  fetch [--bookmark <name>] [--depth <n>]
+  push [--bookmark <name>] [--dry-run] [--confirm <name>]
+  describe -m <message> [--author <name <email>>]
+  commit -m <message> [--author <name <email>>]
+  new
+  rebase <destination>
+
+Protected bookmarks are only pushed with --confirm <bookmark>
+--dry-run before the action prints the jj command that would change the repo
+instead of running it, for push, new and rebase";
+
+/// A single operation run without the TUI through `jjkk exec <action> [args]`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExecAction {
+    Fetch {
+        bookmark: Option<String>,
+        depth:    Option<NonZeroU32>,
+    },
+    Push {
+        /// The current bookmark when not given
+        bookmark: Option<String>,
+        dry_run:  bool,
+        /// Name of the protected bookmark being pushed, like typing it in the TUI
+        confirm:  Option<String>,
+    },
+    Describe {
+        message: String,
+        /// `Name <email>` to describe the change as, e.g. when pair programming
+        author:  Option<String>,
+    },
+    Commit {
+        message: String,
+        author:  Option<String>,
+    },
+    New,
+    Rebase {
+        destination: String,
+    },
+}
+
+impl ExecAction {
+    pub fn parse(args: &[String]) -> Result<Self> {
+        let Some((action, rest)) = args.split_first() else {
+            anyhow::bail!("Missing action\n\n{USAGE}");
+        };
+        let mut flags = Flags::parse(rest)?;
+
+        let action = match action.as_str() {
+            "fetch" => Self::Fetch {
+                bookmark: flags.value("--bookmark"),
+                depth:    flags
+                    .value("--depth")
+                    .map(|depth| depth.parse().context("--depth must be a positive number"))
+                    .transpose()?,
+            },
+            "push" => Self::Push {
+                bookmark: flags.value("--bookmark"),
+                dry_run:  flags.switch("--dry-run"),
+                confirm:  flags.value("--confirm"),
+            },
+            "describe" => Self::Describe {
+                message: flags.required("-m")?,
+                author:  flags.value("--author"),
+            },
+            "commit" => Self::Commit {
+                message: flags.required("-m")?,
+                author:  flags.value("--author"),
+            },
+            "new" => Self::New,
+            "rebase" => Self::Rebase {
+                destination: flags
+                    .positional
+                    .pop()
+                    .context("Missing the destination to rebase onto")?,
+            },
+            other => anyhow::bail!("Unknown action: {other}\n\n{USAGE}"),
+        };
+
+        flags.finish()?;
+        Ok(action)
+    }
+}
+
+/// Arguments after the action, checked off as the action takes them
+#[derive(Debug, Default)]
+struct Flags {
+    values:     Vec<(String, Option<String>)>,
+    positional: Vec<String>,
+}
+
+impl Flags {
+    /// Flags taking a value, everything else starting with `-` is a switch
+    const WITH_VALUE: [&str; 5] = ["--bookmark", "--depth", "--confirm", "--author", "-m"];
+
+    fn parse(args: &[String]) -> Result<Self> {
+        let mut flags = Self::default();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            if Self::WITH_VALUE.contains(&arg.as_str()) {
+                let value = args
+                    .next()
+                    .with_context(|| format!("{arg} needs a value"))?;
+                flags.values.push((arg.clone(), Some(value.clone())));
+            } else if arg.starts_with('-') {
+                flags.values.push((arg.clone(), None));
+            } else {
+                flags.positional.push(arg.clone());
+            }
+        }
+        Ok(flags)
+    }
+
+    fn value(&mut self, name: &str) -> Option<String> {
+        let index = self.values.iter().position(|(flag, _)| flag == name)?;
+        self.values.remove(index).1
+    }
+
+    fn switch(&mut self, name: &str) -> bool {
+        let Some(index) = self.values.iter().position(|(flag, _)| flag == name) else {
+            return false;
+        };
+        self.values.remove(index);
+        true
+    }
+
+    fn required(&mut self, name: &str) -> Result<String> {
+        self.value(name)
+            .with_context(|| format!("Missing {name} <value>"))
+    }
+
+    /// Fail on anything the action didn't take
+    fn finish(self) -> Result<()> {
+        if let Some((flag, _)) = self.values.first() {
+            anyhow::bail!("Unknown option: {flag}\n\n{USAGE}");
+        }
+        if let Some(arg) = self.positional.first() {
+            anyhow::bail!("Unexpected argument: {arg}\n\n{USAGE}");
+        }
+        Ok(())
+    }
+}
+
+/// Run `jjkk exec`, printing what happened on stdout and errors on stderr
+pub fn main(args: &[String]) -> i32 {
+    if matches!(
+        args.first().map(String::as_str),
+        Some("help" | "--help" | "-h")
+    ) {
+        println!("{USAGE}");
+        return 0;
+    }
+    let (dry_run, args) = match args.split_first() {
+        Some((first, rest)) if first == "--dry-run" => (true, rest),
+        _ => (false, args),
+    };
+    match ExecAction::parse(args).and_then(|action| run(action, dry_run)) {
+        Ok(output) => {
+            println!("{output}");
+            0
+        }
+        Err(e) => {
+            // A dry run ends at the first command that would change the repo
+            if let Some(skipped) = e.downcast_ref::<SkippedCommand>() {
+                println!("{skipped}");
+                return 0;
+            }
+            eprintln!("Error: {e}");
+            1
+        }
+    }
+}
+
+fn run(action: ExecAction, dry_run: bool) -> Result<String> {
+    let settings = Settings::load()?;
+    if let Some(jj_executable) = &settings.jj_executable {
+        command::set_executable(jj_executable.clone());
+    }
+    if let Some(seconds) = settings.command_timeout_secs {
+        runner::set_timeout(seconds);
+    }
+    if let Some(path) = &settings.command_log {
+        runner::set_log_file(path.clone());
+    }
+    if dry_run {
+        // These go through jj-lib, there is no command to stop at
+        if matches!(
+            action,
+            ExecAction::Fetch { .. } | ExecAction::Describe { .. } | ExecAction::Commit { .. }
+        ) {
+            anyhow::bail!("--dry-run only works for push, new and rebase");
+        }
+        runner::set_runner(Arc::new(DryRunRunner));
+    }
+    let repo = JjRepo::open(None)?;
+    journal::open(repo.workspace_root());
+    session::coordinate_transactions(repo.workspace_root());
+    let remote = settings.default_remote.as_deref();
+
+    match action {
+        ExecAction::Fetch { bookmark, depth } => {
+            let mut native = native(&settings);
+            let stats =
+                native.git_fetch(None, bookmark.as_deref(), depth.or(settings.fetch_depth))?;
+            Ok(stats.summary())
+        }
+        ExecAction::Push {
+            bookmark,
+            dry_run,
+            confirm,
+        } => {
+            let plan = PushPlan::new(bookmark, &settings)?;
+            if let Some(name) = plan.protected_bookmark(&settings)
+                && !dry_run
+                && confirm.as_deref() != Some(name)
+            {
+                anyhow::bail!("{name} is a protected bookmark, pass --confirm {name} to push it");
+            }
+
+            if plan.preview.is_empty() {
+                return Ok(plan.preview.summary());
+            }
+            let mut lines: Vec<String> = plan
+                .preview
+                .bookmarks
+                .iter()
+                .map(|b| b.detail.clone())
+                .collect();
+            if !dry_run {
+                lines.push(jj_ops::git_push(plan.bookmark.as_deref(), remote)?.summary());
+            }
+            Ok(lines.join("\n"))
+        }
+        ExecAction::Describe { message, author } => {
+            native(&settings).describe(&message, author.as_deref())
+        }
+        ExecAction::Commit { message, author } => actions::commit(
+            &mut native(&settings),
+            &settings,
+            &message,
+            author.as_deref(),
+            None,
+        ),
+        ExecAction::New => {
+            if native(&settings).is_working_copy_empty()? {
+                anyhow::bail!("Already on an empty commit. Add changes first.");
+            }
+            Ok(jj_ops::new_commit()?.summary())
+        }
+        ExecAction::Rebase { destination } => {
+            jj_ops::resolve_revision(&destination)?;
+            Ok(jj_ops::rebase(&destination)?.summary(&destination))
+        }
+    }
+}
+
+fn native(settings: &Settings) -> Native {
+    let mut native = Native::new();
+    if let Some(remote) = &settings.default_remote {
+        native.default_remote.clone_from(remote);
+    }
+    native
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            ExecAction::parse(&args(&["describe", "-m", "Fix the parser"])).unwrap(),
+            ExecAction::Describe {
+                message: "Fix the parser".to_string(),
+                author:  None,
+            }
+        );
+        assert_eq!(
+            ExecAction::parse(&args(&[
+                "commit",
+                "--author",
+                "Ada Lovelace <ada@example.com>",
+                "-m",
+                "Fix the parser"
+            ]))
+            .unwrap(),
+            ExecAction::Commit {
+                message: "Fix the parser".to_string(),
+                author:  Some("Ada Lovelace <ada@example.com>".to_string()),
+            }
+        );
+        assert_eq!(
+            ExecAction::parse(&args(&["push", "--dry-run", "--bookmark", "main"])).unwrap(),
+            ExecAction::Push {
+                bookmark: Some("main".to_string()),
+                dry_run:  true,
+                confirm:  None,
+            }
+        );
+        assert_eq!(
+            ExecAction::parse(&args(&["fetch", "--depth", "10"])).unwrap(),
+            ExecAction::Fetch {
+                bookmark: None,
+                depth:    NonZeroU32::new(10),
+            }
+        );
+        assert!(ExecAction::parse(&args(&["describe"])).is_err());
+        assert!(ExecAction::parse(&args(&["fetch", "--depth", "0"])).is_err());
+        assert!(ExecAction::parse(&args(&["push", "--force"])).is_err());
+        assert!(ExecAction::parse(&args(&["frobnicate"])).is_err());
+    }
+}
@@ -0,0 +1,139 @@
+use std::{
+    fmt::Write,
+    process::{
+        Command,
+        Stdio,
+    },
+    thread,
+};
+
+use anyhow::Result;
+
+/// Web page of the repository behind a git remote url, e.g.
+/// `git@github.com:org/repo.git` becomes `https://github.com/org/repo`
+pub fn web_url(remote_url: &str) -> Option<String> {
+    let remote_url = remote_url.trim();
+    let (host, path) = if let Some(rest) = remote_url
+        .strip_prefix("https://")
+        .or_else(|| remote_url.strip_prefix("http://"))
+        .or_else(|| remote_url.strip_prefix("ssh://"))
+        .or_else(|| remote_url.strip_prefix("git://"))
+    {
+        rest.split_once('/')?
+    } else {
+        // scp-like `user@host:path`
+        remote_url.split_once(':')?
+    };
+
+    // Drop the user and port, the web page is always on the default https port
+    let host = host.rsplit_once('@').map_or(host, |(_, host)| host);
+    let host = host.split_once(':').map_or(host, |(host, _)| host);
+    let path = path.trim_end_matches('/').trim_end_matches(".git");
+    if host.is_empty() || path.is_empty() {
+        return None;
+    }
+    Some(format!("https://{host}/{path}"))
+}
+
+/// Page that opens a new pull request for `bookmark`, or a merge request on GitLab
+pub fn pull_request_url(web_url: &str, bookmark: &str) -> String {
+    let bookmark = encode(bookmark);
+    if is_gitlab(web_url) {
+        format!("{web_url}/-/merge_requests/new?merge_request%5Bsource_branch%5D={bookmark}")
+    } else {
+        format!("{web_url}/pull/new/{bookmark}")
+    }
+}
+
+/// Page comparing `bookmark` with `base`
+pub fn compare_url(web_url: &str, base: &str, bookmark: &str) -> String {
+    let (base, bookmark) = (encode(base), encode(bookmark));
+    if is_gitlab(web_url) {
+        format!("{web_url}/-/compare/{base}...{bookmark}")
+    } else {
+        format!("{web_url}/compare/{base}...{bookmark}")
+    }
+}
+
+/// `name` percent-encoded for a url, bookmark names may contain `#`, `?` or `%`. Slashes
+/// are kept, forges take `feature/x` as it is
+fn encode(name: &str) -> String {
+    name.bytes().fold(String::new(), |mut encoded, byte| {
+        if byte.is_ascii_alphanumeric() || b"-._~/".contains(&byte) {
+            encoded.push(char::from(byte));
+        } else {
+            let _ = write!(encoded, "%{byte:02X}");
+        }
+        encoded
+    })
+}
+
+fn is_gitlab(web_url: &str) -> bool {
+    web_url
+        .split('/')
+        .nth(2)
+        .is_some_and(|host| host.contains("gitlab"))
+}
+
+/// Open `url` in the default browser without waiting for it
+pub fn open(url: &str) -> Result<()> {
+    let mut command = if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else if cfg!(windows) {
+        let mut command = Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    } else {
+        Command::new("xdg-open")
+    };
+    // Whatever the browser prints would end up on top of the ui
+    let mut child = command
+        .arg(url)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    thread::spawn(move || child.wait());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_web_url() {
+        for remote in [
+            "git@github.com:org/repo.git",
+            "https://github.com/org/repo.git",
+            "https://user@github.com/org/repo",
+            "ssh://git@github.com:22/org/repo.git",
+        ] {
+            assert_eq!(
+                web_url(remote).as_deref(),
+                Some("https://github.com/org/repo"),
+                "{remote}"
+            );
+        }
+        assert_eq!(web_url("/srv/git/repo.git"), None);
+    }
+
+    #[test]
+    fn test_forge_urls() {
+        assert_eq!(
+            pull_request_url("https://github.com/org/repo", "feature"),
+            "https://github.com/org/repo/pull/new/feature"
+        );
+        assert_eq!(
+            compare_url("https://gitlab.com/group/repo", "main", "feature"),
+            "https://gitlab.com/group/repo/-/compare/main...feature"
+        );
+        assert_eq!(
+            pull_request_url("https://github.com/org/repo", "fix/#12 100%"),
+            "https://github.com/org/repo/pull/new/fix/%2312%20100%25"
+        );
+        assert_eq!(
+            pull_request_url("https://gitlab.com/group/repo", "a&b"),
+            "https://gitlab.com/group/repo/-/merge_requests/new?merge_request%5Bsource_branch%5D=a%26b"
+        );
+    }
+}
@@ -0,0 +1,275 @@
+use std::{
+    ffi::OsString,
+    io::{
+        self,
+        BufRead,
+        BufReader,
+        Read,
+        Write,
+    },
+    path::PathBuf,
+    sync::{
+        Mutex,
+        OnceLock,
+    },
+};
+
+use super::error::{
+    JjError,
+    JjErrorKind,
+};
+
+/// Set on git and ssh so jjkk knows it was started as their askpass program, the socket
+/// to ask the TUI on
+const ASKPASS_VAR: &str = "JJKK_ASKPASS";
+const SOCKET_VAR: &str = "JJKK_ASKPASS_SOCKET";
+
+/// Credentials entered in the TUI, kept in memory for the rest of the session
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Credentials {
+    pub username: Option<String>,
+    /// Password, token or SSH key passphrase
+    pub secret:   String,
+}
+
+static CREDENTIALS: Mutex<Option<Credentials>> = Mutex::new(None);
+/// Socket the TUI answers prompts on, `None` when it could not be set up
+static SOCKET: OnceLock<Option<PathBuf>> = OnceLock::new();
+
+pub fn set_credentials(credentials: Credentials) {
+    *CREDENTIALS
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner) = Some(credentials);
+}
+
+/// Forget the credentials, e.g. after the remote rejected them
+pub fn clear_credentials() {
+    *CREDENTIALS
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner) = None;
+}
+
+/// Environment for jj commands that talk to a remote
+///
+/// git and ssh never prompt on the terminal the TUI is drawn on, they run jjkk as askpass
+/// program instead. It asks the TUI over a socket only the user can open, so the
+/// credentials never end up in the environment of jj, git, ssh or their hooks
+pub fn environment() -> Vec<(OsString, OsString)> {
+    let mut env = vec![("GIT_TERMINAL_PROMPT".into(), "0".into())];
+    let Ok(executable) = std::env::current_exe() else {
+        return env;
+    };
+    let Some(socket) = SOCKET.get_or_init(serve).clone() else {
+        return env;
+    };
+
+    env.extend([
+        ("GIT_ASKPASS".into(), executable.clone().into_os_string()),
+        ("SSH_ASKPASS".into(), executable.into_os_string()),
+        ("SSH_ASKPASS_REQUIRE".into(), "force".into()),
+        (ASKPASS_VAR.into(), "1".into()),
+        (SOCKET_VAR.into(), socket.into_os_string()),
+    ]);
+    env
+}
+
+/// Remove the socket, once nothing talks to a remote anymore
+pub fn shut_down() {
+    if let Some(Some(socket)) = SOCKET.get()
+        && let Some(dir) = socket.parent()
+    {
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
+
+/// Answer prompts on a socket in a directory only the user can enter, from a thread that
+/// lives as long as the TUI
+#[cfg(unix)]
+fn serve() -> Option<PathBuf> {
+    use std::os::unix::{
+        fs::DirBuilderExt,
+        net::UnixListener,
+    };
+
+    // Socket paths are limited to around 100 bytes, the temp dir keeps it short
+    let dir = std::env::temp_dir().join(format!("jjkk-askpass-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::DirBuilder::new().mode(0o700).create(&dir).ok()?;
+    let socket = dir.join("socket");
+    let listener = UnixListener::bind(&socket).ok()?;
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            // A broken connection only fails that prompt
+            let _ = reply(&stream, &stream);
+        }
+    });
+    Some(socket)
+}
+
+/// No askpass on other platforms, git and ssh fail instead of prompting
+#[cfg(not(unix))]
+fn serve() -> Option<PathBuf> {
+    None
+}
+
+/// Read a prompt line and write the answer, nothing when there is none
+fn reply(request: impl Read, mut response: impl Write) -> io::Result<()> {
+    let mut prompt = String::new();
+    BufReader::new(request).read_line(&mut prompt)?;
+    let credentials = CREDENTIALS
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .clone();
+    if let Some(answer) = credentials.and_then(|credentials| answer(&prompt, credentials)) {
+        response.write_all(answer.as_bytes())?;
+    }
+    Ok(())
+}
+
+/// When started as askpass program, answer the prompt passed as first argument and
+/// return the exit code. Returns `None` for a regular start of jjkk
+pub fn answer_prompt() -> Option<i32> {
+    std::env::var_os(ASKPASS_VAR)?;
+
+    let prompt = std::env::args().nth(1).unwrap_or_default();
+    let answer = std::env::var_os(SOCKET_VAR)
+        .and_then(|socket| ask(&PathBuf::from(socket), &prompt).ok())
+        .filter(|answer| !answer.is_empty());
+    Some(answer.map_or(1, |answer| {
+        println!("{answer}");
+        0
+    }))
+}
+
+/// Ask the TUI listening on `socket` to answer `prompt`
+#[cfg(unix)]
+fn ask(socket: &std::path::Path, prompt: &str) -> io::Result<String> {
+    let mut stream = std::os::unix::net::UnixStream::connect(socket)?;
+    writeln!(stream, "{}", prompt.replace('\n', " "))?;
+    stream.shutdown(std::net::Shutdown::Write)?;
+    let mut answer = String::new();
+    stream.read_to_string(&mut answer)?;
+    Ok(answer)
+}
+
+#[cfg(not(unix))]
+fn ask(_socket: &std::path::Path, _prompt: &str) -> io::Result<String> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+fn answer(prompt: &str, credentials: Credentials) -> Option<String> {
+    let prompt = prompt.to_lowercase();
+    // Never accept unknown host keys on the user's behalf
+    if prompt.contains("yes/no") {
+        return None;
+    }
+    if prompt.starts_with("username") {
+        credentials.username
+    } else if prompt.contains("password") || prompt.contains("passphrase") {
+        Some(credentials.secret)
+    } else {
+        // Whatever else is asked, the secret is not the answer to it
+        None
+    }
+}
+
+/// How the remote wants the user to authenticate
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthMethod {
+    /// Passphrase of the SSH key
+    Ssh,
+    /// Username and password or token
+    Https,
+}
+
+/// What to prompt for when `error` is an authentication failure
+pub fn auth_method(error: &anyhow::Error) -> Option<AuthMethod> {
+    let error = error
+        .downcast_ref::<JjError>()
+        .filter(|e| e.kind == JjErrorKind::AuthFailed)?;
+
+    let stderr = error.stderr.to_lowercase();
+    if ["publickey", "passphrase", "ssh", "host key"]
+        .iter()
+        .any(|pattern| stderr.contains(pattern))
+    {
+        Some(AuthMethod::Ssh)
+    } else {
+        Some(AuthMethod::Https)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_answer() {
+        let credentials = Credentials {
+            username: Some("me".to_string()),
+            secret:   "hunter2".to_string(),
+        };
+        let ask = |prompt: &str| answer(prompt, credentials.clone());
+
+        assert_eq!(
+            ask("Username for 'https://github.com': "),
+            Some("me".to_string())
+        );
+        assert_eq!(
+            ask("Password for 'https://me@github.com': "),
+            Some("hunter2".to_string())
+        );
+        assert_eq!(
+            ask("Enter passphrase for key '/home/me/.ssh/id_ed25519': "),
+            Some("hunter2".to_string())
+        );
+        assert_eq!(
+            ask("Are you sure you want to continue connecting (yes/no/[fingerprint])?"),
+            None
+        );
+        assert_eq!(ask("Verification code: "), None);
+    }
+
+    #[test]
+    fn test_reply() {
+        set_credentials(Credentials {
+            username: None,
+            secret:   "hunter2".to_string(),
+        });
+        let mut response = Vec::new();
+        reply(
+            "Password for 'https://github.com': \n".as_bytes(),
+            &mut response,
+        )
+        .unwrap();
+        assert_eq!(response, b"hunter2");
+
+        clear_credentials();
+        let mut response = Vec::new();
+        reply(
+            "Password for 'https://github.com': \n".as_bytes(),
+            &mut response,
+        )
+        .unwrap();
+        assert!(response.is_empty());
+    }
+
+    #[test]
+    fn test_auth_method() {
+        let ssh = JjError::new(
+            "jj git push",
+            "git@github.com: Permission denied (publickey).",
+        );
+        assert_eq!(auth_method(&ssh.into()), Some(AuthMethod::Ssh));
+
+        let https = JjError::new(
+            "jj git push",
+            "fatal: could not read Username for 'https://github.com': terminal prompts disabled",
+        );
+        assert_eq!(auth_method(&https.into()), Some(AuthMethod::Https));
+
+        let other = JjError::new("jj git push", "Error: boom");
+        assert_eq!(auth_method(&other.into()), None);
+    }
+}
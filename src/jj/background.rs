@@ -0,0 +1,153 @@
+use std::{
+    sync::mpsc::{
+        self,
+        Receiver,
+        TryRecvError,
+    },
+    thread,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+/// What a `BackgroundTask` picked up from its thread
+#[derive(Debug)]
+pub enum Update<T, P> {
+    /// The newest progress reported, the work is still running
+    Progress(P),
+    /// The result and how long the work took
+    Done(T, Duration),
+}
+
+/// Runs slow work like a push on a background thread, so the ui keeps drawing its
+/// spinner while waiting. The work can report progress `P` before its result `T`
+pub struct BackgroundTask<T, P = ()> {
+    receiver: Option<Receiver<Update<T, P>>>,
+    started:  Instant,
+}
+
+impl<T, P> Default for BackgroundTask<T, P> {
+    fn default() -> Self {
+        Self {
+            receiver: None,
+            started:  Instant::now(),
+        }
+    }
+}
+
+impl<T: Send + 'static, P: Send + 'static> BackgroundTask<T, P> {
+    /// Start `work`, dropping the result of anything started earlier
+    pub fn start(&mut self, work: impl FnOnce() -> T + Send + 'static) {
+        self.start_reporting(|_| work());
+    }
+
+    /// Start `work`, which passes its progress to the function it is given
+    pub fn start_reporting(&mut self, work: impl FnOnce(&dyn Fn(P)) -> T + Send + 'static) {
+        let (sender, receiver) = mpsc::channel();
+        self.receiver = Some(receiver);
+        self.started = Instant::now();
+        thread::spawn(move || {
+            // The receiver is gone when something else was started or the work was
+            // cancelled in the meantime
+            let report = |progress| {
+                let _ = sender.send(Update::Progress(progress));
+            };
+            let result = work(&report);
+            let _ = sender.send(Update::Done(result, Duration::ZERO));
+        });
+    }
+
+    /// Drop the result of the running work, if any
+    pub fn cancel(&mut self) {
+        self.receiver = None;
+    }
+
+    pub const fn is_running(&self) -> bool {
+        self.receiver.is_some()
+    }
+
+    /// The result once the work is done, otherwise its newest progress if there is any
+    pub fn poll_update(&mut self) -> Option<Update<T, P>> {
+        let receiver = self.receiver.as_ref()?;
+        let mut latest = None;
+        loop {
+            match receiver.try_recv() {
+                Ok(Update::Done(result, _)) => {
+                    self.receiver = None;
+                    return Some(Update::Done(result, self.started.elapsed()));
+                }
+                Ok(progress) => latest = Some(progress),
+                Err(TryRecvError::Empty) => return latest,
+                // The work panicked, there is nothing to wait for
+                Err(TryRecvError::Disconnected) => {
+                    self.receiver = None;
+                    return latest;
+                }
+            }
+        }
+    }
+
+    /// The result and how long the work took, once it is done
+    pub fn poll(&mut self) -> Option<(T, Duration)> {
+        loop {
+            match self.poll_update()? {
+                Update::Done(result, duration) => return Some((result, duration)),
+                Update::Progress(_) if self.is_running() => {}
+                Update::Progress(_) => return None,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_background_task_result() {
+        let mut task = BackgroundTask::<_>::default();
+        assert!(task.poll().is_none());
+
+        task.start(|| 42);
+        assert!(task.is_running());
+        let (result, _) = loop {
+            if let Some(done) = task.poll() {
+                break done;
+            }
+            thread::sleep(Duration::from_millis(1));
+        };
+        assert_eq!(result, 42);
+        assert!(!task.is_running());
+    }
+
+    #[test]
+    fn test_background_task_progress() {
+        let mut task = BackgroundTask::default();
+        let (resume, wait) = mpsc::channel::<()>();
+        task.start_reporting(move |report| {
+            report(1);
+            report(2);
+            let _ = wait.recv();
+            3
+        });
+
+        let progress = loop {
+            if let Some(Update::Progress(progress)) = task.poll_update() {
+                break progress;
+            }
+            thread::sleep(Duration::from_millis(1));
+        };
+        // Progress reported at once only shows the newest
+        assert!(progress == 1 || progress == 2);
+
+        resume.send(()).unwrap();
+        let result = loop {
+            match task.poll_update() {
+                Some(Update::Done(result, _)) => break result,
+                _ => thread::sleep(Duration::from_millis(1)),
+            }
+        };
+        assert_eq!(result, 3);
+    }
+}
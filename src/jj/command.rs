@@ -0,0 +1,218 @@
+use std::{
+    path::PathBuf,
+    process::{
+        Command,
+        Output,
+    },
+    sync::OnceLock,
+    time::Instant,
+};
+
+use anyhow::Result;
+
+use super::{
+    journal,
+    runner::{
+        self,
+        CommandRunner,
+    },
+};
+
+/// Executable used for every jj subprocess, set once at startup
+static JJ_EXECUTABLE: OnceLock<PathBuf> = OnceLock::new();
+/// Passed to every jj subprocess so that the user's config and environment can't change the
+/// output jjkk parses. No editor can be shown under the ui, commands needing one fail right away
+const GLOBAL_ARGS: [&str; 4] = ["--no-pager", "--color=never", "--config", "ui.editor=false"];
+/// Environment jj reads an editor or pager from, removed so it can't undo `GLOBAL_ARGS`
+const CLEARED_ENV: [&str; 5] = ["JJ_EDITOR", "VISUAL", "EDITOR", "JJ_PAGER", "PAGER"];
+
+/// Use a specific jj executable instead of looking it up on `PATH`.
+/// Handy when `PATH` is not inherited, like terminals launched from a macOS GUI
+pub fn set_executable(path: PathBuf) {
+    // Only the first call wins, the executable never changes while running
+    let _ = JJ_EXECUTABLE.set(path);
+}
+
+pub trait CommandExt {
+    /// Like `Command::output`, but through the configured `CommandRunner`, which also logs it.
+    /// Commands that change the repo are recorded in the journal
+    fn run(&mut self) -> Result<Output>;
+
+    /// Like `run`, passing each line of stdout to `line` as soon as jj prints it
+    fn run_streaming(&mut self, line: &mut dyn FnMut(&str)) -> Result<Output>;
+
+    /// Like `run`, but jj takes over the terminal, e.g. to show a merge tool
+    fn run_interactive(&mut self) -> Result<Output>;
+}
+
+impl CommandExt for Command {
+    fn run(&mut self) -> Result<Output> {
+        run_logged(self, |runner, command| runner.run(command))
+    }
+
+    fn run_streaming(&mut self, line: &mut dyn FnMut(&str)) -> Result<Output> {
+        run_logged(self, |runner, command| runner.run_streaming(command, line))
+    }
+
+    fn run_interactive(&mut self) -> Result<Output> {
+        run_logged(self, |runner, command| runner.run_interactive(command))
+    }
+}
+
+/// Run `command` with `run` on the current runner, logging and journaling it
+fn run_logged(
+    command: &mut Command,
+    run: impl FnOnce(&dyn CommandRunner, &mut Command) -> Result<Output>,
+) -> Result<Output> {
+    let before = journal::operation_before(command);
+    let started = Instant::now();
+    let result = runner::with_current(|runner| run(runner, command));
+    runner::log(&command_line(command), started.elapsed(), &result);
+    if let Some(before) = before {
+        journal::record_command(command, &before);
+    }
+    result
+}
+
+/// `jj` and its subcommand, e.g. `jj git push`, for error messages
+pub fn command_name(command: &Command) -> String {
+    let subcommand: Vec<String> = arguments(command)
+        .into_iter()
+        .take_while(|arg| !arg.starts_with('-'))
+        .take(2)
+        .collect();
+    format!("jj {}", subcommand.join(" "))
+        .trim_end()
+        .to_string()
+}
+
+/// The whole command as typed in a shell, without the `GLOBAL_ARGS`
+pub fn command_line(command: &Command) -> String {
+    format!("jj {}", join_arguments(&arguments(command)))
+        .trim_end()
+        .to_string()
+}
+
+/// `args` as typed in a shell, quoting the empty ones and those with whitespace
+pub fn join_arguments(args: &[impl AsRef<str>]) -> String {
+    let args: Vec<String> = args
+        .iter()
+        .map(|arg| {
+            let arg = arg.as_ref();
+            if arg.is_empty() || arg.contains(char::is_whitespace) {
+                format!("{arg:?}")
+            } else {
+                arg.to_string()
+            }
+        })
+        .collect();
+    args.join(" ")
+}
+
+/// Arguments passed by jjkk, after the `GLOBAL_ARGS`
+pub fn arguments(command: &Command) -> Vec<String> {
+    command
+        .get_args()
+        .skip(GLOBAL_ARGS.len())
+        .map(|arg| arg.to_string_lossy().to_string())
+        .collect()
+}
+
+/// Create a `jj` command using the configured executable, with the `GLOBAL_ARGS`
+pub fn jj() -> Command {
+    let mut command = JJ_EXECUTABLE
+        .get()
+        .map_or_else(|| Command::new("jj"), Command::new);
+    command.args(GLOBAL_ARGS);
+    for name in CLEARED_ENV {
+        command.env_remove(name);
+    }
+    command
+}
+
+/// Turn a path as printed by `jj status` into a fileset expression that
+/// matches exactly that file. Quoting keeps spaces, parentheses and Windows
+/// path separators from being parsed as fileset syntax
+pub fn file_pattern(path: &str) -> String {
+    let escaped = path.replace('\\', "\\\\").replace('"', "\\\"");
+    format!("file:\"{escaped}\"")
+}
+
+/// Turn a filter typed by the user into a fileset expression. A plain glob like
+/// `src/**/*.rs` becomes a `glob:` pattern, anything else is used as a fileset as is
+pub fn fileset_or_glob(filter: &str) -> String {
+    let filter = filter.trim();
+    let is_glob = filter.contains(['*', '?', '['])
+        && !filter.contains([':', '"', '|', '&', '~', '(', ')', ' ']);
+    if is_glob {
+        format!("glob:\"{}\"", filter.replace('\\', "\\\\"))
+    } else {
+        filter.to_string()
+    }
+}
+
+/// Quote a bookmark name when it can't be used as a revset symbol as is
+pub fn revset_symbol(name: &str) -> String {
+    if name
+        .chars()
+        .all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '/' | '.'))
+    {
+        name.to_string()
+    } else {
+        format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jj_ignores_user_output_settings() {
+        let command = jj();
+        let args: Vec<_> = command.get_args().collect();
+        assert!(args.contains(&"--no-pager".as_ref()));
+        assert!(args.contains(&"--color=never".as_ref()));
+    }
+
+    #[test]
+    fn test_command_line() {
+        assert_eq!(
+            command_line(jj().args(["describe", "-m", "fix the thing"])),
+            r#"jj describe -m "fix the thing""#
+        );
+    }
+
+    #[test]
+    fn test_command_name() {
+        assert_eq!(
+            command_name(jj().args(["git", "push", "--remote", "origin"])),
+            "jj git push"
+        );
+        assert_eq!(command_name(jj().args(["log", "-r", "@"])), "jj log");
+        assert_eq!(command_name(&jj()), "jj");
+    }
+
+    #[test]
+    fn test_revset_symbol() {
+        assert_eq!(revset_symbol("release/1.0"), "release/1.0");
+        assert_eq!(revset_symbol("weird name"), "\"weird name\"");
+    }
+
+    #[test]
+    fn test_fileset_or_glob() {
+        assert_eq!(fileset_or_glob(" src/**/*.rs "), r#"glob:"src/**/*.rs""#);
+        assert_eq!(fileset_or_glob("src"), "src");
+        assert_eq!(
+            fileset_or_glob(r#"glob:"*.rs" ~ "tests""#),
+            r#"glob:"*.rs" ~ "tests""#
+        );
+    }
+
+    #[test]
+    fn test_file_pattern_quotes_paths() {
+        assert_eq!(file_pattern("src/main.rs"), r#"file:"src/main.rs""#);
+        assert_eq!(file_pattern("a (1).txt"), r#"file:"a (1).txt""#);
+        assert_eq!(file_pattern(r"src\main.rs"), r#"file:"src\\main.rs""#);
+    }
+}
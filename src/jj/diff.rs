@@ -0,0 +1,647 @@
+use std::{
+    fs,
+    io::Write,
+    ops::Range,
+    path::PathBuf,
+    process::{
+        Command,
+        Stdio,
+    },
+};
+
+use anyhow::{
+    Context,
+    Result,
+};
+
+use super::{
+    command::{
+        CommandExt,
+        jj,
+    },
+    error::command_failed,
+    operations,
+    results::PatchResult,
+};
+
+/// Where each file's section starts in a diff of several files
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DiffAnchors {
+    /// Path and first line of every file section, in diff order
+    anchors: Vec<(String, usize)>,
+}
+
+impl DiffAnchors {
+    /// Find the file sections of git style diff output
+    pub fn parse(diff: &str) -> Self {
+        let anchors = diff
+            .lines()
+            .enumerate()
+            .filter_map(|(line, content)| {
+                // The new path, `diff --git a/<old> b/<new>`
+                let paths = content.strip_prefix("diff --git a/")?;
+                let (_, path) = paths.rsplit_once(" b/")?;
+                Some((path.to_string(), line))
+            })
+            .collect();
+
+        Self { anchors }
+    }
+
+    /// First line of the section of `path`
+    pub fn line_of(&self, path: &str) -> Option<usize> {
+        self.anchors
+            .iter()
+            .find(|(anchor, _)| anchor == path)
+            .map(|(_, line)| *line)
+    }
+
+    /// Path of the section `line` belongs to
+    pub fn file_at(&self, line: usize) -> Option<&str> {
+        self.anchors
+            .iter()
+            .take_while(|(_, start)| *start <= line)
+            .last()
+            .map(|(path, _)| path.as_str())
+    }
+
+    /// Lines of the section of `path` in a diff of `line_count` lines
+    pub fn lines_of(&self, path: &str, line_count: usize) -> Option<Range<usize>> {
+        let index = self.anchors.iter().position(|(anchor, _)| anchor == path)?;
+        let end = self
+            .anchors
+            .get(index + 1)
+            .map_or(line_count, |(_, start)| *start);
+        Some(self.anchors[index].1..end)
+    }
+}
+
+/// Words marking work left to do, looked for in the added lines before committing
+pub const LEFTOVER_MARKERS: [&str; 3] = ["TODO", "FIXME", "XXX"];
+
+/// A changed line of a file, like where a hunk starts, as an entry of the quickfix list
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HunkLocation {
+    pub path: String,
+    /// First changed line in the new version of the file, starting at 1
+    pub line: usize,
+    /// Content of that line, or of the first removed line for hunks only removing lines
+    pub text: String,
+}
+
+impl HunkLocation {
+    /// `path:line: text`, as read by vim's quickfix list and most editors
+    pub fn quickfix_line(&self) -> String {
+        format!("{}:{}: {}", self.path, self.line, self.text)
+    }
+}
+
+/// Every hunk of git style diff output in diff order. Deleted files have nothing left to
+/// edit and are left out
+pub fn hunk_locations(diff: &str) -> Vec<HunkLocation> {
+    let mut hunks = Vec::new();
+    let mut path = String::new();
+    let mut new_line = 0;
+    // Inside a hunk whose first change was not found yet
+    let mut searching = false;
+    // First of the removed lines found so far, used when no line is added in their place
+    let mut removed: Option<&str> = None;
+    let location = |path: &str, line: usize, text: &str| HunkLocation {
+        path: path.to_string(),
+        line: line.max(1),
+        text: text.trim().to_string(),
+    };
+
+    for content in diff.lines() {
+        let boundary = content.starts_with("diff --git ") || content.starts_with("@@ ");
+        if searching && !boundary {
+            match content.chars().next() {
+                Some('+') => {
+                    hunks.push(location(&path, new_line, &content[1..]));
+                    searching = false;
+                    removed = None;
+                }
+                Some('-') => {
+                    removed.get_or_insert_with(|| &content[1..]);
+                }
+                // `\ No newline at end of file` is not a line of the file
+                Some('\\') => {}
+                _ if removed.is_none() => new_line += 1,
+                _ => searching = false,
+            }
+            if searching || removed.is_none() {
+                continue;
+            }
+        }
+        searching = false;
+        if let Some(text) = removed.take() {
+            hunks.push(location(&path, new_line, text));
+        }
+
+        if content.starts_with("diff --git ") {
+            path.clear();
+        } else if let Some(new_path) = content.strip_prefix("+++ ").filter(|_| path.is_empty()) {
+            // `/dev/null` for deleted files
+            new_path
+                .strip_prefix("b/")
+                .unwrap_or_default()
+                .clone_into(&mut path);
+        } else if let Some(header) = content.strip_prefix("@@ ") {
+            new_line = hunk_start(header);
+            searching = !path.is_empty();
+        }
+    }
+    if let Some(text) = removed {
+        hunks.push(location(&path, new_line, text));
+    }
+    hunks
+}
+
+/// Added lines of git style diff output containing one of `LEFTOVER_MARKERS` as a word, in
+/// diff order. Markers already there before the change are left out
+pub fn leftover_markers(diff: &str) -> Vec<HunkLocation> {
+    let mut path = "";
+    diff.lines()
+        .zip(new_line_numbers(diff))
+        .filter_map(|(content, line)| {
+            if let Some(paths) = content.strip_prefix("diff --git a/") {
+                path = paths
+                    .rsplit_once(" b/")
+                    .map_or("", |(_, new_path)| new_path);
+                return None;
+            }
+            // Headers have no line, so `+++` is only skipped outside of hunks
+            let line = line?;
+            let added = content.strip_prefix('+')?;
+            LEFTOVER_MARKERS
+                .iter()
+                .any(|marker| contains_word(added, marker))
+                .then(|| HunkLocation {
+                    path: path.to_string(),
+                    line,
+                    text: added.trim().to_string(),
+                })
+        })
+        .collect()
+}
+
+/// Whether `word` is in `text` with no letter, digit or `_` right before or after it, so `XXX`
+/// is not found in `XXXL`
+fn contains_word(text: &str, word: &str) -> bool {
+    let is_word_char = |c: char| c.is_alphanumeric() || c == '_';
+    text.match_indices(word).any(|(start, _)| {
+        !text[..start].chars().next_back().is_some_and(is_word_char)
+            && !text[start + word.len()..]
+                .chars()
+                .next()
+                .is_some_and(is_word_char)
+    })
+}
+
+/// Line in the new version of the file of every line of git style diff output, `None` for
+/// headers and removed lines
+pub fn new_line_numbers(diff: &str) -> Vec<Option<usize>> {
+    // Line the next added or context line is at, `None` outside of hunks
+    let mut next: Option<usize> = None;
+    diff.lines()
+        .map(|content| {
+            if content.starts_with("diff --git ") {
+                next = None;
+                return None;
+            }
+            if let Some(header) = content.strip_prefix("@@ ") {
+                next = Some(hunk_start(header));
+                return None;
+            }
+            let line = next?;
+            // Some tools strip the space of empty context lines
+            match content.chars().next() {
+                Some('+' | ' ') | None => {
+                    next = Some(line + 1);
+                    Some(line)
+                }
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// A file of git style diff output split into its hunks, to commit only some of them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilePatch {
+    pub path:     String,
+    /// Path before a rename or copy, the same as `path` otherwise
+    pub old_path: String,
+    /// Lines before the first hunk, or the whole section of a file only committed as a whole
+    header:       String,
+    /// Hunks that can be picked one by one, none for renames, mode changes and binary files
+    pub hunks:    Vec<Hunk>,
+}
+
+impl FilePatch {
+    /// Patch of this file with only the hunks `keep` is true for
+    pub fn patch(&self, keep: impl Fn(usize) -> bool) -> String {
+        let mut patch = self.header.clone();
+        for (index, hunk) in self.hunks.iter().enumerate() {
+            if keep(index) {
+                patch.push_str(&hunk.text);
+            }
+        }
+        patch
+    }
+}
+
+/// A hunk of a `FilePatch`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hunk {
+    /// First line in the new version of the file
+    pub line: usize,
+    /// The `@@` line and the lines of the hunk
+    text:     String,
+}
+
+impl Hunk {
+    /// Lines of the hunk after the `@@` line
+    pub fn lines(&self) -> impl Iterator<Item = &str> {
+        self.text.lines().skip(1)
+    }
+}
+
+/// Split git style diff output into its files and their hunks
+pub fn split_patch(diff: &str) -> Vec<FilePatch> {
+    let mut files: Vec<FilePatch> = Vec::new();
+    for content in diff.split_inclusive('\n') {
+        if let Some(paths) = content.strip_prefix("diff --git a/") {
+            let paths = paths.trim_end();
+            let (old_path, path) = paths.rsplit_once(" b/").unwrap_or((paths, paths));
+            files.push(FilePatch {
+                path:     path.to_string(),
+                old_path: old_path.to_string(),
+                header:   content.to_string(),
+                hunks:    Vec::new(),
+            });
+            continue;
+        }
+        let Some(file) = files.last_mut() else {
+            continue;
+        };
+        if let Some(header) = content.strip_prefix("@@ ") {
+            file.hunks.push(Hunk {
+                line: hunk_start(header),
+                text: content.to_string(),
+            });
+        } else if let Some(hunk) = file.hunks.last_mut() {
+            hunk.text.push_str(content);
+        } else {
+            file.header.push_str(content);
+        }
+    }
+
+    // Leaving out a hunk of these would also undo the rename or mode change for the whole file
+    for file in &mut files {
+        let whole = ["rename from ", "copy from ", "old mode ", "Binary files "]
+            .iter()
+            .any(|marker| file.header.lines().any(|line| line.starts_with(marker)));
+        if whole {
+            let hunks: String = file.hunks.drain(..).map(|hunk| hunk.text).collect();
+            file.header.push_str(&hunks);
+        }
+    }
+    files
+}
+
+/// Rows of a list to pick hunks from: every file, followed by its hunks
+pub fn pick_rows(files: &[FilePatch]) -> Vec<(usize, Option<usize>)> {
+    files
+        .iter()
+        .enumerate()
+        .flat_map(|(file, patch)| {
+            std::iter::once((file, None))
+                .chain((0..patch.hunks.len()).map(move |hunk| (file, Some(hunk))))
+        })
+        .collect()
+}
+
+/// What committing some of the hunks of a change takes
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct HunkPick {
+    /// Files with at least one picked hunk, the old path of renames too
+    pub paths:    Vec<String>,
+    /// Patch of the hunks left out of those files, they stay in the working copy
+    pub left_out: String,
+}
+
+/// The files and hunks to commit, `picked` has an entry per hunk of each file, or a single
+/// one for files without hunks to pick
+pub fn pick_hunks(files: &[FilePatch], picked: &[Vec<bool>]) -> HunkPick {
+    let mut pick = HunkPick::default();
+    for (file, picked) in files.iter().zip(picked) {
+        if !picked.contains(&true) {
+            continue;
+        }
+        pick.paths.push(file.path.clone());
+        if file.old_path != file.path {
+            pick.paths.push(file.old_path.clone());
+        }
+        if picked.contains(&false) {
+            pick.left_out
+                .push_str(&file.patch(|hunk| !picked.get(hunk).copied().unwrap_or(true)));
+        }
+    }
+    pick
+}
+
+/// First line of a hunk in the new version of the file, from the header after its `@@ `
+fn hunk_start(header: &str) -> usize {
+    // `@@ -<old>,<count> +<new>,<count> @@`
+    header
+        .split_whitespace()
+        .find_map(|range| range.strip_prefix('+'))
+        .and_then(|range| range.split(',').next()?.parse().ok())
+        .unwrap_or(1)
+}
+
+/// Get the diff of all files in the working copy, or those matching `fileset`, like `jj diff`
+/// shows it
+/// Executes `jj diff --git [fileset]` command
+pub fn get_change_diff(fileset: Option<&str>) -> Result<String> {
+    let output = jj().args(["diff", "--git"]).args(fileset).run()?;
+
+    if !output.status.success() {
+        return Err(command_failed("jj diff", &output.stderr));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Get the changes of `revset` as a patch for `git apply`
+/// Executes `jj diff -r <revset> --git` command
+pub fn get_patch(revset: &str) -> Result<String> {
+    let output = jj().args(["diff", "--git", "-r", revset]).run()?;
+
+    if !output.status.success() {
+        return Err(command_failed("jj diff", &output.stderr));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Save the changes of `revset` as a patch to `path`, refusing to replace an existing file
+pub fn write_patch(revset: &str, path: &str) -> Result<()> {
+    let contents = get_patch(revset)?;
+    let mut file = match fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+    {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+            anyhow::bail!("{path} already exists")
+        }
+        Err(e) => return Err(e).with_context(|| format!("Failed to create {path}")),
+    };
+    file.write_all(contents.as_bytes())
+        .with_context(|| format!("Failed to write {path}"))
+}
+
+/// Apply a unified diff to the working copy, nothing is changed unless every hunk applies
+/// Executes `git apply` command in the workspace root
+pub fn apply_patch(patch: &str) -> Result<PatchResult> {
+    let output = jj().args(["root"]).run()?;
+    if !output.status.success() {
+        return Err(command_failed("jj root", &output.stderr));
+    }
+    let root = PathBuf::from(String::from_utf8_lossy(&output.stdout).trim());
+
+    let mut child = Command::new("git")
+        .args(["apply", "--verbose", "-"])
+        .current_dir(root)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Failed to run git apply")?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(patch.as_bytes())?;
+    }
+    let output = child.wait_with_output()?;
+
+    // git checks every hunk before writing any, so a failure left the files alone
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !output.status.success() {
+        anyhow::bail!("nothing was applied\n\n{}", stderr.trim());
+    }
+    Ok(PatchResult::parse(&stderr))
+}
+
+/// Commit only the picked hunks with `message`, the left out ones stay in the new working copy
+///
+/// The files on disk are never touched, the hunks are left out of jj's own copy of them while
+/// `jj commit -i` runs
+pub fn commit_hunks(pick: &HunkPick, message: &str, author: Option<&str>) -> Result<String> {
+    if pick.left_out.is_empty() {
+        return operations::commit_paths(message, author, &pick.paths, None);
+    }
+
+    let patch = std::env::temp_dir().join(format!("jjkk-left-out-{}.patch", std::process::id()));
+    fs::write(&patch, &pick.left_out)
+        .with_context(|| format!("Failed to write {}", patch.display()))?;
+    let committed = operations::commit_paths(message, author, &pick.paths, Some(&patch));
+    let _ = fs::remove_file(&patch);
+    committed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jj::test_repo::TestRepo;
+
+    #[test]
+    fn test_parse_anchors() {
+        let anchors = DiffAnchors::parse(
+            "diff --git a/src/main.rs b/src/main.rs\n\
+             index 1111111..2222222 100644\n\
+             --- a/src/main.rs\n\
+             +++ b/src/main.rs\n\
+             @@ -1,1 +1,1 @@\n\
+             -old\n\
+             +new\n\
+             diff --git a/old name.txt b/new name.txt\n\
+             rename from old name.txt\n\
+             rename to new name.txt\n",
+        );
+
+        assert_eq!(anchors.line_of("src/main.rs"), Some(0));
+        assert_eq!(anchors.line_of("new name.txt"), Some(7));
+        assert_eq!(anchors.line_of("old name.txt"), None);
+
+        assert_eq!(anchors.file_at(0), Some("src/main.rs"));
+        assert_eq!(anchors.file_at(6), Some("src/main.rs"));
+        assert_eq!(anchors.file_at(8), Some("new name.txt"));
+        assert_eq!(anchors.lines_of("src/main.rs", 10), Some(0..7));
+        assert_eq!(anchors.lines_of("new name.txt", 10), Some(7..10));
+    }
+
+    #[test]
+    fn test_hunk_locations() {
+        let hunks = hunk_locations(
+            "diff --git a/src/main.rs b/src/main.rs\n\
+             --- a/src/main.rs\n\
+             +++ b/src/main.rs\n\
+             @@ -10,4 +10,4 @@ fn main() {\n\
+             \x20   let a = 1;\n\
+             -    let b = 2;\n\
+             +    let b = 3;\n\
+             \x20   let c = 4;\n\
+             @@ -40,2 +40,1 @@\n\
+             -    removed();\n\
+             \x20   kept();\n\
+             diff --git a/gone.rs b/gone.rs\n\
+             --- a/gone.rs\n\
+             +++ /dev/null\n\
+             @@ -1,1 +0,0 @@\n\
+             -fn gone() {}\n",
+        );
+
+        let quickfix: Vec<String> = hunks.iter().map(HunkLocation::quickfix_line).collect();
+        assert_eq!(
+            quickfix,
+            ["src/main.rs:11: let b = 3;", "src/main.rs:40: removed();"]
+        );
+    }
+
+    #[test]
+    fn test_leftover_markers() {
+        let markers = leftover_markers(
+            "diff --git a/src/main.rs b/src/main.rs\n\
+             --- a/src/main.rs\n\
+             +++ b/src/main.rs\n\
+             @@ -1,3 +1,4 @@\n\
+             \x20// TODO: already there\n\
+             -// FIXME: fixed now\n\
+             +let a = 1;\n\
+             +dbg!(a); // XXX remove\n\
+             +let size = \"XXXL\"; // TODOS_URL\n\
+             \x20fn main() {}\n",
+        );
+
+        let quickfix: Vec<String> = markers.iter().map(HunkLocation::quickfix_line).collect();
+        assert_eq!(quickfix, ["src/main.rs:3: dbg!(a); // XXX remove"]);
+    }
+
+    #[test]
+    fn test_contains_word() {
+        assert!(contains_word("// TODO: later", "TODO"));
+        assert!(contains_word("FIXME", "FIXME"));
+        assert!(contains_word("XXXL or XXX", "XXX"));
+        assert!(!contains_word("size XXXL", "XXX"));
+        assert!(!contains_word("TODOS and MY_TODO", "TODO"));
+    }
+
+    #[test]
+    fn test_new_line_numbers() {
+        let numbers = new_line_numbers(
+            "diff --git a/src/main.rs b/src/main.rs\n\
+             --- a/src/main.rs\n\
+             +++ b/src/main.rs\n\
+             @@ -10,3 +10,3 @@ fn main() {\n\
+             \x20   let a = 1;\n\
+             -    let b = 2;\n\
+             +    let b = 3;\n\
+             \n",
+        );
+        assert_eq!(
+            numbers,
+            [None, None, None, None, Some(10), None, Some(11), Some(12)]
+        );
+    }
+
+    #[test]
+    fn test_pick_hunks() {
+        let files = split_patch(
+            "diff --git a/src/main.rs b/src/main.rs\n\
+             --- a/src/main.rs\n\
+             +++ b/src/main.rs\n\
+             @@ -1,1 +1,1 @@\n\
+             -one\n\
+             +uno\n\
+             @@ -20,1 +20,1 @@\n\
+             -twenty\n\
+             +veinte\n\
+             diff --git a/old.txt b/new.txt\n\
+             rename from old.txt\n\
+             rename to new.txt\n\
+             @@ -1,1 +1,1 @@\n\
+             -a\n\
+             +b\n",
+        );
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].hunks.len(), 2);
+        assert_eq!(files[0].hunks[1].line, 20);
+        assert_eq!(
+            files[0].hunks[1].lines().collect::<Vec<_>>(),
+            ["-twenty", "+veinte"]
+        );
+        // Renames go as a whole
+        assert!(files[1].hunks.is_empty());
+
+        let pick = pick_hunks(&files, &[vec![true, false], vec![true]]);
+        assert_eq!(pick.paths, ["src/main.rs", "new.txt", "old.txt"]);
+        assert_eq!(
+            pick.left_out,
+            "diff --git a/src/main.rs b/src/main.rs\n\
+             --- a/src/main.rs\n\
+             +++ b/src/main.rs\n\
+             @@ -20,1 +20,1 @@\n\
+             -twenty\n\
+             +veinte\n"
+        );
+        assert_eq!(
+            pick_hunks(&files, &[vec![false, false], vec![false]]),
+            HunkPick::default()
+        );
+    }
+
+    #[test]
+    fn test_commit_hunks_in_scripted_repo() {
+        let Some(repo) = TestRepo::new() else {
+            return;
+        };
+        let lines: String = (1..=40)
+            .flat_map(|line| [line.to_string(), "\n".to_string()])
+            .collect();
+        repo.write("f.txt", &lines).commit("Base");
+        let changed = lines
+            .replace("\n3\n", "\nthree\n")
+            .replace("\n30\n", "\nthirty\n");
+        repo.write("f.txt", &changed)
+            .write("other.txt", "left alone\n");
+
+        repo.run(|| {
+            let files = split_patch(&get_change_diff(None).unwrap());
+            let picked: Vec<Vec<bool>> = files
+                .iter()
+                .map(|file| {
+                    (0..file.hunks.len().max(1))
+                        .map(|hunk| file.path == "f.txt" && hunk == 0)
+                        .collect()
+                })
+                .collect();
+            commit_hunks(&pick_hunks(&files, &picked), "Only the first hunk", None).unwrap();
+        });
+
+        // Only the picked hunk is committed, the rest stays in the working copy as it was
+        let committed = repo.jj(&["file", "show", "-r", "@-", "f.txt"]);
+        assert_eq!(committed, lines.replace("\n3\n", "\nthree\n"));
+        let left = repo.jj(&["diff", "--git"]);
+        assert!(
+            left.contains("+thirty") && !left.contains("+three"),
+            "{left}"
+        );
+        assert!(left.contains("other.txt"), "{left}");
+        assert_eq!(
+            fs::read_to_string(repo.path().join("f.txt")).unwrap(),
+            changed
+        );
+    }
+}
@@ -0,0 +1,214 @@
+use std::fmt;
+
+/// Failures of jj that are common enough to explain with a hint
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JjErrorKind {
+    NoRemote,
+    AuthFailed,
+    ImmutableCommit,
+    Conflicted,
+    NotARepo,
+    /// jj ran longer than the configured timeout and was stopped
+    TimedOut,
+    Other,
+}
+
+impl JjErrorKind {
+    /// Recognize the failure from jj's error output
+    ///
+    /// Only jj's `Error:` line and the `Caused by:` lines below it count, so a warning or hint
+    /// that mentions e.g. an immutable commit doesn't change the kind. Output without an
+    /// `Error:` line, like errors from jj-lib, is matched as a whole
+    pub fn classify(stderr: &str) -> Self {
+        let stderr = error_lines(stderr).to_lowercase();
+        let any = |patterns: &[&str]| patterns.iter().any(|p| stderr.contains(p));
+
+        if any(&["no jj repo", "not a jj repo", "no jj repository"]) {
+            Self::NotARepo
+        } else if any(&["no git remote", "no such remote", "remote doesn't exist"]) {
+            Self::NoRemote
+        } else if any(&[
+            "permission denied",
+            "authentication failed",
+            "could not read username",
+            "could not read password",
+            "terminal prompts disabled",
+            "host key verification failed",
+        ]) {
+            Self::AuthFailed
+        } else if any(&["is immutable", "immutable commit"]) {
+            Self::ImmutableCommit
+        } else if any(&["has conflicts", "unresolved conflicts"]) {
+            Self::Conflicted
+        } else {
+            Self::Other
+        }
+    }
+
+    const fn message(self) -> &'static str {
+        match self {
+            Self::NoRemote => "No git remote is configured for this repository",
+            Self::AuthFailed => "Authentication with the remote failed",
+            Self::ImmutableCommit => "The commit is immutable and can't be rewritten",
+            Self::Conflicted => "The commit has unresolved conflicts",
+            Self::NotARepo => "Not inside a jj repository",
+            Self::TimedOut => "jj took too long and was stopped",
+            Self::Other => "",
+        }
+    }
+
+    /// What the user can do about it
+    pub const fn hint(self) -> Option<&'static str> {
+        match self {
+            Self::NoRemote => Some("Add one with `jj git remote add origin <url>`"),
+            Self::AuthFailed => Some(
+                "Check that your SSH key is loaded (`ssh-add -l`) or that a git credential helper is configured",
+            ),
+            Self::ImmutableCommit => {
+                Some("Create a new change on top of it with `jj new` and make the changes there")
+            }
+            Self::Conflicted => Some("Resolve the conflicts first, e.g. with `jj resolve`"),
+            Self::NotARepo => Some(
+                "Start jjkk inside a jj repository, or create one with `jj git init --colocate`",
+            ),
+            Self::TimedOut => Some(
+                "It may be waiting for input jjkk can't show, like a password or passphrase prompt or an editor, or for a lock held by another jj process. Raise `command_timeout_secs` in the config if it is just slow",
+            ),
+            Self::Other => None,
+        }
+    }
+}
+
+/// A failed jj command, shown as a short explanation with a hint when the
+/// failure is recognized and as jj's own error output otherwise
+#[derive(Debug, Clone)]
+pub struct JjError {
+    pub kind:    JjErrorKind,
+    pub command: String,
+    pub stderr:  String,
+}
+
+impl JjError {
+    pub fn new(command: &str, stderr: &str) -> Self {
+        Self {
+            kind:    JjErrorKind::classify(stderr),
+            command: command.to_string(),
+            stderr:  stderr.trim().to_string(),
+        }
+    }
+
+    /// `stderr` is what jj printed before it was stopped
+    pub fn timed_out(command: &str, stderr: &str) -> Self {
+        Self {
+            kind:    JjErrorKind::TimedOut,
+            command: command.to_string(),
+            stderr:  stderr.trim().to_string(),
+        }
+    }
+}
+
+impl fmt::Display for JjError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind.hint() {
+            Some(hint) => {
+                write!(f, "{}\n\n{hint}", self.kind.message())?;
+                // The explanation is a guess, jj's own output is what tells what went wrong
+                if !self.stderr.is_empty() {
+                    write!(f, "\n\n{} failed: {}", self.command, self.stderr)?;
+                }
+                Ok(())
+            }
+            None => write!(f, "{} failed: {}", self.command, self.stderr),
+        }
+    }
+}
+
+/// jj's `Error:` line with the `Caused by:` lines below it, or all of `stderr` when there is
+/// no `Error:` line
+fn error_lines(stderr: &str) -> String {
+    let mut lines = stderr
+        .lines()
+        .skip_while(|line| !line.starts_with("Error:"))
+        .peekable();
+    if lines.peek().is_none() {
+        return stderr.to_string();
+    }
+
+    lines
+        .take_while(|line| !line.starts_with("Hint:") && !line.starts_with("Warning:"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+impl std::error::Error for JjError {}
+
+/// Error for a jj command that exited unsuccessfully
+pub fn command_failed(command: &str, stderr: &[u8]) -> anyhow::Error {
+    JjError::new(command, &String::from_utf8_lossy(stderr)).into()
+}
+
+/// Swap an error from jj-lib for a `JjError` when the failure is recognized
+pub fn explain(command: &str, error: anyhow::Error) -> anyhow::Error {
+    let explained = JjError::new(command, &format!("{error:#}"));
+    if explained.kind == JjErrorKind::Other {
+        error
+    } else {
+        explained.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(
+            JjErrorKind::classify("Error: No git remote named 'origin'"),
+            JjErrorKind::NoRemote
+        );
+        assert_eq!(
+            JjErrorKind::classify("git@github.com: Permission denied (publickey)."),
+            JjErrorKind::AuthFailed
+        );
+        assert_eq!(
+            JjErrorKind::classify("Error: Commit 1a2b3c4d is immutable"),
+            JjErrorKind::ImmutableCommit
+        );
+        assert_eq!(
+            JjErrorKind::classify("Error: Won't push commit 1a2b3c4d since it has conflicts"),
+            JjErrorKind::Conflicted
+        );
+        assert_eq!(
+            JjErrorKind::classify("Error: There is no jj repo in \".\""),
+            JjErrorKind::NotARepo
+        );
+        assert_eq!(JjErrorKind::classify("Error: boom"), JjErrorKind::Other);
+        assert_eq!(
+            JjErrorKind::classify(
+                "Warning: Commit 1a2b3c4d is immutable\nError: boom\nHint: It has conflicts"
+            ),
+            JjErrorKind::Other
+        );
+        assert_eq!(
+            JjErrorKind::classify(
+                "Error: Failed to push\nCaused by:\n1: git@github.com: Permission denied"
+            ),
+            JjErrorKind::AuthFailed
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        let error = JjError::new("jj git push", "Error: No git remote named 'origin'\n");
+        assert!(error.to_string().contains("jj git remote add"));
+        assert!(
+            error
+                .to_string()
+                .ends_with("jj git push failed: Error: No git remote named 'origin'")
+        );
+
+        let error = JjError::new("jj rebase", "Error: boom\n");
+        assert_eq!(error.to_string(), "jj rebase failed: Error: boom");
+    }
+}
@@ -0,0 +1,319 @@
+use std::{
+    collections::{
+        BTreeMap,
+        BTreeSet,
+    },
+    fs,
+    path::{
+        Path,
+        PathBuf,
+    },
+    process::Command,
+    time::SystemTime,
+};
+
+use anyhow::{
+    Context,
+    Result,
+};
+
+use super::{
+    command::{
+        CommandExt,
+        jj,
+    },
+    error::command_failed,
+};
+
+/// How jj's view of the repo relates to the refs of its git repository
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GitInteropStatus {
+    /// The git repository is the `.git` next to `.jj`, git commands work in the workspace
+    pub colocated:   bool,
+    /// How long ago git refs were last imported, e.g. "5 minutes ago"
+    pub last_import: Option<String>,
+    pub last_export: Option<String>,
+    /// Bookmarks pointing somewhere else in git
+    pub differences: Vec<RefDifference>,
+}
+
+/// A bookmark whose target differs between jj and git, `None` when it is missing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RefDifference {
+    pub name: String,
+    pub jj:   Option<String>,
+    pub git:  Option<String>,
+}
+
+pub fn get_interop_status() -> Result<GitInteropStatus> {
+    let root = PathBuf::from(run_jj(&["root"])?.trim());
+    let git_dir = PathBuf::from(run_jj(&["git", "root"])?.trim());
+    let (last_import, last_export) = last_import_export(&run_jj(&[
+        "op",
+        "log",
+        "--no-graph",
+        "--ignore-working-copy",
+        "--limit",
+        "200",
+        "-T",
+        r#"description.first_line() ++ "\x1f" ++ time.end().ago() ++ "\n""#,
+    ])?);
+
+    let jj_refs = parse_refs(&run_jj(&[
+        "bookmark",
+        "list",
+        "--ignore-working-copy",
+        "-T",
+        r#"if(!remote, name ++ " " ++ if(normal_target, normal_target.commit_id(), "") ++ "\n")"#,
+    ])?);
+    let git_refs = parse_refs(&git_refs(&git_dir)?);
+
+    Ok(GitInteropStatus {
+        colocated: git_dir == root.join(".git"),
+        last_import,
+        last_export,
+        differences: diff_refs(&jj_refs, &git_refs),
+    })
+}
+
+/// The `.git` directory of a colocated repo, `None` when jj keeps its git repository elsewhere
+pub fn colocated_git_dir() -> Result<Option<PathBuf>> {
+    let root = PathBuf::from(run_jj(&["root"])?.trim());
+    let git_dir = PathBuf::from(run_jj(&["git", "root"])?.trim());
+    Ok((git_dir == root.join(".git")).then_some(git_dir))
+}
+
+/// Commit git's HEAD moved to since jj last imported it, e.g. with `git checkout` or
+/// `git commit`. `None` while both agree
+pub fn git_head_drift(git_dir: &Path) -> Result<Option<String>> {
+    // Ignoring the working copy keeps jj from importing the new HEAD right away
+    let jj_head = run_jj(&[
+        "log",
+        "--no-graph",
+        "--ignore-working-copy",
+        "-r",
+        "git_head()",
+        "-T",
+        "commit_id",
+    ])?;
+    let git_head = run_git(git_dir, &["rev-parse", "HEAD"])?;
+    let git_head = git_head.trim();
+    Ok((jj_head.trim() != git_head).then(|| git_head[..git_head.len().min(8)].to_string()))
+}
+
+/// When git's HEAD last moved, the latest change of `HEAD` and of the branch it is on. Only
+/// worth asking `git_head_drift` again once this changes
+pub fn git_head_modified(git_dir: &Path) -> Option<SystemTime> {
+    let modified = |path: &Path| fs::metadata(path).and_then(|m| m.modified()).ok();
+    let head = git_dir.join("HEAD");
+    // A commit on the checked out branch moves the branch, not HEAD. Packed refs are only
+    // written by `git gc` and `git pack-refs`, never by a commit
+    let branch = fs::read_to_string(&head)
+        .ok()
+        .and_then(|content| Some(git_dir.join(content.strip_prefix("ref: ")?.trim())));
+    modified(&head).max(branch.as_deref().and_then(modified))
+}
+
+/// Import changed git refs into jj
+/// Executes `jj git import` command
+pub fn git_import() -> Result<String> {
+    run_jj(&["git", "import"])
+}
+
+/// Update the git refs to match jj's bookmarks
+/// Executes `jj git export` command
+pub fn git_export() -> Result<String> {
+    run_jj(&["git", "export"])
+}
+
+/// Put git on the branch of `bookmark`, for tools that only read git
+///
+/// jj leaves git's HEAD detached at the parent of the working copy, so `bookmark` has to
+/// point there. Executes `jj git export` and `git symbolic-ref HEAD refs/heads/<bookmark>` commands
+pub fn attach_git_head(bookmark: &str) -> Result<String> {
+    let root = PathBuf::from(run_jj(&["root"])?.trim());
+    let git_dir = PathBuf::from(run_jj(&["git", "root"])?.trim());
+    if git_dir != root.join(".git") {
+        anyhow::bail!("Only colocated repositories share the checked out branch with git");
+    }
+
+    // The git branch has to exist and point where the bookmark does
+    run_jj(&["git", "export"])?;
+    let branch = format!("refs/heads/{bookmark}");
+    let head = run_git(&git_dir, &["rev-parse", "HEAD"])?;
+    let target = run_git(
+        &git_dir,
+        &["rev-parse", "--verify", &format!("{branch}^{{commit}}")],
+    )?;
+    if head.trim() != target.trim() {
+        anyhow::bail!("git HEAD is not at {bookmark}, start a new change on it first");
+    }
+
+    run_git(
+        &git_dir,
+        &["symbolic-ref", "-m", "jjkk: checkout", "HEAD", &branch],
+    )?;
+    Ok(format!("git is on branch {bookmark}"))
+}
+
+fn run_jj(args: &[&str]) -> Result<String> {
+    let output = jj().args(args).run()?;
+
+    if !output.status.success() {
+        return Err(command_failed(
+            &format!("jj {}", args[..2.min(args.len())].join(" ")),
+            &output.stderr,
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Branches of the git repository with their commit ids
+/// Executes `git for-each-ref refs/heads` command
+fn git_refs(git_dir: &Path) -> Result<String> {
+    run_git(
+        git_dir,
+        &[
+            "for-each-ref",
+            "--format=%(refname:strip=2) %(objectname)",
+            "refs/heads",
+        ],
+    )
+}
+
+fn run_git(git_dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .arg("--git-dir")
+        .arg(git_dir)
+        .args(args)
+        .output()
+        .with_context(|| format!("Failed to run git {}", args[0]))?;
+
+    if !output.status.success() {
+        anyhow::bail!(
+            "git {} failed: {}",
+            args[0],
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Parse `name commit_id` lines, an empty id stands for a conflicted or deleted bookmark
+fn parse_refs(output: &str) -> BTreeMap<String, Option<String>> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            let name = parts.next()?;
+            Some((name.to_string(), parts.next().map(str::to_string)))
+        })
+        .collect()
+}
+
+fn diff_refs(
+    jj_refs: &BTreeMap<String, Option<String>>,
+    git_refs: &BTreeMap<String, Option<String>>,
+) -> Vec<RefDifference> {
+    let names: BTreeSet<&String> = jj_refs.keys().chain(git_refs.keys()).collect();
+
+    names
+        .into_iter()
+        .filter_map(|name| {
+            let jj = jj_refs.get(name).cloned().flatten();
+            let git = git_refs.get(name).cloned().flatten();
+            (jj != git).then(|| RefDifference {
+                name: name.clone(),
+                jj,
+                git,
+            })
+        })
+        .collect()
+}
+
+/// Find the newest import and export in "description\x1fago" lines of the op log
+fn last_import_export(op_log: &str) -> (Option<String>, Option<String>) {
+    let mut last_import = None;
+    let mut last_export = None;
+
+    for line in op_log.lines() {
+        let Some((description, ago)) = line.split_once('\x1f') else {
+            continue;
+        };
+        if last_import.is_none() && description.starts_with("import git") {
+            last_import = Some(ago.to_string());
+        }
+        if last_export.is_none() && description.starts_with("export git") {
+            last_export = Some(ago.to_string());
+        }
+    }
+    (last_import, last_export)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_git_head_modified() {
+        let git_dir = std::env::temp_dir().join(format!("jjkk-git-head-{}", std::process::id()));
+        fs::create_dir_all(git_dir.join("refs/heads")).unwrap();
+        let set_modified = |path: &str, secs: u64| {
+            let time = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(secs);
+            fs::File::options()
+                .write(true)
+                .open(git_dir.join(path))
+                .unwrap()
+                .set_modified(time)
+                .unwrap();
+            time
+        };
+
+        fs::write(git_dir.join("HEAD"), "ref: refs/heads/main\n").unwrap();
+        fs::write(git_dir.join("refs/heads/main"), "aaaa\n").unwrap();
+        set_modified("HEAD", 100);
+        let committed = set_modified("refs/heads/main", 200);
+        assert_eq!(git_head_modified(&git_dir), Some(committed));
+
+        // Detached, the way jj leaves it
+        fs::write(git_dir.join("HEAD"), "bbbb\n").unwrap();
+        let detached = set_modified("HEAD", 150);
+        assert_eq!(git_head_modified(&git_dir), Some(detached));
+
+        fs::remove_dir_all(&git_dir).unwrap();
+        assert_eq!(git_head_modified(&git_dir), None);
+    }
+
+    #[test]
+    fn test_diff_refs() {
+        let jj_refs = parse_refs("main aaaa\nfeature bbbb\nconflicted\n");
+        let git_refs = parse_refs("main aaaa\nfeature cccc\nconflicted eeee\nonly-git dddd\n");
+
+        let differences = diff_refs(&jj_refs, &git_refs);
+        let names: Vec<(&str, Option<&str>, Option<&str>)> = differences
+            .iter()
+            .map(|d| (d.name.as_str(), d.jj.as_deref(), d.git.as_deref()))
+            .collect();
+        assert_eq!(names.len(), 3);
+        assert_eq!(names[0].0, "conflicted");
+        assert_eq!(names[1], ("feature", Some("bbbb"), Some("cccc")));
+        assert_eq!(names[2], ("only-git", None, Some("dddd")));
+    }
+
+    #[test]
+    fn test_last_import_export() {
+        let op_log = "snapshot working copy\x1f1 minute ago\n\
+                      import git refs\x1f5 minutes ago\n\
+                      export git refs\x1f1 hour ago\n\
+                      import git refs\x1f2 hours ago\n";
+        assert_eq!(
+            last_import_export(op_log),
+            (
+                Some("5 minutes ago".to_string()),
+                Some("1 hour ago".to_string())
+            )
+        );
+    }
+}
@@ -0,0 +1,251 @@
+use std::{
+    fs::{
+        self,
+        OpenOptions,
+    },
+    io::{
+        ErrorKind,
+        Write,
+    },
+    path::{
+        Path,
+        PathBuf,
+    },
+    process::Command,
+    sync::OnceLock,
+};
+
+use anyhow::{
+    Context,
+    Result,
+};
+
+use super::{
+    command::{
+        command_line,
+        command_name,
+    },
+    operations,
+    runner::is_read_only,
+};
+
+/// Journal of the repo jjkk works in, set once the repo is opened
+static JOURNAL_FILE: OnceLock<PathBuf> = OnceLock::new();
+/// File name of the journal inside the `.jj` directory of the workspace
+const JOURNAL_NAME: &str = "jjkk-journal";
+/// Length of the operation ids shown, as long as `jj op log` shows them
+const SHORT_OPERATION_ID_LENGTH: usize = 12;
+
+/// A change jjkk made to the repo, as recorded in the journal
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JournalEntry {
+    /// When the change was made, as rfc3339
+    pub time:      String,
+    /// Operation before the change, restoring it undoes this change and everything after it
+    pub before:    String,
+    pub after:     String,
+    /// What jjkk ran, like `jj rebase` or `describe working copy` for changes made through jj-lib
+    pub action:    String,
+    pub arguments: String,
+}
+
+impl JournalEntry {
+    pub fn short_before(&self) -> &str {
+        short_id(&self.before)
+    }
+
+    pub fn short_after(&self) -> &str {
+        short_id(&self.after)
+    }
+}
+
+fn short_id(operation: &str) -> &str {
+    &operation[..operation.len().min(SHORT_OPERATION_ID_LENGTH)]
+}
+
+/// Record every change jjkk makes to the workspace at `workspace_root` from now on
+pub fn open(workspace_root: &Path) {
+    // Only the first call wins, jjkk never switches repos while running
+    let _ = JOURNAL_FILE.set(workspace_root.join(".jj").join(JOURNAL_NAME));
+}
+
+/// Operation before running `command`, `None` when the command is not recorded
+pub fn operation_before(command: &Command) -> Option<String> {
+    let path = JOURNAL_FILE.get()?;
+    if is_read_only(command) {
+        return None;
+    }
+    current_operation(path)
+}
+
+/// Record `command` once it ran, `before` is what `operation_before` returned
+pub fn record_command(command: &Command, before: &str) {
+    let Some(after) = JOURNAL_FILE.get().and_then(|path| current_operation(path)) else {
+        return;
+    };
+    let action = command_name(command);
+    let line = command_line(command);
+    let arguments = line.strip_prefix(&action).unwrap_or(&line).trim();
+    record(&action, arguments, before, &after);
+}
+
+/// Whether a jjkk on the workspace, this one or another, made `operation`
+pub fn recorded(operation: &str) -> bool {
+    load().is_ok_and(|entries| {
+        entries
+            .iter()
+            .any(|entry| entry.short_after() == short_id(operation))
+    })
+}
+
+/// The operation head, read from the op heads jj keeps in the repo so that journaling a
+/// command doesn't run jj twice more. Asks jj while several heads wait to be merged
+fn current_operation(journal: &Path) -> Option<String> {
+    journal
+        .parent()
+        .and_then(single_operation_head)
+        .or_else(|| operations::get_operation_id().ok())
+}
+
+/// The only file in `.jj/repo/op_heads/heads` is named after the operation head
+fn single_operation_head(dot_jj: &Path) -> Option<String> {
+    let mut repo = dot_jj.join("repo");
+    // Other workspaces point at the repo of the first one
+    if repo.is_file() {
+        let target = fs::read_to_string(&repo).ok()?;
+        repo = dot_jj.join(target.trim());
+    }
+
+    let mut heads = fs::read_dir(repo.join("op_heads").join("heads"))
+        .ok()?
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        // The lock file sits next to the heads
+        .filter(|name| !name.is_empty() && name.chars().all(|c| c.is_ascii_hexdigit()));
+    let head = heads.next()?;
+    heads.next().is_none().then_some(head)
+}
+
+/// Append a change from operation `before` to `after` to the journal, if one is open
+pub fn record(action: &str, arguments: &str, before: &str, after: &str) {
+    let Some(path) = JOURNAL_FILE.get() else {
+        return;
+    };
+    // Failed commands that left the repo alone, the ids may be of different lengths
+    if short_id(before) == short_id(after) {
+        return;
+    }
+    let line = format!(
+        "{}\t{before}\t{after}\t{}\t{}\n",
+        chrono::Local::now().to_rfc3339(),
+        escape(action),
+        escape(arguments)
+    );
+    // Written at once and synced, so a crash right after still finds the change on disk.
+    // The journal must never break the change itself
+    if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(path) {
+        let _ = file
+            .write_all(line.as_bytes())
+            .and_then(|()| file.sync_data());
+    }
+}
+
+/// Every recorded change, newest first
+pub fn load() -> Result<Vec<JournalEntry>> {
+    let Some(path) = JOURNAL_FILE.get() else {
+        return Ok(Vec::new());
+    };
+    match fs::read_to_string(path) {
+        Ok(contents) => Ok(parse(&contents)),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    }
+}
+
+fn parse(contents: &str) -> Vec<JournalEntry> {
+    // A crash while writing leaves at most a cut off last line, which is skipped
+    contents.lines().rev().filter_map(parse_line).collect()
+}
+
+fn parse_line(line: &str) -> Option<JournalEntry> {
+    let mut fields = line.split('\t');
+    let entry = JournalEntry {
+        time:      fields.next()?.to_string(),
+        before:    fields.next()?.to_string(),
+        after:     fields.next()?.to_string(),
+        action:    unescape(fields.next()?),
+        arguments: unescape(fields.next()?),
+    };
+    fields.next().is_none().then_some(entry)
+}
+
+/// Keep each entry on a single line with tab separated fields
+pub(super) fn escape(field: &str) -> String {
+    field
+        .replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+}
+
+pub(super) fn unescape(field: &str) -> String {
+    let mut unescaped = String::with_capacity(field.len());
+    let mut chars = field.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => unescaped.push('\t'),
+            Some('n') => unescaped.push('\n'),
+            Some(other) => unescaped.push(other),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jj::test_repo::TestRepo;
+
+    #[test]
+    fn test_operation_head_without_jj() {
+        let Some(repo) = TestRepo::new() else {
+            return;
+        };
+        repo.write("README.md", "hello\n").commit("First");
+        let dot_jj = repo.path().join(".jj");
+
+        let head = single_operation_head(&dot_jj).unwrap();
+        let op_log = repo.jj(&[
+            "op",
+            "log",
+            "--no-graph",
+            "--limit",
+            "1",
+            "--ignore-working-copy",
+            "-T",
+            "id",
+        ]);
+        assert_eq!(head, op_log.trim());
+    }
+
+    #[test]
+    fn test_parse_journal() {
+        let contents = format!(
+            "2026-01-01T10:00:00+00:00\taaa\tbbb\tjj rebase\t-b @ -d main\n\
+             2026-01-01T10:01:00+00:00\tbbb\tccc\tdescribe working copy\t{}\n\
+             2026-01-01T10:02:00+00:00\tccc\tdd",
+            escape("first line\n\tindented\\")
+        );
+        let entries = parse(&contents);
+
+        // The cut off last line is skipped, the newest entry comes first
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].action, "describe working copy");
+        assert_eq!(entries[0].arguments, "first line\n\tindented\\");
+        assert_eq!(entries[1].before, "aaa");
+        assert_eq!(entries[1].arguments, "-b @ -d main");
+    }
+}
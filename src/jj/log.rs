@@ -1,61 +1,224 @@
-use std::process::Command;
+use anyhow::Result;
 
-use anyhow::{
-    Context,
-    Result,
+use super::{
+    command::{
+        CommandExt,
+        jj,
+    },
+    error::command_failed,
 };
 
-#[derive(Debug)]
+/// Separates the fields of a single commit in the log template output
+const FIELD_SEPARATOR: char = '\x1f';
+/// Terminates each commit record in the log template output
+const RECORD_SEPARATOR: char = '\x1e';
+
+#[derive(Debug, Clone)]
 pub struct CommitInfo {
-    pub change_id:   String,
+    pub change_id:        String,
     /// Short commit id, currently unused it seems
-    _commit_id:      String,
-    pub description: String,
-    pub author:      String,
+    _commit_id:           String,
+    /// First line of the description
+    pub description:      String,
+    /// Remaining lines of the description, without the first line
+    pub description_body: String,
+    pub author:           String,
+    /// Local bookmarks pointing at this commit
+    pub bookmarks:        Vec<String>,
+    /// Already on a remote, an ancestor of one of the remote bookmarks
+    pub pushed:           bool,
+}
+
+impl CommitInfo {
+    /// First non-empty line of the description body, if any
+    pub fn second_line(&self) -> Option<&str> {
+        self.description_body
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty())
+    }
+}
+
+/// Narrows the log down to the commits touching a path and/or authored in a date range
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LogFilter {
+    /// File or directory relative to the current directory, like jj paths
+    pub path:  Option<String>,
+    /// Start of the date range, any date jj understands, e.g. `2024-01-31` or `2 weeks ago`
+    pub since: Option<String>,
+    pub until: Option<String>,
+}
+
+impl LogFilter {
+    pub const fn is_empty(&self) -> bool {
+        self.path.is_none() && self.since.is_none() && self.until.is_none()
+    }
+
+    /// Set the date range from `since..until`, either end may be left out, a single date is
+    /// the start of the range and empty text clears it
+    pub fn set_date_range(&mut self, range: &str) {
+        let (since, until) = range.split_once("..").unwrap_or((range, ""));
+        let non_empty = |date: &str| Some(date.trim().to_string()).filter(|date| !date.is_empty());
+        self.since = non_empty(since);
+        self.until = non_empty(until);
+    }
+
+    /// Revset of the commits in `log_revset`, the ones the log shows unfiltered, that match
+    /// the filter. `None` when there is nothing to filter
+    pub fn revset(&self, log_revset: &str) -> Option<String> {
+        if self.is_empty() {
+            return None;
+        }
+        let mut parts = vec![format!("({log_revset})")];
+        if let Some(path) = &self.path {
+            parts.push(format!("files({})", quote(path)));
+        }
+        if let Some(since) = &self.since {
+            parts.push(format!("author_date(after:{})", quote(since)));
+        }
+        if let Some(until) = &self.until {
+            parts.push(format!("author_date(before:{})", quote(until)));
+        }
+        Some(parts.join(" & "))
+    }
+
+    /// Short description for the log title, e.g. `src/ since 2024-01-01`
+    pub fn label(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(path) = &self.path {
+            parts.push(path.clone());
+        }
+        if let Some(since) = &self.since {
+            parts.push(format!("since {since}"));
+        }
+        if let Some(until) = &self.until {
+            parts.push(format!("until {until}"));
+        }
+        parts.join(" ")
+    }
 }
 
-pub fn get_log(limit: usize) -> Result<Vec<CommitInfo>> {
-    let output = Command::new("jj")
+/// `text` as a revset string literal
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Get the log of the commits in `revset`, or jj's default log revset when `None`
+pub fn get_log_for_revset(revset: Option<&str>, limit: usize) -> Result<Vec<CommitInfo>> {
+    let mut command = jj();
+    command.args(["log", "--limit", &limit.to_string(), "--no-graph"]);
+    if let Some(revset) = revset {
+        command.args(["-r", revset]);
+    }
+
+    let output = command
         .args([
-            "log",
-            "--limit",
-            &limit.to_string(),
-            "--no-graph",
             "-T",
-            r#"change_id.short() ++ " " ++ commit_id.short() ++ " " ++ description.first_line() ++ " <" ++ author.email() ++ ">\n""#,
+            r#"change_id.short() ++ "\x1f" ++ commit_id.short() ++ "\x1f" ++ description ++ "\x1f<" ++ author.email() ++ ">\x1f" ++ local_bookmarks.map(|b| b.name()).join(" ") ++ "\x1f" ++ if(self.contained_in("::(remote_bookmarks() | root())"), "1", "0") ++ "\x1e""#,
         ])
-        .output()
-        .context("Failed to get log")?;
+        .run()?;
 
     if !output.status.success() {
-        anyhow::bail!("jj log failed: {}", String::from_utf8_lossy(&output.stderr));
+        return Err(command_failed("jj log", &output.stderr));
     }
 
     let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(parse_log(&stdout))
+}
+
+fn parse_log(output: &str) -> Vec<CommitInfo> {
     let mut commits = Vec::new();
 
-    for line in stdout.lines() {
-        // Parse format: "change_id commit_id description <email>"
-        let parts: Vec<&str> = line.splitn(3, ' ').collect();
-        if parts.len() >= 3 {
-            let change_id = parts[0].to_string();
-            let commit_id = parts[1].to_string();
+    for record in output.split(RECORD_SEPARATOR) {
+        // Parse format:
+        // "change_id \x1f commit_id \x1f description \x1f <email> \x1f bookmarks \x1f pushed"
+        let parts: Vec<&str> = record
+            .trim_start_matches('\n')
+            .split(FIELD_SEPARATOR)
+            .collect();
+        if parts.len() < 4 {
+            continue;
+        }
 
-            // Split description and author
-            let rest = parts[2];
-            if let Some(author_start) = rest.rfind('<') {
-                let description = rest[..author_start].trim().to_string();
-                let author = rest[author_start..].to_string();
+        let description = parts[2].trim();
+        let (first_line, body) = description.split_once('\n').unwrap_or((description, ""));
 
-                commits.push(CommitInfo {
-                    change_id,
-                    _commit_id: commit_id,
-                    description,
-                    author,
-                });
-            }
-        }
+        commits.push(CommitInfo {
+            change_id:        parts[0].to_string(),
+            _commit_id:       parts[1].to_string(),
+            description:      first_line.trim().to_string(),
+            description_body: body.trim().to_string(),
+            author:           parts[3].to_string(),
+            bookmarks:        parts
+                .get(4)
+                .map(|names| names.split_whitespace().map(str::to_string).collect())
+                .unwrap_or_default(),
+            pushed:           parts.get(5) == Some(&"1"),
+        });
     }
 
-    Ok(commits)
+    commits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jj::test_repo::TestRepo;
+
+    #[test]
+    fn test_conflicts_in_scripted_repo() {
+        let Some(repo) = TestRepo::new() else {
+            return;
+        };
+        repo.write("a.txt", "base\n")
+            .commit("Base")
+            .conflict("a.txt");
+
+        let conflicted = repo
+            .run(|| get_log_for_revset(Some("conflicts()"), 10))
+            .unwrap();
+        assert_eq!(conflicted.len(), 1);
+        assert!(!conflicted[0].pushed);
+    }
+
+    #[test]
+    fn test_parse_log_bookmarks() {
+        let output = "abc\x1f123\x1ffix parser\n\nbody\x1f<a@b.c>\x1fmain feature\x1f1\x1e\n\
+                      def\x1f456\x1f\x1f<a@b.c>\x1f\x1f0\x1e";
+        let commits = parse_log(output);
+
+        assert_eq!(commits.len(), 2);
+        assert_eq!(commits[0].bookmarks, vec!["main", "feature"]);
+        assert_eq!(commits[0].description, "fix parser");
+        assert_eq!(commits[0].second_line(), Some("body"));
+        assert!(commits[1].bookmarks.is_empty());
+        assert!(commits[0].pushed);
+        assert!(!commits[1].pushed);
+    }
+
+    #[test]
+    fn test_log_filter_revset() {
+        let mut filter = LogFilter::default();
+        assert_eq!(filter.revset("all()"), None);
+
+        filter.path = Some(r#"src/"quoted".rs"#.to_string());
+        filter.set_date_range("2024-01-01..");
+        assert_eq!(filter.until, None);
+        assert_eq!(
+            filter
+                .revset("present(@) | ancestors(immutable_heads().., 2)")
+                .as_deref(),
+            Some(
+                r#"(present(@) | ancestors(immutable_heads().., 2)) & files("src/\"quoted\".rs") & author_date(after:"2024-01-01")"#
+            )
+        );
+
+        filter.set_date_range(" ..2 weeks ago ");
+        assert_eq!(filter.since, None);
+        assert_eq!(filter.until.as_deref(), Some("2 weeks ago"));
+
+        filter.set_date_range("");
+        filter.path = None;
+        assert!(filter.is_empty());
+    }
 }
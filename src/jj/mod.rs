@@ -1,5 +1,23 @@
+pub mod askpass;
+pub mod background;
+pub mod command;
+pub mod diff;
+pub mod error;
+pub mod interop;
+pub mod journal;
 pub mod log;
 pub mod native_operations;
+pub mod notes;
+pub mod op_diff;
 pub mod operations;
+pub mod passthrough;
+pub mod recent;
 pub mod repo;
+pub mod results;
+pub mod runner;
+pub mod session;
+pub mod sidecar;
 pub mod status;
+#[cfg(test)]
+pub mod test_repo;
+pub mod version;
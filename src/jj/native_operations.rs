@@ -1,6 +1,11 @@
 use std::{
     collections::HashMap,
     fmt::Write,
+    num::NonZeroU32,
+    path::{
+        Path,
+        PathBuf,
+    },
     sync::Arc,
 };
 
@@ -8,21 +13,37 @@ use anyhow::{
     Ok,
     Result,
 };
+use futures::executor::block_on;
 use jj_lib::{
+    backend::{
+        CommitId,
+        Signature,
+    },
+    commit::Commit,
     config::{
         ConfigSource,
         StackedConfig,
     },
     git::{
         GitFetch,
+        GitFetchRefExpression,
         GitImportOptions,
+        GitRefKind,
         GitSubprocessOptions,
+        REMOTE_NAME_FOR_LOCAL_GIT_REPO,
         RemoteCallbacks,
         expand_default_fetch_refspecs,
+        expand_fetch_refspecs,
         get_all_remote_names,
         get_git_repo,
+        import_some_refs,
     },
-    object_id::ObjectId,
+    object_id::{
+        HexPrefix,
+        ObjectId,
+        PrefixResolution,
+    },
+    op_store::RefTarget,
     ref_name::{
         RefName,
         RemoteName,
@@ -33,14 +54,36 @@ use jj_lib::{
         StoreFactories,
     },
     settings::UserSettings,
+    str_util::StringExpression,
+    transaction::Transaction,
     workspace::{
         Workspace,
         default_working_copy_factories,
     },
 };
 
+use super::{
+    askpass,
+    command::join_arguments,
+    error::explain,
+    journal,
+    operations::{
+        self,
+        parse_author,
+    },
+    results::FetchStats,
+    session,
+};
+
+/// Length of the ids shown in messages, as long as `jj log` shows them
+const SHORT_ID_LENGTH: usize = 8;
+
+/// How often a transaction is attempted when another process keeps moving the operation head
+const MAX_TRANSACTION_ATTEMPTS: usize = 3;
+
 pub struct Native {
     pub workspace:      Workspace,
+    /// Repo as of the last transaction, reloaded at the operation head before every new one
     pub repo:           Arc<ReadonlyRepo>,
     #[allow(dead_code)] // - not actually dead code, just not yet used in a user facing way
     pub origin_names: Vec<String>,
@@ -51,7 +94,24 @@ impl Native {
     /// Create a new native jj operation handler
     /// for now its empty
     pub fn new() -> Self {
-        let workspace = detect_workspace().expect("Failed to detect workspace");
+        Self::open(Path::new("."))
+    }
+
+    /// Handler for the workspace at `workspace_path` instead of the current directory
+    pub fn open(workspace_path: &Path) -> Self {
+        Self::load(workspace_path, &user_config_paths())
+    }
+
+    /// Handler for the workspace at `workspace_path` with `user_config` as the only user config,
+    /// so the config of whoever runs the tests can't change them
+    #[cfg(test)]
+    pub fn open_with_user_config(workspace_path: &Path, user_config: &Path) -> Self {
+        Self::load(workspace_path, &[user_config.to_path_buf()])
+    }
+
+    fn load(workspace_path: &Path, user_config_paths: &[PathBuf]) -> Self {
+        let workspace = detect_workspace(workspace_path, user_config_paths)
+            .expect("Failed to detect workspace");
         let repo = workspace
             .repo_loader()
             .load_at_head()
@@ -79,115 +139,130 @@ impl Native {
 
     /// Describe the current change with a message using jj-lib
     /// This is a native implementation using the jj-lib crate instead of CLI interop
-    pub fn describe(&self, message: &str) -> Result<String> {
+    /// `author` replaces the author as `Name <email>`, like `jj describe --author`
+    pub fn describe(&mut self, message: &str, author: Option<&str>) -> Result<String> {
         // validate that there is at least some kind of message
         if message.trim().is_empty() {
             return Err(anyhow::anyhow!("Description message cannot be empty"));
         }
-
-        // Start a transaction
-        let mut tx = self.repo.start_transaction();
-
-        // Get the working copy commit ID
-        let wc_commit_id = tx
-            .repo()
-            .view()
-            .get_wc_commit_id(self.workspace.workspace_name())
-            .ok_or_else(|| anyhow::anyhow!("No working copy commit found"))?
-            .clone();
-
-        // Load the working copy commit
-        let wc_commit = tx.repo().store().get_commit(&wc_commit_id)?;
-
-        // Rewrite the commit with the new description
-        tx.repo_mut()
-            .rewrite_commit(&wc_commit)
-            .set_description(message)
-            .write()?;
-
-        // Rebase any descendants
-        tx.repo_mut().rebase_descendants()?;
-
-        // Commit the transaction
-        tx.commit("describe working copy")?;
+        let arguments = message_arguments(message, author);
+        let author = author.map(parse_author).transpose()?;
+
+        transact(&mut self.repo, "describe working copy", &arguments, |tx| {
+            // Get the working copy commit ID
+            let wc_commit_id = tx
+                .repo()
+                .view()
+                .get_wc_commit_id(self.workspace.workspace_name())
+                .ok_or_else(|| anyhow::anyhow!("No working copy commit found"))?
+                .clone();
+
+            // Load the working copy commit
+            let wc_commit = tx.repo().store().get_commit(&wc_commit_id)?;
+
+            // Rewrite the commit with the new description
+            let mut builder = tx
+                .repo_mut()
+                .rewrite_commit(&wc_commit)
+                .set_description(message);
+            if let Some(author) = &author {
+                builder = builder.set_author(with_author(wc_commit.author(), author));
+            }
+            let described = builder.write()?;
+
+            // Rebase any descendants
+            tx.repo_mut().rebase_descendants()?;
+            Ok(described)
+        })?;
 
         Ok(format!(
-            "Working copy commit description updated to: {message}"
+            "Described {}, now commit {}",
+            short_change_id(&described),
+            short_commit_id(&described)
         ))
     }
 
     /// Commit the current change with a message and create a new empty working copy commit
-    /// This is equivalent to `jj commit -m <message>`
-    pub fn commit(&self, message: &str) -> Result<String> {
+    /// This is equivalent to `jj commit -m <message> [--author <author>]`
+    pub fn commit(&mut self, message: &str, author: Option<&str>) -> Result<String> {
         // validate that there is at least some kind of message
         if message.trim().is_empty() {
             return Err(anyhow::anyhow!("Commit message cannot be empty"));
         }
-
-        let mut tx = self.repo.start_transaction();
-
-        let wc_commit_id = tx
-            .repo()
-            .view()
-            .get_wc_commit_id(self.workspace.workspace_name())
-            .ok_or_else(|| anyhow::anyhow!("No working copy commit found"))?
-            .clone();
-
-        let wc_commit = tx.repo().store().get_commit(&wc_commit_id)?;
-
-        let committed = tx
-            .repo_mut()
-            .rewrite_commit(&wc_commit)
-            .set_description(message)
-            .write()?;
-
-        // Create an empty tree for the new working copy commit
-        let empty_tree = tx.repo().store().empty_merged_tree();
-
-        // Create a new empty working copy commit as a child of the committed version
-        let new_wc = tx
-            .repo_mut()
-            .new_commit(
-                vec![committed.id().clone()], // Parent is the commit we just created
-                empty_tree,                   // Empty tree for the new working copy
-            )
-            .write()?;
-
-        // Update the working copy to point to the new empty commit
-        tx.repo_mut().set_wc_commit(
-            self.workspace.workspace_name().to_owned(),
-            new_wc.id().clone(),
-        )?;
-
-        // Rebase any descendants
-        tx.repo_mut().rebase_descendants()?;
-
-        // Commit the transaction
-        tx.commit("commit working copy")?;
+        let arguments = message_arguments(message, author);
+        let author = author.map(parse_author).transpose()?;
+
+        let committed = transact(&mut self.repo, "commit working copy", &arguments, |tx| {
+            let wc_commit_id = tx
+                .repo()
+                .view()
+                .get_wc_commit_id(self.workspace.workspace_name())
+                .ok_or_else(|| anyhow::anyhow!("No working copy commit found"))?
+                .clone();
+
+            let wc_commit = tx.repo().store().get_commit(&wc_commit_id)?;
+
+            let mut builder = tx
+                .repo_mut()
+                .rewrite_commit(&wc_commit)
+                .set_description(message);
+            if let Some(author) = &author {
+                builder = builder.set_author(with_author(wc_commit.author(), author));
+            }
+            let committed = builder.write()?;
+
+            // Create an empty tree for the new working copy commit
+            let empty_tree = tx.repo().store().empty_merged_tree();
+
+            // Create a new empty working copy commit as a child of the committed version
+            let new_wc = tx
+                .repo_mut()
+                .new_commit(
+                    vec![committed.id().clone()], // Parent is the commit we just created
+                    empty_tree,                   // Empty tree for the new working copy
+                )
+                .write()?;
+
+            // Update the working copy to point to the new empty commit
+            tx.repo_mut().set_wc_commit(
+                self.workspace.workspace_name().to_owned(),
+                new_wc.id().clone(),
+            )?;
+
+            // Rebase any descendants
+            tx.repo_mut().rebase_descendants()?;
+            Ok(committed)
+        })?;
 
         Ok(format!(
-            "Created commit {} with description: {}",
-            committed.id().hex(),
-            message
+            "Committed {} as {}",
+            short_change_id(&committed),
+            short_commit_id(&committed)
         ))
     }
 
     /// Fetch changes from the remote git repository using native jj-lib
     /// This is a native implementation using the jj-lib crate instead of CLI interop
-    pub fn git_fetch(&self, remote: Option<&str>) -> Result<String> {
+    /// `bookmark` fetches only that bookmark instead of the default refspecs,
+    /// `depth` limits the fetched history to that many commits, `None` fetches everything
+    pub fn git_fetch(
+        &mut self,
+        remote: Option<&str>,
+        bookmark: Option<&str>,
+        depth: Option<NonZeroU32>,
+    ) -> Result<FetchStats> {
         let remote = remote.map_or_else(
             || self.default_remote.clone(),
             std::borrow::ToOwned::to_owned,
         );
 
-        // Start a transaction
-        let mut tx = self.repo.start_transaction();
-
-        // Get user settings for subprocess options
-        let user_settings = detect_user_settings()?;
-
-        // Create subprocess options from settings
-        let subprocess_options = GitSubprocessOptions::from_settings(&user_settings)?;
+        // Create subprocess options from the settings the workspace was loaded with, git asks
+        // jjkk for credentials
+        let mut subprocess_options =
+            GitSubprocessOptions::from_settings(self.workspace.settings())?;
+        subprocess_options
+            .environment
+            .extend(askpass::environment());
 
         // Create import options with defaults
         // These control how Git refs are imported into jj
@@ -197,59 +272,94 @@ impl Native {
             remote_auto_track_bookmarks: HashMap::new(), // Use default tracking config
         };
 
-        // Get the underlying git repository before creating GitFetch
-        // We need this to expand refspecs
-        let git_repo = get_git_repo(tx.repo().store())?;
-
         let remote_name = RemoteName::new(&remote);
 
-        // Expand the default fetch refspecs for the remote
-        // This determines what refs to fetch (typically refs/heads/*)
-        let (_ignored_refspecs, refspecs) = expand_default_fetch_refspecs(remote_name, &git_repo)?;
-
-        // Create GitFetch handler (after we're done with the immutable borrow above)
-        let mut git_fetch = GitFetch::new(tx.repo_mut(), subprocess_options, &import_options)?;
+        // The network fetch only updates the refs of the git repo. It runs once, in a
+        // transaction that is thrown away, so a retry below doesn't fetch again
+        let mut tx = self.repo.start_transaction();
+        fetch_refs(
+            &mut tx,
+            &subprocess_options,
+            &import_options,
+            remote_name,
+            bookmark,
+            depth,
+        )
+        .map_err(|e| explain("git fetch", e))?;
+        drop(tx);
+
+        let mut arguments = vec!["--remote".to_string(), remote.clone()];
+        if let Some(bookmark) = bookmark {
+            arguments.extend(["--branch".to_string(), bookmark.to_string()]);
+        }
+        if let Some(depth) = depth {
+            arguments.extend(["--depth".to_string(), depth.to_string()]);
+        }
 
-        // Set up callbacks for progress reporting (currently no-op)
-        // You can extend this to provide progress updates
-        let callbacks = RemoteCallbacks::default();
+        // Importing the fetched refs is all that is retried when the operation head moved
+        let stats = transact(&mut self.repo, "fetch from git remote", &arguments, |tx| {
+            Ok(import_some_refs(
+                tx.repo_mut(),
+                &import_options,
+                |kind, symbol| match kind {
+                    GitRefKind::Bookmark => {
+                        symbol.remote == remote_name
+                            && bookmark.is_none_or(|name| symbol.name.as_str() == name)
+                    }
+                    // Like `jj git fetch`, local tags come along and a single bookmark
+                    // fetches no tags
+                    GitRefKind::Tag => {
+                        symbol.remote == REMOTE_NAME_FOR_LOCAL_GIT_REPO
+                            || (bookmark.is_none() && symbol.remote == remote_name)
+                    }
+                },
+            )?)
+        })
+        .map_err(|e| explain("git fetch", e))?;
+
+        Ok(FetchStats {
+            updated_bookmarks: stats
+                .changed_remote_bookmarks
+                .iter()
+                .map(|(symbol, _)| symbol.name.as_str().to_string())
+                .collect(),
+            abandoned_commits: stats.abandoned_commits.len(),
+            remote,
+        })
+    }
 
-        // Perform the actual fetch operation
-        // Parameters:
-        // - remote_name: "origin"
-        // - refspecs: what to fetch
-        // - callbacks: progress reporting
-        // - depth: None for full history (could use Some(n) for shallow fetch)
-        // - fetch_tags_override: None to use git config default
-        git_fetch.fetch(remote_name, refspecs, callbacks, None, None)?;
+    /// Check whether the working copy commit has no changes compared to its parent(s)
+    ///
+    /// Snapshots the working copy first and reloads the repo at the latest operation, so
+    /// edits on disk and changes made through the cli are both seen
+    pub fn is_working_copy_empty(&self) -> Result<bool> {
+        operations::snapshot_working_copy()?;
+        let repo = block_on(self.workspace.repo_loader().load_at_head())?;
 
-        // Import the fetched refs into jj's view
-        let stats = git_fetch.import_refs()?;
+        let wc_commit_id = repo
+            .view()
+            .get_wc_commit_id(self.workspace.workspace_name())
+            .ok_or_else(|| anyhow::anyhow!("No working copy commit found"))?;
+        let wc_commit = repo.store().get_commit(wc_commit_id)?;
 
-        // Commit the transaction
-        tx.commit("fetch from git remote")?;
+        // Merges the parent trees when the working copy has multiple parents
+        let parent_tree = block_on(wc_commit.parent_tree(repo.as_ref()))?;
 
-        // Return a summary of what was fetched
-        Ok(format!(
-            "Fetched from origin\n\
-             {} remote bookmarks imported",
-            stats.changed_remote_bookmarks.len()
-        ))
+        Ok(parent_tree.tree_ids() == wc_commit.tree_ids())
     }
 
-    pub fn track(&self, bookmark_name: &str, remote: Option<&str>) -> Result<String> {
+    pub fn track(&mut self, bookmark_name: &str, remote: Option<&str>) -> Result<String> {
         let remote = remote.map_or_else(
             || self.default_remote.clone(),
             std::borrow::ToOwned::to_owned,
         );
 
-        let mut tx = self.repo.start_transaction();
-
         let remote_name = RemoteName::new(&remote);
         let ref_name = RefName::new(bookmark_name);
         let symbol = ref_name.to_remote_symbol(remote_name);
 
-        let remote_ref = tx.repo().view().get_remote_bookmark(symbol);
+        self.repo = block_on(self.repo.reload_at_head())?;
+        let remote_ref = self.repo.view().get_remote_bookmark(symbol);
 
         if remote_ref.is_tracked() {
             return Ok(format!(
@@ -257,12 +367,17 @@ impl Native {
             ));
         }
 
-        tx.repo_mut().track_remote_bookmark(symbol)?;
-
-        let local_target = tx.repo().view().get_local_bookmark(ref_name);
-        let has_conflict = local_target.has_conflict();
+        let has_conflict = transact(
+            &mut self.repo,
+            &format!("track remote bookmark {bookmark_name}@{remote}"),
+            &[format!("{bookmark_name}@{remote}")],
+            |tx| {
+                tx.repo_mut().track_remote_bookmark(symbol)?;
 
-        tx.commit(format!("track remote bookmark {bookmark_name}@{remote}"))?;
+                let local_target = tx.repo().view().get_local_bookmark(ref_name);
+                Ok(local_target.has_conflict())
+            },
+        )?;
 
         let mut message = String::from("Started tracking 1 remote bookmarks.");
 
@@ -276,16 +391,205 @@ impl Native {
 
         Ok(message)
     }
+
+    /// Resolve a conflicted local bookmark by pointing it at `commit_id`, one of its targets
+    pub fn resolve_bookmark(&mut self, bookmark_name: &str, commit_id: &str) -> Result<String> {
+        let id = CommitId::try_from_hex(commit_id)
+            .ok_or_else(|| anyhow::anyhow!("Invalid commit id: {commit_id}"))?;
+        let ref_name = RefName::new(bookmark_name);
+
+        transact(
+            &mut self.repo,
+            &format!("resolve conflicted bookmark {bookmark_name}"),
+            &[bookmark_name, "-r", commit_id],
+            |tx| {
+                // Someone else may have resolved or moved it since the targets were listed
+                let target = tx.repo().get_local_bookmark(ref_name);
+                if !target.added_ids().any(|added| *added == id) {
+                    anyhow::bail!("Bookmark {bookmark_name} no longer points at {commit_id}");
+                }
+                tx.repo_mut()
+                    .set_local_bookmark_target(ref_name, RefTarget::normal(id.clone()));
+                Ok(())
+            },
+        )?;
+
+        Ok(format!(
+            "Resolved bookmark {bookmark_name} to {}",
+            &commit_id[..commit_id.len().min(12)]
+        ))
+    }
+
+    /// Make the current user the author of `change_id`, authored now, like
+    /// `jj describe --reset-author`
+    pub fn reset_author(&mut self, change_id: &str) -> Result<String> {
+        let prefix = HexPrefix::try_from_reverse_hex(change_id)
+            .ok_or_else(|| anyhow::anyhow!("Invalid change id: {change_id}"))?;
+
+        let author = transact(
+            &mut self.repo,
+            &format!("reset author of {change_id}"),
+            &[change_id],
+            |tx| {
+                let commit_id = match tx.repo().resolve_change_id_prefix(&prefix)? {
+                    PrefixResolution::SingleMatch(targets) if !targets.is_divergent() => targets
+                        .visible_with_offsets()
+                        .map(|(_, id)| id.clone())
+                        .next(),
+                    PrefixResolution::AmbiguousMatch => {
+                        anyhow::bail!("Change id {change_id} is ambiguous")
+                    }
+                    PrefixResolution::SingleMatch(_) => {
+                        anyhow::bail!("Change {change_id} is divergent")
+                    }
+                    PrefixResolution::NoMatch => None,
+                };
+                let commit_id =
+                    commit_id.ok_or_else(|| anyhow::anyhow!("Change {change_id} not found"))?;
+                let commit = tx.repo().store().get_commit(&commit_id)?;
+
+                // The committer of a rewrite is the current user at the current time
+                let builder = tx.repo_mut().rewrite_commit(&commit);
+                let author = builder.committer().clone();
+                builder.set_author(author.clone()).write()?;
+
+                tx.repo_mut().rebase_descendants()?;
+                Ok(author)
+            },
+        )?;
+
+        Ok(format!(
+            "Reset the author of {change_id} to {} <{}>",
+            author.name, author.email
+        ))
+    }
+}
+
+/// Change id in jj's reverse hex, the form jj shows and accepts
+fn short_change_id(commit: &Commit) -> String {
+    commit.change_id().reverse_hex()[..SHORT_ID_LENGTH].to_string()
+}
+
+fn short_commit_id(commit: &Commit) -> String {
+    commit.id().hex()[..SHORT_ID_LENGTH].to_string()
+}
+
+/// `-m <message> [--author <author>]`, as the journal shows a describe or commit
+fn message_arguments(message: &str, author: Option<&str>) -> Vec<String> {
+    let mut arguments = vec!["-m".to_string(), message.to_string()];
+    if let Some(author) = author {
+        arguments.extend(["--author".to_string(), author.to_string()]);
+    }
+    arguments
 }
 
-fn detect_workspace() -> Result<Workspace> {
+/// `signature` with the name and email of `author`, keeping its timestamp
+fn with_author(signature: &Signature, (name, email): &(String, String)) -> Signature {
+    Signature {
+        name:      name.clone(),
+        email:     email.clone(),
+        timestamp: signature.timestamp,
+    }
+}
+
+/// Run `mutate` in a transaction at the operation head and commit it, updating `repo` to
+/// the result. When another process moved the operation head in the meantime, the
+/// transaction is thrown away and retried on the new head instead of writing changes based
+/// on stale state. `mutate` can run more than once, so it must only change the transaction.
+/// The journal records the change as `description` with `arguments`
+fn transact<T>(
+    repo: &mut Arc<ReadonlyRepo>,
+    description: &str,
+    arguments: &[impl AsRef<str>],
+    mut mutate: impl FnMut(&mut Transaction) -> Result<T>,
+) -> Result<T> {
+    // Another jjkk on the workspace committing at the same time would only make this retry
+    let _lock = session::lock_transactions()?;
+    for _ in 0..MAX_TRANSACTION_ATTEMPTS {
+        // jj commands run since the last transaction moved the head, never start from there
+        *repo = block_on(repo.reload_at_head())?;
+
+        let mut tx = repo.start_transaction();
+        let value = mutate(&mut tx)?;
+
+        if head_moved(repo)? {
+            continue;
+        }
+
+        let before = tx.base_repo().op_id().hex();
+        *repo = block_on(tx.commit(description))?;
+        journal::record(
+            description,
+            &join_arguments(arguments),
+            &before,
+            &repo.op_id().hex(),
+        );
+        return Ok(value);
+    }
+
+    anyhow::bail!("The repository kept changing while trying to {description}, please try again")
+}
+
+/// Fetch `bookmark`, or the default refspecs, of `remote_name` into the refs of the git
+/// repo without importing them into jj
+fn fetch_refs(
+    tx: &mut Transaction,
+    subprocess_options: &GitSubprocessOptions,
+    import_options: &GitImportOptions,
+    remote_name: &RemoteName,
+    bookmark: Option<&str>,
+    depth: Option<NonZeroU32>,
+) -> Result<()> {
+    // Get the underlying git repository before creating GitFetch
+    // We need this to expand refspecs
+    let git_repo = get_git_repo(tx.repo().store())?;
+
+    // Expand the fetch refspecs for the remote
+    // This determines what refs to fetch (typically refs/heads/*)
+    let refspecs = match bookmark {
+        // A single bookmark is much faster than everything on large remotes
+        Some(name) => expand_fetch_refspecs(
+            remote_name,
+            GitFetchRefExpression {
+                bookmark: StringExpression::exact(name),
+                tag:      StringExpression::none(),
+            },
+        )?,
+        None => expand_default_fetch_refspecs(remote_name, &git_repo)?.1,
+    };
+
+    // Create GitFetch handler (after we're done with the immutable borrow above)
+    let mut git_fetch = GitFetch::new(tx.repo_mut(), subprocess_options.clone(), import_options)?;
+
+    // Set up callbacks for progress reporting (currently no-op)
+    // You can extend this to provide progress updates
+    let callbacks = RemoteCallbacks::default();
+
+    // Perform the actual fetch operation
+    // Parameters:
+    // - remote_name: "origin"
+    // - refspecs: what to fetch
+    // - callbacks: progress reporting
+    // - depth: None for full history, Some(n) for a shallow fetch
+    // - fetch_tags_override: None to use git config default
+    git_fetch.fetch(remote_name, refspecs, callbacks, depth, None)?;
+    Ok(())
+}
+
+/// Whether the operation head is no longer the operation `repo` was loaded at
+fn head_moved(repo: &ReadonlyRepo) -> Result<bool> {
+    let op_heads = block_on(repo.op_heads_store().get_op_heads())?;
+    Ok(op_heads.as_slice() != std::slice::from_ref(repo.op_id()))
+}
+
+fn detect_workspace(workspace_path: &Path, user_config_paths: &[PathBuf]) -> Result<Workspace> {
     // Create user settings from default config
-    let user_settings = detect_user_settings()?;
+    let user_settings = detect_user_settings(workspace_path, user_config_paths)?;
 
     // Load the workspace
     let workspace = Workspace::load(
         &user_settings,
-        std::path::Path::new("."),
+        workspace_path,
         &StoreFactories::default(),
         &default_working_copy_factories(),
     )?;
@@ -293,20 +597,19 @@ fn detect_workspace() -> Result<Workspace> {
     Ok(workspace)
 }
 
-fn detect_config() -> Result<StackedConfig> {
+fn detect_config(workspace_path: &Path, user_config_paths: &[PathBuf]) -> Result<StackedConfig> {
     // Create stacked config with defaults
     let mut config = StackedConfig::with_defaults();
 
-    // Load user config from standard location (~/.config/jj/config.toml)
-    if let Some(config_dir) = dirs::config_dir() {
-        let user_config_path = config_dir.join("jj").join("config.toml");
-        if user_config_path.exists() {
+    // Load the user config, found by `user_config_paths` outside of tests
+    for user_config_path in user_config_paths {
+        if user_config_path.is_file() {
             config.load_file(ConfigSource::User, user_config_path)?;
         }
     }
 
     // Load repo config from .jj/repo/config.toml if it exists
-    let repo_config_path = std::path::Path::new(".jj/repo/config.toml");
+    let repo_config_path = workspace_path.join(".jj").join("repo").join("config.toml");
     if repo_config_path.exists() {
         config.load_file(ConfigSource::Repo, repo_config_path)?;
     }
@@ -314,8 +617,29 @@ fn detect_config() -> Result<StackedConfig> {
     Ok(config)
 }
 
-fn detect_user_settings() -> Result<UserSettings> {
-    let config = detect_config()?;
+/// User config files the same way the jj cli finds them: $JJ_CONFIG wins, otherwise
+/// ~/.jjconfig.toml and the platform config dir (~/.config/jj/config.toml on Linux,
+/// %APPDATA%\jj\config.toml on Windows)
+fn user_config_paths() -> Vec<PathBuf> {
+    if let Some(jj_config) = std::env::var_os("JJ_CONFIG") {
+        return std::env::split_paths(&jj_config).collect();
+    }
+
+    let mut paths = Vec::new();
+    if let Some(home_dir) = dirs::home_dir() {
+        paths.push(home_dir.join(".jjconfig.toml"));
+    }
+    if let Some(config_dir) = dirs::config_dir() {
+        paths.push(config_dir.join("jj").join("config.toml"));
+    }
+    paths
+}
+
+fn detect_user_settings(
+    workspace_path: &Path,
+    user_config_paths: &[PathBuf],
+) -> Result<UserSettings> {
+    let config = detect_config(workspace_path, user_config_paths)?;
     let user_settings = UserSettings::from_config(config)?;
     Ok(user_settings)
 }
@@ -323,38 +647,72 @@ fn detect_user_settings() -> Result<UserSettings> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::jj::test_repo::TestRepo;
+
+    /// Handler for `repo` with its config instead of the user's
+    fn open(repo: &TestRepo) -> Native {
+        Native::open_with_user_config(&repo.path(), &repo.config_path())
+    }
 
     #[test]
-    #[ignore] // Only run manually in a jj repo
     fn test_describe_jj() {
-        let native = Native::new();
+        let Some(repo) = TestRepo::new() else {
+            return;
+        };
+        let mut native = open(&repo);
 
-        let result = native.describe("Test description from jj-lib");
+        let result = native.describe("Test description from jj-lib", None);
         println!("{:?}", result);
         assert!(result.is_ok());
+        assert_eq!(
+            repo.jj(&["log", "--no-graph", "-r", "@", "-T", "description"]),
+            "Test description from jj-lib\n"
+        );
     }
 
     #[test]
-    #[ignore] // Only run manually in a jj repo
     fn test_commit_jj() {
-        let native = Native::new();
+        let Some(repo) = TestRepo::new() else {
+            return;
+        };
+        repo.write("README.md", "hello\n");
+        let mut native = open(&repo);
 
         // First set up a working copy with some description
-        let describe_result = native.describe("Setting up test commit");
+        let describe_result = native.describe("Setting up test commit", None);
         assert!(describe_result.is_ok());
 
         // Now commit it
-        let commit_result = native.commit("Test commit from jj-lib");
+        let commit_result = native.commit("Test commit from jj-lib", None);
         println!("{:?}", commit_result);
         assert!(commit_result.is_ok());
     }
 
     #[test]
-    #[ignore] // Only run manually in a jj repo with a git remote configured
+    fn test_is_working_copy_empty_sees_edits_on_disk() {
+        let Some(repo) = TestRepo::new() else {
+            return;
+        };
+        let native = open(&repo);
+        assert!(repo.run(|| native.is_working_copy_empty()).unwrap());
+
+        // Not snapshotted by any jj command yet
+        repo.write("README.md", "hello\n");
+        assert!(!repo.run(|| native.is_working_copy_empty()).unwrap());
+    }
+
+    #[test]
     fn test_git_fetch_jj() {
-        let native = Native::new();
+        let Some(repo) = TestRepo::new() else {
+            return;
+        };
+        repo.write("README.md", "hello\n")
+            .commit("First")
+            .bookmark("main", "@-")
+            .remote("origin");
+        let mut native = open(&repo);
 
-        let result = native.git_fetch(None);
+        let result = native.git_fetch(None, None, None);
         println!("{:?}", result);
         assert!(result.is_ok());
     }
@@ -0,0 +1,132 @@
+use std::path::Path;
+
+use anyhow::Result;
+
+use super::sidecar::{
+    Sidecar,
+    SidecarEntry,
+};
+
+/// File name of the notes inside the `.jj` directory of the workspace
+const NOTES_NAME: &str = "jjkk-notes";
+
+/// A review note on a line or a whole file of a change, only kept on this machine
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Note {
+    pub change_id: String,
+    pub path:      String,
+    /// Line in the new version of the file, `None` for a note on the whole file
+    pub line:      Option<usize>,
+    pub text:      String,
+}
+
+impl Note {
+    /// `path:line`, or only the path for a note on the whole file
+    pub fn location(&self) -> String {
+        self.line
+            .map_or_else(|| self.path.clone(), |line| format!("{}:{line}", self.path))
+    }
+}
+
+impl SidecarEntry for Note {
+    fn from_fields(fields: &[String]) -> Option<Self> {
+        let [change_id, path, line, text] = fields else {
+            return None;
+        };
+        Some(Self {
+            change_id: change_id.clone(),
+            path:      path.clone(),
+            line:      match line.as_str() {
+                "" => None,
+                line => Some(line.parse().ok()?),
+            },
+            text:      text.clone(),
+        })
+    }
+
+    /// The line is left empty for notes on files
+    fn to_fields(&self) -> Vec<String> {
+        vec![
+            self.change_id.clone(),
+            self.path.clone(),
+            self.line.map(|line| line.to_string()).unwrap_or_default(),
+            self.text.clone(),
+        ]
+    }
+}
+
+/// Notes on the changes of a workspace, stored next to the repo in `.jj/jjkk-notes`
+#[derive(Debug, Default)]
+pub struct Notes {
+    notes: Sidecar<Note>,
+}
+
+impl Notes {
+    /// Notes of the workspace at `workspace_root`, none when nothing was noted yet
+    pub fn load(workspace_root: &Path) -> Result<Self> {
+        Ok(Self {
+            notes: Sidecar::load(workspace_root, NOTES_NAME)?,
+        })
+    }
+
+    /// Notes on `change_id`, by file and line with the notes on whole files first
+    pub fn of_change(&self, change_id: &str) -> Vec<&Note> {
+        let mut notes: Vec<&Note> = self
+            .notes
+            .entries()
+            .iter()
+            .filter(|note| note.change_id == change_id)
+            .collect();
+        notes.sort_by(|a, b| (&a.path, a.line).cmp(&(&b.path, b.line)));
+        notes
+    }
+
+    pub fn add(&mut self, note: Note) -> Result<()> {
+        self.notes.update(|notes| notes.push(note))
+    }
+
+    pub fn remove(&mut self, note: &Note) -> Result<()> {
+        self.notes.update(|notes| {
+            if let Some(index) = notes.iter().position(|n| n == note) {
+                notes.remove(index);
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_notes_round_trip() {
+        let notes = vec![
+            Note {
+                change_id: "kxqpmzwr".to_string(),
+                path:      "src/main.rs".to_string(),
+                line:      Some(42),
+                text:      "check\tthe error case".to_string(),
+            },
+            Note {
+                change_id: "kxqpmzwr".to_string(),
+                path:      "README.md".to_string(),
+                line:      None,
+                text:      "mention the new key".to_string(),
+            },
+        ];
+        for note in &notes {
+            assert_eq!(Note::from_fields(&note.to_fields()).as_ref(), Some(note));
+        }
+
+        let notes = Notes {
+            notes: Sidecar::in_memory(notes),
+        };
+        let locations: Vec<String> = notes
+            .of_change("kxqpmzwr")
+            .iter()
+            .map(|note| note.location())
+            .collect();
+        assert_eq!(locations, ["README.md", "src/main.rs:42"]);
+        assert!(notes.of_change("zzzzzzzz").is_empty());
+    }
+}
@@ -0,0 +1,252 @@
+use anyhow::Result;
+
+use super::{
+    command::{
+        CommandExt,
+        jj,
+    },
+    error::command_failed,
+};
+
+/// What an operation changed compared to its parent, parsed from `jj op diff`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OperationDiff {
+    /// Summary line of the parent operation
+    pub from:      String,
+    /// Summary line of the operation itself
+    pub to:        String,
+    pub commits:   Vec<CommitChange>,
+    pub bookmarks: Vec<BookmarkChange>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommitChangeKind {
+    Added,
+    Rewritten,
+    Abandoned,
+}
+
+/// A commit that became visible or hidden, `summary` describes the visible one if any
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommitChange {
+    pub kind:      CommitChangeKind,
+    pub change_id: String,
+    pub summary:   String,
+}
+
+/// A local or remote (`name@remote`) bookmark, targets are `None` when it is absent
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BookmarkChange {
+    pub name: String,
+    pub from: Option<String>,
+    pub to:   Option<String>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Section {
+    Commits,
+    Bookmarks,
+    Other,
+}
+
+/// Diff of the operation `steps` operations before the current one, `None` when there is no
+/// operation that old. Executes `jj op diff --no-graph --op @-..` command
+pub fn get_operation_diff(steps: usize) -> Result<Option<OperationDiff>> {
+    let operation = format!("@{}", "-".repeat(steps));
+    let output = jj()
+        .args([
+            "op",
+            "diff",
+            "--no-graph",
+            "--ignore-working-copy",
+            "--op",
+            &operation,
+        ])
+        .run()?;
+
+    if !output.status.success() {
+        // Going past the root operation, any other failure is worth showing
+        if String::from_utf8_lossy(&output.stderr).contains("resolved to no operations") {
+            return Ok(None);
+        }
+        return Err(command_failed("jj op diff", &output.stderr));
+    }
+
+    Ok(Some(OperationDiff::parse(&String::from_utf8_lossy(
+        &output.stdout,
+    ))))
+}
+
+impl OperationDiff {
+    pub fn parse(output: &str) -> Self {
+        let mut diff = Self::default();
+        let mut section = Section::Other;
+        let mut added: Vec<(String, String)> = Vec::new();
+        let mut removed: Vec<(String, String)> = Vec::new();
+
+        for line in output.lines() {
+            if let Some(from) = line.trim_start().strip_prefix("From operation:") {
+                diff.from = from.trim().to_string();
+                continue;
+            }
+            if let Some(to) = line.trim_start().strip_prefix("To operation:") {
+                diff.to = to.trim().to_string();
+                continue;
+            }
+            if line.starts_with("Changed ") && line.ends_with(':') {
+                section = if line == "Changed commits:" {
+                    Section::Commits
+                } else if line.ends_with("bookmarks:") {
+                    Section::Bookmarks
+                } else {
+                    Section::Other
+                };
+                continue;
+            }
+
+            let entry = line
+                .strip_prefix("+ ")
+                .map(|text| (true, text))
+                .or_else(|| line.strip_prefix("- ").map(|text| (false, text)));
+            match (section, entry) {
+                (Section::Commits, Some((is_added, summary))) => {
+                    // Hidden predecessors are printed as e.g. `qpvuntsm/1`
+                    let change_id = summary
+                        .split([' ', '/'])
+                        .next()
+                        .unwrap_or_default()
+                        .to_string();
+                    let entry = (change_id, summary.to_string());
+                    if is_added {
+                        added.push(entry);
+                    } else {
+                        removed.push(entry);
+                    }
+                }
+                (Section::Bookmarks, Some((is_added, target))) => {
+                    let Some(bookmark) = diff.bookmarks.last_mut() else {
+                        continue;
+                    };
+                    let target = target
+                        .trim_start_matches("tracked ")
+                        .trim_start_matches("untracked ");
+                    let target = (target != "(absent)").then(|| target.to_string());
+                    if is_added {
+                        bookmark.to = target;
+                    } else {
+                        bookmark.from = target;
+                    }
+                }
+                (Section::Bookmarks, None) if !line.is_empty() && !line.starts_with(' ') => {
+                    diff.bookmarks.push(BookmarkChange {
+                        name: line.trim_end_matches(':').to_string(),
+                        from: None,
+                        to:   None,
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        for (change_id, summary) in &added {
+            let kind = if removed.iter().any(|(id, _)| id == change_id) {
+                CommitChangeKind::Rewritten
+            } else {
+                CommitChangeKind::Added
+            };
+            diff.commits.push(CommitChange {
+                kind,
+                change_id: change_id.clone(),
+                summary: summary.clone(),
+            });
+        }
+        for (change_id, summary) in removed {
+            if !added.iter().any(|(id, _)| *id == change_id) {
+                diff.commits.push(CommitChange {
+                    kind: CommitChangeKind::Abandoned,
+                    change_id,
+                    summary,
+                });
+            }
+        }
+        diff
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.commits.is_empty() && self.bookmarks.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jj::test_repo::TestRepo;
+
+    #[test]
+    fn test_operation_diff_past_the_root() {
+        let Some(repo) = TestRepo::new() else {
+            return;
+        };
+        repo.write("a.txt", "a\n").commit("First");
+
+        repo.run(|| {
+            assert!(get_operation_diff(0).unwrap().is_some());
+            assert!(get_operation_diff(100).unwrap().is_none());
+        });
+    }
+
+    #[test]
+    fn test_parse_operation_diff() {
+        let output = "\
+From operation: 1b2c3d4e5f6a (2025-01-01 10:00:00) snapshot working copy
+  To operation: 2c3d4e5f6a7b (2025-01-01 10:01:00) rebase commit abc
+
+Changed commits:
++ qpvuntsm 9f3d2a11 main | Fix the parser
+- qpvuntsm/1 5678ef12 (hidden) Fix the parser
++ rlvkpnrz 1234abcd (empty) (no description set)
+- zsuskuln 8765dcba (hidden) Old experiment
+
+Changed local bookmarks:
+main:
++ qpvuntsm 9f3d2a11 main | Fix the parser
+- qpvuntsm/1 5678ef12 (hidden) Fix the parser
+feature:
++ (absent)
+- zsuskuln 8765dcba (hidden) Old experiment
+
+Changed remote bookmarks:
+main@origin:
++ tracked qpvuntsm 9f3d2a11 main | Fix the parser
+- untracked (absent)
+";
+        let diff = OperationDiff::parse(output);
+        assert_eq!(
+            diff.to,
+            "2c3d4e5f6a7b (2025-01-01 10:01:00) rebase commit abc"
+        );
+
+        let kinds: Vec<_> = diff
+            .commits
+            .iter()
+            .map(|commit| (commit.kind, commit.change_id.as_str()))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (CommitChangeKind::Rewritten, "qpvuntsm"),
+                (CommitChangeKind::Added, "rlvkpnrz"),
+                (CommitChangeKind::Abandoned, "zsuskuln"),
+            ]
+        );
+
+        assert_eq!(diff.bookmarks.len(), 3);
+        assert_eq!(diff.bookmarks[1].name, "feature");
+        assert_eq!(diff.bookmarks[1].to, None);
+        assert_eq!(
+            diff.bookmarks[2].to.as_deref(),
+            Some("qpvuntsm 9f3d2a11 main | Fix the parser")
+        );
+        assert_eq!(diff.bookmarks[2].from, None);
+    }
+}
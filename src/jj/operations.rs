@@ -1,134 +1,765 @@
-use std::process::Command;
+use std::path::Path;
 
 use anyhow::{
     Context,
     Result,
 };
 
+use super::{
+    askpass,
+    command::{
+        CommandExt,
+        file_pattern,
+        jj,
+    },
+    error::command_failed,
+    results::{
+        NewChange,
+        PushResult,
+        RebaseResult,
+    },
+    version,
+};
+
 /// basically a copy of `track_current_bookmark` but takes a name argument
 /// to track a specific bookmark handy for when we create a new bookmark
 /// and want to track it right away
 pub fn auto_track_bookmark(name: &str) -> Result<String> {
-    let output = Command::new("jj")
-        .args(["bookmark", "track", name, "--remote=origin"])
-        .output()
-        .context("Failed to run jj bookmark track")?;
+    let output = jj()
+        .args(["bookmark", "track"])
+        .args(version::track_args(name, "origin"))
+        .run()?;
 
     if !output.status.success() {
-        anyhow::bail!(
-            "jj track failed: {}",
-            String::from_utf8_lossy(&output.stderr)
-        );
+        return Err(command_failed("jj track", &output.stderr));
     }
 
     Ok(String::from_utf8_lossy(&output.stdout).to_string())
 }
 
-/// Restore the working copy of a jj repository
-/// Executes `jj restore` command
-pub fn restore_working_copy() -> Result<String> {
-    let output = Command::new("jj")
-        .args(["restore"])
-        .output()
-        .context("Failed to run jj restore")?;
+/// Restore the working copy of a jj repository, only the files matching `fileset` when given
+/// Executes `jj restore [fileset]` command
+pub fn restore_working_copy(fileset: Option<&str>) -> Result<String> {
+    let output = jj().args(["restore"]).args(fileset).run()?;
 
     if !output.status.success() {
-        anyhow::bail!(
-            "jj restore failed: {}",
-            String::from_utf8_lossy(&output.stderr)
-        );
+        return Err(command_failed("jj restore", &output.stderr));
     }
 
     Ok(String::from_utf8_lossy(&output.stdout).to_string())
 }
 
 /// Get the diff of a file from the working copy
-/// Executes `jj diff --no-pager <file_path>` command
+/// Executes `jj diff --git file:"<file_path>"` command
+///
+/// `--git` keeps a `ui.diff-formatter` from the user's config from changing the format the
+/// diff view reads
 pub fn get_file_diff(file_path: &str) -> Result<String> {
-    let output = Command::new("jj")
-        .args(["diff", "--no-pager", file_path])
-        .output()?;
+    let output = jj()
+        .args(["diff", "--git", &file_pattern(file_path)])
+        .run()?;
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Files of the working copy commit and the directories containing them, sorted
+/// Executes `jj file list` command
+pub fn get_tracked_paths() -> Result<Vec<String>> {
+    let output = jj().args(["file", "list"]).run()?;
+
+    if !output.status.success() {
+        return Err(command_failed("jj file list", &output.stderr));
+    }
+
+    Ok(with_directories(
+        String::from_utf8_lossy(&output.stdout).lines(),
+    ))
+}
+
+/// `files` and every directory above them, directories end with `/`
+fn with_directories<'a>(files: impl Iterator<Item = &'a str>) -> Vec<String> {
+    let mut paths = std::collections::BTreeSet::new();
+    for file in files.filter(|file| !file.is_empty()) {
+        for (index, _) in file.match_indices('/') {
+            paths.insert(file[..=index].to_string());
+        }
+        paths.insert(file.to_string());
+    }
+    paths.into_iter().collect()
+}
+
+/// Snapshot a new file that is over `snapshot.max-new-file-size`, raising the limit for this
+/// command only
+/// Executes `jj --config snapshot.max-new-file-size=<size> file track file:"<path>"` command
+pub fn track_large_file(path: &str, size: u64) -> Result<String> {
+    let output = jj()
+        .args([
+            "--config",
+            &format!("snapshot.max-new-file-size={size}"),
+            "file",
+            "track",
+            &file_pattern(path),
+        ])
+        .run()?;
+
+    if !output.status.success() {
+        return Err(command_failed("jj file track", &output.stderr));
+    }
 
     Ok(String::from_utf8_lossy(&output.stdout).to_string())
 }
 
+/// Keep a file out of snapshots by adding it to the `.gitignore` of the current directory,
+/// which is where the paths of `jj status` are relative to
+pub fn ignore_file(path: &str) -> Result<()> {
+    let gitignore = std::path::Path::new(".gitignore");
+    let existing = std::fs::read_to_string(gitignore).unwrap_or_default();
+    let separator = if existing.is_empty() || existing.ends_with('\n') {
+        ""
+    } else {
+        "\n"
+    };
+
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(gitignore)
+        .context("Failed to open .gitignore")?;
+    std::io::Write::write_all(
+        &mut file,
+        format!("{separator}{}\n", gitignore_pattern(path)).as_bytes(),
+    )
+    .context("Failed to write .gitignore")
+}
+
+/// Pattern matching exactly `path`, anchored so files with the same name elsewhere stay tracked
+fn gitignore_pattern(path: &str) -> String {
+    let mut pattern = String::from("/");
+    for c in path.chars() {
+        if matches!(c, '\\' | '*' | '?' | '[') {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+    // Trailing spaces are dropped unless escaped
+    if pattern.ends_with(' ') {
+        pattern.insert(pattern.len() - 1, '\\');
+    }
+    pattern
+}
+
+/// `Name <email>` of the jj user, as used for `Signed-off-by:` trailers
+/// Executes `jj config get user.name` and `jj config get user.email` commands
+pub fn get_user_signature() -> Result<String> {
+    let name = config_get("user.name")?;
+    let email = config_get("user.email")?;
+    Ok(format!("{name} <{email}>"))
+}
+
+/// Split an author given as `Name <email>` into name and email
+pub fn parse_author(author: &str) -> Result<(String, String)> {
+    let parsed = author
+        .trim()
+        .strip_suffix('>')
+        .and_then(|rest| rest.rsplit_once('<'))
+        .map(|(name, email)| (name.trim(), email.trim()))
+        .filter(|(name, email)| !name.is_empty() && email.contains('@'));
+    let Some((name, email)) = parsed else {
+        anyhow::bail!("Authors are written as Name <email>, got {author}");
+    };
+    Ok((name.to_string(), email.to_string()))
+}
+
+/// The revset `jj log` shows without `-r`, configured as `revsets.log`
+pub fn get_log_revset() -> Result<String> {
+    config_get("revsets.log")
+}
+
+fn config_get(key: &str) -> Result<String> {
+    let output = jj().args(["config", "get", key]).run()?;
+
+    if !output.status.success() {
+        return Err(command_failed("jj config get", &output.stderr));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
 /// Create a new empty commit on the working copy.
 /// Executes `jj new` command
-pub fn new_commit() -> Result<String> {
-    let output = Command::new("jj")
-        .args(["new"])
-        .output()
-        .context("Failed to run jj new")?;
+pub fn new_commit() -> Result<NewChange> {
+    let output = jj().args(["new"]).run()?;
 
     if !output.status.success() {
-        anyhow::bail!("jj new failed: {}", String::from_utf8_lossy(&output.stderr));
+        return Err(command_failed("jj new", &output.stderr));
     }
 
-    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    // jj reports the new working copy on stderr
+    Ok(NewChange::parse(&String::from_utf8_lossy(&output.stderr)))
+}
+
+/// Commit only the changes to `paths` with `message`, the rest stays in the new working copy
+///
+/// The hunks of the patch at `left_out` are left out too, git as diff editor of `jj commit -i`
+/// takes them out of jj's copy of the files.
+/// Executes `jj commit [-i] -m <message> [--author <author>] <paths>` command
+pub fn commit_paths(
+    message: &str,
+    author: Option<&str>,
+    paths: &[String],
+    left_out: Option<&Path>,
+) -> Result<String> {
+    if message.trim().is_empty() {
+        anyhow::bail!("Commit message cannot be empty");
+    }
+    let mut command = jj();
+    command.args(["commit", "-m", message]);
+    if let Some(left_out) = left_out {
+        let edit_args = toml::Value::Array(
+            ["-C", "$right", "apply", "-R", &left_out.to_string_lossy()]
+                .into_iter()
+                .map(|arg| toml::Value::String(arg.to_string()))
+                .collect(),
+        );
+        command.args([
+            "-i",
+            "--tool",
+            "jjkk-pick",
+            "--config",
+            "merge-tools.jjkk-pick.program=\"git\"",
+            "--config",
+            &format!("merge-tools.jjkk-pick.edit-args={edit_args}"),
+            "--config",
+            "ui.diff-instructions=false",
+        ]);
+    }
+    let output = command
+        .args(
+            author
+                .map(|author| ["--author", author])
+                .into_iter()
+                .flatten(),
+        )
+        .arg("--")
+        .args(paths.iter().map(|path| file_pattern(path)))
+        .run()?;
+
+    if !output.status.success() {
+        return Err(command_failed("jj commit", &output.stderr));
+    }
+
+    Ok(format!(
+        "Committed {} file(s), the rest stays in the working copy",
+        paths.len()
+    ))
+}
+
+/// Make `change_id` the working copy again, to go on working on it.
+/// Executes `jj edit <change_id>` command
+pub fn edit_change(change_id: &str) -> Result<String> {
+    let output = jj().args(["edit", change_id]).run()?;
+
+    if !output.status.success() {
+        return Err(command_failed("jj edit", &output.stderr));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stderr).to_string())
 }
 
 /// Push changes to the remote git repository
 /// If a bookmark is provided, push that bookmark
 /// Otherwise, push the current change
+/// Pushes to `remote` when set, jj's configured push remote otherwise
 /// Executes `jj git push -b <bookmark>` or `jj git push --change @` command
-pub fn git_push(bookmark: Option<&str>) -> Result<String> {
+pub fn git_push(bookmark: Option<&str>, remote: Option<&str>) -> Result<PushResult> {
+    run_git_push(&push_target(bookmark), remote, false)
+}
+
+/// Show what a push would do without pushing anything
+/// Executes `jj git push --dry-run -b <bookmark>` or `jj git push --dry-run --change @`
+pub fn git_push_dry_run(bookmark: Option<&str>, remote: Option<&str>) -> Result<PushResult> {
+    run_git_push(&push_target(bookmark), remote, true)
+}
+
+/// Push several bookmarks at once
+/// Executes `jj git push -b <name> -b <name>..` command
+pub fn git_push_bookmarks(names: &[String], remote: Option<&str>) -> Result<PushResult> {
+    let target: Vec<&str> = names
+        .iter()
+        .flat_map(|name| ["-b", name.as_str()])
+        .collect();
+    run_git_push(&target, remote, false)
+}
+
+/// Push the deletion of locally deleted bookmarks to the remote
+/// Executes `jj git push --deleted` command
+pub fn git_push_deleted(remote: Option<&str>, dry_run: bool) -> Result<PushResult> {
+    run_git_push(&["--deleted"], remote, dry_run)
+}
+
+fn push_target(bookmark: Option<&str>) -> Vec<&str> {
+    // If no bookmark, push the current change
+    bookmark.map_or_else(|| vec!["--change", "@"], |name| vec!["-b", name])
+}
+
+fn run_git_push(target: &[&str], remote: Option<&str>, dry_run: bool) -> Result<PushResult> {
     let mut args = vec!["git", "push"];
 
-    if let Some(bookmark_name) = bookmark {
-        args.push("-b");
-        args.push(bookmark_name);
-    } else {
-        // If no bookmark, push the current change
-        args.push("--change");
-        args.push("@");
+    if dry_run {
+        args.push("--dry-run");
+    }
+    if let Some(remote) = remote {
+        args.extend_from_slice(&["--remote", remote]);
     }
+    args.extend_from_slice(target);
 
-    let output = Command::new("jj")
-        .args(&args)
-        .output()
-        .context("Failed to run jj git push")?;
+    let output = jj().args(&args).envs(askpass::environment()).run()?;
 
     if !output.status.success() {
-        anyhow::bail!(
-            "jj git push failed: {}",
-            String::from_utf8_lossy(&output.stderr)
-        );
+        return Err(command_failed("jj git push", &output.stderr));
+    }
+
+    // jj reports what it pushes (or would push) on stderr
+    Ok(PushResult::parse(&format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    )))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteInfo {
+    pub name: String,
+    pub url:  String,
+}
+
+/// Get the git remotes of the repository
+/// Executes `jj git remote list` command
+pub fn get_remotes() -> Result<Vec<RemoteInfo>> {
+    let output = jj().args(["git", "remote", "list"]).run()?;
+
+    if !output.status.success() {
+        return Err(command_failed("jj git remote list", &output.stderr));
+    }
+
+    Ok(parse_remotes(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Parse `jj git remote list` lines like `origin https://github.com/user/repo.git`
+fn parse_remotes(output: &str) -> Vec<RemoteInfo> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (name, url) = line.trim().split_once(char::is_whitespace)?;
+            Some(RemoteInfo {
+                name: name.to_string(),
+                url:  url.trim().to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Add a git remote
+/// Executes `jj git remote add <name> <url>` command
+pub fn add_remote(name: &str, url: &str) -> Result<String> {
+    run_git_remote(&["add", name, url])
+}
+
+/// Rename a git remote, its remote bookmarks are renamed along with it
+/// Executes `jj git remote rename <old> <new>` command
+pub fn rename_remote(old: &str, new: &str) -> Result<String> {
+    run_git_remote(&["rename", old, new])
+}
+
+/// Set jj up in the git repository at `root`, git keeps working next to it
+/// Executes `jj git init --colocate <root>` command
+pub fn git_init_colocate(root: &Path) -> Result<String> {
+    let output = jj().args(["git", "init", "--colocate"]).arg(root).run()?;
+
+    if !output.status.success() {
+        return Err(command_failed("jj git init", &output.stderr));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stderr).to_string())
+}
+
+/// Resolve the conflicts of `path` in a merge tool, which takes over the terminal
+/// Executes `jj resolve [--tool <tool>] <path>` command
+pub fn resolve_conflict(path: &str, tool: Option<&str>) -> Result<String> {
+    let mut command = jj();
+    command.arg("resolve");
+    if let Some(tool) = tool {
+        command.args(["--tool", tool]);
+    }
+    let status = command.arg(file_pattern(path)).run_interactive()?.status;
+
+    if !status.success() {
+        anyhow::bail!("jj resolve {path} failed with {status}");
+    }
+    Ok(format!("Resolved {path}"))
+}
+
+/// Side of a two-sided conflict that wins for a whole file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictSide {
+    /// The first side, e.g. the destination of a rebase
+    Ours,
+    /// The second side, e.g. the rebased commit
+    Theirs,
+}
+
+impl ConflictSide {
+    pub const fn label(self) -> &'static str {
+        match self {
+            Self::Ours => "ours",
+            Self::Theirs => "theirs",
+        }
+    }
+
+    /// jj's built-in merge tool that picks this side
+    const fn tool(self) -> &'static str {
+        match self {
+            Self::Ours => ":ours",
+            Self::Theirs => ":theirs",
+        }
+    }
+}
+
+/// Resolve every conflict in `path` by taking one side, writing it to the working copy
+/// Executes `jj resolve --tool :ours|:theirs <path>` command
+pub fn take_conflict_side(path: &str, side: ConflictSide) -> Result<String> {
+    let output = jj()
+        .args(["resolve", "--tool", side.tool()])
+        .arg(file_pattern(path))
+        .run()?;
+
+    if !output.status.success() {
+        return Err(command_failed("jj resolve", &output.stderr));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stderr).to_string())
+}
+
+/// Point a git remote at another url
+/// Executes `jj git remote set-url <name> <url>` command
+pub fn set_remote_url(name: &str, url: &str) -> Result<String> {
+    run_git_remote(&["set-url", name, url])
+}
+
+/// Remove a git remote and forget its remote bookmarks
+/// Executes `jj git remote remove <name>` command
+pub fn remove_remote(name: &str) -> Result<String> {
+    run_git_remote(&["remove", name])
+}
+
+fn run_git_remote(args: &[&str]) -> Result<String> {
+    let output = jj().args(["git", "remote"]).args(args).run()?;
+
+    if !output.status.success() {
+        return Err(command_failed(
+            &format!("jj git remote {}", args[0]),
+            &output.stderr,
+        ));
     }
 
     Ok(String::from_utf8_lossy(&output.stdout).to_string())
 }
 
+/// Snapshot the working copy so the repo head includes changes made on disk since the last
+/// jj command
+///
+/// Executes `jj log -r @ --no-graph -T ""` command, any command that does not ignore the
+/// working copy snapshots it before running
+pub fn snapshot_working_copy() -> Result<()> {
+    let output = jj()
+        .args(["log", "-r", "@", "--no-graph", "-T", "\"\""])
+        .run()?;
+
+    if !output.status.success() {
+        return Err(command_failed("jj log", &output.stderr));
+    }
+
+    Ok(())
+}
+
+/// Id of the latest operation in the operation log
+/// Executes `jj op log --no-graph --limit 1 --ignore-working-copy -T id.short()` command.
+/// Ignoring the working copy keeps this from snapshotting and creating a new operation itself
+pub fn get_operation_id() -> Result<String> {
+    let output = jj()
+        .args([
+            "op",
+            "log",
+            "--no-graph",
+            "--limit",
+            "1",
+            "--ignore-working-copy",
+            "-T",
+            "id.short()",
+        ])
+        .run()?;
+
+    if !output.status.success() {
+        return Err(command_failed("jj op log", &output.stderr));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Put the whole repo back to how it was at `operation`, later operations stay in the op log
+/// Executes `jj op restore <operation>` command
+pub fn restore_operation(operation: &str) -> Result<String> {
+    let output = jj().args(["op", "restore", operation]).run()?;
+
+    if !output.status.success() {
+        return Err(command_failed("jj op restore", &output.stderr));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stderr).trim().to_string())
+}
+
+/// Revset of the commits `jj rebase -b @ --onto <destination>` rewrites
+pub fn rebase_revset(destination: &str) -> String {
+    format!("(({destination})..@)::")
+}
+
 /// Rebase the current change onto the specified destination
-/// Executes `jj rebase -d <destination>` command
-pub fn rebase(destination: &str) -> Result<String> {
-    let output = Command::new("jj")
-        .args(["rebase", "-d", destination])
-        .output()
-        .context("Failed to run jj rebase")?;
+/// Executes `jj rebase --onto <destination>` command
+/// (`--destination` on older jj versions)
+pub fn rebase(destination: &str) -> Result<RebaseResult> {
+    let output = jj()
+        .args(["rebase", version::rebase_destination_flag(), destination])
+        .run()?;
 
     if !output.status.success() {
-        anyhow::bail!(
-            "jj rebase failed: {}",
-            String::from_utf8_lossy(&output.stderr)
-        );
+        return Err(command_failed("jj rebase", &output.stderr));
+    }
+
+    // The rebased commit counts are reported on stderr
+    Ok(RebaseResult::parse(&String::from_utf8_lossy(
+        &output.stderr,
+    )))
+}
+
+/// Rebase only the given commits onto `destination`, their descendants stay where they are
+/// Executes `jj rebase -r <revision>.. --onto <destination>` command
+pub fn rebase_revisions(revisions: &[String], destination: &str) -> Result<RebaseResult> {
+    let output = jj()
+        .arg("rebase")
+        .args(
+            revisions
+                .iter()
+                .flat_map(|revision| ["-r", revision.as_str()]),
+        )
+        .args([version::rebase_destination_flag(), destination])
+        .run()?;
+
+    if !output.status.success() {
+        return Err(command_failed("jj rebase", &output.stderr));
+    }
+
+    Ok(RebaseResult::parse(&String::from_utf8_lossy(
+        &output.stderr,
+    )))
+}
+
+/// Abandon the given commits, their descendants are rebased onto their parents
+/// Executes `jj abandon <revision>..` command
+pub fn abandon(revisions: &[String]) -> Result<String> {
+    let output = jj().arg("abandon").args(revisions).run()?;
+
+    if !output.status.success() {
+        return Err(command_failed("jj abandon", &output.stderr));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stderr).to_string())
+}
+
+/// Name of the bookmark jj's `trunk()` revision is, if any
+/// Executes `jj log -r 'trunk()' --no-graph -T <remote and local bookmarks>` command
+pub fn get_trunk_bookmark() -> Result<Option<String>> {
+    let output = jj()
+        .args([
+            "log",
+            "-r",
+            "trunk()",
+            "--no-graph",
+            "-T",
+            concat!(
+                r#"remote_bookmarks.map(|b| "remote\t" ++ b.name() ++ "\t" ++ b.remote() ++ "\n").join("")"#,
+                r#" ++ local_bookmarks.map(|b| "local\t" ++ b.name() ++ "\n").join("")"#,
+            ),
+        ])
+        .run()?;
+
+    if !output.status.success() {
+        return Err(command_failed("jj log -r trunk()", &output.stderr));
+    }
+
+    Ok(parse_trunk_bookmark(&String::from_utf8_lossy(
+        &output.stdout,
+    )))
+}
+
+/// `trunk()` is a remote bookmark like `main@origin`, its name is the trunk. Other local
+/// bookmarks can sit on the same commit, they are only used when `trunk()` was configured
+/// to a commit no remote bookmark points at
+fn parse_trunk_bookmark(output: &str) -> Option<String> {
+    let mut local = None;
+    for line in output.lines() {
+        match line.split('\t').collect::<Vec<_>>().as_slice() {
+            // The refs of a colocated git repo are no remote
+            ["remote", name, remote] if *remote != "git" => return Some((*name).to_string()),
+            ["local", name] if local.is_none() => local = Some((*name).to_string()),
+            _ => {}
+        }
+    }
+    local
+}
+
+/// Check that `revision` resolves to exactly one commit and return its change id
+/// Executes `jj log -r <revision> --no-graph -T change_id.short()` command
+pub fn resolve_revision(revision: &str) -> Result<String> {
+    let output = jj()
+        .args([
+            "log",
+            "-r",
+            revision,
+            "--no-graph",
+            "--limit",
+            "2",
+            "-T",
+            r#"change_id.short() ++ "\n""#,
+        ])
+        .run()?;
+
+    if !output.status.success() {
+        anyhow::bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let ids: Vec<&str> = stdout.lines().filter(|line| !line.is_empty()).collect();
+    match ids.as_slice() {
+        [id] => Ok((*id).to_string()),
+        [] => anyhow::bail!("{revision} doesn't resolve to any revision"),
+        _ => anyhow::bail!("{revision} resolves to more than one revision"),
+    }
+}
+
+/// Whether `revision` is one of the `immutable()` commits jj refuses to rewrite
+/// Executes `jj log -r '<revision> & immutable()'` command
+pub fn is_immutable(revision: &str) -> Result<bool> {
+    let output = jj()
+        .args([
+            "log",
+            "-r",
+            &format!("({revision}) & immutable()"),
+            "--no-graph",
+            "-T",
+            r#"change_id.short() ++ "\n""#,
+        ])
+        .run()?;
+
+    if !output.status.success() {
+        return Err(command_failed("jj log", &output.stderr));
+    }
+
+    Ok(!String::from_utf8_lossy(&output.stdout).trim().is_empty())
+}
+
+/// Change and commit ids of every commit in `revset`, without a limit
+/// Executes `jj log -r <revset> -T 'change_id.short() ++ "\t" ++ commit_id'` command
+pub fn get_ids(revset: &str) -> Result<Vec<(String, String)>> {
+    let output = jj()
+        .args([
+            "log",
+            "-r",
+            revset,
+            "--no-graph",
+            "-T",
+            r#"change_id.short() ++ "\t" ++ commit_id ++ "\n""#,
+        ])
+        .run()?;
+
+    if !output.status.success() {
+        return Err(command_failed("jj log", &output.stderr));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .map(|(change_id, commit_id)| (change_id.to_string(), commit_id.to_string()))
+        .collect())
+}
+
+/// Local bookmarks whose target is already part of `trunk()`,
+/// leaving out the bookmarks that point at trunk itself
+pub fn get_merged_bookmarks() -> Result<Vec<String>> {
+    get_bookmarks_at("(bookmarks() & ::trunk()) ~ trunk()")
+}
+
+/// Names of the local bookmarks pointing at any commit in `revset`
+/// Executes `jj log -r <revset> -T local_bookmarks` command
+pub fn get_bookmarks_at(revset: &str) -> Result<Vec<String>> {
+    let output = jj()
+        .args([
+            "log",
+            "-r",
+            revset,
+            "--no-graph",
+            "-T",
+            r#"local_bookmarks.map(|b| b.name() ++ "\n").join("")"#,
+        ])
+        .run()?;
+
+    if !output.status.success() {
+        return Err(command_failed("jj log", &output.stderr));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(stdout
+        .lines()
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+/// Delete local bookmarks, the deletion is pushed with `git_push_deleted`
+/// Executes `jj bookmark delete <names>...` command
+pub fn delete_bookmarks(names: &[String]) -> Result<String> {
+    let output = jj().args(["bookmark", "delete"]).args(names).run()?;
+
+    if !output.status.success() {
+        return Err(command_failed("jj bookmark delete", &output.stderr));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stderr).to_string())
+}
+
+/// Create a new bookmark pointing at `revision`
+/// Executes `jj bookmark create <name> -r <revision>` command
+pub fn create_bookmark(name: &str, revision: &str) -> Result<String> {
+    let output = jj()
+        .args(["bookmark", "create", name, "-r", revision])
+        .run()?;
+
+    if !output.status.success() {
+        return Err(command_failed("jj bookmark create", &output.stderr));
     }
 
     Ok(String::from_utf8_lossy(&output.stdout).to_string())
 }
 
-/// Set a bookmark at the current change
-/// Executes `jj bookmark set <name>` command
-pub fn set_bookmark(name: &str) -> Result<String> {
-    let output = Command::new("jj")
-        .args(["bookmark", "set", name])
-        .output()
-        .context("Failed to run jj bookmark set")?;
+/// Move an existing bookmark to `revision`, also backwards or sideways
+/// Executes `jj bookmark set <name> -r <revision> --allow-backwards` command
+pub fn move_bookmark(name: &str, revision: &str) -> Result<String> {
+    let output = jj()
+        .args(["bookmark", "set", name, "-r", revision, "--allow-backwards"])
+        .run()?;
 
     if !output.status.success() {
-        anyhow::bail!(
-            "jj bookmark set failed: {}",
-            String::from_utf8_lossy(&output.stderr)
-        );
+        return Err(command_failed("jj bookmark set", &output.stderr));
     }
 
     Ok(String::from_utf8_lossy(&output.stdout).to_string())
@@ -137,10 +768,9 @@ pub fn set_bookmark(name: &str) -> Result<String> {
 /// Get the name of the current bookmark, if any
 /// Executes `jj log -r @ --no-graph -T bookmarks` command
 pub fn get_current_bookmark() -> Result<Option<String>> {
-    let output = Command::new("jj")
+    let output = jj()
         .args(["log", "-r", "@", "--no-graph", "-T", "bookmarks"])
-        .output()
-        .context("Failed to get current bookmark")?;
+        .run()?;
 
     let stdout = String::from_utf8_lossy(&output.stdout);
     let bookmark = stdout.trim();
@@ -152,126 +782,312 @@ pub fn get_current_bookmark() -> Result<Option<String>> {
     }
 }
 
-/// Check if the working copy is empty (no uncommitted changes or no changes)
-/// Executes `jj status` command
-pub fn is_working_copy_empty() -> Result<bool> {
-    let output = Command::new("jj")
-        .args(["status"])
-        .output()
-        .context("Failed to check working copy status")?;
-
-    let stdout = String::from_utf8_lossy(&output.stdout);
-
-    // Check if output contains "Working copy changes:" followed by nothing
-    // or if it says "The working copy is clean"
-    let is_empty = stdout.contains("The working copy is clean")
-        || stdout
-            .lines()
-            .skip_while(|line| !line.contains("Working copy changes:"))
-            .nth(1)
-            .is_none();
-
-    Ok(is_empty)
-}
-
 #[derive(Debug, Clone)]
 pub struct BookmarkInfo {
     pub name:       String,
     pub is_current: bool,
+    /// Points at several commits, e.g. after it moved both locally and on a remote
+    pub conflicted: bool,
+}
+
+/// One of the commits a conflicted bookmark points at
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BookmarkTarget {
+    pub commit_id:   String,
+    pub change_id:   String,
+    pub description: String,
 }
 
-/// Get the list of bookmarks in the repository
-/// Executes `jj bookmark list` command
+/// Get the list of local bookmarks in the repository
+/// Executes `jj bookmark list -T <name and conflict>` command
 pub fn get_bookmarks() -> Result<Vec<BookmarkInfo>> {
-    let output = Command::new("jj")
-        .args(["bookmark", "list"])
-        .output()
-        .context("Failed to get bookmarks")?;
+    let output = jj()
+        .args([
+            "bookmark",
+            "list",
+            "-T",
+            r#"if(!remote, name ++ if(conflict, "\tconflicted") ++ "\n")"#,
+        ])
+        .run()?;
 
     if !output.status.success() {
-        anyhow::bail!(
-            "jj bookmark list failed: {}",
-            String::from_utf8_lossy(&output.stderr)
-        );
+        return Err(command_failed("jj bookmark list", &output.stderr));
     }
 
     let stdout = String::from_utf8_lossy(&output.stdout);
+    // The current bookmark might have a * suffix (e.g., "master*")
     let current_bookmark = get_current_bookmark().ok().flatten();
+    let current = current_bookmark
+        .as_deref()
+        .map(|current| current.trim_end_matches('*'));
 
-    let mut bookmarks = Vec::new();
-    for line in stdout.lines() {
-        // Parse bookmark lines like "master: xvxormtl a3558703 description"
-        // or "master (conflicted):"
-        // We only want the bookmark name (the part before the colon)
+    Ok(stdout
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let (name, state) = line.split_once('\t').unwrap_or((line, ""));
+            BookmarkInfo {
+                name:       name.to_string(),
+                is_current: current == Some(name),
+                conflicted: state == "conflicted",
+            }
+        })
+        .collect())
+}
 
-        let line = line.trim();
-        if line.is_empty() {
-            continue;
-        }
+/// Remote bookmarks no local bookmark tracks, as `name@remote`
+/// Executes `jj bookmark list --all-remotes -T <untracked remote bookmarks>` command
+pub fn get_untracked_remote_bookmarks() -> Result<Vec<String>> {
+    let output = jj()
+        .args([
+            "bookmark",
+            "list",
+            "--all-remotes",
+            "--ignore-working-copy",
+            "-T",
+            r#"if(remote && !tracked, name ++ "@" ++ remote ++ "\n")"#,
+        ])
+        .run()?;
+
+    if !output.status.success() {
+        return Err(command_failed("jj bookmark list", &output.stderr));
+    }
 
-        // Extract bookmark name (everything before the first colon)
-        if let Some(colon_pos) = line.find(':') {
-            let bookmark_name = line[..colon_pos].trim();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(stdout
+        .lines()
+        .map(str::trim)
+        // The bookmarks of a colocated git repo are no remote anyone pushes to
+        .filter(|name| !name.is_empty() && !name.ends_with("@git"))
+        .map(str::to_string)
+        .collect())
+}
 
-            let bookmark_name = bookmark_name
-                .find(" (")
-                .map_or(bookmark_name, |pos| &bookmark_name[..pos])
-                .trim();
+/// Commits `jj git push` would still have to send, the empty working copy aside
+pub const UNPUSHED_REVSET: &str = "(remote_bookmarks()..@) ~ (@ & empty())";
 
-            if bookmark_name.is_empty() {
-                continue;
-            }
+/// Commits of the current stack that are not on trunk, the empty working copy aside
+pub const AHEAD_OF_TRUNK_REVSET: &str = "(trunk()..@) ~ (@ & empty())";
 
-            // Check if this bookmark is current by comparing with the current bookmark
-            // The current bookmark might have a * suffix (e.g., "master*"), so we need to strip it
-            let is_current = current_bookmark.as_ref().is_some_and(|current| {
-                let current_clean = current.trim_end_matches('*');
-                current_clean == bookmark_name
-            });
-
-            bookmarks.push(BookmarkInfo {
-                name: bookmark_name.to_string(),
-                is_current,
-            });
-        }
+/// Commits trunk got since the current stack branched off, a rebase picks them up
+pub const BEHIND_TRUNK_REVSET: &str = "@..trunk()";
+
+/// Number of commits in `revset`
+/// Executes `jj log -r <revset> --no-graph` command
+pub fn count_commits(revset: &str) -> Result<usize> {
+    let output = jj()
+        .args([
+            "log",
+            "-r",
+            revset,
+            "--no-graph",
+            "--ignore-working-copy",
+            "-T",
+            r#""x\n""#,
+        ])
+        .run()?;
+
+    if !output.status.success() {
+        return Err(command_failed("jj log", &output.stderr));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).lines().count())
+}
+
+/// Commits the conflicted local bookmark `name` points at
+/// Executes `jj bookmark list exact:<name> -T <added targets>` command
+pub fn get_bookmark_targets(name: &str) -> Result<Vec<BookmarkTarget>> {
+    let output = jj()
+        .args([
+            "bookmark",
+            "list",
+            "--ignore-working-copy",
+            "-T",
+            r#"if(!remote, added_targets.map(|c| c.commit_id() ++ "\x1f" ++ c.change_id().short() ++ "\x1f" ++ c.description().first_line() ++ "\n").join(""))"#,
+            &format!("exact:{name}"),
+        ])
+        .run()?;
+
+    if !output.status.success() {
+        return Err(command_failed("jj bookmark list", &output.stderr));
     }
 
-    Ok(bookmarks)
+    Ok(parse_bookmark_targets(&String::from_utf8_lossy(
+        &output.stdout,
+    )))
+}
+
+fn parse_bookmark_targets(output: &str) -> Vec<BookmarkTarget> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\x1f');
+            Some(BookmarkTarget {
+                commit_id:   fields.next().filter(|id| !id.is_empty())?.to_string(),
+                change_id:   fields.next()?.to_string(),
+                description: fields.next().unwrap_or_default().to_string(),
+            })
+        })
+        .collect()
 }
 
 /// Move to a specified bookmark instead.
 /// Executes `jj bookmark set <bookmark>` command
 pub fn checkout_bookmark(bookmark: &str) -> Result<String> {
-    let output = Command::new("jj")
-        .args(["bookmark", "set", bookmark])
-        .output()
-        .context("Failed to checkout bookmark")?;
+    let output = jj().args(["bookmark", "set", bookmark]).run()?;
 
     if !output.status.success() {
-        anyhow::bail!(
-            "jj bookmark set failed: {}",
-            String::from_utf8_lossy(&output.stderr)
-        );
+        return Err(command_failed("jj bookmark set", &output.stderr));
     }
 
     Ok(String::from_utf8_lossy(&output.stdout).to_string())
 }
 
-/// Start work on a new commit based on a bookmark
+/// Start work on a new commit based on a bookmark or revset
 /// Executes `jj new <bookmark>` command
-#[allow(dead_code)] // allow for now as im not sure if im gonna use it short term
-pub fn new_on_bookmark(bookmark: &str) -> Result<String> {
-    let output = Command::new("jj")
-        .args(["new", bookmark])
-        .output()
-        .context("Failed to create new change on bookmark")?;
+pub fn new_on_bookmark(bookmark: &str) -> Result<NewChange> {
+    let output = jj().args(["new", bookmark]).run()?;
 
     if !output.status.success() {
-        anyhow::bail!(
-            "jj new on bookmark failed: {}",
-            String::from_utf8_lossy(&output.stderr)
+        return Err(command_failed("jj new on bookmark", &output.stderr));
+    }
+
+    Ok(NewChange::parse(&String::from_utf8_lossy(&output.stderr)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::jj::{
+        runner::{
+            FakeRunner,
+            with_runner,
+        },
+        test_repo::TestRepo,
+    };
+
+    #[test]
+    fn test_bookmarks_in_scripted_repo() {
+        let Some(repo) = TestRepo::new() else {
+            return;
+        };
+        repo.write("README.md", "hello\n")
+            .commit("First")
+            .bookmark("main", "@-")
+            .remote("origin");
+
+        repo.run(|| {
+            create_bookmark("feature", "@").unwrap();
+            let names: Vec<String> = get_bookmarks()
+                .unwrap()
+                .into_iter()
+                .map(|bookmark| bookmark.name)
+                .collect();
+            // The tracked origin/main is no bookmark of its own
+            assert_eq!(names, ["feature", "main"]);
+
+            delete_bookmarks(&["feature".to_string()]).unwrap();
+            assert!(get_bookmarks().unwrap().iter().all(|b| b.name != "feature"));
+        });
+    }
+
+    #[test]
+    fn test_delete_bookmarks_command() {
+        let runner = Arc::new(FakeRunner {
+            stderr: "Deleted 2 bookmarks.\n".to_string(),
+            ..FakeRunner::default()
+        });
+        let message = with_runner(runner.clone(), || {
+            delete_bookmarks(&["feature".to_string(), "old fix".to_string()])
+        })
+        .unwrap();
+
+        assert_eq!(message, "Deleted 2 bookmarks.\n");
+        assert_eq!(
+            *runner.commands.lock().unwrap(),
+            vec![r#"jj bookmark delete feature "old fix""#]
         );
     }
 
-    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    #[test]
+    fn test_parse_trunk_bookmark() {
+        let output = "\
+remote\tmain\tgit
+remote\tmain\torigin
+local\tdeploy
+local\tmain
+";
+        assert_eq!(parse_trunk_bookmark(output), Some("main".to_string()));
+        assert_eq!(
+            parse_trunk_bookmark("local\tdeploy\nlocal\tmain\n"),
+            Some("deploy".to_string())
+        );
+        assert_eq!(parse_trunk_bookmark(""), None);
+    }
+
+    #[test]
+    fn test_parse_bookmark_targets() {
+        let targets = parse_bookmark_targets(
+            "9f3d2a11aaaa\x1fqpvuntsm\x1fFix the parser\n5678ef12bbbb\x1fzsuskuln\x1f\n",
+        );
+        assert_eq!(targets.len(), 2);
+        assert_eq!(targets[0].commit_id, "9f3d2a11aaaa");
+        assert_eq!(targets[0].description, "Fix the parser");
+        assert_eq!(targets[1].change_id, "zsuskuln");
+        assert!(targets[1].description.is_empty());
+    }
+
+    #[test]
+    fn test_with_directories() {
+        let files = "src/main.rs\nsrc/jj/log.rs\nREADME.md\n";
+        assert_eq!(
+            with_directories(files.lines()),
+            vec![
+                "README.md",
+                "src/",
+                "src/jj/",
+                "src/jj/log.rs",
+                "src/main.rs"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_author() {
+        assert_eq!(
+            parse_author(" Ada Lovelace <ada@example.com> ").unwrap(),
+            ("Ada Lovelace".to_string(), "ada@example.com".to_string())
+        );
+        assert!(parse_author("Ada Lovelace").is_err());
+        assert!(parse_author("<ada@example.com>").is_err());
+    }
+
+    #[test]
+    fn test_gitignore_pattern() {
+        assert_eq!(gitignore_pattern("assets/video.mp4"), "/assets/video.mp4");
+        assert_eq!(gitignore_pattern("data[1]*.bin"), r"/data\[1]\*.bin");
+        assert_eq!(gitignore_pattern("trailing "), r"/trailing\ ");
+    }
+
+    #[test]
+    fn test_parse_remotes() {
+        let remotes = parse_remotes(
+            "origin https://github.com/user/repo.git\nupstream git@github.com:org/repo.git\n",
+        );
+        assert_eq!(
+            remotes,
+            vec![
+                RemoteInfo {
+                    name: "origin".to_string(),
+                    url:  "https://github.com/user/repo.git".to_string(),
+                },
+                RemoteInfo {
+                    name: "upstream".to_string(),
+                    url:  "git@github.com:org/repo.git".to_string(),
+                },
+            ]
+        );
+    }
 }
@@ -0,0 +1,89 @@
+use anyhow::{
+    Context,
+    Result,
+};
+
+use super::command::{
+    CommandExt,
+    jj,
+};
+
+/// Output of a jj command typed by the user
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandOutput {
+    pub success: bool,
+    /// stdout followed by stderr, jj reports most changes on stderr
+    pub output:  String,
+}
+
+/// Run the jj command typed as `command_line`, with or without the leading `jj`
+pub fn run(command_line: &str) -> Result<CommandOutput> {
+    let mut args = split_args(command_line)?;
+    if args.first().is_some_and(|arg| arg == "jj") {
+        args.remove(0);
+    }
+    if args.is_empty() {
+        anyhow::bail!("Type a jj command, e.g. op diff");
+    }
+
+    let output = jj().args(&args).run()?;
+
+    let mut text = String::from_utf8_lossy(&output.stdout).to_string();
+    text.push_str(&String::from_utf8_lossy(&output.stderr));
+    Ok(CommandOutput {
+        success: output.status.success(),
+        output:  text,
+    })
+}
+
+/// Split a command line into arguments like a shell would, honoring quotes and backslashes
+fn split_args(command_line: &str) -> Result<Vec<String>> {
+    let mut args = Vec::new();
+    let mut current: Option<String> = None;
+    let mut quote = None;
+    let mut chars = command_line.chars();
+
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (None, c) if c.is_whitespace() => {
+                if let Some(arg) = current.take() {
+                    args.push(arg);
+                }
+            }
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                current.get_or_insert_with(String::new);
+            }
+            (Some(open), c) if c == open => quote = None,
+            (None | Some('"'), '\\') => {
+                let escaped = chars.next().context("Trailing backslash")?;
+                current.get_or_insert_with(String::new).push(escaped);
+            }
+            (_, c) => current.get_or_insert_with(String::new).push(c),
+        }
+    }
+
+    if quote.is_some() {
+        anyhow::bail!("Unclosed quote");
+    }
+    args.extend(current);
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_args() {
+        assert_eq!(
+            split_args(r#"describe -m "Fix the parser"  -r @-"#).unwrap(),
+            vec!["describe", "-m", "Fix the parser", "-r", "@-"]
+        );
+        assert_eq!(
+            split_args(r#"log -r 'author("me")' -T "a\"b" """#).unwrap(),
+            vec!["log", "-r", r#"author("me")"#, "-T", r#"a"b"#, ""]
+        );
+        assert!(split_args(r#"describe -m "oops"#).is_err());
+    }
+}
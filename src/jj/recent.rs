@@ -0,0 +1,132 @@
+use std::path::Path;
+
+use anyhow::Result;
+
+use super::sidecar::{
+    Sidecar,
+    SidecarEntry,
+};
+
+/// File name of the recent contexts inside the `.jj` directory of the workspace
+const RECENT_NAME: &str = "jjkk-recent";
+/// How many contexts are remembered, older ones are forgotten
+const MAX_RECENT: usize = 10;
+
+/// Something worked on before, switching to it picks up the work again
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecentContext {
+    /// A checked out bookmark, switched to with a new change on it
+    Bookmark(String),
+    /// A change that was edited, switched to by editing it again
+    Change {
+        change_id:   String,
+        /// First line of the description when the change was left
+        description: String,
+    },
+}
+
+impl RecentContext {
+    /// Whether both are the same bookmark or change, whatever the description says now
+    fn same_as(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Bookmark(a), Self::Bookmark(b))
+            | (Self::Change { change_id: a, .. }, Self::Change { change_id: b, .. }) => a == b,
+            _ => false,
+        }
+    }
+}
+
+/// A context as its kind followed by what tells it apart
+impl SidecarEntry for RecentContext {
+    fn from_fields(fields: &[String]) -> Option<Self> {
+        match fields {
+            [kind, name] if kind == "bookmark" => Some(Self::Bookmark(name.clone())),
+            [kind, change_id, description] if kind == "change" => Some(Self::Change {
+                change_id:   change_id.clone(),
+                description: description.clone(),
+            }),
+            _ => None,
+        }
+    }
+
+    fn to_fields(&self) -> Vec<String> {
+        match self {
+            Self::Bookmark(name) => vec!["bookmark".to_string(), name.clone()],
+            Self::Change {
+                change_id,
+                description,
+            } => vec!["change".to_string(), change_id.clone(), description.clone()],
+        }
+    }
+}
+
+/// Recently used bookmarks and changes of a workspace, most recent first, stored next to the
+/// repo in `.jj/jjkk-recent`
+#[derive(Debug, Default)]
+pub struct RecentContexts {
+    contexts: Sidecar<RecentContext>,
+}
+
+impl RecentContexts {
+    /// Recent contexts of the workspace at `workspace_root`, none on the first run
+    pub fn load(workspace_root: &Path) -> Result<Self> {
+        Ok(Self {
+            contexts: Sidecar::load(workspace_root, RECENT_NAME)?,
+        })
+    }
+
+    pub fn contexts(&self) -> &[RecentContext] {
+        self.contexts.entries()
+    }
+
+    /// Move `context` to the top, forgetting the oldest one when the list is full
+    pub fn visit(&mut self, context: RecentContext) -> Result<()> {
+        self.contexts.update(|contexts| {
+            contexts.retain(|c| !c.same_as(&context));
+            contexts.insert(0, context);
+            contexts.truncate(MAX_RECENT);
+        })
+    }
+
+    pub fn remove(&mut self, context: &RecentContext) -> Result<()> {
+        self.contexts
+            .update(|contexts| contexts.retain(|c| !c.same_as(context)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_visit_moves_to_top() {
+        let mut recent = RecentContexts::default();
+        for i in 0..=MAX_RECENT {
+            recent
+                .visit(RecentContext::Bookmark(format!("feature-{i}")))
+                .unwrap();
+        }
+        let change = |description: &str| RecentContext::Change {
+            change_id:   "kxqz".to_string(),
+            description: description.to_string(),
+        };
+        recent.visit(change("Draft")).unwrap();
+        recent.visit(change("Fix the parser")).unwrap();
+
+        // The change is listed once, with the description it was last left with
+        let contexts = recent.contexts();
+        assert_eq!(contexts.len(), MAX_RECENT);
+        assert_eq!(contexts[0], change("Fix the parser"));
+        assert_eq!(
+            contexts[1],
+            RecentContext::Bookmark(format!("feature-{MAX_RECENT}"))
+        );
+
+        for context in contexts {
+            assert_eq!(
+                RecentContext::from_fields(&context.to_fields()).as_ref(),
+                Some(context)
+            );
+        }
+    }
+}
@@ -1,10 +1,15 @@
-use std::path::PathBuf;
+use std::path::{
+    Path,
+    PathBuf,
+};
 
 use anyhow::Result;
 
+use super::error::JjError;
+
 // Placeholder for now - will implement with jj-lib once we figure out the API
 pub struct JjRepo {
-    _workspace_root: PathBuf,
+    workspace_root: PathBuf,
 }
 
 impl JjRepo {
@@ -13,20 +18,42 @@ impl JjRepo {
 
         // TODO : make this a bit nicer
         if !cwd.join(".jj").is_dir() {
-            anyhow::bail!("No jj repository found at {}", cwd.display());
+            return Err(JjError::new(
+                "jjkk",
+                &format!("No jj repository found at {}", cwd.display()),
+            )
+            .into());
         }
 
         // TODO: Open workspace with jj-lib
         Ok(Self {
-            _workspace_root: cwd,
+            workspace_root: cwd,
         })
     }
+
+    pub fn workspace_root(&self) -> &Path {
+        &self.workspace_root
+    }
+}
+
+/// Root of the git repository `path` is in, when jj was never set up in it. `jj git init
+/// --colocate` makes it one
+///
+/// Looks up from `path` to the first directory with a `.jj` or `.git`, so a subdirectory of a
+/// jj repo is never mistaken for one
+pub fn git_only_root(path: &Path) -> Option<PathBuf> {
+    let root = path
+        .ancestors()
+        .find(|dir| dir.join(".jj").is_dir() || dir.join(".git").exists())?;
+    (!root.join(".jj").is_dir()).then(|| root.to_path_buf())
 }
 
 #[derive(Debug, Clone)]
 pub struct FileStatus {
-    pub path:   String,
-    pub status: ChangeType,
+    pub path:     String,
+    pub status:   ChangeType,
+    /// Previous path of renamed or copied files
+    pub old_path: Option<String>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -34,6 +61,15 @@ pub enum ChangeType {
     Added,
     Modified,
     Deleted,
+    Renamed,
+    Copied,
+    /// File has unresolved conflicts
+    Conflicted,
+    /// New file of `size` bytes that jj refused to snapshot, it is over
+    /// `snapshot.max-new-file-size`
+    TooLarge {
+        size: u64,
+    },
 }
 
 impl ChangeType {
@@ -42,6 +78,33 @@ impl ChangeType {
             Self::Added => "A",
             Self::Modified => "M",
             Self::Deleted => "D",
+            Self::Renamed => "R",
+            Self::Copied => "C",
+            Self::Conflicted => "U",
+            Self::TooLarge { .. } => "!",
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_git_only_root() {
+        let root = std::env::temp_dir().join(format!("jjkk-git-only-{}", std::process::id()));
+        let nested = root.join("src").join("deep");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::create_dir_all(root.join(".git")).unwrap();
+
+        assert_eq!(git_only_root(&nested), Some(root.clone()));
+
+        std::fs::create_dir_all(root.join(".jj")).unwrap();
+        assert_eq!(git_only_root(&nested), None);
+        // A nested git repo inside the jj repo is one of its own
+        std::fs::create_dir_all(nested.join(".git")).unwrap();
+        assert_eq!(git_only_root(&nested), Some(nested.clone()));
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,312 @@
+use std::fmt::Write;
+
+/// What a fetch imported from the remote
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FetchStats {
+    pub remote: String,
+    /// Remote bookmarks that were created, moved or deleted by the fetch
+    pub updated_bookmarks: Vec<String>,
+    /// Local commits abandoned because the remote rewrote them
+    pub abandoned_commits: usize,
+}
+
+impl FetchStats {
+    pub fn summary(&self) -> String {
+        let mut summary = match self.updated_bookmarks.len() {
+            0 => format!("Fetched from {}, already up to date", self.remote),
+            1 => format!(
+                "Fetched from {}, updated {}",
+                self.remote, self.updated_bookmarks[0]
+            ),
+            count => format!("Fetched from {}, updated {count} bookmarks", self.remote),
+        };
+        if self.abandoned_commits > 0 {
+            let _ = write!(summary, ", abandoned {} commit(s)", self.abandoned_commits);
+        }
+        summary
+    }
+}
+
+/// How a push changes a bookmark on the remote
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PushAction {
+    Add,
+    MoveForward,
+    MoveBackward,
+    MoveSideways,
+    Delete,
+}
+
+/// One bookmark a push updates, `detail` is jj's line about it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BookmarkPush {
+    pub name:   String,
+    pub action: PushAction,
+    pub detail: String,
+}
+
+/// What a push (or a dry-run of it) changes on the remote
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PushResult {
+    pub remote:    Option<String>,
+    pub bookmarks: Vec<BookmarkPush>,
+}
+
+impl PushResult {
+    /// Parse the output of `jj git push`, e.g.
+    /// ```text
+    /// Changes to push to origin:
+    ///   Add bookmark feature to 1a2b3c4d
+    ///   Move forward bookmark main from 1a2b3c4d to 5e6f7a8b
+    /// ```
+    pub fn parse(output: &str) -> Self {
+        let mut result = Self::default();
+
+        for line in output.lines().map(str::trim) {
+            if let Some(remote) = line
+                .strip_prefix("Changes to push to ")
+                .and_then(|rest| rest.strip_suffix(':'))
+            {
+                result.remote = Some(remote.to_string());
+                continue;
+            }
+
+            let actions = [
+                ("Add bookmark ", PushAction::Add),
+                ("Move forward bookmark ", PushAction::MoveForward),
+                ("Move backward bookmark ", PushAction::MoveBackward),
+                ("Move sideways bookmark ", PushAction::MoveSideways),
+                ("Delete bookmark ", PushAction::Delete),
+            ];
+            for (prefix, action) in actions {
+                if let Some(name) = line
+                    .strip_prefix(prefix)
+                    .and_then(|rest| rest.split_whitespace().next())
+                {
+                    result.bookmarks.push(BookmarkPush {
+                        name: name.to_string(),
+                        action,
+                        detail: line.to_string(),
+                    });
+                    break;
+                }
+            }
+        }
+
+        result
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.bookmarks.is_empty()
+    }
+
+    pub fn summary(&self) -> String {
+        let remote = self.remote.as_deref().unwrap_or("remote");
+        match self.bookmarks.as_slice() {
+            [] => "Nothing to push".to_string(),
+            [bookmark] if bookmark.action == PushAction::Delete => {
+                format!("Deleted bookmark {} on {remote}", bookmark.name)
+            }
+            // `name@remote` is how jj refers to the pushed bookmark, ready to copy
+            [bookmark] => format!("Pushed bookmark {}@{remote}", bookmark.name),
+            bookmarks => format!("Pushed {} bookmarks to {remote}", bookmarks.len()),
+        }
+    }
+}
+
+/// What a rebase rewrote
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RebaseResult {
+    /// Commits rebased, including their descendants
+    pub rewritten: usize,
+    /// Commits that were already on top of the destination
+    pub skipped:   usize,
+}
+
+impl RebaseResult {
+    /// Parse the output of `jj rebase`, e.g.
+    /// ```text
+    /// Rebased 2 commits to destination
+    /// Rebased 1 descendant commits
+    /// Skipped rebase of 1 commits that were already in place
+    /// ```
+    pub fn parse(output: &str) -> Self {
+        let count = |rest: &str| {
+            rest.split_whitespace()
+                .next()
+                .and_then(|n| n.parse::<usize>().ok())
+                .unwrap_or(0)
+        };
+
+        let mut result = Self::default();
+        for line in output.lines().map(str::trim) {
+            if let Some(rest) = line.strip_prefix("Rebased ") {
+                result.rewritten += count(rest);
+            } else if let Some(rest) = line.strip_prefix("Skipped rebase of ") {
+                result.skipped += count(rest);
+            }
+        }
+        result
+    }
+
+    pub fn summary(&self, destination: &str) -> String {
+        match (self.rewritten, self.skipped) {
+            (0, 0) => format!("Nothing to rebase onto {destination}"),
+            (0, _) => format!("Already on top of {destination}"),
+            (rewritten, _) => format!("Rebased {rewritten} commit(s) onto {destination}"),
+        }
+    }
+}
+
+/// Which files a patch changed
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PatchResult {
+    pub applied: Vec<String>,
+}
+
+impl PatchResult {
+    /// Parse the output of `git apply --verbose`, e.g.
+    /// ```text
+    /// Checking patch src/main.rs...
+    /// Checking patch README.md...
+    /// Applied patch src/main.rs cleanly.
+    /// Applied patch README.md cleanly.
+    /// ```
+    pub fn parse(output: &str) -> Self {
+        let applied = output
+            .lines()
+            .filter_map(|line| {
+                line.trim()
+                    .strip_prefix("Applied patch ")
+                    .and_then(|rest| rest.strip_suffix(" cleanly."))
+            })
+            .map(str::to_string)
+            .collect();
+        Self { applied }
+    }
+
+    pub fn summary(&self) -> String {
+        format!("Applied patch to {} file(s)", self.applied.len())
+    }
+}
+
+/// The working copy commit a command like `jj new` moved to
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NewChange {
+    pub change_id: String,
+    pub commit_id: String,
+}
+
+impl NewChange {
+    /// Parse the output of `jj new`, e.g.
+    /// ```text
+    /// Working copy  (@) now at: kxryzmor 7b3e0a1c (empty) (no description set)
+    /// Parent commit (@-)      : qpvuntsm 5e6f7a8b feature | Add the thing
+    /// ```
+    pub fn parse(output: &str) -> Self {
+        output
+            .lines()
+            .find_map(|line| line.split_once("(@) now at:"))
+            .map(|(_, rest)| {
+                let mut ids = rest.split_whitespace().map(str::to_string);
+                Self {
+                    change_id: ids.next().unwrap_or_default(),
+                    commit_id: ids.next().unwrap_or_default(),
+                }
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn summary(&self) -> String {
+        if self.change_id.is_empty() {
+            return "Created new change".to_string();
+        }
+        format!("Created new change {} ({})", self.change_id, self.commit_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_push() {
+        let output = "\
+Changes to push to origin:
+  Add bookmark feature to 1a2b3c4d5e6f
+  Move forward bookmark main from 1a2b3c4d5e6f to 5e6f7a8b9c0d
+  Delete bookmark old from 0d9c8b7a6f5e
+Dry-run requested, not pushing.
+";
+        let result = PushResult::parse(output);
+        assert_eq!(result.remote.as_deref(), Some("origin"));
+        let names: Vec<(&str, PushAction)> = result
+            .bookmarks
+            .iter()
+            .map(|b| (b.name.as_str(), b.action))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("feature", PushAction::Add),
+                ("main", PushAction::MoveForward),
+                ("old", PushAction::Delete),
+            ]
+        );
+        assert_eq!(result.summary(), "Pushed 3 bookmarks to origin");
+
+        assert!(PushResult::parse("Nothing changed.\n").is_empty());
+
+        let result = PushResult::parse(
+            "Changes to push to origin:\n  Add bookmark push-kxryzmor to 7b3e0a1c\n",
+        );
+        assert_eq!(result.summary(), "Pushed bookmark push-kxryzmor@origin");
+    }
+
+    #[test]
+    fn test_parse_new_change() {
+        let output = "\
+Working copy  (@) now at: kxryzmor 7b3e0a1c (empty) (no description set)
+Parent commit (@-)      : qpvuntsm 5e6f7a8b feature | Add the thing
+";
+        let change = NewChange::parse(output);
+        assert_eq!(change.change_id, "kxryzmor");
+        assert_eq!(change.commit_id, "7b3e0a1c");
+        assert_eq!(change.summary(), "Created new change kxryzmor (7b3e0a1c)");
+
+        assert_eq!(NewChange::parse("").summary(), "Created new change");
+    }
+
+    #[test]
+    fn test_parse_rebase() {
+        let output = "\
+Rebased 2 commits to destination
+Rebased 1 descendant commits
+Working copy  (@) now at: kxqpwmlu 1a2b3c4d Change
+";
+        let result = RebaseResult::parse(output);
+        assert_eq!(result.rewritten, 3);
+        assert_eq!(result.skipped, 0);
+
+        let result = RebaseResult::parse("Skipped rebase of 1 commits that were already in place");
+        assert_eq!(result.summary("main"), "Already on top of main");
+    }
+
+    #[test]
+    fn test_parse_patch() {
+        let output = "\
+Checking patch src/main.rs...
+Checking patch README.md...
+Applied patch src/main.rs cleanly.
+Applied patch README.md cleanly.
+";
+        let result = PatchResult::parse(output);
+        assert_eq!(result.applied, vec!["src/main.rs", "README.md"]);
+        assert_eq!(result.summary(), "Applied patch to 2 file(s)");
+        assert!(
+            PatchResult::parse("error: corrupt patch at line 3")
+                .applied
+                .is_empty()
+        );
+    }
+}
@@ -0,0 +1,517 @@
+use std::{
+    cell::RefCell,
+    fs::OpenOptions,
+    io::{
+        BufRead,
+        BufReader,
+        Read,
+        Write,
+    },
+    path::PathBuf,
+    process::{
+        Child,
+        Command,
+        Output,
+        Stdio,
+    },
+    sync::{
+        Arc,
+        Mutex,
+        OnceLock,
+        PoisonError,
+        mpsc::{
+            self,
+            Receiver,
+            RecvTimeoutError,
+        },
+    },
+    thread,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+use anyhow::{
+    Context,
+    Result,
+};
+
+use super::{
+    command::{
+        arguments,
+        command_line,
+        command_name,
+    },
+    error::JjError,
+};
+
+/// Seconds a jj subprocess may run when `command_timeout_secs` is not configured
+const DEFAULT_TIMEOUT_SECS: u64 = 120;
+/// Seconds a command talking to a remote may run when `command_timeout_secs` is not
+/// configured, a big fetch or push over a slow connection takes a while
+const NETWORK_TIMEOUT_SECS: u64 = 600;
+/// How long the output of a stopped command is still read, until its pipes are closed
+const STOPPED_OUTPUT_GRACE: Duration = Duration::from_millis(500);
+/// Commands that talk to a remote
+const NETWORK_COMMANDS: [&[&str]; 3] = [&["git", "fetch"], &["git", "push"], &["git", "clone"]];
+/// Commands that only read the repo, the ones a dry run still runs
+const READ_ONLY_COMMANDS: [&[&str]; 15] = [
+    &["--version"],
+    &["log"],
+    &["status"],
+    &["diff"],
+    &["show"],
+    &["root"],
+    &["evolog"],
+    &["config", "get"],
+    &["config", "list"],
+    &["file", "list"],
+    &["file", "show"],
+    &["bookmark", "list"],
+    &["op", "log"],
+    &["op", "diff"],
+    &["git", "remote", "list"],
+];
+
+/// How long a jj subprocess may run before it is stopped, set once at startup
+static TIMEOUT: OnceLock<Duration> = OnceLock::new();
+/// Runner of every jj subprocess, `SystemRunner` when not set
+static RUNNER: OnceLock<Arc<dyn CommandRunner>> = OnceLock::new();
+/// File every jj command is appended to, set once at startup
+static LOG_FILE: OnceLock<PathBuf> = OnceLock::new();
+
+thread_local! {
+    /// Replaces `RUNNER` on this thread only, so tests running side by side can fake jj
+    static THREAD_RUNNER: RefCell<Option<Arc<dyn CommandRunner>>> = const { RefCell::new(None) };
+}
+
+/// Runs the jj commands of `jj::operations`, swapped out to preview them or fake jj in tests
+pub trait CommandRunner: Send + Sync {
+    fn run(&self, command: &mut Command) -> Result<Output>;
+
+    /// Like `run`, but passes each line of stdout to `line` while the command still runs.
+    /// Runners that only have the whole output pass its lines once it is done
+    fn run_streaming(&self, command: &mut Command, line: &mut dyn FnMut(&str)) -> Result<Output> {
+        let output = self.run(command)?;
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .for_each(line);
+        Ok(output)
+    }
+
+    /// Run `command` on the terminal, for commands that take it over like `jj resolve` with
+    /// a merge tool. The output is empty, it went to the terminal
+    fn run_interactive(&self, command: &mut Command) -> Result<Output> {
+        self.run(command)
+    }
+}
+
+/// Stop jj subprocesses that run longer than `seconds`, 0 lets them run forever
+pub fn set_timeout(seconds: u64) {
+    let timeout = if seconds == 0 {
+        Duration::MAX
+    } else {
+        Duration::from_secs(seconds)
+    };
+    // Only the first call wins, like the executable
+    let _ = TIMEOUT.set(timeout);
+}
+
+/// Run every jj command through `runner` from now on, only the first call wins
+pub fn set_runner(runner: Arc<dyn CommandRunner>) {
+    let _ = RUNNER.set(runner);
+}
+
+/// Append every jj command to `path`, a tab separated line each
+pub fn set_log_file(path: PathBuf) {
+    let _ = LOG_FILE.set(path);
+}
+
+/// Run `f` with the runner of this thread, or the one of the whole process
+pub fn with_current<T>(f: impl FnOnce(&dyn CommandRunner) -> T) -> T {
+    let runner = THREAD_RUNNER
+        .with_borrow(Clone::clone)
+        .or_else(|| RUNNER.get().cloned());
+    match runner {
+        Some(runner) => f(runner.as_ref()),
+        None => f(&SystemRunner),
+    }
+}
+
+/// Fake jj with `runner` for the jj commands `f` runs on this thread
+#[cfg(test)]
+pub fn with_runner<T>(runner: Arc<dyn CommandRunner>, f: impl FnOnce() -> T) -> T {
+    THREAD_RUNNER.set(Some(runner));
+    let result = f();
+    THREAD_RUNNER.set(None);
+    result
+}
+
+/// Record a finished command in the log file, when there is one. A line is the time, the
+/// duration in milliseconds, the exit code (or `error`) and the command
+pub fn log(command_line: &str, duration: Duration, result: &Result<Output>) {
+    let Some(path) = LOG_FILE.get() else {
+        return;
+    };
+    let status = result.as_ref().map_or_else(
+        |_| "error".to_string(),
+        |output| {
+            output
+                .status
+                .code()
+                .map_or_else(|| "signal".to_string(), |code| code.to_string())
+        },
+    );
+    let line = format!(
+        "{}\t{}\t{status}\t{command_line}\n",
+        chrono::Local::now().to_rfc3339(),
+        duration.as_millis()
+    );
+    // Logging must never break the command itself
+    if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(path) {
+        let _ = file.write_all(line.as_bytes());
+    }
+}
+
+/// Whether `command` only reads the repo
+pub fn is_read_only(command: &Command) -> bool {
+    let args = subcommand_arguments(command);
+    args.iter().any(|arg| arg == "--dry-run") || starts_with_any(&args, &READ_ONLY_COMMANDS)
+}
+
+/// How long `command` may run, the configured timeout or the default for its kind
+fn timeout(command: &Command) -> Duration {
+    TIMEOUT.get().copied().unwrap_or_else(|| {
+        let network = starts_with_any(&subcommand_arguments(command), &NETWORK_COMMANDS);
+        Duration::from_secs(if network {
+            NETWORK_TIMEOUT_SECS
+        } else {
+            DEFAULT_TIMEOUT_SECS
+        })
+    })
+}
+
+/// Arguments of `command` from the subcommand on
+fn subcommand_arguments(command: &Command) -> Vec<String> {
+    let mut args = arguments(command);
+    // Config overrides for this command only, like `--config snapshot.max-new-file-size=..`
+    while args.first().is_some_and(|arg| arg == "--config") {
+        args.drain(..2.min(args.len()));
+    }
+    args
+}
+
+fn starts_with_any(args: &[String], prefixes: &[&[&str]]) -> bool {
+    prefixes
+        .iter()
+        .any(|prefix| args.len() >= prefix.len() && args[..prefix.len()] == **prefix)
+}
+
+/// Spawns jj, stopping it once it runs longer than the configured timeout,
+/// e.g. because it waits for a prompt nobody can see
+pub struct SystemRunner;
+
+impl CommandRunner for SystemRunner {
+    fn run(&self, command: &mut Command) -> Result<Output> {
+        let name = command_name(command);
+        let deadline = Instant::now().checked_add(timeout(command));
+        let mut child = spawn(command, &name)?;
+        let stdout = PipeReader::start(child.stdout.take());
+        let stderr = PipeReader::start(child.stderr.take());
+
+        // Both pipes close once jj exits
+        let finished = stdout.wait(deadline) && stderr.wait(deadline);
+        if !finished {
+            return Err(stop_timed_out(&mut child, &stderr, &name));
+        }
+
+        Ok(Output {
+            status: child.wait()?,
+            stdout: stdout.take(),
+            stderr: stderr.take(),
+        })
+    }
+
+    fn run_streaming(&self, command: &mut Command, line: &mut dyn FnMut(&str)) -> Result<Output> {
+        let name = command_name(command);
+        let deadline = Instant::now().checked_add(timeout(command));
+        let mut child = spawn(command, &name)?;
+        let lines = read_lines(child.stdout.take());
+        let stderr = PipeReader::start(child.stderr.take());
+
+        let mut stdout = Vec::new();
+        loop {
+            let next = deadline.map_or_else(
+                || lines.recv().map_err(RecvTimeoutError::from),
+                |deadline| lines.recv_timeout(deadline.saturating_duration_since(Instant::now())),
+            );
+            match next {
+                Ok(text) => {
+                    line(&text);
+                    stdout.extend_from_slice(text.as_bytes());
+                    stdout.push(b'\n');
+                }
+                // stdout closed, jj is done
+                Err(RecvTimeoutError::Disconnected) => break,
+                Err(RecvTimeoutError::Timeout) => {
+                    return Err(stop_timed_out(&mut child, &stderr, &name));
+                }
+            }
+        }
+        if !stderr.wait(deadline) {
+            return Err(stop_timed_out(&mut child, &stderr, &name));
+        }
+
+        Ok(Output {
+            status: child.wait()?,
+            stdout,
+            stderr: stderr.take(),
+        })
+    }
+
+    /// Without a timeout, nobody can tell how long a person takes in a merge tool
+    fn run_interactive(&self, command: &mut Command) -> Result<Output> {
+        let status = command
+            .stdin(Stdio::inherit())
+            .stdout(Stdio::inherit())
+            .stderr(Stdio::inherit())
+            .status()
+            .with_context(|| format!("Failed to run {}", command_name(command)))?;
+        Ok(Output {
+            status,
+            stdout: Vec::new(),
+            stderr: Vec::new(),
+        })
+    }
+}
+
+/// Start `command` with piped output, in a group of its own so stopping it also stops
+/// whatever it started, like ssh
+fn spawn(command: &mut Command, name: &str) -> Result<Child> {
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(command, 0);
+    command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run {name}"))
+}
+
+/// Stop `child` once it ran out of time, the error keeps what it printed on `stderr`
+fn stop_timed_out(child: &mut Child, stderr: &PipeReader, name: &str) -> anyhow::Error {
+    stop(child);
+    stderr.wait(Instant::now().checked_add(STOPPED_OUTPUT_GRACE));
+    let stderr = String::from_utf8_lossy(&stderr.take()).to_string();
+    JjError::timed_out(name, &stderr).into()
+}
+
+/// Kill `child` and its process group, then reap it
+fn stop(child: &mut Child) {
+    #[cfg(unix)]
+    {
+        use rustix::process::{
+            Pid,
+            Signal,
+            kill_process_group,
+        };
+        let _ = kill_process_group(Pid::from_child(child), Signal::KILL);
+    }
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+/// A pipe read to the end on another thread, what was read so far stays available when it
+/// is never closed
+struct PipeReader {
+    buffer: Arc<Mutex<Vec<u8>>>,
+    closed: Receiver<()>,
+}
+
+impl PipeReader {
+    fn start(pipe: Option<impl Read + Send + 'static>) -> Self {
+        let buffer = Arc::new(Mutex::new(Vec::new()));
+        let (sender, closed) = mpsc::channel();
+        let output = Arc::clone(&buffer);
+        thread::spawn(move || {
+            if let Some(mut pipe) = pipe {
+                let mut chunk = [0; 8192];
+                while let Ok(read @ 1..) = pipe.read(&mut chunk) {
+                    output
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .extend_from_slice(&chunk[..read]);
+                }
+            }
+            // Nobody waits anymore when the command timed out
+            let _ = sender.send(());
+        });
+        Self { buffer, closed }
+    }
+
+    /// Whether the pipe closed before `deadline`, `None` waits forever
+    fn wait(&self, deadline: Option<Instant>) -> bool {
+        let result = deadline.map_or_else(
+            || self.closed.recv().map_err(RecvTimeoutError::from),
+            |deadline| {
+                self.closed
+                    .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+            },
+        );
+        !matches!(result, Err(RecvTimeoutError::Timeout))
+    }
+
+    fn take(&self) -> Vec<u8> {
+        std::mem::take(&mut self.buffer.lock().unwrap_or_else(PoisonError::into_inner))
+    }
+}
+
+/// The lines of `pipe` as another thread reads them, the receiver disconnects once it closed
+fn read_lines(pipe: Option<impl Read + Send + 'static>) -> Receiver<String> {
+    let (sender, lines) = mpsc::channel();
+    thread::spawn(move || {
+        let Some(pipe) = pipe else {
+            return;
+        };
+        let mut pipe = BufReader::new(pipe);
+        let mut line = Vec::new();
+        while let Ok(1..) = pipe.read_until(b'\n', &mut line) {
+            let text = String::from_utf8_lossy(&line);
+            // Nobody listens anymore when the command timed out
+            if sender
+                .send(text.trim_end_matches(['\n', '\r']).to_string())
+                .is_err()
+            {
+                return;
+            }
+            line.clear();
+        }
+    });
+    lines
+}
+
+/// A command a dry run stopped at instead of changing the repo
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkippedCommand(pub String);
+
+impl std::fmt::Display for SkippedCommand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Would run: {}", self.0)
+    }
+}
+
+impl std::error::Error for SkippedCommand {}
+
+/// Runs the commands that only read the repo and stops at the first one that would change
+/// it, failing with a `SkippedCommand` that tells what would have run
+pub struct DryRunRunner;
+
+impl CommandRunner for DryRunRunner {
+    fn run(&self, command: &mut Command) -> Result<Output> {
+        if is_read_only(command) {
+            SystemRunner.run(command)
+        } else {
+            Err(SkippedCommand(command_line(command)).into())
+        }
+    }
+}
+
+/// Answers every command with the same output and remembers what was run
+#[cfg(test)]
+#[derive(Default)]
+pub struct FakeRunner {
+    pub stdout:   String,
+    pub stderr:   String,
+    pub commands: std::sync::Mutex<Vec<String>>,
+}
+
+#[cfg(test)]
+impl CommandRunner for FakeRunner {
+    fn run(&self, command: &mut Command) -> Result<Output> {
+        self.commands
+            .lock()
+            .expect("fake runner lock")
+            .push(command_line(command));
+        Ok(Output {
+            status: std::process::ExitStatus::default(),
+            stdout: self.stdout.clone().into_bytes(),
+            stderr: self.stderr.clone().into_bytes(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jj::command::jj;
+
+    #[test]
+    fn test_is_read_only() {
+        assert!(is_read_only(jj().args(["log", "-r", "@"])));
+        assert!(is_read_only(jj().args(["git", "push", "--dry-run"])));
+        assert!(is_read_only(jj().args(["bookmark", "list"])));
+        assert!(!is_read_only(jj().args(["bookmark", "delete", "main"])));
+        assert!(!is_read_only(jj().args([
+            "--config",
+            "snapshot.max-new-file-size=1",
+            "file",
+            "track"
+        ])));
+    }
+
+    #[test]
+    fn test_timeout() {
+        assert_eq!(
+            timeout(jj().args(["log"])),
+            Duration::from_secs(DEFAULT_TIMEOUT_SECS)
+        );
+        assert_eq!(
+            timeout(jj().args(["git", "push", "--bookmark", "main"])),
+            Duration::from_secs(NETWORK_TIMEOUT_SECS)
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_timed_out_command_keeps_its_stderr() {
+        let mut command = Command::new("sh");
+        command.args(["-c", "echo waiting for a prompt >&2; sleep 30"]);
+        let started = Instant::now();
+        std::os::unix::process::CommandExt::process_group(&mut command, 0);
+        let mut child = command.stderr(Stdio::piped()).spawn().unwrap();
+        let stderr = PipeReader::start(child.stderr.take());
+
+        assert!(!stderr.wait(Instant::now().checked_add(Duration::from_millis(300))));
+        stop(&mut child);
+        // The sleep was stopped with the shell, the pipe closed long before it would end
+        assert!(stderr.wait(Instant::now().checked_add(STOPPED_OUTPUT_GRACE)));
+        assert!(started.elapsed() < Duration::from_secs(10));
+        assert_eq!(stderr.take(), b"waiting for a prompt\n");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_streamed_lines_arrive_while_running() {
+        let mut command = Command::new("sh");
+        command.args(["-c", "echo one; echo two; echo done >&2"]);
+        let mut lines = Vec::new();
+        let output = SystemRunner
+            .run_streaming(&mut command, &mut |line| lines.push(line.to_string()))
+            .unwrap();
+        assert_eq!(lines, ["one", "two"]);
+        assert_eq!(output.stdout, b"one\ntwo\n");
+        assert_eq!(output.stderr, b"done\n");
+    }
+
+    #[test]
+    fn test_dry_run_skips_changes() {
+        let error = DryRunRunner
+            .run(jj().args(["rebase", "-o", "main"]))
+            .unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<SkippedCommand>(),
+            Some(&SkippedCommand("jj rebase -o main".to_string()))
+        );
+    }
+}
@@ -0,0 +1,146 @@
+use std::{
+    fs::{
+        File,
+        OpenOptions,
+        TryLockError,
+    },
+    io::{
+        Read,
+        Write,
+    },
+    path::{
+        Path,
+        PathBuf,
+    },
+    sync::OnceLock,
+    thread,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+use anyhow::{
+    Context,
+    Result,
+};
+
+/// File names of the locks inside the `.jj` directory of the workspace
+const SESSION_LOCK_NAME: &str = "jjkk-session.lock";
+const TRANSACTION_LOCK_NAME: &str = "jjkk-transaction.lock";
+
+/// Lock every jj-lib transaction of this process takes first, set once the repo is opened
+static TRANSACTION_LOCK: OnceLock<PathBuf> = OnceLock::new();
+/// How long a transaction waits for the one of another jjkk, they take well under a second
+const TRANSACTION_LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+/// Pause between attempts to take the transaction lock
+const TRANSACTION_LOCK_RETRY: Duration = Duration::from_millis(50);
+
+/// Whether this is the only jjkk running on the workspace
+#[derive(Debug)]
+pub enum Session {
+    /// Nobody else runs jjkk here for as long as this is kept. The OS releases the lock when
+    /// jjkk exits, also after a crash, so it never goes stale
+    Exclusive { _lock: File },
+    /// Another jjkk already runs on the workspace, `pid` is its process id when it could be read
+    Shared { pid: Option<u32> },
+}
+
+/// Take the session lock of the workspace at `workspace_root`, or find who holds it
+pub fn lock_session(workspace_root: &Path) -> Result<Session> {
+    let path = workspace_root.join(".jj").join(SESSION_LOCK_NAME);
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(&path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+
+    match file.try_lock() {
+        Ok(()) => {
+            // Tells the next jjkk who is running, nothing depends on it being written
+            let _ = file
+                .set_len(0)
+                .and_then(|()| write!(file, "{}", std::process::id()));
+            Ok(Session::Exclusive { _lock: file })
+        }
+        Err(TryLockError::WouldBlock) => {
+            let mut contents = String::new();
+            let pid = file
+                .read_to_string(&mut contents)
+                .ok()
+                .and_then(|_| contents.trim().parse().ok());
+            Ok(Session::Shared { pid })
+        }
+        Err(TryLockError::Error(e)) => {
+            Err(e).with_context(|| format!("Failed to lock {}", path.display()))
+        }
+    }
+}
+
+/// Make jj-lib transactions wait for those of other jjkk processes on the workspace at
+/// `workspace_root` from now on
+pub fn coordinate_transactions(workspace_root: &Path) {
+    // Only the first call wins, jjkk never switches repos while running
+    let _ = TRANSACTION_LOCK.set(workspace_root.join(".jj").join(TRANSACTION_LOCK_NAME));
+}
+
+/// Wait until no other jjkk is in a transaction, held until the returned file is dropped.
+/// `None` when transactions are not coordinated. Fails after `TRANSACTION_LOCK_TIMEOUT`
+/// instead of freezing the ui behind a stuck jjkk
+pub fn lock_transactions() -> Result<Option<File>> {
+    let Some(path) = TRANSACTION_LOCK.get() else {
+        return Ok(None);
+    };
+    let file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+
+    let deadline = Instant::now() + TRANSACTION_LOCK_TIMEOUT;
+    loop {
+        match file.try_lock() {
+            Ok(()) => return Ok(Some(file)),
+            Err(TryLockError::WouldBlock) if Instant::now() < deadline => {
+                thread::sleep(TRANSACTION_LOCK_RETRY);
+            }
+            Err(TryLockError::WouldBlock) => anyhow::bail!(
+                "Another jjkk on this workspace has been changing it for over {} seconds, \
+                 try again once it is done",
+                TRANSACTION_LOCK_TIMEOUT.as_secs()
+            ),
+            Err(TryLockError::Error(e)) => {
+                return Err(e).with_context(|| format!("Failed to lock {}", path.display()));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_second_session_is_shared() {
+        let root = std::env::temp_dir().join(format!("jjkk-session-{}", std::process::id()));
+        std::fs::create_dir_all(root.join(".jj")).unwrap();
+
+        let first = lock_session(&root).unwrap();
+        assert!(matches!(first, Session::Exclusive { .. }));
+        // Locks are per open file, a second open in the same process conflicts like another jjkk
+        match lock_session(&root).unwrap() {
+            Session::Shared { pid } => assert_eq!(pid, Some(std::process::id())),
+            Session::Exclusive { .. } => panic!("the session lock was taken twice"),
+        }
+
+        drop(first);
+        assert!(matches!(
+            lock_session(&root).unwrap(),
+            Session::Exclusive { .. }
+        ));
+        let _ = std::fs::remove_dir_all(root);
+    }
+}
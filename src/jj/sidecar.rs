@@ -0,0 +1,133 @@
+use std::{
+    fs,
+    io::ErrorKind,
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+use anyhow::{
+    Context,
+    Result,
+};
+
+use super::journal::{
+    escape,
+    unescape,
+};
+
+/// What jjkk keeps in a file of its own next to the repo, a line of tab separated fields each
+pub trait SidecarEntry: Sized {
+    /// The entry stored as `fields`, `None` skips a line that is not one
+    fn from_fields(fields: &[String]) -> Option<Self>;
+
+    fn to_fields(&self) -> Vec<String>;
+}
+
+/// Entries stored in `.jj/<name>` of a workspace
+#[derive(Debug)]
+pub struct Sidecar<T> {
+    /// Where the entries are saved, `None` keeps them in memory only
+    file:    Option<PathBuf>,
+    entries: Vec<T>,
+}
+
+impl<T> Default for Sidecar<T> {
+    fn default() -> Self {
+        Self {
+            file:    None,
+            entries: Vec::new(),
+        }
+    }
+}
+
+impl<T: SidecarEntry> Sidecar<T> {
+    /// Entries of the file `name` in the `.jj` directory of `workspace_root`, none when it
+    /// was not written yet
+    pub fn load(workspace_root: &Path, name: &str) -> Result<Self> {
+        let file = workspace_root.join(".jj").join(name);
+        let entries = match fs::read_to_string(&file) {
+            Ok(contents) => parse(&contents),
+            Err(e) if e.kind() == ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", file.display())),
+        };
+        Ok(Self {
+            file: Some(file),
+            entries,
+        })
+    }
+
+    /// Entries kept in memory only
+    #[cfg(test)]
+    pub const fn in_memory(entries: Vec<T>) -> Self {
+        Self {
+            file: None,
+            entries,
+        }
+    }
+
+    pub fn entries(&self) -> &[T] {
+        &self.entries
+    }
+
+    /// Change the entries with `update` and save them
+    pub fn update(&mut self, update: impl FnOnce(&mut Vec<T>)) -> Result<()> {
+        update(&mut self.entries);
+        let Some(file) = &self.file else {
+            return Ok(());
+        };
+        fs::write(file, format(&self.entries))
+            .with_context(|| format!("Failed to write {}", file.display()))
+    }
+}
+
+fn parse<T: SidecarEntry>(contents: &str) -> Vec<T> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<String> = line.split('\t').map(unescape).collect();
+            T::from_fields(&fields)
+        })
+        .collect()
+}
+
+fn format<T: SidecarEntry>(entries: &[T]) -> String {
+    entries
+        .iter()
+        .map(|entry| {
+            let fields: Vec<String> = entry.to_fields().iter().map(|f| escape(f)).collect();
+            fields.join("\t") + "\n"
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    impl SidecarEntry for (String, String) {
+        fn from_fields(fields: &[String]) -> Option<Self> {
+            match fields {
+                [a, b] => Some((a.clone(), b.clone())),
+                _ => None,
+            }
+        }
+
+        fn to_fields(&self) -> Vec<String> {
+            vec![self.0.clone(), self.1.clone()]
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let entries = vec![
+            ("a\tb".to_string(), "two\nlines\\".to_string()),
+            (String::new(), "empty".to_string()),
+        ];
+        let contents = format(&entries);
+        assert_eq!(contents.lines().count(), 2);
+        // A line with the wrong number of fields is skipped
+        assert_eq!(parse::<(String, String)>(&(contents + "x\n")), entries);
+    }
+}
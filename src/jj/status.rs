@@ -1,47 +1,399 @@
-use std::process::Command;
-
 use anyhow::Result;
 
-use super::repo::{
-    ChangeType,
-    FileStatus,
+use super::{
+    background::{
+        BackgroundTask,
+        Update,
+    },
+    command::{
+        CommandExt,
+        jj,
+    },
+    error::command_failed,
+    repo::{
+        ChangeType,
+        FileStatus,
+    },
 };
 
-pub fn get_working_copy_status() -> Result<Vec<FileStatus>> {
-    let output = Command::new("jj").args(["status", "--no-pager"]).output()?;
+/// Lines of `jj status` output read between two partial results of a `StatusLoader`
+const PARTIAL_STATUS_LINES: usize = 500;
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let mut files = Vec::new();
+/// Changed files of the working copy, only those matching `fileset` when given
+pub fn get_working_copy_status(fileset: Option<&str>) -> Result<Vec<FileStatus>> {
+    let output = jj().arg("status").args(fileset).run()?;
 
-    for line in stdout.lines() {
-        if let Some(file_status) = parse_status_line(line) {
-            files.push(file_status);
-        }
+    if !output.status.success() {
+        return Err(command_failed("jj status", &output.stderr));
     }
 
+    let mut files = parse_status(&String::from_utf8_lossy(&output.stdout));
+    // The warning doesn't honor the fileset, a filtered list leaves them out
+    if fileset.is_none() {
+        add_refused_files(&mut files, &String::from_utf8_lossy(&output.stderr));
+    }
     Ok(files)
 }
 
-fn parse_status_line(line: &str) -> Option<FileStatus> {
-    let line = line.trim();
+/// What a `StatusLoader` picked up from its background thread
+#[derive(Debug)]
+pub enum StatusUpdate {
+    /// The files listed so far, more are on the way
+    Partial(Vec<FileStatus>),
+    Done(Result<Vec<FileStatus>>),
+}
 
-    // Parse "A file.txt" or "M file.txt" or "D file.txt" format
-    if line.len() < 3 {
-        return None;
+/// Loads the working copy status on a background thread, passing on the files as jj lists
+/// them so huge working copies fill the file list while `jj status` is still running
+#[derive(Default)]
+pub struct StatusLoader {
+    task: BackgroundTask<Result<Vec<FileStatus>>, Vec<FileStatus>>,
+}
+
+impl StatusLoader {
+    /// Start loading the status, dropping the result of any earlier load
+    pub fn start(&mut self, fileset: Option<String>) {
+        self.task
+            .start_reporting(move |report| stream_status(fileset.as_deref(), report));
     }
 
-    let status_char = line.chars().next()?;
+    pub fn cancel(&mut self) {
+        self.task.cancel();
+    }
+
+    pub const fn is_loading(&self) -> bool {
+        self.task.is_running()
+    }
+
+    /// The newest update of the background thread, if there is one
+    pub fn poll(&mut self) -> Option<StatusUpdate> {
+        Some(match self.task.poll_update()? {
+            Update::Progress(files) => StatusUpdate::Partial(files),
+            Update::Done(result, _) => StatusUpdate::Done(result),
+        })
+    }
+}
+
+/// Run `jj status`, reporting the files parsed so far every `PARTIAL_STATUS_LINES` lines
+fn stream_status(
+    fileset: Option<&str>,
+    report: &dyn Fn(Vec<FileStatus>),
+) -> Result<Vec<FileStatus>> {
+    let mut parser = StatusParser::default();
+    let mut lines = 0;
+    let output = jj()
+        .arg("status")
+        .args(fileset)
+        .run_streaming(&mut |line| {
+            parser.push_line(line);
+            lines += 1;
+            if lines % PARTIAL_STATUS_LINES == 0 {
+                report(parser.files.clone());
+            }
+        })?;
+
+    if !output.status.success() {
+        return Err(command_failed("jj status", &output.stderr));
+    }
+
+    let mut files = parser.files;
+    if fileset.is_none() {
+        add_refused_files(&mut files, &String::from_utf8_lossy(&output.stderr));
+    }
+    Ok(files)
+}
+
+/// Files over the snapshot size limit are only mentioned in a warning on stderr
+fn add_refused_files(files: &mut Vec<FileStatus>, stderr: &str) {
+    for (path, size) in parse_refused_files(stderr) {
+        if !files.iter().any(|file| file.path == path) {
+            files.push(FileStatus {
+                path,
+                status: ChangeType::TooLarge { size },
+                old_path: None,
+            });
+        }
+    }
+}
+
+/// Which part of the `jj status` output is being parsed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum Section {
+    #[default]
+    Other,
+    Changes,
+    Conflicts,
+}
+
+/// Parses `jj status` output a line at a time
+#[derive(Debug, Default)]
+struct StatusParser {
+    section: Section,
+    files:   Vec<FileStatus>,
+}
+
+impl StatusParser {
+    fn push_line(&mut self, line: &str) {
+        let trimmed = line.trim();
+
+        if trimmed.starts_with("Working copy changes:") {
+            self.section = Section::Changes;
+            return;
+        }
+        // Newer versions prefix the header with "Warning: "
+        if trimmed.contains("unresolved conflicts at these paths:") {
+            self.section = Section::Conflicts;
+            return;
+        }
+
+        match self.section {
+            Section::Changes => {
+                if let Some(file_status) = parse_status_line(trimmed) {
+                    self.files.push(file_status);
+                } else {
+                    self.section = Section::Other;
+                }
+            }
+            Section::Conflicts => {
+                let Some(path) = parse_conflict_line(trimmed) else {
+                    self.section = Section::Other;
+                    return;
+                };
+
+                // Conflicted files usually also show up as modified, keep a single entry
+                if let Some(existing) = self.files.iter_mut().find(|file| file.path == path) {
+                    existing.status = ChangeType::Conflicted;
+                } else {
+                    self.files.push(FileStatus {
+                        path,
+                        status: ChangeType::Conflicted,
+                        old_path: None,
+                    });
+                }
+            }
+            Section::Other => {}
+        }
+    }
+}
+
+/// Files listed in the complete output of `jj status`
+pub fn parse_status(output: &str) -> Vec<FileStatus> {
+    let mut parser = StatusParser::default();
+    for line in output.lines() {
+        parser.push_line(line);
+    }
+    parser.files
+}
+
+fn parse_status_line(line: &str) -> Option<FileStatus> {
+    // Parse "A file.txt", "M file.txt", "D file.txt" and
+    // "R {old => new}.txt" / "C dir/{a => b}.txt" format
+    let (status_char, path) = line.split_once(' ')?;
     let change_type = match status_char {
-        'A' => ChangeType::Added,
-        'M' => ChangeType::Modified,
-        'D' => ChangeType::Deleted,
+        "A" => ChangeType::Added,
+        "M" => ChangeType::Modified,
+        "D" => ChangeType::Deleted,
+        "R" => ChangeType::Renamed,
+        "C" => ChangeType::Copied,
         _ => return None,
     };
 
-    let path = line[1..].trim().to_string();
+    let path = path.trim();
+    if path.is_empty() {
+        return None;
+    }
+
+    if matches!(change_type, ChangeType::Renamed | ChangeType::Copied)
+        && let Some((old_path, new_path)) = expand_rename(path)
+    {
+        return Some(FileStatus {
+            path:     new_path,
+            status:   change_type,
+            old_path: Some(old_path),
+        });
+    }
 
     Some(FileStatus {
-        path,
-        status: change_type,
+        path:     path.to_string(),
+        status:   change_type,
+        old_path: None,
     })
 }
+
+/// Expand jj's compact rename notation `dir/{old => new}/file` into the
+/// old and the new path
+fn expand_rename(path: &str) -> Option<(String, String)> {
+    let open = path.find('{')?;
+    let close = open + path[open..].find('}')?;
+    let (old_part, new_part) = path[open + 1..close].split_once(" => ")?;
+
+    let prefix = &path[..open];
+    let suffix = &path[close + 1..];
+    let join = |middle: &str| {
+        // An empty side like `{ => sub}/` leaves a doubled separator behind
+        let joined = format!("{prefix}{middle}{suffix}");
+        joined.replace("//", "/").replace("\\\\", "\\")
+    };
+
+    Some((join(old_part.trim()), join(new_part.trim())))
+}
+
+/// Files listed below jj's "Refused to snapshot some files" warning, with their size in bytes
+fn parse_refused_files(stderr: &str) -> Vec<(String, u64)> {
+    let mut files = Vec::new();
+    let mut in_list = false;
+
+    for line in stderr.lines() {
+        if line.contains("Refused to snapshot some files:") {
+            in_list = true;
+            continue;
+        }
+        if !in_list {
+            continue;
+        }
+        match line.strip_prefix("  ").and_then(parse_refused_line) {
+            Some(file) => files.push(file),
+            None => in_list = false,
+        }
+    }
+
+    files
+}
+
+/// Parse "big.bin: 1.2MiB (1258291 bytes); the maximum size allowed is ..." lines
+fn parse_refused_line(line: &str) -> Option<(String, u64)> {
+    let bytes_end = line.find(" bytes)")?;
+    let bytes_start = line[..bytes_end].rfind('(')? + 1;
+    let size = line[bytes_start..bytes_end].parse().ok()?;
+    let path_end = line[..bytes_start].rfind(": ")?;
+    Some((line[..path_end].to_string(), size))
+}
+
+/// Parse "src/file.rs    2-sided conflict" style lines
+fn parse_conflict_line(line: &str) -> Option<String> {
+    if line.is_empty() || !line.contains("conflict") {
+        return None;
+    }
+
+    // The path is separated from the description by at least two spaces
+    let path = line.split_once("  ").map_or(line, |(path, _)| path).trim();
+    (!path.is_empty()).then(|| path.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // jj 0.2x style output
+    const STATUS_SIMPLE: &str = "\
+Working copy changes:
+A src/new.rs
+M src/main.rs
+D old.txt
+Working copy  (@) : kxqpwmlu 1a2b3c4d Add things
+Parent commit (@-): zzzzzzzz 00000000 (empty) (no description set)
+";
+
+    const STATUS_RENAMES: &str = "\
+Working copy changes:
+R src/{app.rs => application.rs}
+C {a.txt => b.txt}
+R src/{ => ui}/layout.rs
+Working copy  (@) : kxqpwmlu 1a2b3c4d Move things around
+Parent commit (@-): zzzzzzzz 00000000 main | Initial commit
+";
+
+    // jj 0.3x style output with a conflict warning section
+    const STATUS_CONFLICTS: &str = "\
+Working copy changes:
+M src/lib.rs
+A notes.md
+Working copy  (@) : kxqpwmlu 1a2b3c4d (conflict) Rebased change
+Parent commit (@-): zzzzzzzz 00000000 main | Initial commit
+Warning: There are unresolved conflicts at these paths:
+src/lib.rs    2-sided conflict
+src/other.rs    2-sided conflict including 1 deletion
+";
+
+    const STATUS_CLEAN: &str = "\
+The working copy has no changes.
+Working copy  (@) : kxqpwmlu 1a2b3c4d (empty) (no description set)
+Parent commit (@-): zzzzzzzz 00000000 main | Initial commit
+";
+
+    #[test]
+    fn test_parse_simple_status() {
+        let files = parse_status(STATUS_SIMPLE);
+        let parsed: Vec<_> = files.iter().map(|f| (f.status, f.path.as_str())).collect();
+        assert_eq!(
+            parsed,
+            vec![
+                (ChangeType::Added, "src/new.rs"),
+                (ChangeType::Modified, "src/main.rs"),
+                (ChangeType::Deleted, "old.txt"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_renames_and_copies() {
+        let files = parse_status(STATUS_RENAMES);
+        assert_eq!(files.len(), 3);
+
+        assert_eq!(files[0].status, ChangeType::Renamed);
+        assert_eq!(files[0].path, "src/application.rs");
+        assert_eq!(files[0].old_path.as_deref(), Some("src/app.rs"));
+
+        assert_eq!(files[1].status, ChangeType::Copied);
+        assert_eq!(files[1].path, "b.txt");
+        assert_eq!(files[1].old_path.as_deref(), Some("a.txt"));
+
+        assert_eq!(files[2].path, "src/ui/layout.rs");
+        assert_eq!(files[2].old_path.as_deref(), Some("src/layout.rs"));
+    }
+
+    #[test]
+    fn test_parse_conflicts() {
+        let files = parse_status(STATUS_CONFLICTS);
+        let parsed: Vec<_> = files.iter().map(|f| (f.status, f.path.as_str())).collect();
+        assert_eq!(
+            parsed,
+            vec![
+                (ChangeType::Conflicted, "src/lib.rs"),
+                (ChangeType::Added, "notes.md"),
+                (ChangeType::Conflicted, "src/other.rs"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_clean_status() {
+        assert!(parse_status(STATUS_CLEAN).is_empty());
+    }
+
+    #[test]
+    fn test_description_lines_are_not_files() {
+        // "A" followed by a space outside of the changes section must be ignored
+        let output = "Working copy  (@) : kxqpwmlu 1a2b3c4d\nA line that looks like a file\n";
+        assert!(parse_status(output).is_empty());
+    }
+
+    #[test]
+    fn test_parse_refused_files() {
+        let stderr = "\
+Warning: Refused to snapshot some files:
+  assets/video.mp4: 12.0MiB (12582912 bytes); the maximum size allowed is 1.0MiB (1048576 bytes)
+  odd: name.bin: 2.0MiB (2097152 bytes); the maximum size allowed is 1.0MiB (1048576 bytes)
+Hint: This is to prevent large files from being added by accident. You can fix this by:
+  - Adding the file to `.gitignore`
+";
+        assert_eq!(
+            parse_refused_files(stderr),
+            vec![
+                ("assets/video.mp4".to_string(), 12_582_912),
+                ("odd: name.bin".to_string(), 2_097_152),
+            ]
+        );
+        assert!(parse_refused_files("Warning: something else\n  - a hint\n").is_empty());
+    }
+}
@@ -0,0 +1,190 @@
+//! Throwaway jj repos with scripted histories, so operations can be tested against a real jj
+//! without touching the repo the tests run in. Tests using them need jj and git on `PATH` and
+//! are skipped when jj is not installed
+
+use std::{
+    fs,
+    path::{
+        Path,
+        PathBuf,
+    },
+    process::{
+        Command,
+        Output,
+    },
+    sync::{
+        Arc,
+        atomic::{
+            AtomicUsize,
+            Ordering,
+        },
+    },
+};
+
+use anyhow::Result;
+
+use super::runner::{
+    CommandRunner,
+    SystemRunner,
+    with_runner,
+};
+
+/// Tells the repos of one test run apart
+static NEXT_REPO: AtomicUsize = AtomicUsize::new(0);
+
+/// A jj repo in the temp directory, removed again when dropped
+pub struct TestRepo {
+    root: PathBuf,
+}
+
+impl TestRepo {
+    /// An empty repo, `None` when jj is not installed and the test should be skipped
+    pub fn new() -> Option<Self> {
+        if Command::new("jj").arg("--version").output().is_err() {
+            eprintln!("jj is not installed, skipping a test that needs a real repo");
+            return None;
+        }
+
+        let root = std::env::temp_dir().join(format!(
+            "jjkk-test-{}-{}",
+            std::process::id(),
+            NEXT_REPO.fetch_add(1, Ordering::Relaxed)
+        ));
+        let repo = Self { root };
+        fs::create_dir_all(repo.root.join("repo")).expect("create the test repo");
+        // The user's config must not change what the tests see
+        fs::write(
+            repo.config_path(),
+            "[user]\nname = \"Test User\"\nemail = \"test@example.com\"\n",
+        )
+        .expect("write the test config");
+        repo.jj(&["git", "init"]);
+        Some(repo)
+    }
+
+    /// The workspace, where the operations under test run
+    pub fn path(&self) -> PathBuf {
+        self.root.join("repo")
+    }
+
+    /// The only user config of the repo, used instead of `~/.config/jj`
+    pub fn config_path(&self) -> PathBuf {
+        self.root.join("config.toml")
+    }
+
+    /// Run jj in the repo with a fixed identity, failing the test when it fails
+    pub fn jj(&self, args: &[&str]) -> String {
+        let output = isolate(
+            Command::new("jj").args(args),
+            &self.path(),
+            &self.config_path(),
+        )
+        .output()
+        .expect("run jj");
+        assert!(
+            output.status.success(),
+            "jj {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8_lossy(&output.stdout).to_string()
+    }
+
+    /// Write `content` to `path` in the working copy
+    pub fn write(&self, path: &str, content: &str) -> &Self {
+        let path = self.path().join(path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).expect("create the parent directory");
+        }
+        fs::write(path, content).expect("write the file");
+        self
+    }
+
+    /// Commit the working copy, like `jj commit -m <message>`
+    pub fn commit(&self, message: &str) -> &Self {
+        self.jj(&["commit", "-m", message]);
+        self
+    }
+
+    /// Start a new change on `revision`
+    pub fn new_on(&self, revision: &str) -> &Self {
+        self.jj(&["new", revision]);
+        self
+    }
+
+    /// Create bookmark `name` at `revision`
+    pub fn bookmark(&self, name: &str, revision: &str) -> &Self {
+        self.jj(&["bookmark", "create", name, "-r", revision]);
+        self
+    }
+
+    /// Two changes on top of `@-` that edit `path` differently and their merge, which
+    /// is left conflicted as the working copy
+    pub fn conflict(&self, path: &str) -> &Self {
+        let base = self.jj(&["log", "--no-graph", "-r", "@-", "-T", "change_id"]);
+        self.write(path, "ours\n").commit("Ours");
+        let ours = self.jj(&["log", "--no-graph", "-r", "@-", "-T", "change_id"]);
+        self.new_on(&base).write(path, "theirs\n").commit("Theirs");
+        self.jj(&["new", &ours, "@-"]);
+        self
+    }
+
+    /// Add a bare git repo with the bookmarks as they are now as remote `name` and fetch
+    /// from it, so the bookmarks exist on the remote too
+    pub fn remote(&self, name: &str) -> &Self {
+        let bare = self.root.join(format!("{name}.git"));
+        // Newer jj colocates by default, older ones keep the git repo in their own store
+        let colocated = self.path().join(".git");
+        let git_repo = if colocated.is_dir() {
+            colocated
+        } else {
+            self.path().join(".jj/repo/store/git")
+        };
+        let status = Command::new("git")
+            .args(["clone", "--bare", "--quiet"])
+            .arg(&git_repo)
+            .arg(&bare)
+            .status()
+            .expect("run git clone");
+        assert!(status.success(), "git clone of the test repo failed");
+        self.jj(&["git", "remote", "add", name, &bare.to_string_lossy()]);
+        self.jj(&["git", "fetch", "--remote", name]);
+        self
+    }
+
+    /// Run `f` with every jj command of `jj::operations` going to this repo
+    pub fn run<T>(&self, f: impl FnOnce() -> T) -> T {
+        let runner = RepoRunner {
+            workspace: self.path(),
+            config:    self.config_path(),
+        };
+        with_runner(Arc::new(runner), f)
+    }
+}
+
+impl Drop for TestRepo {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
+
+/// Point `command` at the test repo, with only the test config and a fixed identity
+fn isolate<'a>(command: &'a mut Command, workspace: &Path, config: &Path) -> &'a mut Command {
+    command
+        .current_dir(workspace)
+        .env("JJ_CONFIG", config)
+        .env("JJ_USER", "Test User")
+        .env("JJ_EMAIL", "test@example.com")
+}
+
+/// Runs the commands of the code under test in a `TestRepo`
+struct RepoRunner {
+    workspace: PathBuf,
+    config:    PathBuf,
+}
+
+impl CommandRunner for RepoRunner {
+    fn run(&self, command: &mut Command) -> Result<Output> {
+        SystemRunner.run(isolate(command, &self.workspace, &self.config))
+    }
+}
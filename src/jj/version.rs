@@ -0,0 +1,131 @@
+use std::{
+    fmt,
+    sync::OnceLock,
+};
+
+use anyhow::{
+    Context,
+    Result,
+};
+
+use super::command::{
+    CommandExt,
+    jj,
+};
+
+/// Oldest jj release whose output jjkk knows how to parse
+pub const MINIMUM_SUPPORTED: JjVersion = JjVersion::new(0, 28, 0);
+
+/// `jj rebase` renamed `--destination` to `--onto`
+const REBASE_ONTO: JjVersion = JjVersion::new(0, 36, 0);
+/// `jj bookmark track` gained the `--remote` flag
+const TRACK_REMOTE_FLAG: JjVersion = JjVersion::new(0, 35, 0);
+
+/// Version of the jj cli detected at startup
+static DETECTED: OnceLock<JjVersion> = OnceLock::new();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct JjVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl JjVersion {
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    /// Parse the output of `jj --version`, e.g. `jj 0.39.0-1a2b3c4d`
+    pub fn parse(output: &str) -> Option<Self> {
+        let version = output.split_whitespace().nth(1)?;
+        // drop build metadata and pre-release suffixes
+        let version = version.split(['-', '+']).next()?;
+
+        let mut parts = version.split('.').map(str::parse::<u32>);
+        let major = parts.next()?.ok()?;
+        let minor = parts.next()?.ok()?;
+        let patch = parts.next().and_then(Result::ok).unwrap_or(0);
+
+        Some(Self::new(major, minor, patch))
+    }
+
+    pub fn is_supported(self) -> bool {
+        self >= MINIMUM_SUPPORTED
+    }
+}
+
+impl fmt::Display for JjVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// Run `jj --version` and remember the result for the compatibility checks
+pub fn detect() -> Result<JjVersion> {
+    let output = jj()
+        .arg("--version")
+        .run()
+        .context("Failed to run jj, is it installed and on your PATH?")?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let version = JjVersion::parse(&stdout)
+        .with_context(|| format!("Unrecognized jj version output: {}", stdout.trim()))?;
+
+    let _ = DETECTED.set(version);
+    Ok(version)
+}
+
+/// Whether the detected jj is at least `version`.
+/// Without a detected version the newest behavior is assumed
+fn at_least(version: JjVersion) -> bool {
+    DETECTED.get().is_none_or(|detected| *detected >= version)
+}
+
+/// Flag naming the destination of `jj rebase`
+pub fn rebase_destination_flag() -> &'static str {
+    if at_least(REBASE_ONTO) {
+        "--onto"
+    } else {
+        "--destination"
+    }
+}
+
+/// Arguments selecting a remote bookmark for `jj bookmark track`
+pub fn track_args(name: &str, remote: &str) -> Vec<String> {
+    if at_least(TRACK_REMOTE_FLAG) {
+        vec![name.to_string(), format!("--remote={remote}")]
+    } else {
+        vec![format!("{name}@{remote}")]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(
+            JjVersion::parse("jj 0.39.0-8d6f9bb3d1e6b1a4a7f8e1d5c6b7a8f9e0d1c2b3\n"),
+            Some(JjVersion::new(0, 39, 0))
+        );
+        assert_eq!(
+            JjVersion::parse("jj 0.25.0"),
+            Some(JjVersion::new(0, 25, 0))
+        );
+        assert_eq!(JjVersion::parse("jj 1.2"), Some(JjVersion::new(1, 2, 0)));
+        assert_eq!(JjVersion::parse("not jj"), None);
+    }
+
+    #[test]
+    fn test_minimum_supported() {
+        assert!(!JjVersion::new(0, 20, 0).is_supported());
+        assert!(JjVersion::new(0, 39, 0).is_supported());
+        assert!(JjVersion::new(1, 0, 0).is_supported());
+    }
+}
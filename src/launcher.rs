@@ -0,0 +1,123 @@
+use std::{
+    path::Path,
+    process::{
+        Command,
+        Stdio,
+    },
+    thread,
+};
+
+use anyhow::{
+    Context,
+    Result,
+};
+
+/// Placeholders in configured commands, replaced by the directory or file and the line
+const PATH_PLACEHOLDER: &str = "{path}";
+const LINE_PLACEHOLDER: &str = "{line}";
+
+/// Show `dir` in the file manager, `command` replaces the one of the platform
+pub fn open_file_manager(command: Option<&str>, dir: &Path) -> Result<()> {
+    let default = if cfg!(target_os = "macos") {
+        "open"
+    } else if cfg!(windows) {
+        "explorer"
+    } else {
+        "xdg-open"
+    };
+    spawn(&build(command.unwrap_or(default), dir), dir)
+}
+
+/// Start a new terminal window in `dir`, `command` replaces the one of the platform
+pub fn open_terminal(command: Option<&str>, dir: &Path) -> Result<()> {
+    let default = if cfg!(target_os = "macos") {
+        "open -a Terminal".to_string()
+    } else if cfg!(windows) {
+        "cmd /C start cmd".to_string()
+    } else {
+        // The terminal the user picked, or the one the distribution set up
+        std::env::var("TERMINAL").unwrap_or_else(|_| "x-terminal-emulator".to_string())
+    };
+    let command = command.unwrap_or(&default);
+    // Terminals open in the directory they are started in, only macOS' `open` needs the path
+    let command = if command.contains(PATH_PLACEHOLDER) || command.starts_with("open ") {
+        build(command, dir)
+    } else {
+        command.split_whitespace().map(str::to_string).collect()
+    };
+    spawn(&command, dir)
+}
+
+/// Edit `path` at `line` in the terminal and wait for the editor to exit. `command` takes
+/// `{path}` and `{line}`, `$VISUAL` or `$EDITOR` opened at `+<line>` when not set
+pub fn edit(command: Option<&str>, path: &Path, line: usize) -> Result<String> {
+    let default = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .map_or_else(
+            |_| "vi +{line} {path}".to_string(),
+            |editor| format!("{editor} +{{line}} {{path}}"),
+        );
+    let command = command
+        .unwrap_or(&default)
+        .replace(LINE_PLACEHOLDER, &line.to_string());
+    let args = build(&command, path);
+    let (program, args) = args
+        .split_first()
+        .context("The configured editor is empty")?;
+
+    let status = Command::new(program)
+        .args(args)
+        .status()
+        .with_context(|| format!("Failed to start {program}"))?;
+    if !status.success() {
+        anyhow::bail!("{program} failed with {status}");
+    }
+    Ok(format!("Edited {}:{line}", path.display()))
+}
+
+/// Split `command` into its arguments, with `path` in place of the placeholder or at the end
+fn build(command: &str, path: &Path) -> Vec<String> {
+    let path = path.to_string_lossy();
+    let mut args: Vec<String> = command
+        .split_whitespace()
+        .map(|arg| arg.replace(PATH_PLACEHOLDER, &path))
+        .collect();
+    if !command.contains(PATH_PLACEHOLDER) {
+        args.push(path.to_string());
+    }
+    args
+}
+
+/// Start `args` in `dir` without waiting for it. It is waited for on a thread of its own, so
+/// it does not stay behind as a zombie after it exits
+fn spawn(args: &[String], dir: &Path) -> Result<()> {
+    let (program, args) = args
+        .split_first()
+        .context("The configured command is empty")?;
+    // Whatever it prints would end up on top of the ui
+    let mut child = Command::new(program)
+        .args(args)
+        .current_dir(dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .with_context(|| format!("Failed to start {program}"))?;
+    thread::spawn(move || child.wait());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_command() {
+        let dir = Path::new("/work/repo");
+        assert_eq!(build("nautilus", dir), ["nautilus", "/work/repo"]);
+        assert_eq!(
+            build("wezterm start --cwd {path}", dir),
+            ["wezterm", "start", "--cwd", "/work/repo"]
+        );
+    }
+}
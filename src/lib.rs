@@ -0,0 +1,36 @@
+//! What the benchmarks in `benches/` measure, built with the `bench` feature only. The binary
+//! declares its modules in `main.rs` itself, nothing here is a stable api
+#![cfg(feature = "bench")]
+// Only what the benchmarks reach is used or documented from here
+#![allow(dead_code, missing_docs)]
+
+mod actions;
+mod app;
+mod cli;
+mod clipboard;
+mod config;
+mod exec;
+mod forge;
+mod jj;
+mod launcher;
+mod notifications;
+mod ui;
+
+/// The paths every refresh and frame goes through
+pub mod bench {
+    pub use crate::{
+        app::App,
+        cli::Args,
+        jj::{
+            repo::{
+                ChangeType,
+                FileStatus,
+            },
+            status::parse_status,
+        },
+        ui::{
+            layout::render_ui,
+            widgets::diff_view::highlight,
+        },
+    };
+}
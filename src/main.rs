@@ -1,18 +1,42 @@
 //! jjkk - A terminal UI for the jj version control system
 
+mod actions;
 mod app;
+mod cli;
+mod clipboard;
 mod config;
+mod exec;
+mod forge;
 mod jj;
+mod launcher;
+mod notifications;
 mod ui;
 
-use std::io;
+use std::{
+    io::{
+        self,
+        Write,
+    },
+    path::Path,
+    process::Command,
+    time::Duration,
+};
 
 use anyhow::Result;
-use app::App;
+use app::{
+    App,
+    TerminalAction,
+};
+use cli::Args;
+use config::settings::Settings;
 use crossterm::{
     event::{
         self,
+        DisableBracketedPaste,
+        DisableFocusChange,
         DisableMouseCapture,
+        EnableBracketedPaste,
+        EnableFocusChange,
         EnableMouseCapture,
         Event,
     },
@@ -35,29 +59,52 @@ use ui::layout::render_ui;
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    // git and ssh start jjkk again to ask for credentials, answer them and exit
+    if let Some(code) = jj::askpass::answer_prompt() {
+        std::process::exit(code);
+    }
+
+    let args = Args::parse();
+    if let Some(exec_args) = &args.exec {
+        std::process::exit(exec::main(exec_args));
+    }
+
+    // Git users trying jj start in their clone, set jj up there before the ui takes over
+    if let Some(root) = jj::repo::git_only_root(&std::env::current_dir()?) {
+        if !offer_git_init(&root)? {
+            return Ok(());
+        }
+        // jjkk runs at the root of the workspace, like when started there
+        std::env::set_current_dir(&root)?;
+    }
+
     // Setup terminal
-    enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
-    let backend = CrosstermBackend::new(stdout);
+    enter_terminal()?;
+    let backend = CrosstermBackend::new(io::stdout());
     let mut terminal = Terminal::new(backend)?;
 
     // Create app state
-    let mut app = App::new()?;
+    let mut app = App::new(&args)?;
+
+    app.load_highlighting();
 
-    // Load initial status, bookmarks, and log
-    app.refresh_all()?;
+    // Make sure the jj cli is usable before parsing any of its output
+    app.check_jj_version();
+    app.check_other_instance();
+
+    // Load initial status, bookmarks, and log, the status streams in while drawing
+    app.start_refresh_all();
+
+    if args.recover {
+        app.show_journal();
+    }
 
     // Run the application
     let res = run_app(&mut terminal, &mut app);
 
     // Restore terminal
-    disable_raw_mode()?;
-    execute!(
-        terminal.backend_mut(),
-        LeaveAlternateScreen,
-        DisableMouseCapture
-    )?;
+    leave_terminal()?;
+    jj::askpass::shut_down();
     terminal.show_cursor()?;
 
     if let Err(err) = res {
@@ -67,21 +114,98 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
+/// How long to wait for input before checking the background work again
+const FOCUSED_POLL_INTERVAL: Duration = Duration::from_millis(8);
+/// Waiting longer keeps a jjkk in a background pane from spinning the CPU
+const UNFOCUSED_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+fn handle_event<B: Backend>(
+    terminal: &mut Terminal<B>,
+    app: &mut App,
+    event: &Event,
+) -> Result<()> {
+    match event {
+        Event::Key(key) => {
+            app.handle_key_event(*key)?;
+            app.needs_redraw = true; // Mark for redraw after handling input
+        }
+        Event::Mouse(mouse) => {
+            app.handle_mouse_event(*mouse);
+            app.needs_redraw = true;
+        }
+        Event::Resize(..) => {
+            terminal.autoresize()?;
+            app.handle_resize();
+        }
+        Event::FocusLost => app.handle_focus_lost(),
+        Event::FocusGained => app.handle_focus_gained(),
+        Event::Paste(text) => {
+            app.handle_paste(text);
+            app.needs_redraw = true;
+        }
+    }
+    Ok(())
+}
+
 fn run_app<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> Result<()> {
     loop {
+        // Nothing changes on screen while nobody looks, checking for changes waits for focus
+        let focused = app.is_focused();
         app.update_status_message_timeout();
+        if focused {
+            app.check_external_changes();
+            app.check_clock();
+        }
+        app.poll_highlighting();
+        app.poll_status();
+        app.poll_interop();
+        app.poll_push()?;
+
+        // Load the diff only after a burst of j/k, the selection itself follows every key
+        if !event::poll(Duration::ZERO)? {
+            app.update_pending_diff()?;
+        }
 
         // Only draw if needed or when loading spinner is active
-        if app.needs_redraw || app.loading_message.is_some() {
+        if app.needs_redraw || (focused && app.loading_message.is_some()) {
             terminal.draw(|f| render_ui(f, app))?;
             app.needs_redraw = false;
+            app.clamp_scroll_offsets();
         }
 
-        if event::poll(std::time::Duration::from_millis(8))?
-            && let Event::Key(key) = event::read()?
-        {
-            app.handle_key_event(key)?;
-            app.needs_redraw = true; // Mark for redraw after handling input
+        let timeout = if focused {
+            FOCUSED_POLL_INTERVAL
+        } else {
+            UNFOCUSED_POLL_INTERVAL
+        };
+        if event::poll(timeout)? {
+            // Handle everything typed since the last frame in order before drawing again,
+            // a frame per key makes fast typing lag behind
+            loop {
+                handle_event(terminal, app, &event::read()?)?;
+                if app.should_quit || app.has_terminal_action() || !event::poll(Duration::ZERO)? {
+                    break;
+                }
+            }
+        }
+
+        if let Some(action) = app.take_terminal_action() {
+            leave_terminal()?;
+            let result = match &action {
+                TerminalAction::Suspend => suspend(),
+                TerminalAction::Shell { command } => run_shell_command(command),
+                TerminalAction::Resolve { path, tool } => {
+                    jj::operations::resolve_conflict(path, tool.as_deref())
+                }
+                TerminalAction::Edit { path, line, editor } => {
+                    launcher::edit(editor.as_deref(), path, *line)
+                }
+            };
+            enter_terminal()?;
+            // Whatever the shell left on the screen is gone, draw everything again
+            terminal.clear()?;
+            app.finish_terminal_action(&action, result)?;
+            app.needs_redraw = true;
         }
 
         if app.should_quit {
@@ -91,3 +215,92 @@ fn run_app<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> Result<()>
 
     Ok(())
 }
+
+fn enter_terminal() -> Result<()> {
+    enable_raw_mode()?;
+    execute!(
+        io::stdout(),
+        EnterAlternateScreen,
+        EnableMouseCapture,
+        EnableFocusChange
+    )?;
+    // Not supported by the legacy Windows console, pastes are typed in key by key there
+    let _ = execute!(io::stdout(), EnableBracketedPaste);
+    Ok(())
+}
+
+/// Give the terminal back to the shell
+fn leave_terminal() -> Result<()> {
+    disable_raw_mode()?;
+    execute!(
+        io::stdout(),
+        LeaveAlternateScreen,
+        DisableMouseCapture,
+        DisableFocusChange
+    )?;
+    let _ = execute!(io::stdout(), DisableBracketedPaste);
+    Ok(())
+}
+
+/// Stop like a shell job, this returns once the shell resumes jjkk with `fg`
+#[cfg(unix)]
+fn suspend() -> Result<String> {
+    use rustix::process::{
+        Signal,
+        getpid,
+        kill_process,
+    };
+    kill_process(getpid(), Signal::TSTP)?;
+    Ok("Resumed".to_string())
+}
+
+#[cfg(not(unix))]
+fn suspend() -> Result<String> {
+    anyhow::bail!("Suspending is only supported on unix")
+}
+
+/// Ask whether to run `jj git init --colocate` in the current git repository,
+/// false when the user declined
+fn offer_git_init(root: &Path) -> Result<bool> {
+    if let Some(jj_executable) = Settings::load()?.jj_executable {
+        jj::command::set_executable(jj_executable);
+    }
+
+    print!(
+        "The git repository at {} is not set up for jj yet. Run `jj git init --colocate` there? [y/N] ",
+        root.display()
+    );
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    if !matches!(answer.trim(), "y" | "Y" | "yes") {
+        println!("jjkk needs a jj repository, nothing was changed");
+        return Ok(false);
+    }
+
+    print!("{}", jj::operations::git_init_colocate(root)?);
+    Ok(true)
+}
+
+/// Run `command` in the user's shell and wait for Enter, so its output can be read
+fn run_shell_command(command: &str) -> Result<String> {
+    let (shell, flag) = if cfg!(windows) {
+        ("cmd".to_string(), "/C")
+    } else {
+        (
+            std::env::var("SHELL").unwrap_or_else(|_| "sh".to_string()),
+            "-c",
+        )
+    };
+
+    println!("$ {command}");
+    let status = Command::new(shell).args([flag, command]).status()?;
+    print!("\n[{status}] Press Enter to return to jjkk");
+    io::stdout().flush()?;
+    io::stdin().read_line(&mut String::new())?;
+
+    if !status.success() {
+        anyhow::bail!("{command} failed with {status}");
+    }
+    Ok(format!("Ran {command}"))
+}
@@ -0,0 +1,72 @@
+use std::{
+    collections::VecDeque,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+/// How many notifications are kept around before the oldest are dropped
+const MAX_NOTIFICATIONS: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationLevel {
+    Info,
+    Warning,
+    Error,
+}
+
+/// A single entry in the notification log
+#[derive(Debug, Clone)]
+pub struct Notification {
+    pub message:   String,
+    pub level:     NotificationLevel,
+    pub timestamp: Instant,
+    /// How long the jj operation behind this notification took, if any
+    pub duration:  Option<Duration>,
+}
+
+/// History of status messages, warnings and errors of this session
+#[derive(Debug, Default)]
+pub struct NotificationLog {
+    entries: VecDeque<Notification>,
+}
+
+impl NotificationLog {
+    pub fn push(
+        &mut self,
+        level: NotificationLevel,
+        message: impl Into<String>,
+        duration: Option<Duration>,
+    ) {
+        if self.entries.len() == MAX_NOTIFICATIONS {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(Notification {
+            message: message.into(),
+            level,
+            timestamp: Instant::now(),
+            duration,
+        });
+    }
+
+    /// Newest notifications first
+    pub fn iter_newest(&self) -> impl Iterator<Item = &Notification> {
+        self.entries.iter().rev()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// Format a duration for humans, e.g. `340ms` or `2.4s`
+pub fn format_duration(duration: Duration) -> String {
+    if duration.as_secs() == 0 {
+        format!("{}ms", duration.as_millis())
+    } else if duration.as_secs() < 60 {
+        format!("{:.1}s", duration.as_secs_f64())
+    } else {
+        format!("{}m {}s", duration.as_secs() / 60, duration.as_secs() % 60)
+    }
+}
@@ -10,9 +10,14 @@ use ratatui::{
         Modifier,
         Style,
     },
+    text::{
+        Line,
+        Span,
+    },
     widgets::{
         Block,
         Borders,
+        Paragraph,
         Tabs,
     },
 };
@@ -20,6 +25,7 @@ use ratatui::{
 use crate::{
     app::{
         App,
+        LayoutMode,
         PopupState,
         Tab,
     },
@@ -27,16 +33,33 @@ use crate::{
         tabs::{
             bookmarks::render_bookmarks,
             log::render_log,
+            remotes::render_remotes,
             working_copy::render_working_copy,
         },
         widgets::{
+            form::render_form,
             popup::{
                 FeedbackType,
+                render_bookmark_conflict_popup,
                 render_bookmark_select_popup,
+                render_command_output_popup,
+                render_commit_hunks_popup,
+                render_confirm_popup,
+                render_conflicts_popup,
+                render_context_menu,
                 render_feedback_popup,
                 render_help_popup,
+                render_hunks_popup,
                 render_input_popup,
+                render_journal_popup,
+                render_notes_popup,
+                render_notifications_popup,
+                render_operation_diff_popup,
+                render_path_select_popup,
+                render_recent_popup,
+                render_theme_picker,
             },
+            revision_picker::render_revision_picker,
             status_bar::render_status_bar,
         },
     },
@@ -48,23 +71,32 @@ pub fn render_ui(f: &mut Frame, app: &mut App) {
     let size = f.area();
 
     // Create main layout
+    let banner_height = u16::from(app.git_head_drift.is_some());
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
-            Constraint::Length(3), // Tab bar
-            Constraint::Min(0),    // Main content
-            Constraint::Length(1), // Status bar
+            Constraint::Length(3),             // Tab bar
+            Constraint::Length(banner_height), // Git HEAD drift banner
+            Constraint::Min(0),                // Main content
+            Constraint::Length(1),             // Status bar
         ])
         .split(size);
 
-    // Render tab bar
-    render_tab_bar(f, app, chunks[0]);
+    if app.layout_mode == LayoutMode::Zen {
+        // Only the current pane, on the whole screen
+        render_tab_content(f, app, size);
+    } else {
+        // Render tab bar
+        render_tab_bar(f, app, chunks[0]);
+
+        render_git_head_banner(f, app, chunks[1]);
 
-    // Render current tab content
-    render_tab_content(f, app, chunks[1]);
+        // Render current tab content
+        render_tab_content(f, app, chunks[2]);
 
-    // Render status bar
-    render_status_bar(f, app, chunks[2]);
+        // Render status bar
+        render_status_bar(f, app, chunks[3]);
+    }
 
     // Render popups on top
     // Handle Input popup separately due to mutable borrow of textarea
@@ -73,28 +105,25 @@ pub fn render_ui(f: &mut Frame, app: &mut App) {
         if let PopupState::Input {
             ref title,
             ref mut textarea,
+            ref details,
             ..
         } = app.popup_state
         {
-            render_input_popup(f, theme, title.as_str(), textarea, size);
+            render_input_popup(f, theme, title.as_str(), textarea, details, size);
         }
     } else {
         match &app.popup_state {
-            PopupState::BookmarkSelect {
-                content,
-                cursor_position,
-                available_bookmarks,
-                selected_index,
-            } => {
-                render_bookmark_select_popup(
-                    f,
-                    app,
-                    content,
-                    *cursor_position,
-                    available_bookmarks,
-                    *selected_index,
-                    size,
-                );
+            PopupState::BookmarkSelect { finder, purpose } => {
+                render_bookmark_select_popup(f, app, finder, purpose, size);
+            }
+            PopupState::PathSelect { finder } => {
+                render_path_select_popup(f, app, finder, size);
+            }
+            PopupState::Form { title, form, .. } => {
+                render_form(f, app, title, form, size);
+            }
+            PopupState::RevisionPicker { picker, .. } => {
+                render_revision_picker(f, app, picker, size);
             }
             PopupState::Error { message } => {
                 render_feedback_popup(f, app, message, size, &FeedbackType::Error);
@@ -105,32 +134,139 @@ pub fn render_ui(f: &mut Frame, app: &mut App) {
             PopupState::Help => {
                 render_help_popup(f, app, size);
             }
+            PopupState::Confirm {
+                title,
+                lines,
+                required_input,
+                typed,
+                ..
+            } => {
+                let typed = required_input.as_ref().map(|_| typed.as_str());
+                render_confirm_popup(f, app, title, lines, typed, size);
+            }
+            PopupState::Conflicts { commits } => {
+                render_conflicts_popup(f, app, commits, size);
+            }
+            PopupState::Notifications => {
+                render_notifications_popup(f, app, size);
+            }
+            PopupState::ContextMenu {
+                title,
+                items,
+                selected,
+                ..
+            } => {
+                render_context_menu(f, app, title, items, *selected, size);
+            }
+            PopupState::BookmarkConflict {
+                name,
+                targets,
+                selected,
+            } => {
+                render_bookmark_conflict_popup(f, app, name, targets, *selected, size);
+            }
+            PopupState::CommitHunks {
+                files,
+                picked,
+                selected,
+            } => {
+                render_commit_hunks_popup(f, app, files, picked, *selected, size);
+            }
+            PopupState::Hunks {
+                title,
+                hunks,
+                selected,
+                action,
+            } => {
+                render_hunks_popup(f, app, title, hunks, *selected, *action, size);
+            }
+            PopupState::Notes { selected } => {
+                render_notes_popup(f, app, *selected, size);
+            }
+            PopupState::Recent { selected } => {
+                render_recent_popup(f, app, *selected, size);
+            }
+            PopupState::ThemePicker { selected, .. } => {
+                render_theme_picker(f, app, *selected, size);
+            }
+            PopupState::Journal { entries, selected } => {
+                render_journal_popup(f, app, entries, *selected, size);
+            }
+            PopupState::OperationDiff { steps, diff } => {
+                render_operation_diff_popup(f, app, *steps, diff, size);
+            }
+            PopupState::CommandOutput {
+                title,
+                output,
+                success,
+            } => {
+                render_command_output_popup(f, app, title, output, *success, size);
+            }
             PopupState::None | PopupState::Input { .. } => {}
         }
     }
 }
 
 fn render_tab_bar(f: &mut Frame, app: &App, area: Rect) {
-    let tab_titles = vec!["1: Working Copy", "2: Bookmarks", "3: Log"];
+    // Pending work at a glance, counted when the data was last loaded
+    let badge_style = Style::default()
+        .fg(app.theme.peach)
+        .add_modifier(Modifier::BOLD);
+    let title = |title: &'static str, count: usize| {
+        let mut spans = vec![Span::raw(title)];
+        if count > 0 {
+            spans.push(Span::styled(format!(" {count}"), badge_style));
+        }
+        Line::from(spans)
+    };
+    let tab_titles = vec![
+        title("1: Working Copy", app.files.len()),
+        title("2: Bookmarks", app.untracked_remote_bookmarks),
+        title("3: Log", app.unpushed_commits),
+        title("4: Remotes", 0),
+    ];
     let selected_index = match app.current_tab {
         Tab::WorkingCopy => 0,
         Tab::Bookmarks => 1,
         Tab::Log => 2,
+        Tab::Remotes => 3,
     };
 
     let tabs = Tabs::new(tab_titles)
         .block(Block::default().borders(Borders::ALL).title("jjkk"))
         .select(selected_index)
         .style(Style::default().fg(app.theme.text))
-        .highlight_style(
+        .highlight_style(if app.theme.uses_color() {
             Style::default()
                 .fg(app.theme.lavender)
-                .add_modifier(Modifier::BOLD),
-        );
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().add_modifier(Modifier::BOLD | Modifier::UNDERLINED)
+        });
 
     f.render_widget(tabs, area);
 }
 
+/// Warn that git's HEAD moved without jj knowing, e.g. after `git checkout` in a colocated repo
+fn render_git_head_banner(f: &mut Frame, app: &App, area: Rect) {
+    let Some(commit) = &app.git_head_drift else {
+        return;
+    };
+    let style = if app.theme.uses_color() {
+        Style::default()
+            .fg(app.theme.base)
+            .bg(app.theme.peach)
+            .add_modifier(Modifier::BOLD)
+    } else {
+        Style::default().add_modifier(Modifier::BOLD | Modifier::REVERSED)
+    };
+    let banner = Paragraph::new(Line::from(format!(
+        " git HEAD moved to {commit} outside of jj (git checkout or commit) - press I to import git refs"
+    )))
+    .style(style);
+    f.render_widget(banner, area);
+}
+
 fn render_tab_content(f: &mut Frame, app: &mut App, area: Rect) {
     match app.current_tab {
         Tab::WorkingCopy => {
@@ -142,5 +278,68 @@ fn render_tab_content(f: &mut Frame, app: &mut App, area: Rect) {
         Tab::Log => {
             render_log(f, app, area);
         }
+        Tab::Remotes => {
+            render_remotes(f, app, area);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::{
+        Terminal,
+        backend::TestBackend,
+    };
+
+    use super::*;
+    use crate::{
+        cli::Args,
+        config::settings::Settings,
+        jj::{
+            native_operations::Native,
+            repo::JjRepo,
+            test_repo::TestRepo,
+        },
+    };
+
+    /// The rows of the last drawn frame
+    fn screen(terminal: &Terminal<TestBackend>) -> String {
+        let buffer = terminal.backend().buffer();
+        buffer
+            .content
+            .chunks(usize::from(buffer.area.width))
+            .map(|row| {
+                row.iter()
+                    .map(ratatui::buffer::Cell::symbol)
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn test_render_working_copy_of_scripted_repo() {
+        let Some(repo) = TestRepo::new() else {
+            return;
+        };
+        repo.write("README.md", "hello\n")
+            .commit("First")
+            .write("src/main.rs", "fn main() {}\n");
+        let mut app = App::with_repo(
+            &Args::default(),
+            Settings::default(),
+            JjRepo::open(Some(repo.path())).unwrap(),
+            Native::open_with_user_config(&repo.path(), &repo.config_path()),
+            None,
+        )
+        .unwrap();
+        repo.run(|| app.refresh_all()).unwrap();
+
+        let mut terminal = Terminal::new(TestBackend::new(120, 40)).unwrap();
+        repo.run(|| terminal.draw(|f| render_ui(f, &mut app)))
+            .unwrap();
+        let screen = screen(&terminal);
+        assert!(screen.contains("src/main.rs"), "{screen}");
+        assert!(!screen.contains("README.md"), "{screen}");
     }
 }
@@ -1,3 +1,4 @@
 pub mod layout;
 pub mod tabs;
+pub mod text;
 pub mod widgets;
@@ -18,7 +18,13 @@ use ratatui::{
     },
 };
 
-use crate::app::App;
+use crate::{
+    app::App,
+    ui::{
+        text::truncate,
+        widgets::scrollbar::render_list_scrollbar,
+    },
+};
 
 pub fn render_bookmarks(f: &mut Frame, app: &mut App, area: Rect) {
     // Use cached bookmarks data
@@ -37,6 +43,9 @@ pub fn render_bookmarks(f: &mut Frame, app: &mut App, area: Rect) {
         return;
     }
 
+    // Borders plus the current bookmark marker
+    let name_width = usize::from(area.width.saturating_sub(4));
+
     // Create list items
     let items: Vec<ListItem> = bookmarks
         .iter()
@@ -57,25 +66,44 @@ pub fn render_bookmarks(f: &mut Frame, app: &mut App, area: Rect) {
             };
 
             let prefix = if bookmark.is_current { "* " } else { "  " };
-            let content = format!("{}{}", prefix, bookmark.name);
+            let suffix = if bookmark.conflicted {
+                " (conflicted)"
+            } else {
+                ""
+            };
+            let content = format!(
+                "{}{}",
+                prefix,
+                truncate(&bookmark.name, name_width.saturating_sub(suffix.len()))
+            );
 
-            ListItem::new(Line::from(Span::styled(content, style)))
+            let mut spans = vec![Span::styled(content, style)];
+            if bookmark.conflicted {
+                spans.push(Span::styled(suffix, Style::default().fg(app.theme.peach)));
+            }
+            ListItem::new(Line::from(spans))
         })
         .collect();
 
+    let item_heights: Vec<usize> = items.iter().map(ListItem::height).collect();
     let list = List::new(items)
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .title("Bookmarks (* = current, j/k to navigate, Enter to checkout)")
+                .title(
+                    "Bookmarks (* = current, Enter to checkout or resolve a conflict, D to delete, C to clean up merged)",
+                )
                 .border_style(Style::default().fg(app.theme.surface1)),
         )
         .style(Style::default().bg(app.theme.base))
-        .highlight_style(
-            Style::default()
-                .bg(app.theme.surface1)
-                .add_modifier(Modifier::BOLD),
-        );
+        .highlight_style(app.theme.highlight_style());
 
     f.render_stateful_widget(list, area, &mut app.bookmark_list_state);
+    render_list_scrollbar(
+        f,
+        app,
+        area,
+        &item_heights,
+        app.bookmark_list_state.offset(),
+    );
 }
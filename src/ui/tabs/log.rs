@@ -18,7 +18,32 @@ use ratatui::{
     },
 };
 
-use crate::app::App;
+use crate::{
+    app::App,
+    config::settings::LogColors,
+    jj::log::CommitInfo,
+    ui::{
+        text::{
+            display_width,
+            split_at_width,
+            truncate,
+        },
+        widgets::{
+            bookmark_chips::{
+                bookmark_chips,
+                chips_width,
+            },
+            scrollbar::render_list_scrollbar,
+        },
+    },
+};
+
+/// Descriptions are never truncated to less than this many columns
+const MIN_DESCRIPTION_WIDTH: usize = 10;
+/// Shown before commits that exist only locally
+const LOCAL_MARKER: &str = "↑";
+/// Shown before commits that are already on a remote
+const PUSHED_MARKER: &str = "·";
 
 pub fn render_log(f: &mut Frame, app: &mut App, area: Rect) {
     // Get log with configured limit
@@ -40,21 +65,40 @@ pub fn render_log(f: &mut Frame, app: &mut App, area: Rect) {
         return;
     }
 
+    let show_preview = app.show_log_preview;
+    let color_by_author = app.settings.ui.log_colors == LogColors::Author;
+    // Usable width inside the borders
+    let content_width = usize::from(area.width.saturating_sub(2));
+
     // Create list items
     let items: Vec<ListItem> = commits
         .iter()
         .enumerate()
         .map(|(i, commit)| {
             let is_selected = i == app.selected_log_index;
+            // Tells whose commits are whose in a shared history at a glance
+            let author_color = color_by_author.then(|| app.theme.author_color(&commit.author));
 
-            let change_style = if is_selected {
+            let mut change_style = if is_selected {
                 Style::default()
-                    .fg(app.theme.blue)
+                    .fg(author_color.unwrap_or(app.theme.blue))
                     .bg(app.theme.surface1)
                     .add_modifier(Modifier::BOLD)
             } else {
-                Style::default().fg(app.theme.blue)
+                Style::default().fg(author_color.unwrap_or(app.theme.blue))
             };
+            // Picked for a batch action
+            if app.selected_changes.contains(&commit.change_id) {
+                change_style = change_style
+                    .fg(app.theme.green)
+                    .add_modifier(Modifier::REVERSED);
+            }
+            // The other end of the commit range for patches
+            if app.marked_log_change.as_ref() == Some(&commit.change_id) {
+                change_style = change_style
+                    .fg(app.theme.peach)
+                    .add_modifier(Modifier::REVERSED);
+            }
 
             let desc_style = if is_selected {
                 Style::default()
@@ -67,37 +111,116 @@ pub fn render_log(f: &mut Frame, app: &mut App, area: Rect) {
 
             let author_style = if is_selected {
                 Style::default()
-                    .fg(app.theme.subtext0)
+                    .fg(author_color.unwrap_or(app.theme.subtext0))
                     .bg(app.theme.surface1)
             } else {
-                Style::default().fg(app.theme.subtext0)
+                Style::default().fg(author_color.unwrap_or(app.theme.subtext0))
             };
 
-            let content = vec![
+            // What a push would send stands out from what is already on a remote
+            let (push_marker, push_marker_style) = if commit.pushed {
+                (PUSHED_MARKER, Style::default().fg(app.theme.overlay0))
+            } else {
+                (LOCAL_MARKER, Style::default().fg(app.theme.peach))
+            };
+
+            let indent = display_width(push_marker) + 1 + display_width(&commit.change_id) + 1;
+            // Leave room for the author on the first line, but never squeeze the
+            // description away completely
+            let description_width = content_width
+                .saturating_sub(
+                    indent + chips_width(&commit.bookmarks) + 1 + display_width(&commit.author),
+                )
+                .max(MIN_DESCRIPTION_WIDTH);
+            let (description, preview) = split_description(
+                commit,
+                description_width,
+                content_width.saturating_sub(indent),
+                show_preview,
+            );
+
+            let mut content = vec![
+                Span::styled(push_marker, push_marker_style),
+                Span::raw(" "),
                 Span::styled(&commit.change_id, change_style),
                 Span::raw(" "),
-                Span::styled(&commit.description, desc_style),
+            ];
+            content.extend(bookmark_chips(app, &commit.bookmarks));
+            content.extend([
+                Span::styled(description, desc_style),
                 Span::raw(" "),
                 Span::styled(&commit.author, author_style),
-            ];
+            ]);
 
-            ListItem::new(Line::from(content))
+            let mut lines = vec![Line::from(content)];
+            if let Some(preview) = preview {
+                let preview_style = if is_selected {
+                    Style::default()
+                        .fg(app.theme.overlay1)
+                        .bg(app.theme.surface1)
+                } else {
+                    Style::default().fg(app.theme.overlay0)
+                };
+                lines.push(Line::from(vec![
+                    Span::raw(" ".repeat(indent)),
+                    Span::styled(preview, preview_style),
+                ]));
+            }
+
+            ListItem::new(lines)
         })
         .collect();
 
+    let item_heights: Vec<usize> = items.iter().map(ListItem::height).collect();
     let list = List::new(items)
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .title(format!("Log (last {limit} commits, j/k to navigate)"))
+                .title(if app.log_filter.is_empty() {
+                    format!(
+                        "Log (last {limit} commits, j/k to navigate, v to toggle preview, M to mark a range, m for actions)"
+                    )
+                } else {
+                    format!(
+                        "Log of {} (last {limit} matching commits, Esc to show all)",
+                        app.log_filter.label()
+                    )
+                })
                 .border_style(Style::default().fg(app.theme.surface1)),
         )
         .style(Style::default().bg(app.theme.base))
-        .highlight_style(
-            Style::default()
-                .bg(app.theme.surface1)
-                .add_modifier(Modifier::BOLD),
-        );
+        .highlight_style(app.theme.highlight_style());
 
     f.render_stateful_widget(list, area, &mut app.log_list_state);
+    render_list_scrollbar(f, app, area, &item_heights, app.log_list_state.offset());
+}
+
+/// Fit the description of a log entry into the first line and compute the
+/// dimmed second line, if previews are enabled.
+/// The preview prefers the second line of the description, otherwise it is
+/// the part of the first line that did not fit into `first_width` columns
+fn split_description(
+    commit: &CommitInfo,
+    first_width: usize,
+    preview_width: usize,
+    show_preview: bool,
+) -> (String, Option<String>) {
+    if !show_preview {
+        return (truncate(&commit.description, first_width), None);
+    }
+
+    if let Some(second_line) = commit.second_line() {
+        return (
+            truncate(&commit.description, first_width),
+            Some(truncate(second_line, preview_width)),
+        );
+    }
+
+    let (head, tail) = split_at_width(&commit.description, first_width);
+    let tail = tail.trim();
+    if tail.is_empty() {
+        (head.to_string(), None)
+    } else {
+        (head.to_string(), Some(truncate(tail, preview_width)))
+    }
 }
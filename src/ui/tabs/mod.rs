@@ -1,3 +1,4 @@
 pub mod bookmarks;
 pub mod log;
+pub mod remotes;
 pub mod working_copy;
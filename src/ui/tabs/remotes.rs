@@ -0,0 +1,205 @@
+use ratatui::{
+    Frame,
+    layout::{
+        Constraint,
+        Direction,
+        Layout,
+        Rect,
+    },
+    style::{
+        Modifier,
+        Style,
+    },
+    text::{
+        Line,
+        Span,
+    },
+    widgets::{
+        Block,
+        Borders,
+        List,
+        ListItem,
+        Paragraph,
+    },
+};
+
+use crate::{
+    app::App,
+    ui::text::{
+        display_width,
+        truncate,
+    },
+};
+
+/// Differing bookmarks listed before the rest are summarized in one line
+const MAX_DIFFERENCES: usize = 5;
+
+pub fn render_remotes(f: &mut Frame, app: &mut App, area: Rect) {
+    let interop_lines = interop_lines(app);
+    let interop_height = u16::try_from(interop_lines.len() + 2).unwrap_or(u16::MAX);
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(0),                 // Remote list
+            Constraint::Length(interop_height), // Git interop
+        ])
+        .split(area);
+
+    render_remote_list(f, app, rows[0]);
+
+    let paragraph = Paragraph::new(interop_lines)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Git interop (I to import, E to export)")
+                .border_style(Style::default().fg(app.theme.surface1)),
+        )
+        .style(Style::default().bg(app.theme.base));
+    f.render_widget(paragraph, rows[1]);
+}
+
+fn render_remote_list(f: &mut Frame, app: &mut App, area: Rect) {
+    if app.remotes.is_empty() {
+        let paragraph = Paragraph::new("No git remotes configured.\nPress 'a' to add one.")
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title("Remotes")
+                    .border_style(Style::default().fg(app.theme.surface1)),
+            )
+            .style(Style::default().fg(app.theme.subtext0).bg(app.theme.base));
+        f.render_widget(paragraph, area);
+        return;
+    }
+
+    let name_width = app
+        .remotes
+        .iter()
+        .map(|remote| display_width(&remote.name))
+        .max()
+        .unwrap_or(0);
+    // Borders, the default marker and the gap between name and url
+    let url_width = usize::from(area.width.saturating_sub(6)).saturating_sub(name_width);
+
+    let items: Vec<ListItem> = app
+        .remotes
+        .iter()
+        .enumerate()
+        .map(|(i, remote)| {
+            let is_default = remote.name == app.native_ops.default_remote;
+            let name_style = if i == app.selected_remote_index {
+                Style::default()
+                    .fg(app.theme.text)
+                    .bg(app.theme.surface1)
+                    .add_modifier(Modifier::BOLD)
+            } else if is_default {
+                Style::default()
+                    .fg(app.theme.lavender)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(app.theme.text)
+            };
+
+            let prefix = if is_default { "* " } else { "  " };
+            let padding = " ".repeat(name_width - display_width(&remote.name) + 1);
+            ListItem::new(Line::from(vec![
+                Span::styled(format!("{prefix}{}{padding}", remote.name), name_style),
+                Span::styled(
+                    truncate(&remote.url, url_width),
+                    Style::default().fg(app.theme.subtext0),
+                ),
+            ]))
+        })
+        .collect();
+
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Remotes (* = default, Enter to make default, a to add, e to rename, D to remove)")
+                .border_style(Style::default().fg(app.theme.surface1)),
+        )
+        .style(Style::default().bg(app.theme.base))
+        .highlight_style(app.theme.highlight_style());
+
+    f.render_stateful_widget(list, area, &mut app.remote_list_state);
+}
+
+fn interop_lines(app: &App) -> Vec<Line<'static>> {
+    let label = Style::default().fg(app.theme.subtext0);
+    let Some(status) = &app.interop else {
+        return vec![Line::styled("Not backed by a git repository", label)];
+    };
+    let short = |id: &Option<String>| {
+        id.as_deref()
+            .map_or_else(|| "(none)".to_string(), |id| id.chars().take(12).collect())
+    };
+
+    let mut lines = vec![
+        Line::from(vec![
+            Span::styled("Colocated:   ", label),
+            Span::styled(
+                if status.colocated { "yes" } else { "no" },
+                Style::default().fg(app.theme.text),
+            ),
+        ]),
+        Line::from(vec![
+            Span::styled("Last import: ", label),
+            Span::styled(
+                status
+                    .last_import
+                    .clone()
+                    .unwrap_or_else(|| "never".to_string()),
+                Style::default().fg(app.theme.text),
+            ),
+        ]),
+        Line::from(vec![
+            Span::styled("Last export: ", label),
+            Span::styled(
+                status
+                    .last_export
+                    .clone()
+                    .unwrap_or_else(|| "never".to_string()),
+                Style::default().fg(app.theme.text),
+            ),
+        ]),
+    ];
+
+    if status.differences.is_empty() {
+        lines.push(Line::styled(
+            "jj and git agree on all bookmarks",
+            Style::default().fg(app.theme.green),
+        ));
+        return lines;
+    }
+
+    lines.push(Line::styled(
+        format!(
+            "{} bookmark(s) differ (jj -> git):",
+            status.differences.len()
+        ),
+        Style::default().fg(app.theme.yellow),
+    ));
+    for difference in status.differences.iter().take(MAX_DIFFERENCES) {
+        lines.push(Line::from(vec![
+            Span::styled(
+                format!("  {} ", difference.name),
+                Style::default().fg(app.theme.text),
+            ),
+            Span::styled(
+                format!("{} -> {}", short(&difference.jj), short(&difference.git)),
+                label,
+            ),
+        ]));
+    }
+    if status.differences.len() > MAX_DIFFERENCES {
+        lines.push(Line::styled(
+            format!(
+                "  ... and {} more",
+                status.differences.len() - MAX_DIFFERENCES
+            ),
+            label,
+        ));
+    }
+    lines
+}
@@ -24,14 +24,61 @@ use ratatui::{
         Wrap,
     },
 };
-use syntect::easy::HighlightLines;
 
 use crate::{
-    app::App,
-    jj::repo::ChangeType,
+    app::{
+        App,
+        LayoutMode,
+    },
+    jj::{
+        log::CommitInfo,
+        repo::ChangeType,
+    },
+    ui::{
+        text::{
+            display_width,
+            truncate,
+            wrapped_rows,
+        },
+        widgets::{
+            bookmark_chips::{
+                bookmark_chips,
+                chips_width,
+            },
+            diff_view::{
+                WordChange,
+                WordDiffLine,
+                is_header,
+            },
+            scrollbar::{
+                render_list_scrollbar,
+                render_scrollbar,
+            },
+        },
+    },
 };
 
 pub fn render_working_copy(f: &mut Frame, app: &mut App, area: Rect) {
+    // Reading the diff is what zen mode is for
+    if app.layout_mode == LayoutMode::Zen {
+        render_diff_view(f, app, area);
+        return;
+    }
+
+    let mini_log_height = if app.mini_log_lines > 0 {
+        u16::try_from(app.mini_log_lines + 2).unwrap_or(u16::MAX)
+    } else {
+        0
+    };
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(4),               // Working copy header
+            Constraint::Min(0),                  // Files and diff
+            Constraint::Length(mini_log_height), // Mini log
+        ])
+        .split(area);
+
     // Split into left (file list) and right (diff view)
     let chunks = Layout::default()
         .direction(Direction::Horizontal)
@@ -39,13 +86,133 @@ pub fn render_working_copy(f: &mut Frame, app: &mut App, area: Rect) {
             Constraint::Percentage(30), // File list
             Constraint::Percentage(70), // Diff view
         ])
-        .split(area);
+        .split(rows[1]);
 
+    render_header(f, app, rows[0]);
     render_file_list(f, app, chunks[0]);
     render_diff_view(f, app, chunks[1]);
+    if mini_log_height > 0 {
+        render_mini_log(f, app, rows[2]);
+    }
+}
+
+/// The newest commits of the log, to keep the stack in view while working
+fn render_mini_log(f: &mut Frame, app: &App, area: Rect) {
+    // Usable width inside the borders
+    let width = usize::from(area.width.saturating_sub(2));
+    let working_copy = app.working_copy.as_ref().map(|commit| &commit.change_id);
+
+    let lines: Vec<Line> = app
+        .log_commits
+        .iter()
+        .take(app.mini_log_lines)
+        .map(|commit| {
+            let is_working_copy = Some(&commit.change_id) == working_copy;
+            let marker = if is_working_copy { "@ " } else { "  " };
+            let description_width = width.saturating_sub(
+                display_width(marker)
+                    + display_width(&commit.change_id)
+                    + 1
+                    + chips_width(&commit.bookmarks),
+            );
+
+            let mut spans = vec![
+                Span::styled(marker, Style::default().fg(app.theme.green)),
+                Span::styled(
+                    commit.change_id.clone(),
+                    Style::default().fg(app.theme.blue),
+                ),
+                Span::raw(" "),
+            ];
+            spans.extend(bookmark_chips(app, &commit.bookmarks));
+            spans.push(Span::styled(
+                truncate(&commit.description, description_width),
+                Style::default().fg(app.theme.text),
+            ));
+            Line::from(spans)
+        })
+        .collect();
+
+    let mini_log = Paragraph::new(lines)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Log (L to hide)")
+                .border_style(Style::default().fg(app.theme.surface1)),
+        )
+        .style(Style::default().bg(app.theme.base));
+
+    f.render_widget(mini_log, area);
+}
+
+/// Show which change the edits land in, and what it is based on
+fn render_header(f: &mut Frame, app: &App, area: Rect) {
+    // Usable width inside the borders
+    let width = usize::from(area.width.saturating_sub(2));
+
+    let commit_line = |label: &'static str, commit: Option<&CommitInfo>| {
+        let mut spans = vec![Span::styled(label, Style::default().fg(app.theme.subtext0))];
+        let Some(commit) = commit else {
+            spans.push(Span::styled("-", Style::default().fg(app.theme.overlay0)));
+            return Line::from(spans);
+        };
+
+        let description_width = width.saturating_sub(
+            display_width(label)
+                + display_width(&commit.change_id)
+                + 1
+                + chips_width(&commit.bookmarks),
+        );
+        spans.push(Span::styled(
+            commit.change_id.clone(),
+            Style::default().fg(app.theme.blue),
+        ));
+        spans.push(Span::raw(" "));
+        spans.extend(bookmark_chips(app, &commit.bookmarks));
+        spans.push(if commit.description.is_empty() {
+            Span::styled("(empty)", Style::default().fg(app.theme.overlay0))
+        } else {
+            Span::styled(
+                truncate(&commit.description, description_width),
+                Style::default().fg(app.theme.text),
+            )
+        });
+        Line::from(spans)
+    };
+
+    let header = Paragraph::new(vec![
+        commit_line("@      ", app.working_copy.as_ref()),
+        commit_line("parent ", app.working_copy_parent.as_ref()),
+    ])
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(header_title(app))
+            .border_style(Style::default().fg(app.theme.surface1)),
+    )
+    .style(Style::default().bg(app.theme.base));
+
+    f.render_widget(header, area);
+}
+
+/// Names trunk and how far the stack is from it, behind means a rebase picks up new commits
+fn header_title(app: &App) -> String {
+    let trunk = app.trunk.as_deref().unwrap_or("trunk()");
+    match app.trunk_distance {
+        Some((0, 0)) => format!("Working copy (on {trunk})"),
+        Some((ahead, 0)) => format!("Working copy ({ahead} ahead of {trunk})"),
+        Some((ahead, behind)) => {
+            format!("Working copy ({ahead} ahead, {behind} behind {trunk}, rebase to catch up)")
+        }
+        None if app.trunk.is_some() => format!("Working copy (trunk: {trunk})"),
+        None => "Working copy".to_string(),
+    }
 }
 
 fn render_file_list(f: &mut Frame, app: &mut App, area: Rect) {
+    // Borders plus the status symbol and its trailing space
+    let path_width = usize::from(area.width.saturating_sub(4));
+
     let items: Vec<ListItem> = app
         .files
         .iter()
@@ -56,8 +223,17 @@ fn render_file_list(f: &mut Frame, app: &mut App, area: Rect) {
                 ChangeType::Added => app.theme.green,
                 ChangeType::Modified => app.theme.blue,
                 ChangeType::Deleted => app.theme.red,
+                ChangeType::Renamed => app.theme.yellow,
+                ChangeType::Copied => app.theme.teal,
+                ChangeType::Conflicted => app.theme.peach,
+                ChangeType::TooLarge { .. } => app.theme.maroon,
             };
 
+            let display_path = file.old_path.as_ref().map_or_else(
+                || file.path.clone(),
+                |old_path| format!("{old_path} -> {}", file.path),
+            );
+
             let style = if i == app.selected_file_index {
                 Style::default()
                     .fg(app.theme.text)
@@ -70,58 +246,162 @@ fn render_file_list(f: &mut Frame, app: &mut App, area: Rect) {
             ListItem::new(Line::from(vec![
                 Span::styled(symbol, Style::default().fg(color)),
                 Span::raw(" "),
-                Span::styled(&file.path, style),
+                Span::styled(truncate(&display_path, path_width), style),
             ]))
         })
         .collect();
 
+    let title = app.status_filter.as_ref().map_or_else(
+        || "Files".to_string(),
+        |fileset| format!("Files matching {fileset}"),
+    );
+    let title = if app.status_loader.is_loading() {
+        format!("{title} (loading, {} so far)", app.files.len())
+    } else {
+        title
+    };
+
+    let item_heights: Vec<usize> = items.iter().map(ListItem::height).collect();
     let list = List::new(items)
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .title("Files")
+                .title(title)
                 .border_style(Style::default().fg(app.theme.surface1)),
         )
         .style(Style::default().bg(app.theme.base))
-        .highlight_style(
+        .highlight_style(app.theme.highlight_style());
+
+    f.render_stateful_widget(list, area, &mut app.file_list_state);
+    render_list_scrollbar(f, app, area, &item_heights, app.file_list_state.offset());
+}
+
+/// Why a new file is missing from the snapshot and what can be done about it
+fn large_file_lines(app: &App, size: u64) -> Vec<Line<'static>> {
+    vec![
+        Line::from(Span::styled(
+            format!("! Not snapshotted: {size} bytes is over snapshot.max-new-file-size"),
             Style::default()
-                .bg(app.theme.surface1)
+                .fg(app.theme.yellow)
                 .add_modifier(Modifier::BOLD),
-        );
+        )),
+        Line::from(""),
+        Line::from("T: track it anyway, raising the limit for this file only"),
+        Line::from("G: add it to .gitignore"),
+    ]
+}
 
-    f.render_stateful_widget(list, area, &mut app.file_list_state);
+/// A changed line of a prose file, the removed words crossed out before the added ones
+fn word_diff_line<'a>(app: &App, words: &'a [(WordChange, String)]) -> Line<'a> {
+    let (removed_style, added_style) = if app.theme.uses_color() {
+        (
+            Style::default().fg(app.theme.red),
+            Style::default().fg(app.theme.green),
+        )
+    } else {
+        (
+            Style::default(),
+            Style::default().add_modifier(Modifier::BOLD),
+        )
+    };
+    let mut spans = vec![Span::styled("~", Style::default().fg(app.theme.yellow))];
+    spans.extend(words.iter().map(|(change, text)| {
+        let style = match change {
+            WordChange::Equal => Style::default().fg(app.theme.text),
+            WordChange::Removed => removed_style.add_modifier(Modifier::CROSSED_OUT),
+            WordChange::Added => added_style.add_modifier(Modifier::UNDERLINED),
+        };
+        Span::styled(text.as_str(), style)
+    }));
+    Line::from(spans)
+}
+
+/// Put the review notes below the diff lines they are on, a single row each as
+/// `App::diff_line_rows` counts them
+fn with_notes<'a>(app: &'a App, lines: Vec<Option<Line<'a>>>, width: usize) -> Vec<Line<'a>> {
+    let notes = app.diff_notes();
+    if notes.is_empty() {
+        return lines.into_iter().flatten().collect();
+    }
+    let style = Style::default()
+        .fg(app.theme.yellow)
+        .add_modifier(Modifier::ITALIC);
+    let mut notes = notes.into_iter().peekable();
+    let mut with_notes = Vec::with_capacity(lines.len() + notes.len());
+    for (i, line) in lines.into_iter().enumerate() {
+        with_notes.extend(line);
+        while let Some((_, note)) = notes.next_if(|(index, _)| *index == i) {
+            let text = format!("  note: {}", note.text);
+            with_notes.push(Line::from(Span::styled(truncate(&text, width), style)));
+        }
+    }
+    with_notes
 }
 
 fn render_diff_view(f: &mut Frame, app: &App, area: Rect) {
-    let lines: Vec<Line> = app.current_diff.as_ref().map_or_else(
+    // Huge diffs are only colored by line type, highlighting them costs more than it helps
+    let too_large = app
+        .current_diff
+        .as_ref()
+        .is_some_and(|diff| diff.lines().count() > app.settings.ui.highlight_max_lines);
+
+    // `None` for the lines folded into the word diff below them
+    let lines: Vec<Option<Line>> = app.current_diff.as_ref().map_or_else(
         || {
-            if app.files.is_empty() {
+            let lines = if app.status_loader.is_loading() {
+                vec![Line::from("Loading the working copy...")]
+            } else if app.files.is_empty() {
                 vec![Line::from("No changes in working copy")]
+            } else if let Some(ChangeType::TooLarge { size }) = app
+                .files
+                .get(app.selected_file_index)
+                .map(|file| file.status)
+            {
+                large_file_lines(app, size)
             } else {
                 vec![Line::from("Select a file to view diff")]
-            }
+            };
+            lines.into_iter().map(Some).collect()
         },
         |diff| {
-            // Get file extension for syntax detection
-            let file_path = app
-                .files
-                .get(app.selected_file_index)
-                .map(|f| f.path.as_str());
-
-            // Use cached syntect resources from app
-            let ps = &app.syntax_set;
-            let theme = &app.theme_set.themes["base16-ocean.dark"];
-
-            // // Try to detect syntax from file path
-            let syntax = file_path
-                .and_then(|path| ps.find_syntax_for_file(path).ok().flatten())
-                .or_else(|| Some(ps.find_syntax_plain_text()));
+            // Without colors, added and removed lines are told apart by their modifiers
+            let (added_style, removed_style) = if app.theme.uses_color() {
+                (
+                    Style::default().fg(app.theme.green),
+                    Style::default().fg(app.theme.red),
+                )
+            } else {
+                (
+                    Style::default().add_modifier(Modifier::BOLD),
+                    Style::default().add_modifier(Modifier::DIM),
+                )
+            };
+            // Syntax colored spans, once the background highlighting is done
+            let highlighted = |i: usize, marker: Option<(&'static str, Color)>| {
+                app.highlighter.line(i).map(|ranges| {
+                    let marker = marker
+                        .map(|(marker, color)| Span::styled(marker, Style::default().fg(color)));
+                    marker
+                        .into_iter()
+                        .chain(ranges.iter().map(|(color, text)| {
+                            Span::styled(text.as_str(), Style::default().fg(*color))
+                        }))
+                        .collect::<Vec<Span>>()
+                })
+            };
 
-            // Parse diff and apply syntax highlighting
             diff.lines()
-                .map(|line| {
+                .enumerate()
+                .map(|(i, line)| {
+                    match app.word_diff.get(i) {
+                        Some(WordDiffLine::Folded) => return None,
+                        Some(WordDiffLine::Words(words)) => {
+                            return Some(word_diff_line(app, words));
+                        }
+                        _ => {}
+                    }
                     // Check for diff-specific lines first
-                    if line.starts_with("+++") || line.starts_with("---") {
+                    let line = if line.starts_with("+++") || line.starts_with("---") {
                         // File headers
                         Line::from(Span::styled(line, Style::default().fg(app.theme.lavender)))
                     } else if line.starts_with("@@") {
@@ -132,90 +412,84 @@ fn render_diff_view(f: &mut Frame, app: &App, area: Rect) {
                                 .fg(app.theme.blue)
                                 .add_modifier(Modifier::BOLD),
                         ))
-                    } else if line.starts_with("diff ") || line.starts_with("index ") {
+                    } else if is_header(line) {
                         // Diff header
                         Line::from(Span::styled(line, Style::default().fg(app.theme.lavender)))
-                    } else if let Some(content) = line.strip_prefix('+') {
-                        // Added line - apply syntax highlighting to the content (skip the + prefix)
-                        syntax.map_or_else(
-                            || Line::from(Span::styled(line, Style::default().fg(app.theme.green))),
-                            |syntax| {
-                                let mut h = HighlightLines::new(syntax, theme);
-                                let ranges = h.highlight_line(content, ps).unwrap_or_default();
-                                let spans: Vec<Span> = std::iter::once(Span::styled(
-                                    "+",
-                                    Style::default().fg(app.theme.green),
-                                ))
-                                .chain(ranges.into_iter().map(|(style, text)| {
-                                    let color = syntect_to_ratatui_color(style.foreground);
-                                    Span::styled(text, Style::default().fg(color))
-                                }))
-                                .collect();
-                                Line::from(spans).style(Style::default().fg(app.theme.green))
-                            },
+                    } else if line.starts_with('+') {
+                        highlighted(i, Some(("+", app.theme.green))).map_or_else(
+                            || Line::from(Span::styled(line, added_style)),
+                            |spans| Line::from(spans).style(Style::default().fg(app.theme.green)),
                         )
-                    } else if let Some(content) = line.strip_prefix('-') {
-                        // Removed line - apply syntax highlighting to the content (skip the -
-                        // prefix)
-
-                        syntax.map_or_else(
-                            || Line::from(Span::styled(line, Style::default().fg(app.theme.red))),
-                            |syntax| {
-                                let mut h = HighlightLines::new(syntax, theme);
-                                let ranges = h.highlight_line(content, ps).unwrap_or_default();
-                                let spans: Vec<Span> = std::iter::once(Span::styled(
-                                    "-",
-                                    Style::default().fg(app.theme.red),
-                                ))
-                                .chain(ranges.into_iter().map(|(style, text)| {
-                                    let color = syntect_to_ratatui_color(style.foreground);
-                                    Span::styled(text, Style::default().fg(color))
-                                }))
-                                .collect();
-                                Line::from(spans).style(Style::default().fg(app.theme.red))
-                            },
+                    } else if line.starts_with('-') {
+                        highlighted(i, Some(("-", app.theme.red))).map_or_else(
+                            || Line::from(Span::styled(line, removed_style)),
+                            |spans| Line::from(spans).style(Style::default().fg(app.theme.red)),
                         )
                     } else {
-                        // Context line - apply syntax highlighting
-                        syntax.map_or_else(
+                        // Context line
+                        highlighted(i, None).map_or_else(
                             || Line::from(Span::styled(line, Style::default().fg(app.theme.text))),
-                            |syntax| {
-                                let mut h = HighlightLines::new(syntax, theme);
-                                let ranges = h.highlight_line(line, ps).unwrap_or_default();
-                                let spans: Vec<Span> = ranges
-                                    .into_iter()
-                                    .map(|(style, text)| {
-                                        let color = syntect_to_ratatui_color(style.foreground);
-                                        Span::styled(text, Style::default().fg(color))
-                                    })
-                                    .collect();
-                                Line::from(spans)
-                            },
+                            Line::from,
                         )
-                    }
+                    };
+                    Some(line)
                 })
                 .collect()
         },
     );
 
-    // Calculate visible area height (subtract 2 for borders)
+    // Calculate visible area size (subtract 2 for borders)
     let content_height = area.height.saturating_sub(2) as usize;
+    let content_width = area.width.saturating_sub(2) as usize;
+    app.diff_wrap_width.set(content_width);
 
-    // Calculate scroll offset bounds
-    let max_scroll = lines.len().saturating_sub(content_height);
+    let lines = with_notes(app, lines, content_width);
+
+    // Long lines wrap, scrolling counts the rows on screen rather than the lines
+    let line_rows: Vec<usize> = lines
+        .iter()
+        .map(|line| {
+            wrapped_rows(
+                line.spans.iter().map(|span| span.content.as_ref()),
+                content_width,
+            )
+        })
+        .collect();
+    let total_rows: usize = line_rows.iter().sum();
+    let max_scroll = total_rows.saturating_sub(content_height);
+    app.diff_scroll_max.set(max_scroll);
     let scroll_offset = app.diff_scroll_offset.min(max_scroll);
 
-    // Slice lines based on scroll offset
+    // Skip the lines scrolled past as a whole, the paragraph scrolls into the first one
+    let (first_line, first_line_row) = line_rows
+        .iter()
+        .scan(0, |row, rows| {
+            let start = *row;
+            *row += rows;
+            Some(start)
+        })
+        .enumerate()
+        .take_while(|&(_, start)| start <= scroll_offset)
+        .last()
+        .unwrap_or((0, 0));
     let visible_lines: Vec<Line> = lines
         .into_iter()
-        .skip(scroll_offset)
+        .skip(first_line)
         .take(content_height)
         .collect();
 
+    let name = match (
+        app.diff_anchors.is_some(),
+        too_large && app.settings.ui.syntax_highlighting,
+    ) {
+        (true, _) => "Diff of the whole change",
+        (false, true) => "Diff (large, not highlighted)",
+        (false, false) => "Diff",
+    };
     let title = if app.current_diff.is_some() && max_scroll > 0 {
-        format!("Diff (Shift+J/K to scroll, {scroll_offset}/{max_scroll})")
+        format!("{name} (Shift+J/K to scroll, {scroll_offset}/{max_scroll})")
     } else {
-        "Diff".to_string()
+        name.to_string()
     };
 
     let paragraph = Paragraph::new(visible_lines)
@@ -226,12 +500,12 @@ fn render_diff_view(f: &mut Frame, app: &App, area: Rect) {
                 .border_style(Style::default().fg(app.theme.surface1)),
         )
         .style(Style::default().bg(app.theme.base))
-        .wrap(Wrap { trim: false });
+        .wrap(Wrap { trim: false })
+        .scroll((
+            u16::try_from(scroll_offset - first_line_row).unwrap_or(u16::MAX),
+            0,
+        ));
 
     f.render_widget(paragraph, area);
-}
-
-// Helper function to convert syntect color to ratatui color
-const fn syntect_to_ratatui_color(color: syntect::highlighting::Color) -> Color {
-    Color::Rgb(color.r, color.g, color.b)
+    render_scrollbar(f, app, area, total_rows, scroll_offset);
 }
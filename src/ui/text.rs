@@ -0,0 +1,201 @@
+use std::collections::VecDeque;
+
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::{
+    UnicodeWidthChar,
+    UnicodeWidthStr,
+};
+
+const ELLIPSIS: char = '…';
+const ZERO_WIDTH_SPACE: &str = "\u{200b}";
+const NO_BREAK_SPACE: &str = "\u{00a0}";
+
+/// Number of terminal columns the string occupies
+pub fn display_width(s: &str) -> usize {
+    UnicodeWidthStr::width(s)
+}
+
+/// Rows the line made of `spans` takes up in a paragraph wrapped to `columns` with
+/// `Wrap { trim: false }`. Counted the way ratatui wraps: between words, splitting only the
+/// words longer than a row
+pub fn wrapped_rows<'a>(spans: impl IntoIterator<Item = &'a str>, columns: usize) -> usize {
+    let columns = columns.max(1);
+    let mut rows = 0;
+    // Graphemes and columns of the row being filled, the word after it and the whitespace
+    // between them
+    let (mut row_graphemes, mut row_width) = (0, 0);
+    let (mut word_graphemes, mut word_width) = (0, 0);
+    let mut whitespace: VecDeque<usize> = VecDeque::new();
+    let mut whitespace_width = 0;
+    let mut after_word = false;
+
+    let graphemes = spans
+        .into_iter()
+        .flat_map(|span| span.graphemes(true))
+        // ratatui drops control characters, like the tabs in a diff
+        .filter(|grapheme| !grapheme.contains(char::is_control));
+    for grapheme in graphemes {
+        let is_whitespace = grapheme == ZERO_WIDTH_SPACE
+            || grapheme.chars().all(char::is_whitespace) && grapheme != NO_BREAK_SPACE;
+        let width = display_width(grapheme);
+        if width > columns {
+            continue;
+        }
+
+        // The word and the whitespace before it go on the row once the word ends, or right
+        // away when they don't even fit an empty row
+        if (after_word && is_whitespace)
+            || (row_graphemes == 0 && word_width + whitespace_width + width > columns)
+        {
+            row_graphemes += whitespace.len() + word_graphemes;
+            row_width += whitespace_width + word_width;
+            whitespace.clear();
+            whitespace_width = 0;
+            (word_graphemes, word_width) = (0, 0);
+        }
+
+        if row_width >= columns
+            || (width > 0 && row_width + whitespace_width + word_width >= columns)
+        {
+            let mut remaining = columns.saturating_sub(row_width);
+            rows += 1;
+            (row_graphemes, row_width) = (0, 0);
+            // The whitespace that still fit ends the row instead of starting the next one
+            while let Some(&front) = whitespace.front() {
+                if front > remaining {
+                    break;
+                }
+                whitespace_width -= front;
+                remaining -= front;
+                whitespace.pop_front();
+            }
+            if is_whitespace && whitespace.is_empty() {
+                continue;
+            }
+        }
+
+        if is_whitespace {
+            whitespace_width += width;
+            whitespace.push_back(width);
+        } else {
+            word_width += width;
+            word_graphemes += 1;
+        }
+        after_word = !is_whitespace;
+    }
+
+    if row_graphemes + whitespace.len() + word_graphemes > 0 {
+        rows += 1;
+    }
+    rows.max(1)
+}
+
+/// Start of the grapheme before byte `offset` of `s`. Editing by graphemes keeps characters
+/// made of several code points, like accented letters and emoji, in one piece
+pub fn previous_grapheme(s: &str, offset: usize) -> usize {
+    s[..offset]
+        .grapheme_indices(true)
+        .next_back()
+        .map_or(0, |(start, _)| start)
+}
+
+/// End of the grapheme after byte `offset` of `s`
+pub fn next_grapheme(s: &str, offset: usize) -> usize {
+    s[offset..]
+        .graphemes(true)
+        .next()
+        .map_or(s.len(), |grapheme| offset + grapheme.len())
+}
+
+/// Pasted text for a single line input, line breaks and tabs become spaces and a trailing
+/// line break is dropped
+pub fn single_line(text: &str) -> String {
+    text.trim_end_matches(['\r', '\n'])
+        .replace("\r\n", " ")
+        .replace(['\r', '\n', '\t'], " ")
+}
+
+/// Split a string at the last char boundary that fits into `max_width` columns
+pub fn split_at_width(s: &str, max_width: usize) -> (&str, &str) {
+    let mut width = 0;
+    for (byte_pos, c) in s.char_indices() {
+        let char_width = c.width().unwrap_or(0);
+        if width + char_width > max_width {
+            return s.split_at(byte_pos);
+        }
+        width += char_width;
+    }
+    (s, "")
+}
+
+/// Truncate a string to fit into `max_width` columns, ending in an ellipsis
+/// when anything had to be cut off
+pub fn truncate(s: &str, max_width: usize) -> String {
+    if display_width(s) <= max_width {
+        return s.to_string();
+    }
+    if max_width == 0 {
+        return String::new();
+    }
+
+    let (head, _) = split_at_width(s, max_width - 1);
+    let mut truncated = head.to_string();
+    truncated.push(ELLIPSIS);
+    truncated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncate_ascii() {
+        assert_eq!(truncate("hello world", 20), "hello world");
+        assert_eq!(truncate("hello world", 6), "hello…");
+        assert_eq!(truncate("hello", 0), "");
+    }
+
+    #[test]
+    fn test_wrapped_rows() {
+        assert_eq!(wrapped_rows([""], 80), 1);
+        assert_eq!(wrapped_rows(["a".repeat(80).as_str()], 80), 1);
+        assert_eq!(wrapped_rows(["a".repeat(81).as_str()], 80), 2);
+        assert_eq!(wrapped_rows(["abcde"], 0), 5);
+        // Words move to the next row whole, which takes more rows than the width suggests
+        assert_eq!(wrapped_rows(["aa bbbb cc"], 5), 3);
+        assert_eq!(wrapped_rows(["+", "let x", " = 1;"], 6), 2);
+        // Wide characters don't fit the last column
+        assert_eq!(wrapped_rows(["ab日本"], 3), 2);
+    }
+
+    #[test]
+    fn test_truncate_wide_chars() {
+        // each CJK char takes two columns
+        assert_eq!(truncate("日本語テキスト", 7), "日本語…");
+        assert!(display_width(&truncate("🦀🦀🦀🦀", 5)) <= 5);
+    }
+
+    #[test]
+    fn test_grapheme_boundaries() {
+        // An e with a combining acute accent, and a flag made of two code points
+        let s = "e\u{301}🇩🇪x";
+        assert_eq!(next_grapheme(s, 0), 3);
+        assert_eq!(next_grapheme(s, 3), 11);
+        assert_eq!(previous_grapheme(s, 11), 3);
+        assert_eq!(previous_grapheme(s, 3), 0);
+        assert_eq!(next_grapheme(s, s.len()), s.len());
+    }
+
+    #[test]
+    fn test_single_line() {
+        assert_eq!(single_line("main\n"), "main");
+        assert_eq!(single_line("a\r\nb\tc\r\n"), "a b c");
+    }
+
+    #[test]
+    fn test_split_at_width_never_splits_chars() {
+        let (head, tail) = split_at_width("aé日b", 3);
+        assert_eq!(head, "aé");
+        assert_eq!(tail, "日b");
+    }
+}
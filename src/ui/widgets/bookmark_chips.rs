@@ -0,0 +1,42 @@
+use ratatui::{
+    style::{
+        Modifier,
+        Style,
+    },
+    text::Span,
+};
+
+use crate::{
+    app::App,
+    ui::text::display_width,
+};
+
+/// Render bookmark names as colored chips, or `[name]` without colors.
+/// Every chip is followed by a space
+pub fn bookmark_chips(app: &App, bookmarks: &[String]) -> Vec<Span<'static>> {
+    bookmarks
+        .iter()
+        .flat_map(|name| {
+            let chip = if app.theme.uses_color() {
+                Span::styled(
+                    format!(" {name} "),
+                    Style::default()
+                        .fg(app.theme.base)
+                        .bg(app.theme.mauve)
+                        .add_modifier(Modifier::BOLD),
+                )
+            } else {
+                Span::styled(
+                    format!("[{name}]"),
+                    Style::default().add_modifier(Modifier::BOLD),
+                )
+            };
+            [chip, Span::raw(" ")]
+        })
+        .collect()
+}
+
+/// Columns taken by `bookmark_chips`
+pub fn chips_width(bookmarks: &[String]) -> usize {
+    bookmarks.iter().map(|name| display_width(name) + 3).sum()
+}
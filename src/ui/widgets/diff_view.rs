@@ -1 +1,272 @@
-// Diff view widget - placeholder
+use std::sync::Arc;
+
+use ratatui::style::Color;
+use similar::{
+    ChangeTag,
+    TextDiff,
+};
+use syntect::{
+    easy::HighlightLines,
+    highlighting::Theme,
+    parsing::SyntaxSet,
+};
+
+use crate::jj::background::BackgroundTask;
+
+/// Syntax colored pieces of a diff line, without its `+`/`-` marker
+pub type HighlightedLine = Vec<(Color, String)>;
+
+/// Highlights diffs on a background thread, so selecting a large file never blocks input
+#[derive(Default)]
+pub struct DiffHighlighter {
+    /// Highlighted lines of the current diff, `None` for diff and hunk headers
+    lines: Option<Vec<Option<HighlightedLine>>>,
+    task:  BackgroundTask<Vec<Option<HighlightedLine>>>,
+}
+
+impl DiffHighlighter {
+    /// Start highlighting `diff` of the file at `path`, dropping the result of any earlier diff
+    pub fn start(&mut self, diff: String, path: String, syntax_set: Arc<SyntaxSet>, theme: Theme) {
+        self.lines = None;
+        self.task
+            .start(move || highlight(&diff, &path, &syntax_set, &theme));
+    }
+
+    pub fn clear(&mut self) {
+        self.lines = None;
+        self.task.cancel();
+    }
+
+    /// Pick up the result of the background thread, true when the highlighted lines arrived
+    pub fn poll(&mut self) -> bool {
+        let Some((lines, _)) = self.task.poll() else {
+            return false;
+        };
+        self.lines = Some(lines);
+        true
+    }
+
+    /// Highlighted content of line `index` of the diff, once it is ready
+    pub fn line(&self, index: usize) -> Option<&HighlightedLine> {
+        self.lines.as_ref()?.get(index)?.as_ref()
+    }
+}
+
+/// What a piece of a word diff line is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WordChange {
+    Equal,
+    Removed,
+    Added,
+}
+
+/// How a line of a diff is shown as a word diff
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WordDiffLine {
+    /// Shown as it is, like context and header lines or changes that only add or remove lines
+    Line,
+    /// A removed line, its words are shown in the added lines below it
+    Folded,
+    /// An added line with the words removed from it, the changed words marked
+    Words(Vec<(WordChange, String)>),
+}
+
+/// Fold the removed lines of git style diff output into the added lines below them, showing
+/// which words changed. Only changes whose first removed line `prose` is true for are folded
+pub fn word_diff(diff: &str, prose: impl Fn(usize) -> bool) -> Vec<WordDiffLine> {
+    let lines: Vec<&str> = diff.lines().collect();
+    let mut word_lines = vec![WordDiffLine::Line; lines.len()];
+    // `---` and `+++` headers only look like changed lines outside of hunks
+    let mut in_hunk = false;
+    let mut i = 0;
+    while i < lines.len() {
+        if lines[i].starts_with("@@") {
+            in_hunk = true;
+        } else if lines[i].starts_with("diff ") {
+            in_hunk = false;
+        }
+        if !in_hunk || !lines[i].starts_with('-') {
+            i += 1;
+            continue;
+        }
+
+        let removed_end = i + count_prefixed(&lines[i..], '-');
+        let added_end = removed_end + count_prefixed(&lines[removed_end..], '+');
+        if added_end > removed_end && prose(i) {
+            let old = strip_markers(&lines[i..removed_end]);
+            let new = strip_markers(&lines[removed_end..added_end]);
+            word_lines[i..removed_end].fill(WordDiffLine::Folded);
+            for (line, words) in word_lines[removed_end..added_end]
+                .iter_mut()
+                .zip(changed_words(&old, &new))
+            {
+                *line = WordDiffLine::Words(words);
+            }
+        }
+        i = added_end;
+    }
+    word_lines
+}
+
+fn count_prefixed(lines: &[&str], marker: char) -> usize {
+    lines
+        .iter()
+        .take_while(|line| line.starts_with(marker))
+        .count()
+}
+
+fn strip_markers(lines: &[&str]) -> String {
+    lines
+        .iter()
+        .map(|line| &line[1..])
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Words of `new` with the words of `old` that were removed, a line for every line of `new`
+fn changed_words(old: &str, new: &str) -> Vec<Vec<(WordChange, String)>> {
+    fn push(lines: &mut [Vec<(WordChange, String)>], kind: WordChange, text: &str) {
+        let Some(line) = lines.last_mut() else {
+            return;
+        };
+        match line.last_mut() {
+            Some((last_kind, last_text)) if *last_kind == kind => last_text.push_str(text),
+            _ if text.is_empty() => {}
+            _ => line.push((kind, text.to_string())),
+        }
+    }
+
+    let mut lines = vec![Vec::new()];
+
+    for change in TextDiff::from_words(old, new).iter_all_changes() {
+        let kind = match change.tag() {
+            ChangeTag::Equal => WordChange::Equal,
+            ChangeTag::Delete => WordChange::Removed,
+            ChangeTag::Insert => WordChange::Added,
+        };
+        for (n, text) in change.value().split('\n').enumerate() {
+            if n > 0 {
+                // Only the lines of `new` are shown, removed line breaks become spaces
+                if kind == WordChange::Removed {
+                    push(&mut lines, kind, " ");
+                } else {
+                    lines.push(Vec::new());
+                }
+            }
+            push(&mut lines, kind, text);
+        }
+    }
+    lines
+}
+
+/// Lines describing the diff rather than the file content
+pub fn is_header(line: &str) -> bool {
+    ["+++", "---", "@@", "diff ", "index "]
+        .iter()
+        .any(|prefix| line.starts_with(prefix))
+}
+
+/// Highlight every line of `diff` as the language of `path`, `None` for the headers
+pub fn highlight(
+    diff: &str,
+    path: &str,
+    syntax_set: &SyntaxSet,
+    theme: &Theme,
+) -> Vec<Option<HighlightedLine>> {
+    let syntax = syntax_set
+        .find_syntax_for_file(path)
+        .ok()
+        .flatten()
+        .unwrap_or_else(|| syntax_set.find_syntax_plain_text());
+
+    diff.lines()
+        .map(|line| {
+            if is_header(line) {
+                return None;
+            }
+            let content = line
+                .strip_prefix('+')
+                .or_else(|| line.strip_prefix('-'))
+                .unwrap_or(line);
+
+            // Lines of a diff are not contiguous, each one is highlighted on its own
+            let mut highlighter = HighlightLines::new(syntax, theme);
+            let ranges = highlighter.highlight_line(content, syntax_set).ok()?;
+            Some(
+                ranges
+                    .into_iter()
+                    .map(|(style, text)| {
+                        (syntect_to_ratatui_color(style.foreground), text.to_string())
+                    })
+                    .collect(),
+            )
+        })
+        .collect()
+}
+
+// Helper function to convert syntect color to ratatui color
+const fn syntect_to_ratatui_color(color: syntect::highlighting::Color) -> Color {
+    Color::Rgb(color.r, color.g, color.b)
+}
+
+#[cfg(test)]
+mod tests {
+    use syntect::highlighting::ThemeSet;
+
+    use super::*;
+
+    #[test]
+    fn test_word_diff() {
+        let diff = "--- a/README.md\n+++ b/README.md\n@@ -1,2 +1,2 @@\n\
+                    -The quick brown fox\n\
+                    +The slow brown fox\n\
+                    \x20jumps\n\
+                    +over\n";
+
+        let lines = word_diff(diff, |_| true);
+        assert_eq!(
+            lines[..3],
+            [WordDiffLine::Line, WordDiffLine::Line, WordDiffLine::Line]
+        );
+        assert_eq!(lines[3], WordDiffLine::Folded);
+        assert_eq!(
+            lines[4],
+            WordDiffLine::Words(vec![
+                (WordChange::Equal, "The ".to_string()),
+                (WordChange::Removed, "quick".to_string()),
+                (WordChange::Added, "slow".to_string()),
+                (WordChange::Equal, " brown fox".to_string()),
+            ])
+        );
+        // Only added lines stay as they are
+        assert_eq!(lines[6], WordDiffLine::Line);
+        assert!(
+            word_diff(diff, |_| false)
+                .iter()
+                .all(|line| *line == WordDiffLine::Line)
+        );
+    }
+
+    #[test]
+    fn test_highlight_skips_headers() {
+        let syntax_set = SyntaxSet::load_defaults_newlines();
+        let themes = ThemeSet::load_defaults();
+        let diff = "--- a/main.rs\n+++ b/main.rs\n@@ -1 +1 @@\n-fn old() {}\n+fn new() {}\n";
+
+        let lines = highlight(
+            diff,
+            "main.rs",
+            &syntax_set,
+            &themes.themes["base16-ocean.dark"],
+        );
+        assert_eq!(lines.len(), 5);
+        assert!(lines[..3].iter().all(Option::is_none));
+        let added: String = lines[4]
+            .as_ref()
+            .unwrap()
+            .iter()
+            .map(|(_, text)| text.as_str())
+            .collect();
+        assert_eq!(added, "fn new() {}");
+    }
+}
@@ -0,0 +1,338 @@
+use crossterm::event::{
+    KeyCode,
+    KeyEvent,
+    KeyModifiers,
+};
+use ratatui::{
+    Frame,
+    layout::{
+        Constraint,
+        Direction,
+        Layout,
+        Rect,
+    },
+    style::{
+        Modifier,
+        Style,
+    },
+    text::{
+        Line,
+        Span,
+    },
+    widgets::{
+        Block,
+        Borders,
+        Clear,
+        Paragraph,
+    },
+};
+
+use crate::{
+    app::App,
+    ui::text::{
+        next_grapheme,
+        previous_grapheme,
+        single_line,
+    },
+};
+
+/// Checks the trimmed text of a field, the message is shown below the field
+pub type Validator = fn(&str) -> Result<(), String>;
+
+/// A single line text field of a `Form`
+#[derive(Debug, Clone)]
+pub struct FormField {
+    pub label:           &'static str,
+    pub value:           String,
+    /// Byte offset of the cursor in `value`, always between two graphemes
+    pub cursor_position: usize,
+    pub validate:        Validator,
+    /// Why the value was rejected on the last submit
+    pub error:           Option<String>,
+}
+
+impl FormField {
+    pub const fn new(label: &'static str, validate: Validator) -> Self {
+        Self {
+            label,
+            value: String::new(),
+            cursor_position: 0,
+            validate,
+            error: None,
+        }
+    }
+
+    /// Start with `value` typed in, the cursor at its end
+    pub fn with_value(mut self, value: &str) -> Self {
+        self.value = value.to_string();
+        self.cursor_position = value.len();
+        self
+    }
+
+    /// Insert pasted `text` at the cursor in one go
+    pub fn insert_str(&mut self, text: &str) {
+        let text = single_line(text);
+        self.value.insert_str(self.cursor_position, &text);
+        self.cursor_position += text.len();
+        self.error = None;
+    }
+}
+
+/// What a key press in the form resulted in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FormEvent {
+    None,
+    Cancel,
+    /// Every field is valid, these are their trimmed values in order
+    Submit(Vec<String>),
+}
+
+/// Several text fields filled in at once, Tab moves between them
+#[derive(Debug, Clone)]
+pub struct Form {
+    pub fields:  Vec<FormField>,
+    pub focused: usize,
+}
+
+impl Form {
+    pub const fn new(fields: Vec<FormField>) -> Self {
+        Self { fields, focused: 0 }
+    }
+
+    /// Insert pasted `text` into the focused field
+    pub fn insert_str(&mut self, text: &str) {
+        if let Some(field) = self.fields.get_mut(self.focused) {
+            field.insert_str(text);
+        }
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> FormEvent {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Esc => return FormEvent::Cancel,
+            KeyCode::Enter => return self.submit(),
+            KeyCode::Tab | KeyCode::Down => {
+                self.focused = (self.focused + 1) % self.fields.len().max(1);
+            }
+            KeyCode::BackTab | KeyCode::Up => {
+                self.focused = self
+                    .focused
+                    .checked_sub(1)
+                    .unwrap_or_else(|| self.fields.len().saturating_sub(1));
+            }
+            code => {
+                let Some(field) = self.fields.get_mut(self.focused) else {
+                    return FormEvent::None;
+                };
+                match code {
+                    KeyCode::Char('u') if ctrl => {
+                        field.value.replace_range(..field.cursor_position, "");
+                        field.cursor_position = 0;
+                    }
+                    KeyCode::Char(c) if !ctrl => {
+                        // A combining character joins the grapheme before the cursor
+                        field.value.insert(field.cursor_position, c);
+                        field.cursor_position += c.len_utf8();
+                    }
+                    KeyCode::Backspace if field.cursor_position > 0 => {
+                        let start = previous_grapheme(&field.value, field.cursor_position);
+                        field.value.replace_range(start..field.cursor_position, "");
+                        field.cursor_position = start;
+                    }
+                    KeyCode::Left => {
+                        field.cursor_position =
+                            previous_grapheme(&field.value, field.cursor_position);
+                    }
+                    KeyCode::Right => {
+                        field.cursor_position = next_grapheme(&field.value, field.cursor_position);
+                    }
+                    KeyCode::Home => field.cursor_position = 0,
+                    KeyCode::End => field.cursor_position = field.value.len(),
+                    _ => return FormEvent::None,
+                }
+                // Typing fixes the problem, or at least the old message no longer applies
+                field.error = None;
+            }
+        }
+        FormEvent::None
+    }
+
+    /// Validate every field, focusing the first invalid one instead of submitting
+    fn submit(&mut self) -> FormEvent {
+        for field in &mut self.fields {
+            field.error = (field.validate)(field.value.trim()).err();
+        }
+        if let Some(invalid) = self.fields.iter().position(|field| field.error.is_some()) {
+            self.focused = invalid;
+            return FormEvent::None;
+        }
+        FormEvent::Submit(
+            self.fields
+                .iter()
+                .map(|field| field.value.trim().to_string())
+                .collect(),
+        )
+    }
+}
+
+pub fn required(value: &str) -> Result<(), String> {
+    if value.is_empty() {
+        Err("Required".to_string())
+    } else {
+        Ok(())
+    }
+}
+
+/// A name jj can take as a single argument, like a remote or bookmark name
+pub fn name(value: &str) -> Result<(), String> {
+    required(value)?;
+    if value.contains(char::is_whitespace) {
+        return Err("Can't contain spaces".to_string());
+    }
+    Ok(())
+}
+
+/// A line of a file, starting at 1, or nothing
+pub fn line_number(value: &str) -> Result<(), String> {
+    if value.is_empty() || value.parse::<usize>().is_ok_and(|line| line > 0) {
+        Ok(())
+    } else {
+        Err("Must be a line number".to_string())
+    }
+}
+
+pub fn render_form(f: &mut Frame, app: &App, title: &str, form: &Form, area: Rect) {
+    // Every field takes its bordered input and a line for its error
+    let field_count = u16::try_from(form.fields.len()).unwrap_or(u16::MAX);
+    let height = field_count
+        .saturating_mul(4)
+        .saturating_add(3)
+        .min(area.height);
+    let width = (area.width * 3 / 5).max(40).min(area.width);
+    let popup_area = Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    };
+
+    let block = Block::default()
+        .title(title.to_string())
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(app.theme.lavender))
+        .style(Style::default().bg(app.theme.surface0));
+    let inner_area = block.inner(popup_area);
+
+    let mut constraints: Vec<Constraint> = form
+        .fields
+        .iter()
+        .flat_map(|_| [Constraint::Length(3), Constraint::Length(1)])
+        .collect();
+    constraints.push(Constraint::Length(1));
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(constraints)
+        .split(inner_area);
+
+    f.render_widget(Clear, popup_area);
+    f.render_widget(block, popup_area);
+
+    for (index, field) in form.fields.iter().enumerate() {
+        let focused = index == form.focused;
+        let border = if field.error.is_some() {
+            app.theme.red
+        } else if focused {
+            app.theme.peach
+        } else {
+            app.theme.surface2
+        };
+
+        let mut value = field.value.clone();
+        if focused {
+            value.insert(field.cursor_position.min(value.len()), '█');
+        }
+        let input = Paragraph::new(Line::from(Span::styled(
+            value,
+            Style::default().fg(app.theme.text),
+        )))
+        .block(
+            Block::default()
+                .title(field.label)
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(border)),
+        );
+        f.render_widget(input, chunks[index * 2]);
+
+        if let Some(error) = &field.error {
+            f.render_widget(
+                Paragraph::new(Span::styled(
+                    format!(" {error}"),
+                    Style::default()
+                        .fg(app.theme.red)
+                        .add_modifier(Modifier::BOLD),
+                )),
+                chunks[index * 2 + 1],
+            );
+        }
+    }
+
+    let help = Paragraph::new(Span::styled(
+        "Tab/Shift+Tab: next/previous field | Enter: submit | Esc: cancel",
+        Style::default().fg(app.theme.subtext0),
+    ));
+    f.render_widget(help, chunks[form.fields.len() * 2]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(form: &mut Form, code: KeyCode) -> FormEvent {
+        form.handle_key(KeyEvent::from(code))
+    }
+
+    #[test]
+    fn test_form_validates_before_submitting() {
+        let mut form = Form::new(vec![
+            FormField::new("Name", name),
+            FormField::new("URL", required),
+        ]);
+
+        for c in "up stream".chars() {
+            press(&mut form, KeyCode::Char(c));
+        }
+        press(&mut form, KeyCode::Tab);
+        for c in "https://example.com/repo.git".chars() {
+            press(&mut form, KeyCode::Char(c));
+        }
+
+        // The invalid name gets the focus back
+        assert_eq!(press(&mut form, KeyCode::Enter), FormEvent::None);
+        assert_eq!(form.focused, 0);
+        assert!(form.fields[0].error.is_some());
+        assert!(form.fields[1].error.is_none());
+
+        form.fields[0].cursor_position = 3;
+        press(&mut form, KeyCode::Backspace);
+        assert!(form.fields[0].error.is_none());
+        assert_eq!(
+            press(&mut form, KeyCode::Enter),
+            FormEvent::Submit(vec![
+                "upstream".to_string(),
+                "https://example.com/repo.git".to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn test_form_focus_wraps() {
+        let mut form = Form::new(vec![
+            FormField::new("A", required),
+            FormField::new("B", required),
+        ]);
+        press(&mut form, KeyCode::BackTab);
+        assert_eq!(form.focused, 1);
+        press(&mut form, KeyCode::Tab);
+        assert_eq!(form.focused, 0);
+    }
+}
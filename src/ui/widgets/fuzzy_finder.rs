@@ -0,0 +1,392 @@
+use crossterm::event::{
+    KeyCode,
+    KeyEvent,
+    KeyModifiers,
+};
+use ratatui::{
+    Frame,
+    layout::Rect,
+    style::{
+        Modifier,
+        Style,
+    },
+    text::{
+        Line,
+        Span,
+    },
+    widgets::{
+        List,
+        ListItem,
+        ListState,
+        Paragraph,
+    },
+};
+
+use crate::{
+    app::App,
+    ui::text::{
+        next_grapheme,
+        previous_grapheme,
+        single_line,
+    },
+};
+
+/// Something that can be searched for in a `FuzzyFinder`
+pub trait FuzzyItem {
+    /// Text the query is matched against
+    fn search_text(&self) -> String;
+
+    /// Text the query is replaced with when completing with Tab
+    fn completion(&self) -> String {
+        self.search_text()
+    }
+}
+
+impl FuzzyItem for String {
+    fn search_text(&self) -> String {
+        self.clone()
+    }
+}
+
+/// An item matching the query, `positions` are the char indices of the
+/// matched characters in its search text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FuzzyMatch {
+    pub index:     usize,
+    pub score:     i64,
+    pub positions: Vec<usize>,
+}
+
+/// What a key press in the finder resulted in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FinderEvent {
+    /// Nothing to do for the caller
+    None,
+    /// The query or the selection changed
+    Changed,
+    Cancel,
+    /// The item at this index of `items` was picked
+    Pick(usize),
+    /// The typed query was picked instead of one of the items
+    Query(String),
+}
+
+/// Text input with a list of items filtered and ranked by fuzzy matching
+#[derive(Debug, Clone)]
+pub struct FuzzyFinder<T> {
+    pub items:           Vec<T>,
+    pub query:           String,
+    /// Byte offset of the cursor in `query`, always between two graphemes
+    pub cursor_position: usize,
+    pub selected_index:  usize,
+    /// Offer the typed query as an extra row after the matches
+    pub query_row:       bool,
+}
+
+impl<T: FuzzyItem> FuzzyFinder<T> {
+    pub const fn new(items: Vec<T>, query_row: bool) -> Self {
+        Self {
+            items,
+            query: String::new(),
+            cursor_position: 0,
+            selected_index: 0,
+            query_row,
+        }
+    }
+
+    /// Items matching the query, best match first
+    pub fn matches(&self) -> Vec<FuzzyMatch> {
+        let mut matches: Vec<FuzzyMatch> = self
+            .items
+            .iter()
+            .enumerate()
+            .filter_map(|(index, item)| {
+                fuzzy_match(&self.query, &item.search_text()).map(|(score, positions)| FuzzyMatch {
+                    index,
+                    score,
+                    positions,
+                })
+            })
+            .collect();
+        // Stable sort keeps the item order for equal scores
+        matches.sort_by_key(|m| std::cmp::Reverse(m.score));
+        matches
+    }
+
+    /// The currently selected item, `None` when the query row is selected
+    pub fn selected(&self) -> Option<&T> {
+        self.matches()
+            .get(self.selected_index)
+            .map(|m| &self.items[m.index])
+    }
+
+    fn has_query_row(&self) -> bool {
+        self.query_row && !self.query.trim().is_empty()
+    }
+
+    fn row_count(&self) -> usize {
+        self.matches().len() + usize::from(self.has_query_row())
+    }
+
+    /// Insert pasted `text` at the cursor in one go
+    pub fn insert_str(&mut self, text: &str) {
+        let text = single_line(text);
+        self.query.insert_str(self.cursor_position, &text);
+        self.cursor_position += text.len();
+        self.selected_index = 0;
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> FinderEvent {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+
+        match key.code {
+            KeyCode::Esc => return FinderEvent::Cancel,
+            KeyCode::Enter => {
+                if let Some(m) = self.matches().get(self.selected_index) {
+                    return FinderEvent::Pick(m.index);
+                }
+                if self.has_query_row() {
+                    return FinderEvent::Query(self.query.trim().to_string());
+                }
+                return FinderEvent::None;
+            }
+            KeyCode::Up => self.select_previous(),
+            KeyCode::Char('p') if ctrl => self.select_previous(),
+            KeyCode::Down => self.select_next(),
+            KeyCode::Char('n') if ctrl => self.select_next(),
+            KeyCode::Tab => {
+                // Complete the query with the selected item
+                let Some(completion) = self.selected().map(FuzzyItem::completion) else {
+                    return FinderEvent::None;
+                };
+                self.query = completion;
+                self.cursor_position = self.query.len();
+                self.selected_index = 0;
+            }
+            KeyCode::Char(c) => {
+                // A combining character joins the grapheme before the cursor
+                self.query.insert(self.cursor_position, c);
+                self.cursor_position += c.len_utf8();
+                self.selected_index = 0;
+            }
+            KeyCode::Backspace => {
+                if self.cursor_position == 0 {
+                    return FinderEvent::None;
+                }
+                let start = previous_grapheme(&self.query, self.cursor_position);
+                self.query.replace_range(start..self.cursor_position, "");
+                self.cursor_position = start;
+                self.selected_index = 0;
+            }
+            KeyCode::Left => {
+                self.cursor_position = previous_grapheme(&self.query, self.cursor_position);
+            }
+            KeyCode::Right => {
+                self.cursor_position = next_grapheme(&self.query, self.cursor_position);
+            }
+            KeyCode::Home => {
+                self.cursor_position = 0;
+            }
+            KeyCode::End => {
+                self.cursor_position = self.query.len();
+            }
+            _ => return FinderEvent::None,
+        }
+        FinderEvent::Changed
+    }
+
+    const fn select_previous(&mut self) {
+        self.selected_index = self.selected_index.saturating_sub(1);
+    }
+
+    fn select_next(&mut self) {
+        let rows = self.row_count();
+        if rows > 0 {
+            self.selected_index = (self.selected_index + 1).min(rows - 1);
+        }
+    }
+}
+
+/// Match `query` against `text` as a case-insensitive subsequence.
+/// Consecutive characters and matches at the start of words score higher,
+/// an empty query matches everything
+pub fn fuzzy_match(query: &str, text: &str) -> Option<(i64, Vec<usize>)> {
+    let mut score = 0;
+    let mut positions = Vec::new();
+    let mut text_chars = text.chars().enumerate();
+    let mut previous_char = ' ';
+
+    for query_char in query.chars().flat_map(char::to_lowercase) {
+        loop {
+            let (index, c) = text_chars.next()?;
+            let is_word_start = !previous_char.is_alphanumeric();
+            previous_char = c;
+
+            if c.to_lowercase().eq(std::iter::once(query_char)) {
+                score += 1;
+                if positions.last().is_some_and(|&prev| prev + 1 == index) {
+                    score += 5;
+                }
+                if is_word_start {
+                    score += 3;
+                }
+                positions.push(index);
+                break;
+            }
+        }
+    }
+
+    Some((score, positions))
+}
+
+/// Split `text` into spans, using `matched` for the chars at `positions`.
+/// `offset` is the char index of the start of `text` in the search text
+pub fn highlight_matches(
+    text: &str,
+    positions: &[usize],
+    offset: usize,
+    style: Style,
+    matched: Style,
+) -> Vec<Span<'static>> {
+    let mut spans = Vec::new();
+    let mut current = String::new();
+    let mut current_matched = false;
+
+    for (index, c) in text.chars().enumerate() {
+        let is_match = positions.contains(&(offset + index));
+        if is_match != current_matched && !current.is_empty() {
+            let style = if current_matched { matched } else { style };
+            spans.push(Span::styled(std::mem::take(&mut current), style));
+        }
+        current_matched = is_match;
+        current.push(c);
+    }
+    if !current.is_empty() {
+        spans.push(Span::styled(
+            current,
+            if current_matched { matched } else { style },
+        ));
+    }
+    spans
+}
+
+/// Style for the matched characters of a row
+pub fn match_style(app: &App) -> Style {
+    if app.theme.uses_color() {
+        Style::default()
+            .fg(app.theme.yellow)
+            .add_modifier(Modifier::BOLD)
+    } else {
+        Style::default().add_modifier(Modifier::UNDERLINED)
+    }
+}
+
+/// Render the query with a cursor
+pub fn render_query<T>(f: &mut Frame, app: &App, finder: &FuzzyFinder<T>, area: Rect) {
+    let mut query = finder.query.clone();
+    query.insert(finder.cursor_position.min(query.len()), '█');
+
+    let line = Line::from(vec![
+        Span::styled("> ", Style::default().fg(app.theme.peach)),
+        Span::styled(query, Style::default().fg(app.theme.text)),
+    ]);
+    f.render_widget(Paragraph::new(line), area);
+}
+
+/// Render the matches with `row`, followed by the query row rendered with `query_row`
+pub fn render_matches<T: FuzzyItem>(
+    f: &mut Frame,
+    app: &App,
+    finder: &FuzzyFinder<T>,
+    area: Rect,
+    row: impl Fn(&T, &FuzzyMatch) -> Line<'static>,
+    query_row: impl Fn(&str) -> Line<'static>,
+) {
+    let mut items: Vec<ListItem> = finder
+        .matches()
+        .iter()
+        .map(|m| ListItem::new(row(&finder.items[m.index], m)))
+        .collect();
+    if finder.has_query_row() {
+        items.push(ListItem::new(query_row(finder.query.trim())));
+    }
+
+    let highlight = if app.theme.uses_color() {
+        Style::default()
+            .bg(app.theme.surface1)
+            .add_modifier(Modifier::BOLD)
+    } else {
+        app.theme.highlight_style()
+    };
+    let list = List::new(items).highlight_style(highlight);
+    let mut state = ListState::default().with_selected(Some(finder.selected_index));
+    f.render_stateful_widget(list, area, &mut state);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    impl FuzzyItem for &str {
+        fn search_text(&self) -> String {
+            (*self).to_string()
+        }
+    }
+
+    #[test]
+    fn test_fuzzy_match() {
+        assert_eq!(fuzzy_match("mn", "main").map(|m| m.1), Some(vec![0, 3]));
+        assert!(fuzzy_match("nm", "main").is_none());
+        assert!(fuzzy_match("MAIN", "main").is_some());
+        assert!(fuzzy_match("", "main").is_some());
+        // Consecutive and word start matches win
+        assert!(fuzzy_match("fix", "fix parser") > fuzzy_match("fix", "f-i-x"));
+        assert!(fuzzy_match("p", "fix parser") > fuzzy_match("p", "fix api"));
+    }
+
+    #[test]
+    fn test_highlight_matches() {
+        let style = Style::default();
+        let matched = Style::default().add_modifier(Modifier::BOLD);
+        let spans = highlight_matches("main", &[4, 5], 3, style, matched);
+        let parts: Vec<&str> = spans.iter().map(|s| s.content.as_ref()).collect();
+        assert_eq!(parts, vec!["m", "ai", "n"]);
+        assert_eq!(spans[1].style, matched);
+    }
+
+    #[test]
+    fn test_query_row_after_matches() {
+        let mut finder = FuzzyFinder::new(vec!["main", "feature"], true);
+        for c in "mai".chars() {
+            finder.handle_key(KeyEvent::from(KeyCode::Char(c)));
+        }
+        assert_eq!(finder.matches().len(), 1);
+        assert_eq!(
+            finder.handle_key(KeyEvent::from(KeyCode::Enter)),
+            FinderEvent::Pick(0)
+        );
+
+        finder.handle_key(KeyEvent::from(KeyCode::Down));
+        assert_eq!(
+            finder.handle_key(KeyEvent::from(KeyCode::Enter)),
+            FinderEvent::Query("mai".to_string())
+        );
+    }
+
+    #[test]
+    fn test_edit_by_grapheme() {
+        let mut finder = FuzzyFinder::new(vec!["café"], false);
+        // A decomposed é, as some input methods type it
+        for c in "cafe\u{301}日".chars() {
+            finder.handle_key(KeyEvent::from(KeyCode::Char(c)));
+        }
+        finder.handle_key(KeyEvent::from(KeyCode::Left));
+        finder.handle_key(KeyEvent::from(KeyCode::Backspace));
+        assert_eq!(finder.query, "caf日");
+
+        finder.handle_key(KeyEvent::from(KeyCode::End));
+        finder.handle_key(KeyEvent::from(KeyCode::Backspace));
+        assert_eq!(finder.query, "caf");
+        assert_eq!(finder.cursor_position, 3);
+    }
+}
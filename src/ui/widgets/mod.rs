@@ -1,4 +1,9 @@
+pub mod bookmark_chips;
 pub mod diff_view;
 pub mod file_list;
+pub mod form;
+pub mod fuzzy_finder;
 pub mod popup;
+pub mod revision_picker;
+pub mod scrollbar;
 pub mod status_bar;
@@ -1,3 +1,4 @@
+use crossterm::event::KeyCode;
 use ratatui::{
     Frame,
     layout::{
@@ -19,18 +20,57 @@ use ratatui::{
         Block,
         Borders,
         Clear,
-        List,
-        ListItem,
         Paragraph,
         Wrap,
     },
 };
 use tui_textarea::TextArea;
 
+use super::{
+    fuzzy_finder::{
+        FuzzyFinder,
+        FuzzyItem,
+        highlight_matches,
+        match_style,
+        render_matches,
+        render_query,
+    },
+    scrollbar::render_scrollbar,
+};
 use crate::{
-    app::App,
+    app::{
+        App,
+        BookmarkPurpose,
+        HunkAction,
+        MenuItem,
+    },
     config::Theme,
-    jj::operations::BookmarkInfo,
+    jj::{
+        diff::{
+            self,
+            FilePatch,
+            HunkLocation,
+        },
+        journal::JournalEntry,
+        log::CommitInfo,
+        op_diff::{
+            CommitChangeKind,
+            OperationDiff,
+        },
+        operations::{
+            BookmarkInfo,
+            BookmarkTarget,
+        },
+        recent::RecentContext,
+    },
+    notifications::{
+        NotificationLevel,
+        format_duration,
+    },
+    ui::text::{
+        truncate,
+        wrapped_rows,
+    },
 };
 
 pub enum FeedbackType {
@@ -43,9 +83,21 @@ pub fn render_input_popup(
     theme: &Theme,
     title: &str,
     textarea: &mut TextArea<'static>,
+    details: &[String],
     area: Rect,
 ) {
-    let popup_area = centered_rect(60, 40, area);
+    let popup_area = if details.is_empty() {
+        centered_rect(60, 40, area)
+    } else {
+        // Extra information goes into its own box below the input
+        let outer = centered_rect(60, 70, area);
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(55), Constraint::Min(3)])
+            .split(outer);
+        render_input_details(f, theme, details, chunks[1]);
+        chunks[0]
+    };
 
     // Create an owned string for the title to satisfy 'static lifetime
     let title_owned = title.to_string();
@@ -75,13 +127,41 @@ pub fn render_input_popup(
     };
 
     let help_text = Paragraph::new(Span::styled(
-        "Enter to confirm | Alt+Enter for newline | Esc to cancel",
+        "Enter to confirm | Alt+Enter for newline | Up/Down for history | Esc to cancel",
         Style::default().fg(theme.subtext0),
     ));
 
     f.render_widget(help_text, help_area);
 }
 
+fn render_input_details(f: &mut Frame, theme: &Theme, details: &[String], area: Rect) {
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(theme.surface2))
+        .style(Style::default().bg(theme.surface0));
+
+    let lines: Vec<Line> = details
+        .iter()
+        .enumerate()
+        .map(|(i, detail)| {
+            // The first line is a heading for the rest
+            let style = if i == 0 {
+                Style::default()
+                    .fg(theme.subtext1)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(theme.text)
+            };
+            Line::from(Span::styled(detail.as_str(), style))
+        })
+        .collect();
+
+    let paragraph = Paragraph::new(lines).block(block);
+
+    f.render_widget(Clear, area);
+    f.render_widget(paragraph, area);
+}
+
 pub fn render_feedback_popup(
     f: &mut Frame,
     app: &App,
@@ -111,25 +191,176 @@ pub fn render_feedback_popup(
         .border_style(Style::default().fg(border_fg))
         .style(Style::default().bg(app.theme.surface0));
 
-    let text = vec![
-        Line::from(Span::styled(message, Style::default().fg(fg))),
-        Line::from(""),
+    // Errors explained by `JjError` put their hint on separate lines
+    let mut text: Vec<Line> = message
+        .lines()
+        .map(|line| Line::from(Span::styled(line, Style::default().fg(fg))))
+        .collect();
+    text.push(Line::from(""));
+    text.push(Line::from(Span::styled(
+        "Press Enter or Esc to close, j/k to scroll",
+        Style::default().fg(app.theme.subtext0),
+    )));
+
+    render_scrollable_text(f, app, text, block, popup_area, Alignment::Center);
+}
+
+/// Warn about the commits an operation left conflicted
+pub fn render_conflicts_popup(f: &mut Frame, app: &App, commits: &[CommitInfo], area: Rect) {
+    let popup_area = centered_rect(60, 40, area);
+    let block = Block::default()
+        .title(format!("{} new conflicted commit(s)", commits.len()))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(app.theme.red))
+        .style(Style::default().bg(app.theme.surface0));
+
+    let mut text = vec![
         Line::from(Span::styled(
-            "Press Enter or Esc to close",
-            Style::default().fg(app.theme.subtext0),
+            "The operation left conflicts behind, resolve them before pushing:",
+            Style::default().fg(app.theme.yellow),
         )),
+        Line::from(""),
     ];
+    text.extend(commits.iter().map(|commit| {
+        Line::from(vec![
+            Span::styled(&commit.change_id, Style::default().fg(app.theme.blue)),
+            Span::raw(" "),
+            Span::styled(
+                if commit.description.is_empty() {
+                    "(no description)"
+                } else {
+                    commit.description.as_str()
+                },
+                Style::default().fg(app.theme.text),
+            ),
+        ])
+    }));
+    text.push(Line::from(""));
+    text.push(Line::from(Span::styled(
+        "Enter: go to the first conflict | Esc: close | j/k: scroll",
+        Style::default().fg(app.theme.subtext0),
+    )));
+
+    render_scrollable_text(f, app, text, block, popup_area, Alignment::Left);
+}
 
-    let paragraph = Paragraph::new(text)
+/// A list popup with one selected row, like the hunks or the journal
+struct SelectableList<'a, T> {
+    items:    &'a [T],
+    selected: usize,
+    /// Shown instead of the rows when there are none
+    empty:    Option<&'a str>,
+    /// Keys of the popup, below the rows
+    help:     &'a str,
+}
+
+/// Render `list` into a popup, `row` draws an item in the style of whether it is selected
+fn render_selectable_list<T>(
+    f: &mut Frame,
+    app: &App,
+    block: Block<'_>,
+    popup_area: Rect,
+    list: &SelectableList<'_, T>,
+    row: impl Fn(&T, Style) -> Vec<Span<'static>>,
+) {
+    let mut lines = Vec::new();
+    if let (true, Some(empty)) = (list.items.is_empty(), list.empty) {
+        lines.push(Line::from(Span::styled(
+            empty,
+            Style::default().fg(app.theme.overlay0),
+        )));
+    }
+    // Scroll so the selected row stays in view above the help line
+    let visible = usize::from(popup_area.height.saturating_sub(4)).max(1);
+    lines.extend(selectable_rows(
+        app,
+        list.items,
+        list.selected,
+        visible,
+        row,
+    ));
+
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        list.help,
+        Style::default().fg(app.theme.subtext0),
+    )));
+
+    render_scrollable_text(f, app, lines, block, popup_area, Alignment::Left);
+}
+
+/// At most `visible` rows of `items`, from where the `selected` one is still in view. The
+/// selected row is highlighted and marked with `> `
+fn selectable_rows<T>(
+    app: &App,
+    items: &[T],
+    selected: usize,
+    visible: usize,
+    row: impl Fn(&T, Style) -> Vec<Span<'static>>,
+) -> Vec<Line<'static>> {
+    let first = selected.saturating_sub(visible.saturating_sub(1));
+    items
+        .iter()
+        .enumerate()
+        .skip(first)
+        .take(visible)
+        .map(|(i, item)| {
+            let style = if i == selected {
+                Style::default()
+                    .fg(app.theme.text)
+                    .bg(app.theme.surface1)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(app.theme.text)
+            };
+            let mut spans = vec![Span::styled(if i == selected { "> " } else { "  " }, style)];
+            spans.extend(row(item, style));
+            Line::from(spans)
+        })
+        .collect()
+}
+
+/// Render wrapped text into a popup, scrolled by `app.popup_scroll`.
+/// A scrollbar is drawn on the right border when the text does not fit
+fn render_scrollable_text(
+    f: &mut Frame,
+    app: &App,
+    lines: Vec<Line<'_>>,
+    block: Block<'_>,
+    popup_area: Rect,
+    alignment: Alignment,
+) {
+    let inner = block.inner(popup_area);
+    let inner_width = usize::from(inner.width.max(1));
+
+    // Rows the text takes up once wrapped to the popup width
+    let total_rows: usize = lines
+        .iter()
+        .map(|line| {
+            wrapped_rows(
+                line.spans.iter().map(|span| span.content.as_ref()),
+                inner_width,
+            )
+        })
+        .sum();
+    let max_scroll = total_rows.saturating_sub(usize::from(inner.height));
+    app.popup_scroll_max.set(max_scroll);
+    let scroll = app.popup_scroll.min(max_scroll);
+
+    let paragraph = Paragraph::new(lines)
         .block(block)
         .wrap(Wrap { trim: false })
-        .alignment(Alignment::Center);
+        .alignment(alignment)
+        .style(Style::default().fg(app.theme.text))
+        .scroll((u16::try_from(scroll).unwrap_or(u16::MAX), 0));
 
     f.render_widget(Clear, popup_area);
     f.render_widget(paragraph, popup_area);
+
+    render_scrollbar(f, app, popup_area, total_rows, scroll);
 }
 
-fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
+pub fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
     let popup_layout = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
@@ -169,10 +400,27 @@ pub fn render_help_popup(f: &mut Frame, app: &App, area: Rect) {
         Line::from("  k/↑         Move up"),
         Line::from("  Shift+J     Scroll diff down"),
         Line::from("  Shift+K     Scroll diff up"),
-        Line::from("  1/2/3       Switch to tab 1/2/3"),
+        Line::from("  1/2/3/4     Switch to tab 1/2/3/4"),
         Line::from("  Tab         Next tab"),
         Line::from("  Shift+Tab   Previous tab"),
         Line::from("  Enter       Select/checkout item"),
+        Line::from("  Enter       Resolve a conflicted bookmark (Bookmarks)"),
+        Line::from("  Alt+Enter   New change on the bookmark, git on its branch too (Bookmarks)"),
+        Line::from("  A           Apply a patch from a file or the clipboard (Working Copy)"),
+        Line::from("  v           Toggle whole-change diff (Working Copy)"),
+        Line::from("  e           List changed hunks, Enter edits one in $EDITOR (Working Copy)"),
+        Line::from("  i           Review notes on the change, shown in the diff (Working Copy)"),
+        Line::from("  S           Find TODO/FIXME/XXX in the lines the change adds (Working Copy)"),
+        Line::from("  H           Toggle syntax highlighting (Working Copy)"),
+        Line::from("  M           Resolve the conflicted file in the merge tool (Working Copy)"),
+        Line::from("  </>         Take ours/theirs for the whole conflicted file (Working Copy)"),
+        Line::from("  L           Toggle the mini log (Working Copy)"),
+        Line::from("  v           Toggle description preview (Log)"),
+        Line::from("  M           Mark the other end of a commit range (Log)"),
+        Line::from("  Space       Select commits for a batch action, Esc clears (Log)"),
+        Line::from("  x/r/p       Abandon / rebase / push bookmarks of the selected commits (Log)"),
+        Line::from("  y/w         Copy the patch to the clipboard / write it to a file (Log)"),
+        Line::from("  P/W         Limit to a path / date range, Esc clears (Log)"),
         Line::from(""),
         Line::from(Span::styled(
             "Working Copy Operations",
@@ -182,9 +430,25 @@ pub fn render_help_popup(f: &mut Frame, app: &App, area: Rect) {
         )),
         Line::from("  d           Describe current change"),
         Line::from("  c           Commit working copy"),
+        Line::from("  C           Commit only the picked files and hunks"),
         Line::from("  n           Create new commit"),
-        Line::from("  R           Refresh status"),
-        Line::from("  X           Restore working copy"),
+        Line::from("  T/G         Track a file over the size limit anyway / ignore it"),
+        Line::from("  z           Zen mode, only the current pane"),
+        Line::from("  Ctrl+Z      Suspend to the shell, resume with fg"),
+        Line::from("  !           Run a shell command"),
+        Line::from("  o           Open the folder of the selected file in the file manager"),
+        Line::from("  Ctrl+T      Open a terminal in the workspace root"),
+        Line::from("  Alt+T       Pick a theme, previewed while selecting and saved on Enter"),
+        Line::from("  :           Run a jj command"),
+        Line::from("  O           Show what the latest operation changed"),
+        Line::from("  Ctrl+O      Journal of what jjkk changed, restore to before any change"),
+        Line::from("  Ctrl+E      Switch back to a recent bookmark or change"),
+        Line::from("  g           Go to a change, commit or bookmark in the log"),
+        Line::from("  R           Refresh the current tab"),
+        Line::from("  Ctrl+R      Refresh everything"),
+        Line::from("  U           Reset the author to you (Working Copy/Log)"),
+        Line::from("  /           Filter files by fileset or glob, Esc clears"),
+        Line::from("  X           Restore working copy (only the filtered files)"),
         Line::from(""),
         Line::from(Span::styled(
             "Text Input (in popups)",
@@ -195,6 +459,12 @@ pub fn render_help_popup(f: &mut Frame, app: &App, area: Rect) {
         Line::from("  Enter             Submit/confirm"),
         Line::from("  Alt+Enter         Insert newline"),
         Line::from("  Esc               Cancel"),
+        Line::from("  Up/Down           Recall previous inputs"),
+        Line::from("  Ctrl+A/E          Start/end of line"),
+        Line::from("  Ctrl+W/U          Delete word/to start of line"),
+        Line::from("  Ctrl+Y            Paste deleted text"),
+        Line::from("  Alt+S/C           Signed-off-by/Co-authored-by trailer"),
+        Line::from("  Alt+A             Author override (describe/commit)"),
         Line::from(""),
         Line::from(Span::styled(
             "Remote Operations",
@@ -203,6 +473,11 @@ pub fn render_help_popup(f: &mut Frame, app: &App, area: Rect) {
                 .add_modifier(Modifier::BOLD),
         )),
         Line::from("  f           Fetch from remote"),
+        Line::from("  F           Fetch with a custom depth (shallow fetch)"),
+        Line::from("  Ctrl+F      Fetch a single bookmark"),
+        Line::from("  a/e/D       Add/edit/remove remote (Remotes)"),
+        Line::from("  Enter       Make the selected remote the default (Remotes)"),
+        Line::from("  I/E         Import/export git refs (Remotes, I also when git HEAD moved)"),
         Line::from("  p           Push to remote"),
         Line::from("  t           Track the current bookmark (if untracked)"),
         Line::from(""),
@@ -212,7 +487,11 @@ pub fn render_help_popup(f: &mut Frame, app: &App, area: Rect) {
                 .fg(app.theme.mauve)
                 .add_modifier(Modifier::BOLD),
         )),
-        Line::from("  b           Set bookmark"),
+        Line::from(
+            "  b           Create/move bookmark (selected commit in Log, Alt+R: any revision)",
+        ),
+        Line::from("  D           Delete bookmark (Bookmarks)"),
+        Line::from("  C           Clean up bookmarks merged into trunk (Bookmarks)"),
         Line::from("  r           Rebase to destination"),
         Line::from(""),
         Line::from(Span::styled(
@@ -222,36 +501,42 @@ pub fn render_help_popup(f: &mut Frame, app: &App, area: Rect) {
                 .add_modifier(Modifier::BOLD),
         )),
         Line::from("  ?           Show this help"),
+        Line::from("  m           Actions for the selected item (also right click)"),
+        Line::from("  N           Show notification log"),
         Line::from("  q           Quit (or close help)"),
         Line::from(""),
         Line::from(Span::styled(
-            "Press '?' or 'q' or Esc to close",
+            "Press '?' or 'q' or Esc to close, j/k/PageUp/PageDown to scroll",
             Style::default().fg(app.theme.subtext0),
         )),
     ];
 
-    let paragraph = Paragraph::new(help_text)
-        .block(block)
-        .wrap(Wrap { trim: false })
-        .style(Style::default().fg(app.theme.text));
+    render_scrollable_text(f, app, help_text, block, popup_area, Alignment::Left);
+}
 
-    f.render_widget(Clear, popup_area);
-    f.render_widget(paragraph, popup_area);
+impl FuzzyItem for BookmarkInfo {
+    fn search_text(&self) -> String {
+        self.name.clone()
+    }
 }
 
 pub fn render_bookmark_select_popup(
     f: &mut Frame,
     app: &App,
-    content: &str,
-    cursor_position: usize,
-    available_bookmarks: &[BookmarkInfo],
-    selected_index: usize,
+    finder: &FuzzyFinder<BookmarkInfo>,
+    purpose: &BookmarkPurpose,
     area: Rect,
 ) {
     let popup_area = centered_rect(60, 60, area);
+    let (title, query_action) = match purpose {
+        BookmarkPurpose::Set { revision } => {
+            (format!("Create or move bookmark to {revision}"), "Create")
+        }
+        BookmarkPurpose::Fetch => ("Fetch bookmark".to_string(), "Fetch"),
+    };
 
     let block = Block::default()
-        .title("Set Bookmark")
+        .title(title)
         .borders(Borders::ALL)
         .border_style(Style::default().fg(app.theme.lavender))
         .style(Style::default().bg(app.theme.surface0));
@@ -267,71 +552,796 @@ pub fn render_bookmark_select_popup(
         ])
         .split(inner_area);
 
-    // Render the input field with cursor
-    let content_with_cursor = if content.is_empty() {
-        "█".to_string()
-    } else {
-        let mut chars: Vec<char> = content.chars().collect();
-        if cursor_position >= chars.len() {
-            chars.push('█');
-        } else {
-            chars.insert(cursor_position, '█');
-        }
-        chars.into_iter().collect()
-    };
+    f.render_widget(Clear, popup_area);
+    f.render_widget(block, popup_area);
+    render_query(f, app, finder, chunks[0]);
 
-    let input_text = vec![Line::from(content_with_cursor)];
-    let input_paragraph = Paragraph::new(input_text)
-        .style(Style::default().fg(app.theme.text))
-        .wrap(Wrap { trim: false });
+    // Marker prefix takes two columns
+    let name_width = usize::from(chunks[1].width.saturating_sub(2));
+    let matched = match_style(app);
+
+    render_matches(
+        f,
+        app,
+        finder,
+        chunks[1],
+        |bookmark, m| {
+            let style = if bookmark.is_current {
+                // Highlight current bookmark with a different color
+                Style::default()
+                    .fg(app.theme.green)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(app.theme.text)
+            };
+
+            let prefix = if bookmark.is_current { "* " } else { "  " };
+            let mut spans = vec![Span::styled(prefix, style)];
+            spans.extend(highlight_matches(
+                &truncate(&bookmark.name, name_width),
+                &m.positions,
+                0,
+                style,
+                matched,
+            ));
+            Line::from(spans)
+        },
+        |name| {
+            Line::from(vec![
+                Span::styled("+ ", Style::default().fg(app.theme.green)),
+                Span::styled(
+                    format!(
+                        "{query_action} {}",
+                        truncate(name, name_width.saturating_sub(query_action.len() + 1))
+                    ),
+                    Style::default().fg(app.theme.subtext0),
+                ),
+            ])
+        },
+    );
+
+    // Help text
+    let mut help = vec![Line::from(Span::styled(
+        "↑↓/Ctrl+N/P: navigate | Tab: autocomplete | Enter: confirm | Esc: cancel",
+        Style::default().fg(app.theme.subtext0),
+    ))];
+    if matches!(purpose, BookmarkPurpose::Set { .. }) {
+        help.push(Line::from(Span::styled(
+            "Alt+R: pick another revision",
+            Style::default().fg(app.theme.subtext0),
+        )));
+    }
+    f.render_widget(Paragraph::new(help).alignment(Alignment::Center), chunks[2]);
+}
+
+pub fn render_path_select_popup(
+    f: &mut Frame,
+    app: &App,
+    finder: &FuzzyFinder<String>,
+    area: Rect,
+) {
+    let popup_area = centered_rect(60, 60, area);
+    let block = Block::default()
+        .title("Limit the log to a file or directory")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(app.theme.lavender))
+        .style(Style::default().bg(app.theme.surface0));
+
+    let inner_area = block.inner(popup_area);
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3), // Input field
+            Constraint::Min(5),    // Suggestions list
+            Constraint::Length(2), // Help text
+        ])
+        .split(inner_area);
+
+    f.render_widget(Clear, popup_area);
+    f.render_widget(block, popup_area);
+    render_query(f, app, finder, chunks[0]);
 
-    // Filter bookmarks
-    let filtered: Vec<&BookmarkInfo> = if content.is_empty() {
-        available_bookmarks.iter().collect()
+    let path_width = usize::from(chunks[1].width);
+    let matched = match_style(app);
+    render_matches(
+        f,
+        app,
+        finder,
+        chunks[1],
+        |path, m| {
+            // Directories stand out from the files in them
+            let style = if path.ends_with('/') {
+                Style::default().fg(app.theme.blue)
+            } else {
+                Style::default().fg(app.theme.text)
+            };
+            Line::from(highlight_matches(
+                &truncate(path, path_width),
+                &m.positions,
+                0,
+                style,
+                matched,
+            ))
+        },
+        |path| {
+            Line::from(Span::styled(
+                truncate(path, path_width),
+                Style::default().fg(app.theme.subtext0),
+            ))
+        },
+    );
+
+    let help = Paragraph::new(vec![Line::from(Span::styled(
+        "↑↓/Ctrl+N/P: navigate | Tab: complete | Enter: filter | Esc: cancel",
+        Style::default().fg(app.theme.subtext0),
+    ))])
+    .alignment(Alignment::Center);
+    f.render_widget(help, chunks[2]);
+}
+
+pub fn render_notifications_popup(f: &mut Frame, app: &App, area: Rect) {
+    let popup_area = centered_rect(80, 70, area);
+
+    let block = Block::default()
+        .title("Notifications (newest first)")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(app.theme.lavender))
+        .style(Style::default().bg(app.theme.surface0));
+
+    let mut lines: Vec<Line> = if app.notifications.is_empty() {
+        vec![Line::from(Span::styled(
+            "Nothing happened yet",
+            Style::default().fg(app.theme.subtext0),
+        ))]
     } else {
-        available_bookmarks
-            .iter()
-            .filter(|b| b.name.to_lowercase().contains(&content.to_lowercase()))
+        app.notifications
+            .iter_newest()
+            .map(|notification| {
+                let (marker, color) = match notification.level {
+                    NotificationLevel::Info => ("info ", app.theme.green),
+                    NotificationLevel::Warning => ("warn ", app.theme.yellow),
+                    NotificationLevel::Error => ("error", app.theme.red),
+                };
+                let age = format_duration(notification.timestamp.elapsed());
+                let took = notification
+                    .duration
+                    .map(|duration| format!(" (took {})", format_duration(duration)))
+                    .unwrap_or_default();
+
+                Line::from(vec![
+                    Span::styled(
+                        format!("{marker} "),
+                        Style::default().fg(color).add_modifier(Modifier::BOLD),
+                    ),
+                    Span::styled(
+                        format!("{age:>8} ago  "),
+                        Style::default().fg(app.theme.subtext0),
+                    ),
+                    Span::styled(
+                        notification.message.clone(),
+                        Style::default().fg(app.theme.text),
+                    ),
+                    Span::styled(took, Style::default().fg(app.theme.overlay1)),
+                ])
+            })
             .collect()
     };
 
-    // Render suggestions list
-    let suggestions: Vec<ListItem> = filtered
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        "Press 'N' or Esc to close, j/k to scroll",
+        Style::default().fg(app.theme.subtext0),
+    )));
+
+    render_scrollable_text(f, app, lines, block, popup_area, Alignment::Left);
+}
+
+/// Actions for the selected item, each with the key that runs it
+pub fn render_context_menu(
+    f: &mut Frame,
+    app: &App,
+    title: &str,
+    items: &[MenuItem],
+    selected: usize,
+    area: Rect,
+) {
+    let label_width = items.iter().map(|item| item.label.len()).max().unwrap_or(0);
+    // Key column, label and borders
+    let width = u16::try_from(label_width + 12)
+        .unwrap_or(u16::MAX)
+        .min(area.width);
+    let height = u16::try_from(items.len() + 2)
+        .unwrap_or(u16::MAX)
+        .min(area.height);
+    let popup_area = Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    };
+
+    let block = Block::default()
+        .title(truncate(title, usize::from(width.saturating_sub(2))))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(app.theme.lavender))
+        .style(Style::default().bg(app.theme.surface0));
+
+    let lines: Vec<Line> = items
         .iter()
         .enumerate()
-        .map(|(i, bookmark)| {
-            let style = if i == selected_index {
-                Style::default()
-                    .fg(app.theme.base)
-                    .bg(app.theme.lavender)
-                    .add_modifier(Modifier::BOLD)
-            } else if bookmark.is_current {
-                // Highlight current bookmark with a different color
+        .map(|(i, item)| {
+            let style = if i == selected {
                 Style::default()
-                    .fg(app.theme.green)
+                    .fg(app.theme.text)
+                    .bg(app.theme.surface1)
                     .add_modifier(Modifier::BOLD)
             } else {
                 Style::default().fg(app.theme.text)
             };
-
-            let prefix = if bookmark.is_current { "* " } else { "  " };
-            ListItem::new(format!("{}{}", prefix, bookmark.name)).style(style)
+            Line::from(vec![
+                Span::styled(
+                    format!(" {:<8} ", key_label(item.key)),
+                    style.fg(app.theme.blue),
+                ),
+                Span::styled(item.label, style),
+            ])
         })
         .collect();
 
-    let suggestions_list = List::new(suggestions).style(Style::default().fg(app.theme.text));
+    f.render_widget(Clear, popup_area);
+    f.render_widget(Paragraph::new(lines).block(block), popup_area);
+}
 
-    // Help text
-    let help = Paragraph::new(vec![Line::from(Span::styled(
-        "↑↓/jk: navigate | Tab: autocomplete | Enter: confirm | Esc: cancel",
+fn key_label(key: KeyCode) -> String {
+    match key {
+        KeyCode::Enter => "Enter".to_string(),
+        KeyCode::Char(' ') => "Space".to_string(),
+        KeyCode::Char(c) => c.to_string(),
+        other => other.to_string(),
+    }
+}
+
+/// Targets of the conflicted bookmark `name`, the selected one is what it gets resolved to
+pub fn render_bookmark_conflict_popup(
+    f: &mut Frame,
+    app: &App,
+    name: &str,
+    targets: &[BookmarkTarget],
+    selected: usize,
+    area: Rect,
+) {
+    let popup_area = centered_rect(70, 50, area);
+
+    let block = Block::default()
+        .title(format!("Resolve conflicted bookmark {name}"))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(app.theme.peach))
+        .style(Style::default().bg(app.theme.surface0));
+
+    let muted = Style::default().fg(app.theme.subtext0);
+    let mut lines = vec![
+        Line::from(Span::styled(
+            format!(
+                "{name} points at {} commits, pick the one it should keep:",
+                targets.len()
+            ),
+            muted,
+        )),
+        Line::from(""),
+    ];
+    for (i, target) in targets.iter().enumerate() {
+        let style = if i == selected {
+            Style::default()
+                .fg(app.theme.text)
+                .bg(app.theme.surface1)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(app.theme.text)
+        };
+        let description = if target.description.is_empty() {
+            "(no description set)"
+        } else {
+            &target.description
+        };
+        lines.push(Line::from(vec![
+            Span::styled(if i == selected { "> " } else { "  " }, style),
+            Span::styled(format!("{} ", target.change_id), style.fg(app.theme.blue)),
+            Span::styled(
+                format!("{} ", &target.commit_id[..target.commit_id.len().min(8)]),
+                style.fg(app.theme.overlay1),
+            ),
+            Span::styled(description.to_string(), style),
+        ]));
+    }
+
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        "j/k: select | Enter: resolve to this commit | Esc: cancel",
+        muted,
+    )));
+
+    render_scrollable_text(f, app, lines, block, popup_area, Alignment::Left);
+}
+
+/// Locations in the change, `path:line: text` like a quickfix list
+pub fn render_hunks_popup(
+    f: &mut Frame,
+    app: &App,
+    title: &str,
+    hunks: &[HunkLocation],
+    selected: usize,
+    action: HunkAction,
+    area: Rect,
+) {
+    let popup_area = centered_rect(80, 70, area);
+
+    let block = Block::default()
+        .title(format!("{} {title}", hunks.len()))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(app.theme.lavender))
+        .style(Style::default().bg(app.theme.surface0));
+
+    let list = SelectableList {
+        items: hunks,
+        selected,
+        empty: None,
+        help: match action {
+            HunkAction::Edit => {
+                "j/k: select | Enter: edit here | i: add a note | y: copy as quickfix list | Esc: close"
+            }
+            HunkAction::ShowInDiff => {
+                "j/k: select | Enter: show in the diff | e: edit here | i: add a note | y: copy as quickfix list | Esc: close"
+            }
+        },
+    };
+    render_selectable_list(f, app, block, popup_area, &list, |hunk, style| {
+        vec![
+            Span::styled(
+                format!("{}:{}: ", hunk.path, hunk.line),
+                style.fg(app.theme.blue),
+            ),
+            Span::styled(hunk.text.clone(), style),
+        ]
+    });
+}
+
+/// Files and hunks with whether they are picked for the commit, above the lines of the
+/// selected hunk
+pub fn render_commit_hunks_popup(
+    f: &mut Frame,
+    app: &App,
+    files: &[FilePatch],
+    picked: &[Vec<bool>],
+    selected: usize,
+    area: Rect,
+) {
+    let popup_area = centered_rect(80, 80, area);
+    let rows = diff::pick_rows(files);
+    let picked_count = picked.iter().flatten().filter(|&&picked| picked).count();
+    let total = picked.iter().map(Vec::len).sum::<usize>();
+
+    let block = Block::default()
+        .title(format!("Commit {picked_count} of {total} hunk(s)"))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(app.theme.green))
+        .style(Style::default().bg(app.theme.surface0));
+
+    // The list takes half of the popup, the selected hunk the rest above the help line
+    let inner = usize::from(popup_area.height.saturating_sub(4)).max(2);
+    let visible = (inner / 2).max(1);
+    let mut lines = selectable_rows(app, &rows, selected, visible, |&(file, hunk), style| {
+        let patch = &files[file];
+        let file_row = || {
+            let check = if picked[file].iter().all(|&picked| picked) {
+                "[x] "
+            } else if picked[file].contains(&true) {
+                "[~] "
+            } else {
+                "[ ] "
+            };
+            vec![
+                Span::styled(check, style.fg(app.theme.green)),
+                Span::styled(patch.path.clone(), style.fg(app.theme.blue)),
+            ]
+        };
+        let hunk_row = |hunk: usize| {
+            let check = if picked[file][hunk] { "[x] " } else { "[ ] " };
+            let first_change = patch.hunks[hunk]
+                .lines()
+                .find(|line| line.starts_with(['+', '-']))
+                .unwrap_or_default();
+            vec![
+                Span::styled(format!("    {check}"), style.fg(app.theme.green)),
+                Span::styled(
+                    format!("line {}: ", patch.hunks[hunk].line),
+                    style.fg(app.theme.overlay0),
+                ),
+                Span::styled(first_change.trim().to_string(), style),
+            ]
+        };
+        hunk.map_or_else(file_row, hunk_row)
+    });
+    lines.resize(visible, Line::from(""));
+
+    lines.push(Line::from(Span::styled(
+        "─".repeat(usize::from(popup_area.width.saturating_sub(2))),
+        Style::default().fg(app.theme.overlay0),
+    )));
+    let preview = inner.saturating_sub(visible + 1);
+    match rows.get(selected) {
+        Some(&(file, hunk)) if !files[file].hunks.is_empty() => {
+            let hunk = &files[file].hunks[hunk.unwrap_or(0)];
+            for line in hunk.lines().take(preview) {
+                let color = match line.chars().next() {
+                    Some('+') => app.theme.green,
+                    Some('-') => app.theme.red,
+                    _ => app.theme.subtext0,
+                };
+                lines.push(Line::from(Span::styled(
+                    line.to_string(),
+                    Style::default().fg(color),
+                )));
+            }
+        }
+        Some(_) => lines.push(Line::from(Span::styled(
+            "Renames, mode changes and binary files are committed as a whole",
+            Style::default().fg(app.theme.overlay0),
+        ))),
+        None => {}
+    }
+
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        "j/k: select | Space: pick | a: pick all or none | Enter: commit the picked ones | Esc: close",
         Style::default().fg(app.theme.subtext0),
-    ))])
-    .alignment(Alignment::Center);
+    )));
+
+    render_scrollable_text(f, app, lines, block, popup_area, Alignment::Left);
+}
+
+/// Review notes on the working copy change, by file and line
+pub fn render_notes_popup(f: &mut Frame, app: &App, selected: usize, area: Rect) {
+    let popup_area = centered_rect(80, 70, area);
+    let notes = app.working_copy_notes();
+
+    let block = Block::default()
+        .title(format!("{} review note(s) on this change", notes.len()))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(app.theme.yellow))
+        .style(Style::default().bg(app.theme.surface0));
+
+    let list = SelectableList {
+        items: &notes,
+        selected,
+        empty: Some("No notes yet, press a to add one on the selected file"),
+        help: "j/k: select | Enter: show the file | a: add a note on the selected file | d: delete | Esc: close",
+    };
+    render_selectable_list(f, app, block, popup_area, &list, |note, style| {
+        vec![
+            Span::styled(format!("{}: ", note.location()), style.fg(app.theme.blue)),
+            Span::styled(note.text.clone(), style),
+        ]
+    });
+}
+
+pub fn render_recent_popup(f: &mut Frame, app: &App, selected: usize, area: Rect) {
+    let popup_area = centered_rect(70, 50, area);
+    let contexts = app.recent_contexts();
+
+    let block = Block::default()
+        .title("Recent bookmarks and changes")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(app.theme.blue))
+        .style(Style::default().bg(app.theme.surface0));
+
+    let list = SelectableList {
+        items: &contexts,
+        selected,
+        empty: None,
+        help: "j/k: select | Enter: switch (new change on a bookmark, edit a change) | d: forget | Esc: close",
+    };
+    render_selectable_list(
+        f,
+        app,
+        block,
+        popup_area,
+        &list,
+        |context, style| match context {
+            RecentContext::Bookmark(name) => vec![
+                Span::styled("bookmark ", style.fg(app.theme.overlay0)),
+                Span::styled(name.clone(), style.fg(app.theme.green)),
+            ],
+            RecentContext::Change {
+                change_id,
+                description,
+            } => vec![
+                Span::styled("change   ", style.fg(app.theme.overlay0)),
+                Span::styled(format!("{change_id} "), style.fg(app.theme.blue)),
+                Span::styled(description.clone(), style),
+            ],
+        },
+    );
+}
+
+/// Built-in themes with a sample of each one's colors, the selected theme is already applied
+pub fn render_theme_picker(f: &mut Frame, app: &App, selected: usize, area: Rect) {
+    let width = 50.min(area.width);
+    let height = u16::try_from(Theme::NAMES.len() + 6)
+        .unwrap_or(u16::MAX)
+        .min(area.height);
+    let popup_area = Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    };
+
+    let block = Block::default()
+        .title("Theme")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(app.theme.lavender))
+        .style(Style::default().bg(app.theme.surface0));
+
+    let mut lines: Vec<Line> = Theme::NAMES
+        .iter()
+        .enumerate()
+        .map(|(i, name)| {
+            let style = if i == selected {
+                app.theme.highlight_style()
+            } else {
+                Style::default().fg(app.theme.text)
+            };
+            Line::from(Span::styled(
+                format!("{}{name}", if i == selected { "> " } else { "  " }),
+                style,
+            ))
+        })
+        .collect();
+
+    // The colors the ui uses most, in the selected theme
+    lines.push(Line::from(""));
+    let swatches = [
+        app.theme.text,
+        app.theme.blue,
+        app.theme.green,
+        app.theme.yellow,
+        app.theme.peach,
+        app.theme.red,
+        app.theme.mauve,
+        app.theme.lavender,
+    ];
+    lines.push(Line::from(
+        swatches
+            .iter()
+            .map(|color| Span::styled("██ ", Style::default().fg(*color)))
+            .collect::<Vec<_>>(),
+    ));
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        "j/k: preview | Enter: save | Esc: go back",
+        Style::default().fg(app.theme.subtext0),
+    )));
 
-    // Render everything
     f.render_widget(Clear, popup_area);
-    f.render_widget(block, popup_area);
-    f.render_widget(input_paragraph, chunks[0]);
-    f.render_widget(suggestions_list, chunks[1]);
-    f.render_widget(help, chunks[2]);
+    f.render_widget(Paragraph::new(lines).block(block), popup_area);
+}
+
+/// Changes jjkk made to the repo, newest first, with the operation before each one
+pub fn render_journal_popup(
+    f: &mut Frame,
+    app: &App,
+    entries: &[JournalEntry],
+    selected: usize,
+    area: Rect,
+) {
+    let popup_area = centered_rect(80, 70, area);
+
+    let block = Block::default()
+        .title("Journal - what jjkk changed (newest first)")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(app.theme.peach))
+        .style(Style::default().bg(app.theme.surface0));
+
+    let list = SelectableList {
+        items: entries,
+        selected,
+        empty: None,
+        help: "j/k: select | Enter: restore the repo to before this change | Esc: close",
+    };
+    render_selectable_list(f, app, block, popup_area, &list, |entry, style| {
+        // Seconds and the time zone only make the list harder to read
+        let time = entry
+            .time
+            .get(..16)
+            .unwrap_or(&entry.time)
+            .replace('T', " ");
+        vec![
+            Span::styled(format!("{time} "), style.fg(app.theme.overlay1)),
+            Span::styled(
+                format!("{} -> {} ", entry.short_before(), entry.short_after()),
+                style.fg(app.theme.blue),
+            ),
+            Span::styled(
+                format!("{} ", entry.action),
+                style.add_modifier(Modifier::BOLD),
+            ),
+            Span::styled(
+                entry
+                    .arguments
+                    .lines()
+                    .next()
+                    .unwrap_or_default()
+                    .to_string(),
+                style,
+            ),
+        ]
+    });
+}
+
+/// Structured `jj op diff` of the operation `steps` operations before the current one
+pub fn render_operation_diff_popup(
+    f: &mut Frame,
+    app: &App,
+    steps: usize,
+    diff: &OperationDiff,
+    area: Rect,
+) {
+    let popup_area = centered_rect(80, 70, area);
+    let title = match steps {
+        0 => "Operation diff (latest)".to_string(),
+        steps => format!("Operation diff ({steps} before latest)"),
+    };
+
+    let block = Block::default()
+        .title(title)
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(app.theme.lavender))
+        .style(Style::default().bg(app.theme.surface0));
+
+    let heading = Style::default()
+        .fg(app.theme.lavender)
+        .add_modifier(Modifier::BOLD);
+    let muted = Style::default().fg(app.theme.subtext0);
+    let mut lines = vec![
+        Line::from(vec![
+            Span::styled("From ", muted),
+            Span::raw(diff.from.clone()),
+        ]),
+        Line::from(vec![
+            Span::styled("  To ", muted),
+            Span::raw(diff.to.clone()),
+        ]),
+    ];
+
+    if diff.is_empty() {
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(
+            "No commits or bookmarks changed",
+            muted,
+        )));
+    }
+
+    if !diff.commits.is_empty() {
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled("Commits", heading)));
+        for commit in &diff.commits {
+            let (label, color) = match commit.kind {
+                CommitChangeKind::Added => ("added    ", app.theme.green),
+                CommitChangeKind::Rewritten => ("rewritten", app.theme.yellow),
+                CommitChangeKind::Abandoned => ("abandoned", app.theme.red),
+            };
+            lines.push(Line::from(vec![
+                Span::styled(format!("  {label} "), Style::default().fg(color)),
+                Span::raw(commit.summary.clone()),
+            ]));
+        }
+    }
+
+    if !diff.bookmarks.is_empty() {
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled("Bookmarks", heading)));
+        for bookmark in &diff.bookmarks {
+            let (label, color) = match (&bookmark.from, &bookmark.to) {
+                (None, Some(_)) => ("created", app.theme.green),
+                (Some(_), None) => ("deleted", app.theme.red),
+                _ => ("moved  ", app.theme.yellow),
+            };
+            lines.push(Line::from(vec![
+                Span::styled(format!("  {label} "), Style::default().fg(color)),
+                Span::styled(
+                    bookmark.name.clone(),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+            ]));
+            for (marker, target) in [("-", &bookmark.from), ("+", &bookmark.to)] {
+                if let Some(target) = target {
+                    lines.push(Line::from(Span::styled(
+                        format!("            {marker} {target}"),
+                        muted,
+                    )));
+                }
+            }
+        }
+    }
+
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        "[/]: older/newer operation | j/k: scroll | Esc: close",
+        muted,
+    )));
+
+    render_scrollable_text(f, app, lines, block, popup_area, Alignment::Left);
+}
+
+/// Output of a jj command typed at the `:` prompt, the border is red when it failed
+pub fn render_command_output_popup(
+    f: &mut Frame,
+    app: &App,
+    title: &str,
+    output: &str,
+    success: bool,
+    area: Rect,
+) {
+    let popup_area = centered_rect(80, 70, area);
+    let border_color = if success {
+        app.theme.lavender
+    } else {
+        app.theme.red
+    };
+
+    let block = Block::default()
+        .title(format!("jj {title}"))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(border_color))
+        .style(Style::default().bg(app.theme.surface0));
+
+    let mut lines: Vec<Line> = if output.trim().is_empty() {
+        vec![Line::from(Span::styled(
+            "No output",
+            Style::default().fg(app.theme.subtext0),
+        ))]
+    } else {
+        output.lines().map(Line::from).collect()
+    };
+
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        "Press Esc to close, j/k to scroll",
+        Style::default().fg(app.theme.subtext0),
+    )));
+
+    render_scrollable_text(f, app, lines, block, popup_area, Alignment::Left);
+}
+
+/// `typed` is the text entered so far when the action needs a typed confirmation
+pub fn render_confirm_popup(
+    f: &mut Frame,
+    app: &App,
+    title: &str,
+    lines: &[String],
+    typed: Option<&str>,
+    area: Rect,
+) {
+    let popup_area = centered_rect(70, 60, area);
+
+    let block = Block::default()
+        .title(title.to_string())
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(app.theme.peach))
+        .style(Style::default().bg(app.theme.surface0));
+
+    let mut text: Vec<Line> = lines.iter().map(|line| Line::from(line.as_str())).collect();
+    if let Some(typed) = typed {
+        text.push(Line::from(vec![
+            Span::styled("> ", Style::default().fg(app.theme.peach)),
+            Span::styled(
+                format!("{typed}_"),
+                Style::default()
+                    .fg(app.theme.text)
+                    .add_modifier(Modifier::BOLD),
+            ),
+        ]));
+    }
+
+    render_scrollable_text(f, app, text, block, popup_area, Alignment::Left);
 }
@@ -0,0 +1,249 @@
+use crossterm::event::KeyEvent;
+use ratatui::{
+    Frame,
+    layout::{
+        Alignment,
+        Constraint,
+        Direction,
+        Layout,
+        Rect,
+    },
+    style::Style,
+    text::{
+        Line,
+        Span,
+    },
+    widgets::{
+        Block,
+        Borders,
+        Clear,
+        Paragraph,
+    },
+};
+
+use super::{
+    fuzzy_finder::{
+        FinderEvent,
+        FuzzyFinder,
+        FuzzyItem,
+        highlight_matches,
+        match_style,
+        render_matches,
+        render_query,
+    },
+    popup::centered_rect,
+};
+use crate::{
+    app::App,
+    jj::{
+        command::revset_symbol,
+        log::CommitInfo,
+        operations::BookmarkInfo,
+    },
+    ui::text::{
+        display_width,
+        truncate,
+    },
+};
+
+/// Where a candidate of the picker comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CandidateKind {
+    Bookmark,
+    Commit,
+    /// A well known revset like `trunk()`
+    Revset,
+}
+
+/// Something that can be picked, `revision` is what gets passed to jj
+#[derive(Debug, Clone)]
+pub struct RevisionCandidate {
+    pub revision:    String,
+    pub label:       String,
+    pub description: String,
+    pub kind:        CandidateKind,
+}
+
+impl FuzzyItem for RevisionCandidate {
+    fn search_text(&self) -> String {
+        format!("{} {}", self.label, self.description)
+    }
+
+    fn completion(&self) -> String {
+        self.revision.clone()
+    }
+}
+
+/// What a key press in the picker resulted in
+pub enum PickerEvent {
+    /// Keep the picker open
+    None,
+    Cancel,
+    /// A revision was picked, it still has to be validated by the caller
+    Submit(String),
+}
+
+/// Popup to pick a revision out of the cached bookmarks and commits,
+/// or to type a raw revset
+#[derive(Debug, Clone)]
+pub struct RevisionPicker {
+    pub title:  String,
+    pub finder: FuzzyFinder<RevisionCandidate>,
+    /// Shown below the list, e.g. when the picked revset does not resolve
+    pub error:  Option<String>,
+}
+
+impl RevisionPicker {
+    pub fn new(title: &str, candidates: Vec<RevisionCandidate>) -> Self {
+        Self {
+            title:  title.to_string(),
+            finder: FuzzyFinder::new(candidates, true),
+            error:  None,
+        }
+    }
+
+    /// Build the candidates from cached data, `first` is listed before everything else
+    pub fn candidates(
+        first: Option<RevisionCandidate>,
+        bookmarks: &[BookmarkInfo],
+        commits: &[CommitInfo],
+    ) -> Vec<RevisionCandidate> {
+        let first_label = first.as_ref().map(|c| c.label.clone());
+        let mut candidates: Vec<RevisionCandidate> = first.into_iter().collect();
+        candidates.extend(
+            bookmarks
+                .iter()
+                .filter(|b| first_label.as_ref() != Some(&b.name))
+                .map(|b| RevisionCandidate {
+                    revision:    revset_symbol(&b.name),
+                    label:       b.name.clone(),
+                    description: String::new(),
+                    kind:        CandidateKind::Bookmark,
+                }),
+        );
+        candidates.extend(commits.iter().map(|commit| RevisionCandidate {
+            revision:    commit.change_id.clone(),
+            label:       commit.change_id.clone(),
+            description: commit.description.clone(),
+            kind:        CandidateKind::Commit,
+        }));
+        candidates
+    }
+
+    /// Insert pasted `text` into the query
+    pub fn insert_str(&mut self, text: &str) {
+        self.finder.insert_str(text);
+        self.error = None;
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> PickerEvent {
+        match self.finder.handle_key(key) {
+            FinderEvent::None => PickerEvent::None,
+            FinderEvent::Changed => {
+                self.error = None;
+                PickerEvent::None
+            }
+            FinderEvent::Cancel => PickerEvent::Cancel,
+            FinderEvent::Pick(index) => {
+                PickerEvent::Submit(self.finder.items[index].revision.clone())
+            }
+            FinderEvent::Query(query) => PickerEvent::Submit(query),
+        }
+    }
+}
+
+pub fn render_revision_picker(f: &mut Frame, app: &App, picker: &RevisionPicker, area: Rect) {
+    const TAG_WIDTH: usize = 9;
+
+    let popup_area = centered_rect(70, 70, area);
+
+    let block = Block::default()
+        .title(picker.title.clone())
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(app.theme.lavender))
+        .style(Style::default().bg(app.theme.surface0));
+
+    let inner_area = block.inner(popup_area);
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(2), // Query
+            Constraint::Min(3),    // Candidates
+            Constraint::Length(1), // Error
+            Constraint::Length(1), // Help text
+        ])
+        .split(inner_area);
+
+    f.render_widget(Clear, popup_area);
+    f.render_widget(block, popup_area);
+    render_query(f, app, &picker.finder, chunks[0]);
+
+    let width = usize::from(chunks[1].width);
+    let matched = match_style(app);
+    render_matches(
+        f,
+        app,
+        &picker.finder,
+        chunks[1],
+        |candidate, m| {
+            let (tag, color) = match candidate.kind {
+                CandidateKind::Bookmark => ("bookmark", app.theme.mauve),
+                CandidateKind::Commit => ("commit", app.theme.blue),
+                CandidateKind::Revset => ("revset", app.theme.peach),
+            };
+            let description_width =
+                width.saturating_sub(TAG_WIDTH + display_width(&candidate.label) + 1);
+
+            let mut spans = vec![Span::styled(
+                format!("{tag:<TAG_WIDTH$}"),
+                Style::default().fg(app.theme.overlay1),
+            )];
+            spans.extend(highlight_matches(
+                &candidate.label,
+                &m.positions,
+                0,
+                Style::default().fg(color),
+                matched,
+            ));
+            spans.push(Span::raw(" "));
+            // The description follows the label and a space in the search text
+            spans.extend(highlight_matches(
+                &truncate(&candidate.description, description_width),
+                &m.positions,
+                candidate.label.chars().count() + 1,
+                Style::default().fg(app.theme.subtext0),
+                matched,
+            ));
+            Line::from(spans)
+        },
+        |query| {
+            Line::from(vec![
+                Span::styled(
+                    format!("{:<TAG_WIDTH$}", "revset"),
+                    Style::default().fg(app.theme.overlay1),
+                ),
+                Span::styled(
+                    truncate(query, width.saturating_sub(TAG_WIDTH)),
+                    Style::default().fg(app.theme.peach),
+                ),
+            ])
+        },
+    );
+
+    if let Some(error) = &picker.error {
+        f.render_widget(
+            Paragraph::new(Span::styled(
+                truncate(error, width),
+                Style::default().fg(app.theme.red),
+            )),
+            chunks[2],
+        );
+    }
+
+    let help = Paragraph::new(Line::from(Span::styled(
+        "↑↓/Ctrl+N/P: navigate | Tab: complete | Enter: pick | Esc: cancel",
+        Style::default().fg(app.theme.subtext0),
+    )))
+    .alignment(Alignment::Center);
+    f.render_widget(help, chunks[3]);
+}
@@ -0,0 +1,53 @@
+use ratatui::{
+    Frame,
+    layout::{
+        Margin,
+        Rect,
+    },
+    style::Style,
+    widgets::{
+        Scrollbar,
+        ScrollbarOrientation,
+        ScrollbarState,
+    },
+};
+
+use crate::app::App;
+
+/// Draw a scrollbar over the right border of the bordered `area` when its
+/// `content_rows` don't fit, `top_row` is the first one on screen
+pub fn render_scrollbar(f: &mut Frame, app: &App, area: Rect, content_rows: usize, top_row: usize) {
+    let visible_rows = usize::from(area.height.saturating_sub(2));
+    let Some(max_top_row) = content_rows
+        .checked_sub(visible_rows)
+        .filter(|&max| max > 0)
+    else {
+        return;
+    };
+
+    let mut scrollbar_state =
+        ScrollbarState::new(max_top_row + 1).position(top_row.min(max_top_row));
+    let scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight)
+        .style(Style::default().fg(app.theme.overlay1));
+    f.render_stateful_widget(
+        scrollbar,
+        area.inner(Margin {
+            vertical:   1,
+            horizontal: 0,
+        }),
+        &mut scrollbar_state,
+    );
+}
+
+/// Scrollbar of a list whose items are `item_heights` rows tall, `offset` is the
+/// first item on screen as left in the list state by rendering it
+pub fn render_list_scrollbar(
+    f: &mut Frame,
+    app: &App,
+    area: Rect,
+    item_heights: &[usize],
+    offset: usize,
+) {
+    let top_row = item_heights.iter().take(offset).sum();
+    render_scrollbar(f, app, area, item_heights.iter().sum(), top_row);
+}
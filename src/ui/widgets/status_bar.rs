@@ -1,38 +1,117 @@
+use chrono::{
+    Local,
+    Timelike,
+};
 use ratatui::{
     Frame,
     layout::Rect,
     style::Style,
+    text::{
+        Line,
+        Span,
+    },
     widgets::Paragraph,
 };
 
-use crate::app::App;
+use crate::{
+    app::App,
+    config::settings::StatusSegment,
+};
+
+const KEYHINTS: &str = "q: quit | hjkl: navigate | f: fetch | p: push | r: rebase  | d: describe | b: bookmark | X: restore | R: refresh";
 
+/// Render the segments configured in `ui.status_bar`, separated by `|`
 pub fn render_status_bar(f: &mut Frame, app: &App, area: Rect) {
-    let status_text = app.loading_message.as_ref().map_or_else(|| {
-        app.status_message.as_ref().map_or_else(
-            || {
-                // Show default keybinds
-                "q: quit | hjkl: navigate | f: fetch | p: push | r: rebase  | d: describe | b: bookmark | X: restore | R: refresh".to_string()
-            },
-            |msg| {
-                // Show success message
-                format!("✓ {msg}")
-            }
-        )
-    }, |loading_msg| {
-        // Show loading spinner with message
-        format!("{} {}", app.get_spinner_char(), loading_msg)
-    });
-
-    let style = if app.loading_message.is_some() {
-        Style::default().fg(app.theme.yellow).bg(app.theme.base)
-    } else if app.status_message.is_some() {
-        Style::default().fg(app.theme.green).bg(app.theme.base)
-    } else {
-        Style::default().fg(app.theme.subtext0).bg(app.theme.base)
-    };
-
-    let status = Paragraph::new(status_text).style(style);
+    let mut spans = Vec::new();
+    for segment in &app.settings.ui.status_bar {
+        let Some(span) = render_segment(app, *segment) else {
+            continue;
+        };
+        if !spans.is_empty() {
+            spans.push(Span::styled(" | ", Style::default().fg(app.theme.surface2)));
+        }
+        spans.push(span);
+    }
+
+    let status = Paragraph::new(Line::from(spans)).style(Style::default().bg(app.theme.base));
 
     f.render_widget(status, area);
 }
+
+/// The content of `segment`, `None` when it has nothing to show right now
+fn render_segment(app: &App, segment: StatusSegment) -> Option<Span<'static>> {
+    // Stale data is worth more attention than the last status message
+    let external_operation = app
+        .external_operation
+        .as_ref()
+        .filter(|_| app.loading_message.is_none());
+    let busy = app.loading_message.is_some() || external_operation.is_some();
+
+    match segment {
+        StatusSegment::Repo => app.working_copy.as_ref().map(|commit| {
+            let mut text = format!("@ {}", commit.change_id);
+            for bookmark in &commit.bookmarks {
+                text.push(' ');
+                text.push_str(bookmark);
+            }
+            Span::styled(text, Style::default().fg(app.theme.lavender))
+        }),
+        StatusSegment::Operation => {
+            if let Some(loading_message) = &app.loading_message {
+                // Show loading spinner with message, and for how long once it takes a while
+                let elapsed = app
+                    .loading_start
+                    .map(|start| start.elapsed().as_secs())
+                    .filter(|&seconds| seconds > 0)
+                    .map(|seconds| {
+                        let ellipsis = if app.theme.ascii_markers() {
+                            "..."
+                        } else {
+                            "…"
+                        };
+                        format!("{ellipsis} {seconds}s")
+                    })
+                    .unwrap_or_default();
+                return Some(Span::styled(
+                    format!("{} {loading_message}{elapsed}", app.get_spinner_char()),
+                    Style::default().fg(app.theme.yellow),
+                ));
+            }
+            external_operation.map(|operation| {
+                Span::styled(
+                    format!(
+                        "! Repo changed externally (operation {operation}) - press Ctrl+R to reload"
+                    ),
+                    Style::default().fg(app.theme.peach),
+                )
+            })
+        }
+        StatusSegment::Message => app.status_message.as_ref().filter(|_| !busy).map(|msg| {
+            // Show success message
+            let marker = if app.theme.ascii_markers() {
+                '*'
+            } else {
+                '✓'
+            };
+            Span::styled(
+                format!("{marker} {msg}"),
+                Style::default().fg(app.theme.green),
+            )
+        }),
+        StatusSegment::Keyhints => (!busy && app.status_message.is_none())
+            .then(|| Span::styled(KEYHINTS, Style::default().fg(app.theme.subtext0))),
+        StatusSegment::Clock => {
+            let now = Local::now();
+            app.clock_minute.set(Some(clock_minute(&now)));
+            Some(Span::styled(
+                now.format("%H:%M").to_string(),
+                Style::default().fg(app.theme.subtext0),
+            ))
+        }
+    }
+}
+
+/// Minute of the day, the clock needs a redraw when it changes
+pub fn clock_minute<T: Timelike>(time: &T) -> u32 {
+    time.hour() * 60 + time.minute()
+}